@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::net::IpAddr;
 
 #[derive(Debug, Parser)]
 #[command(name = "wallguard-cli")]
@@ -63,6 +64,18 @@ pub enum Command {
         /// Maximum number of packets per batch sent to the server
         #[arg(long)]
         batch_size: Option<usize>,
+
+        /// Port to serve the agent metrics on in the OpenMetrics format
+        #[arg(long)]
+        metrics_port: Option<u16>,
+
+        /// Address to serve the agent metrics on (localhost if unset)
+        #[arg(long)]
+        metrics_host: Option<IpAddr>,
+
+        /// Stop serving the agent metrics, forgetting the saved port and address
+        #[arg(long, conflicts_with_all = ["metrics_port", "metrics_host"])]
+        no_metrics: bool,
     },
 
     /// Get agent version
@@ -1,11 +1,12 @@
 //! Persists the arguments last used with `wallguard-cli start`, so that
-//! after the first successful start, `control-channel-url`, `platform` and
-//! `batch-size` no longer need to be repeated on the command line — any
-//! flag omitted on a later `start` reuses its cached value, and any flag
-//! passed overwrites the cache for next time.
+//! after the first successful start, `control-channel-url`, `platform`,
+//! `batch-size` and the metrics endpoint no longer need to be repeated on
+//! the command line — any flag omitted on a later `start` reuses its cached
+//! value, and any flag passed overwrites the cache for next time.
 
 use crate::arguments::Platform;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::path::PathBuf;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -13,6 +14,8 @@ pub struct StartConfig {
     pub control_channel_url: Option<String>,
     pub platform: Option<Platform>,
     pub batch_size: Option<usize>,
+    pub metrics_port: Option<u16>,
+    pub metrics_host: Option<IpAddr>,
 }
 
 fn config_path() -> PathBuf {
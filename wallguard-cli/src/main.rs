@@ -174,6 +174,12 @@ fn print_cached_start_args() {
     if let Some(batch_size) = cached.batch_size {
         println!("  --batch-size           : {batch_size}");
     }
+    if let Some(port) = cached.metrics_port {
+        println!("  --metrics-port         : {port}");
+    }
+    if let Some(host) = cached.metrics_host {
+        println!("  --metrics-host         : {host}");
+    }
 }
 
 /// Hard-kills the running agent process by name. This is the same
@@ -359,6 +365,9 @@ pub async fn main() -> AnyResult<()> {
             control_channel_url,
             platform,
             batch_size,
+            metrics_port,
+            metrics_host,
+            no_metrics,
         } => {
             let lock_path = wallguard_common::single_instance::agent_lock_path();
 
@@ -399,17 +408,29 @@ pub async fn main() -> AnyResult<()> {
             });
 
             let batch_size = batch_size.or(cached.batch_size);
+            let (metrics_port, metrics_host) = if no_metrics {
+                (None, None)
+            } else {
+                (
+                    metrics_port.or(cached.metrics_port),
+                    metrics_host.or(cached.metrics_host),
+                )
+            };
 
             // Persist the effective values (whatever was just passed, or
             // whatever was already cached) so the next `start` can omit them.
             cached.control_channel_url = Some(control_channel_url.clone());
             cached.platform = Some(platform.clone());
             cached.batch_size = batch_size;
+            cached.metrics_port = metrics_port;
+            cached.metrics_host = metrics_host;
             if let Err(err) = cached.save() {
                 eprintln!("WARNING: Failed to persist start configuration: {err}");
             }
 
             let batch_size_str = batch_size.map(|n| n.to_string());
+            let metrics_port_str = metrics_port.map(|port| port.to_string());
+            let metrics_host_str = metrics_host.map(|host| host.to_string());
             let platform_str = platform.to_string();
 
             let mut service_args = vec![
@@ -422,6 +443,14 @@ pub async fn main() -> AnyResult<()> {
                 service_args.push("--batch-size");
                 service_args.push(s.as_str());
             }
+            if let Some(ref s) = metrics_port_str {
+                service_args.push("--metrics-port");
+                service_args.push(s.as_str());
+            }
+            if let Some(ref s) = metrics_host_str {
+                service_args.push("--metrics-host");
+                service_args.push(s.as_str());
+            }
 
             if autostart::enable_service("wallguard", &service_args)
                 .await
@@ -452,6 +481,12 @@ pub async fn main() -> AnyResult<()> {
             if let Some(n) = batch_size {
                 cmd.arg("--batch-size").arg(n.to_string());
             }
+            if let Some(port) = metrics_port {
+                cmd.arg("--metrics-port").arg(port.to_string());
+            }
+            if let Some(host) = metrics_host {
+                cmd.arg("--metrics-host").arg(host.to_string());
+            }
             cmd.stdout(Stdio::null()).stderr(Stdio::null());
 
             if let Err(err) = cmd.spawn() {
@@ -1,10 +1,11 @@
 const OUTPUT_DIR: &str = "./src/protobuf";
 const INCLUDE_PATHS: [&str; 2] = ["../proto", "/usr/include"];
-const PROTO_FILES: [&str; 4] = [
+const PROTO_FILES: [&str; 5] = [
     "../proto/cli.proto",
     "../proto/models.proto",
     "../proto/commands.proto",
     "../proto/service.proto",
+    "../proto/remote_desktop.proto",
 ];
 
 fn main() {
@@ -27,6 +28,30 @@ fn main() {
             "wallguard_service.SystemResource",
             "#[derive(serde::Serialize, serde::Deserialize)]",
         )
+        .type_attribute(
+            "wallguard_service.InterfaceStats",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            "wallguard_service.FirewallHealth",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            "wallguard_service.GatewayHealth",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            "wallguard_service.HaStatus",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            "wallguard_service.ProcessSnapshot",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            "wallguard_service.ProcessInfo",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
         .type_attribute(
             "wallguard_service.ServiceInfo",
             "#[derive(serde::Serialize, serde::Deserialize)]",
@@ -1,6 +1,7 @@
 pub mod cert_verifier;
 pub mod os_if;
 pub mod protobuf;
+pub mod rd_protocol;
 pub mod single_instance;
 pub mod timestamped_packet;
 pub mod wallguard_interface;
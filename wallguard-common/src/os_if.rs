@@ -21,6 +21,8 @@ pub struct InterfaceSnapshot {
     pub subnet_mask: Option<IpAddr>,
     #[serde(with = "serde_ipaddr_option")]
     pub gateway: Option<IpAddr>,
+    pub link_speed_mbps: Option<u64>,
+    pub oper_state: Option<String>,
 }
 
 impl InterfaceSnapshot {
@@ -34,9 +36,11 @@ impl InterfaceSnapshot {
 
     pub fn take_all() -> Vec<InterfaceSnapshot> {
         let interfaces = datalink::interfaces();
+        let mut links = link_info();
         let mut iface_map: HashMap<String, InterfaceSnapshot> = HashMap::new();
 
         for iface in interfaces {
+            let (link_speed_mbps, oper_state) = links.remove(&iface.name).unwrap_or_default();
             iface_map.insert(
                 iface.name.clone(),
                 InterfaceSnapshot {
@@ -50,6 +54,8 @@ impl InterfaceSnapshot {
                     ip_addresses: Vec::new(),
                     subnet_mask: None,
                     gateway: None,
+                    link_speed_mbps,
+                    oper_state,
                 },
             );
         }
@@ -73,3 +79,110 @@ impl InterfaceSnapshot {
         iface_map.into_values().collect()
     }
 }
+
+type LinkInfo = (Option<u64>, Option<String>);
+
+/// Link speed and operational state, as reported by sysfs.
+#[cfg(target_os = "linux")]
+fn link_info() -> HashMap<String, LinkInfo> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+        return HashMap::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| {
+            let read = |file: &str| {
+                std::fs::read_to_string(entry.path().join(file))
+                    .ok()
+                    .map(|value| value.trim().to_string())
+            };
+
+            // the speed is -1 (or not readable at all) when the link is down or virtual
+            let speed = read("speed").and_then(|speed| speed.parse().ok());
+
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                (speed, read("operstate")),
+            )
+        })
+        .collect()
+}
+
+/// Link speed and operational state, as reported by `ifconfig`.
+#[cfg(target_os = "freebsd")]
+fn link_info() -> HashMap<String, LinkInfo> {
+    std::process::Command::new("ifconfig")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_ifconfig_links(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+fn link_info() -> HashMap<String, LinkInfo> {
+    HashMap::new()
+}
+
+/// Parses the `media:` and `status:` lines of every interface block, e.g.
+/// `media: Ethernet autoselect (1000baseT <full-duplex>)` and `status: active`.
+#[cfg_attr(not(target_os = "freebsd"), allow(dead_code))]
+fn parse_ifconfig_links(output: &str) -> HashMap<String, LinkInfo> {
+    let mut links: HashMap<String, LinkInfo> = HashMap::new();
+    let mut current = None;
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            current = line.split(':').next().map(str::to_string);
+            continue;
+        }
+
+        let Some(name) = &current else {
+            continue;
+        };
+        let link = links.entry(name.clone()).or_default();
+        let line = line.trim();
+
+        if let Some(media) = line.strip_prefix("media:") {
+            link.0 = media
+                .split_once('(')
+                .and_then(|(_, active)| active.split_whitespace().next())
+                .and_then(parse_media_speed);
+        } else if let Some(status) = line.strip_prefix("status:") {
+            link.1 = Some(match status.trim() {
+                "active" | "associated" | "running" => String::from("up"),
+                "no carrier" => String::from("down"),
+                other => other.to_string(),
+            });
+        }
+    }
+
+    links
+}
+
+/// Speed in Mbit/s of a media subtype such as `1000baseT`, `2500Base-T` or `10Gbase-SR`.
+#[cfg_attr(not(target_os = "freebsd"), allow(dead_code))]
+fn parse_media_speed(media: &str) -> Option<u64> {
+    let media = media.to_ascii_lowercase();
+    let (speed, _) = media.split_once("base")?;
+    match speed.strip_suffix('g') {
+        Some(gigabits) => gigabits.parse::<u64>().ok().map(|g| g * 1000),
+        None => speed.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ifconfig_links() {
+        let output = "igb0: flags=8863<UP,BROADCAST,RUNNING,SIMPLEX,MULTICAST> metric 0 mtu 1500\n\tether 00:0c:29:aa:bb:cc\n\tmedia: Ethernet autoselect (1000baseT <full-duplex>)\n\tstatus: active\nix0: flags=8822<BROADCAST,SIMPLEX,MULTICAST> metric 0 mtu 1500\n\tmedia: Ethernet autoselect (10Gbase-SR <full-duplex>)\n\tstatus: no carrier\nlo0: flags=8049<UP,LOOPBACK,RUNNING,MULTICAST> metric 0 mtu 16384\n\tinet 127.0.0.1 netmask 0xff000000\n";
+
+        let links = parse_ifconfig_links(output);
+        assert_eq!(links["igb0"], (Some(1000), Some(String::from("up"))));
+        assert_eq!(links["ix0"], (Some(10_000), Some(String::from("down"))));
+        assert_eq!(links["lo0"], (None, None));
+    }
+}
@@ -17,4 +17,8 @@ pub mod wallguard_service;
 #[rustfmt::skip]
 pub mod wallguard_models;
 
+#[allow(clippy::enum_variant_names)]
+#[rustfmt::skip]
+pub mod wallguard_rd;
+
 mod utils;
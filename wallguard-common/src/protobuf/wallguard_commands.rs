@@ -35,9 +35,36 @@ pub mod client_message {
         #[prost(message, tag = "2")]
         Authentication(super::Authentication),
         #[prost(message, tag = "4")]
-        Heartbeat(()),
+        Heartbeat(super::Heartbeat),
     }
 }
+/// Health of the agent, reported with every heartbeat
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Heartbeat {
+    #[prost(uint64, tag = "1")]
+    pub uptime_seconds: u64,
+    #[prost(bool, tag = "2")]
+    pub traffic_monitoring: bool,
+    #[prost(bool, tag = "3")]
+    pub telemetry_monitoring: bool,
+    #[prost(bool, tag = "4")]
+    pub configuration_monitoring: bool,
+    #[prost(bool, tag = "5")]
+    pub services_monitoring: bool,
+    #[prost(uint64, tag = "6")]
+    pub queued_items: u64,
+    #[prost(uint64, tag = "7")]
+    pub spool_bytes: u64,
+    #[prost(string, optional, tag = "8")]
+    pub last_error: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(int64, tag = "9")]
+    pub last_error_timestamp: i64,
+    /// Agent clock, in milliseconds since the epoch
+    #[prost(int64, tag = "10")]
+    pub timestamp: i64,
+    #[prost(string, tag = "11")]
+    pub version: ::prost::alloc::string::String,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AuthenticationData {
     #[prost(string, optional, tag = "1")]
@@ -66,10 +93,60 @@ pub struct UiSessionData {
     pub protocol: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CaptureSessionData {
+    #[prost(string, tag = "1")]
+    pub tunnel_token: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub interface: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub bpf_filter: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "4")]
+    pub duration_seconds: u32,
+    #[prost(uint64, tag = "5")]
+    pub max_bytes: u64,
+    #[prost(bool, tag = "6")]
+    pub promiscuous: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoteDesktopSessionData {
+    #[prost(string, tag = "1")]
+    pub tunnel_token: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub disable_clipboard_to_host: bool,
+    #[prost(bool, tag = "3")]
+    pub disable_clipboard_to_viewer: bool,
+    #[prost(enumeration = "RemoteDesktopRole", tag = "4")]
+    pub role: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FlowExportSettings {
+    #[prost(bool, tag = "1")]
+    pub enabled: bool,
+    #[prost(enumeration = "FlowExportProtocol", tag = "2")]
+    pub protocol: i32,
+    #[prost(string, repeated, tag = "3")]
+    pub collectors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint32, tag = "4")]
+    pub active_timeout_seconds: u32,
+    #[prost(uint32, tag = "5")]
+    pub idle_timeout_seconds: u32,
+    #[prost(uint32, tag = "6")]
+    pub observation_domain_id: u32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ProcessSnapshotSettings {
+    #[prost(bool, tag = "1")]
+    pub enabled: bool,
+    #[prost(uint32, tag = "2")]
+    pub interval_seconds: u32,
+    #[prost(uint32, tag = "3")]
+    pub top_n: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ServerMessage {
     #[prost(
         oneof = "server_message::Message",
-        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14"
+        tags = "1, 2, 3, 4, 5, 6, 7, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18"
     )]
     pub message: ::core::option::Option<server_message::Message>,
 }
@@ -91,8 +168,6 @@ pub mod server_message {
         OpenTtySessionCommand(::prost::alloc::string::String),
         #[prost(message, tag = "7")]
         OpenUiSessionCommand(super::UiSessionData),
-        #[prost(string, tag = "8")]
-        OpenRemoteDesktopSessionCommand(::prost::alloc::string::String),
         #[prost(message, tag = "9")]
         DeviceAuthorizedMessage(super::AuthenticationData),
         #[prost(message, tag = "10")]
@@ -105,5 +180,65 @@ pub mod server_message {
         CreateNatRule(super::super::wallguard_models::NatRule),
         #[prost(message, tag = "14")]
         CreateAlias(super::super::wallguard_models::Alias),
+        #[prost(message, tag = "15")]
+        OpenCaptureSessionCommand(super::CaptureSessionData),
+        #[prost(message, tag = "16")]
+        ConfigureFlowExportCommand(super::FlowExportSettings),
+        #[prost(message, tag = "17")]
+        ConfigureProcessSnapshotsCommand(super::ProcessSnapshotSettings),
+        #[prost(message, tag = "18")]
+        OpenRemoteDesktopSessionCommand(super::RemoteDesktopSessionData),
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RemoteDesktopRole {
+    Controller = 0,
+    ViewOnly = 1,
+}
+impl RemoteDesktopRole {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Controller => "CONTROLLER",
+            Self::ViewOnly => "VIEW_ONLY",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CONTROLLER" => Some(Self::Controller),
+            "VIEW_ONLY" => Some(Self::ViewOnly),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum FlowExportProtocol {
+    NetflowV9 = 0,
+    Ipfix = 1,
+}
+impl FlowExportProtocol {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::NetflowV9 => "NETFLOW_V9",
+            Self::Ipfix => "IPFIX",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "NETFLOW_V9" => Some(Self::NetflowV9),
+            "IPFIX" => Some(Self::Ipfix),
+            _ => None,
+        }
     }
 }
@@ -0,0 +1,414 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Hello {
+    #[prost(uint32, tag = "1")]
+    pub protocol_version: u32,
+    #[prost(enumeration = "Feature", repeated, tag = "2")]
+    pub features: ::prost::alloc::vec::Vec<i32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VideoFrame {
+    #[prost(enumeration = "Codec", tag = "1")]
+    pub codec: i32,
+    #[prost(uint32, tag = "2")]
+    pub width: u32,
+    #[prost(uint32, tag = "3")]
+    pub height: u32,
+    #[prost(bool, tag = "4")]
+    pub keyframe: bool,
+    /// Capture time, in milliseconds since the start of the session
+    #[prost(uint64, tag = "5")]
+    pub timestamp_ms: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CursorShape {
+    #[prost(uint32, tag = "1")]
+    pub width: u32,
+    #[prost(uint32, tag = "2")]
+    pub height: u32,
+    #[prost(uint32, tag = "3")]
+    pub hotspot_x: u32,
+    #[prost(uint32, tag = "4")]
+    pub hotspot_y: u32,
+    /// RGBA, row by row
+    #[prost(bytes = "vec", tag = "5")]
+    pub pixels: ::prost::alloc::vec::Vec<u8>,
+}
+/// Position of the hotspot in pixels of the latest VideoFrame; the shape is
+/// scaled like the frames. Agents that cannot report the pointer draw it in the
+/// frames instead: viewers keep their local pointer until the first update.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CursorUpdate {
+    #[prost(int32, tag = "1")]
+    pub x: i32,
+    #[prost(int32, tag = "2")]
+    pub y: i32,
+    #[prost(bool, tag = "3")]
+    pub visible: bool,
+    /// Only sent when the shape changes
+    #[prost(message, optional, tag = "4")]
+    pub shape: ::core::option::Option<CursorShape>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Clipboard {
+    #[prost(string, tag = "1")]
+    pub text: ::prost::alloc::string::String,
+}
+/// Geometry is in desktop coordinates
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Monitor {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(int32, tag = "3")]
+    pub x: i32,
+    #[prost(int32, tag = "4")]
+    pub y: i32,
+    #[prost(uint32, tag = "5")]
+    pub width: u32,
+    #[prost(uint32, tag = "6")]
+    pub height: u32,
+    #[prost(bool, tag = "7")]
+    pub primary: bool,
+    /// Pixels per desktop unit, for HiDPI outputs
+    #[prost(float, tag = "8")]
+    pub scale: f32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScreenLayout {
+    #[prost(message, repeated, tag = "1")]
+    pub monitors: ::prost::alloc::vec::Vec<Monitor>,
+    /// Monitor shown in the frames, unset for the whole desktop
+    #[prost(uint32, optional, tag = "2")]
+    pub selected: ::core::option::Option<u32>,
+}
+/// In pixels of the latest VideoFrame
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct MouseMove {
+    #[prost(int32, tag = "1")]
+    pub x: i32,
+    #[prost(int32, tag = "2")]
+    pub y: i32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct MouseButtonEvent {
+    #[prost(enumeration = "MouseButton", tag = "1")]
+    pub button: i32,
+    #[prost(bool, tag = "2")]
+    pub pressed: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeyEvent {
+    /// KeyboardEvent.key and KeyboardEvent.code, as reported by the browser.
+    /// The physical code is injected when known, for the host layout to
+    /// translate: the key is only used without one.
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub code: ::prost::alloc::string::String,
+    #[prost(bool, tag = "3")]
+    pub pressed: bool,
+}
+/// Typed on the host whatever its layout, e.g. to paste into a login prompt
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TextInput {
+    #[prost(string, tag = "1")]
+    pub text: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InputEvent {
+    #[prost(oneof = "input_event::Event", tags = "1, 2, 3, 4")]
+    pub event: ::core::option::Option<input_event::Event>,
+}
+/// Nested message and enum types in `InputEvent`.
+pub mod input_event {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        MouseMove(super::MouseMove),
+        #[prost(message, tag = "2")]
+        MouseButton(super::MouseButtonEvent),
+        #[prost(message, tag = "3")]
+        Key(super::KeyEvent),
+        #[prost(message, tag = "4")]
+        Text(super::TextInput),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct RequestKeyframe {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SelectMonitor {
+    /// Unset to show the whole desktop
+    #[prost(uint32, optional, tag = "1")]
+    pub id: ::core::option::Option<u32>,
+}
+/// Asks the session holding control to hand it over
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct RequestControl {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GrantControl {
+    /// Session to hand control to
+    #[prost(uint32, tag = "1")]
+    pub session: u32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ReleaseControl {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Control {
+    #[prost(oneof = "control::Action", tags = "1, 2, 3, 4, 5, 6")]
+    pub action: ::core::option::Option<control::Action>,
+}
+/// Nested message and enum types in `Control`.
+pub mod control {
+    #[derive(Clone, Copy, PartialEq, ::prost::Oneof)]
+    pub enum Action {
+        #[prost(message, tag = "1")]
+        RequestKeyframe(super::RequestKeyframe),
+        #[prost(enumeration = "super::Quality", tag = "2")]
+        SetQuality(i32),
+        #[prost(message, tag = "3")]
+        SelectMonitor(super::SelectMonitor),
+        #[prost(message, tag = "4")]
+        RequestControl(super::RequestControl),
+        #[prost(message, tag = "5")]
+        GrantControl(super::GrantControl),
+        #[prost(message, tag = "6")]
+        ReleaseControl(super::ReleaseControl),
+    }
+}
+/// Sent to every session when it joins and whenever control changes hands
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ControlState {
+    /// Session the message is sent to
+    #[prost(uint32, tag = "1")]
+    pub session: u32,
+    #[prost(enumeration = "Role", tag = "2")]
+    pub role: i32,
+    /// Session holding control, unset if none
+    #[prost(uint32, optional, tag = "3")]
+    pub holder: ::core::option::Option<u32>,
+}
+/// Sent to the session holding control when another one asks for it
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ControlRequest {
+    #[prost(uint32, tag = "1")]
+    pub session: u32,
+}
+/// Sent by the agent, relayed to the viewers
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AgentMessage {
+    #[prost(oneof = "agent_message::Message", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub message: ::core::option::Option<agent_message::Message>,
+}
+/// Nested message and enum types in `AgentMessage`.
+pub mod agent_message {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(message, tag = "1")]
+        Hello(super::Hello),
+        #[prost(message, tag = "2")]
+        VideoFrame(super::VideoFrame),
+        #[prost(message, tag = "3")]
+        Cursor(super::CursorUpdate),
+        #[prost(message, tag = "4")]
+        Clipboard(super::Clipboard),
+        #[prost(message, tag = "5")]
+        ScreenLayout(super::ScreenLayout),
+        #[prost(message, tag = "6")]
+        ControlState(super::ControlState),
+        #[prost(message, tag = "7")]
+        ControlRequest(super::ControlRequest),
+    }
+}
+/// Sent by the viewers, relayed to the agent
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ViewerMessage {
+    #[prost(oneof = "viewer_message::Message", tags = "1, 2, 3, 4")]
+    pub message: ::core::option::Option<viewer_message::Message>,
+}
+/// Nested message and enum types in `ViewerMessage`.
+pub mod viewer_message {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(message, tag = "1")]
+        Hello(super::Hello),
+        #[prost(message, tag = "2")]
+        Input(super::InputEvent),
+        #[prost(message, tag = "3")]
+        Clipboard(super::Clipboard),
+        #[prost(message, tag = "4")]
+        Control(super::Control),
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Feature {
+    Unspecified = 0,
+    Clipboard = 1,
+    Cursor = 2,
+    ScreenLayout = 3,
+    Quality = 4,
+    Control = 5,
+    TextInput = 6,
+}
+impl Feature {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "FEATURE_UNSPECIFIED",
+            Self::Clipboard => "FEATURE_CLIPBOARD",
+            Self::Cursor => "FEATURE_CURSOR",
+            Self::ScreenLayout => "FEATURE_SCREEN_LAYOUT",
+            Self::Quality => "FEATURE_QUALITY",
+            Self::Control => "FEATURE_CONTROL",
+            Self::TextInput => "FEATURE_TEXT_INPUT",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "FEATURE_UNSPECIFIED" => Some(Self::Unspecified),
+            "FEATURE_CLIPBOARD" => Some(Self::Clipboard),
+            "FEATURE_CURSOR" => Some(Self::Cursor),
+            "FEATURE_SCREEN_LAYOUT" => Some(Self::ScreenLayout),
+            "FEATURE_QUALITY" => Some(Self::Quality),
+            "FEATURE_CONTROL" => Some(Self::Control),
+            "FEATURE_TEXT_INPUT" => Some(Self::TextInput),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Codec {
+    Unspecified = 0,
+    H264 = 1,
+}
+impl Codec {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "CODEC_UNSPECIFIED",
+            Self::H264 => "CODEC_H264",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CODEC_UNSPECIFIED" => Some(Self::Unspecified),
+            "CODEC_H264" => Some(Self::H264),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MouseButton {
+    Unspecified = 0,
+    Left = 1,
+    Middle = 2,
+    Right = 3,
+    Back = 4,
+    Forward = 5,
+}
+impl MouseButton {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "MOUSE_BUTTON_UNSPECIFIED",
+            Self::Left => "MOUSE_BUTTON_LEFT",
+            Self::Middle => "MOUSE_BUTTON_MIDDLE",
+            Self::Right => "MOUSE_BUTTON_RIGHT",
+            Self::Back => "MOUSE_BUTTON_BACK",
+            Self::Forward => "MOUSE_BUTTON_FORWARD",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "MOUSE_BUTTON_UNSPECIFIED" => Some(Self::Unspecified),
+            "MOUSE_BUTTON_LEFT" => Some(Self::Left),
+            "MOUSE_BUTTON_MIDDLE" => Some(Self::Middle),
+            "MOUSE_BUTTON_RIGHT" => Some(Self::Right),
+            "MOUSE_BUTTON_BACK" => Some(Self::Back),
+            "MOUSE_BUTTON_FORWARD" => Some(Self::Forward),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Quality {
+    Unspecified = 0,
+    Low = 1,
+    Balanced = 2,
+    High = 3,
+}
+impl Quality {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "QUALITY_UNSPECIFIED",
+            Self::Low => "QUALITY_LOW",
+            Self::Balanced => "QUALITY_BALANCED",
+            Self::High => "QUALITY_HIGH",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "QUALITY_UNSPECIFIED" => Some(Self::Unspecified),
+            "QUALITY_LOW" => Some(Self::Low),
+            "QUALITY_BALANCED" => Some(Self::Balanced),
+            "QUALITY_HIGH" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+/// Chosen when the tunnel is created, shared by all the viewers of the tunnel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Role {
+    Unspecified = 0,
+    /// Never sends input
+    ViewOnly = 1,
+    /// Sends input while holding control
+    Controller = 2,
+}
+impl Role {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "ROLE_UNSPECIFIED",
+            Self::ViewOnly => "ROLE_VIEW_ONLY",
+            Self::Controller => "ROLE_CONTROLLER",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ROLE_UNSPECIFIED" => Some(Self::Unspecified),
+            "ROLE_VIEW_ONLY" => Some(Self::ViewOnly),
+            "ROLE_CONTROLLER" => Some(Self::Controller),
+            _ => None,
+        }
+    }
+}
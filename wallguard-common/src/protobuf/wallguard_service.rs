@@ -64,6 +64,141 @@ pub struct SystemResource {
     /// will be a map<string, float> once we have Rust datastore
     #[prost(string, tag = "11")]
     pub temperatures: ::prost::alloc::string::String,
+    /// Only sampled every few ticks, absent otherwise
+    #[prost(message, optional, tag = "12")]
+    pub firewall_health: ::core::option::Option<FirewallHealth>,
+    /// Keyed by interface name
+    #[prost(map = "string, message", tag = "13")]
+    pub interfaces: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        InterfaceStats,
+    >,
+    /// Only sent when enabled by the server, at its own interval
+    #[prost(message, optional, tag = "14")]
+    pub processes: ::core::option::Option<ProcessSnapshot>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InterfaceStats {
+    /// Counters accumulated since the previous sample
+    #[prost(uint64, tag = "1")]
+    pub rx_bytes: u64,
+    #[prost(uint64, tag = "2")]
+    pub tx_bytes: u64,
+    #[prost(uint64, tag = "3")]
+    pub rx_packets: u64,
+    #[prost(uint64, tag = "4")]
+    pub tx_packets: u64,
+    #[prost(uint64, tag = "5")]
+    pub rx_errors: u64,
+    #[prost(uint64, tag = "6")]
+    pub tx_errors: u64,
+    #[prost(uint64, tag = "7")]
+    pub rx_drops: u64,
+    #[prost(uint64, tag = "8")]
+    pub tx_drops: u64,
+    /// Unknown when the link is down or virtual
+    #[prost(uint64, optional, tag = "9")]
+    pub link_speed_mbps: ::core::option::Option<u64>,
+    #[prost(string, tag = "10")]
+    pub oper_state: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FirewallHealth {
+    /// pf state table or nf_conntrack entries
+    #[prost(uint64, tag = "1")]
+    pub states: u64,
+    #[prost(uint64, tag = "2")]
+    pub states_limit: u64,
+    /// mbuf clusters, FreeBSD only
+    #[prost(uint64, tag = "3")]
+    pub mbufs: u64,
+    #[prost(uint64, tag = "4")]
+    pub mbufs_limit: u64,
+    #[prost(message, repeated, tag = "5")]
+    pub gateways: ::prost::alloc::vec::Vec<GatewayHealth>,
+    #[prost(message, repeated, tag = "6")]
+    pub ha: ::prost::alloc::vec::Vec<HaStatus>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GatewayHealth {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(float, tag = "2")]
+    pub latency_ms: f32,
+    #[prost(float, tag = "3")]
+    pub stddev_ms: f32,
+    #[prost(float, tag = "4")]
+    pub loss_percent: f32,
+    /// "online", "delay", "loss" or "down"
+    #[prost(string, tag = "5")]
+    pub status: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HaStatus {
+    /// "carp" or "vrrp"
+    #[prost(string, tag = "1")]
+    pub protocol: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub interface: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub vhid: u32,
+    /// "MASTER", "BACKUP", "INIT" or "FAULT"
+    #[prost(string, tag = "4")]
+    pub state: ::prost::alloc::string::String,
+    /// keepalived instance name, empty for CARP
+    #[prost(string, tag = "5")]
+    pub instance: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProcessSnapshot {
+    /// Sorted by descending CPU usage
+    #[prost(message, repeated, tag = "1")]
+    pub top_cpu: ::prost::alloc::vec::Vec<ProcessInfo>,
+    /// Sorted by descending resident memory
+    #[prost(message, repeated, tag = "2")]
+    pub top_memory: ::prost::alloc::vec::Vec<ProcessInfo>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProcessInfo {
+    #[prost(uint32, tag = "1")]
+    pub pid: u32,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub user: ::prost::alloc::string::String,
+    #[prost(float, tag = "4")]
+    pub cpu_usage: f32,
+    /// Resident set size in bytes
+    #[prost(uint64, tag = "5")]
+    pub memory: u64,
+}
+/// Ingestion streams carry the JWT in the `authorization` metadata
+/// instead of repeating it in every batch.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConnectionsBatch {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub connections: ::prost::alloc::vec::Vec<Connection>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SystemResourcesBatch {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub resources: ::prost::alloc::vec::Vec<SystemResource>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct IngestAck {
+    /// Sequence number of the last batch that has been stored, 0 if none
+    #[prost(uint64, tag = "1")]
+    pub last_sequence: u64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeviceSettingsRequest {
@@ -145,6 +280,7 @@ pub enum ServiceProtocol {
     Ssh = 2,
     Tty = 3,
     Rd = 4,
+    Capture = 5,
 }
 impl ServiceProtocol {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -158,6 +294,7 @@ impl ServiceProtocol {
             Self::Ssh => "SSH",
             Self::Tty => "TTY",
             Self::Rd => "RD",
+            Self::Capture => "CAPTURE",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -168,6 +305,7 @@ impl ServiceProtocol {
             "SSH" => Some(Self::Ssh),
             "TTY" => Some(Self::Tty),
             "RD" => Some(Self::Rd),
+            "CAPTURE" => Some(Self::Capture),
             _ => None,
         }
     }
@@ -371,6 +509,58 @@ pub mod wall_guard_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        pub async fn stream_connections_data(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::ConnectionsBatch>,
+        ) -> std::result::Result<tonic::Response<super::IngestAck>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/wallguard_service.WallGuard/StreamConnectionsData",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "wallguard_service.WallGuard",
+                        "StreamConnectionsData",
+                    ),
+                );
+            self.inner.client_streaming(req, path, codec).await
+        }
+        pub async fn stream_system_resources_data(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::SystemResourcesBatch>,
+        ) -> std::result::Result<tonic::Response<super::IngestAck>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/wallguard_service.WallGuard/StreamSystemResourcesData",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "wallguard_service.WallGuard",
+                        "StreamSystemResourcesData",
+                    ),
+                );
+            self.inner.client_streaming(req, path, codec).await
+        }
         pub async fn handle_config_data(
             &mut self,
             request: impl tonic::IntoRequest<super::ConfigSnapshot>,
@@ -465,6 +655,14 @@ pub mod wall_guard_server {
             &self,
             request: tonic::Request<super::SystemResourcesData>,
         ) -> std::result::Result<tonic::Response<()>, tonic::Status>;
+        async fn stream_connections_data(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::ConnectionsBatch>>,
+        ) -> std::result::Result<tonic::Response<super::IngestAck>, tonic::Status>;
+        async fn stream_system_resources_data(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SystemResourcesBatch>>,
+        ) -> std::result::Result<tonic::Response<super::IngestAck>, tonic::Status>;
         async fn handle_config_data(
             &self,
             request: tonic::Request<super::ConfigSnapshot>,
@@ -741,6 +939,98 @@ pub mod wall_guard_server {
                     };
                     Box::pin(fut)
                 }
+                "/wallguard_service.WallGuard/StreamConnectionsData" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamConnectionsDataSvc<T: WallGuard>(pub Arc<T>);
+                    impl<
+                        T: WallGuard,
+                    > tonic::server::ClientStreamingService<super::ConnectionsBatch>
+                    for StreamConnectionsDataSvc<T> {
+                        type Response = super::IngestAck;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::ConnectionsBatch>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as WallGuard>::stream_connections_data(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamConnectionsDataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/wallguard_service.WallGuard/StreamSystemResourcesData" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamSystemResourcesDataSvc<T: WallGuard>(pub Arc<T>);
+                    impl<
+                        T: WallGuard,
+                    > tonic::server::ClientStreamingService<super::SystemResourcesBatch>
+                    for StreamSystemResourcesDataSvc<T> {
+                        type Response = super::IngestAck;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::SystemResourcesBatch>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as WallGuard>::stream_system_resources_data(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamSystemResourcesDataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/wallguard_service.WallGuard/HandleConfigData" => {
                     #[allow(non_camel_case_types)]
                     struct HandleConfigDataSvc<T: WallGuard>(pub Arc<T>);
@@ -0,0 +1,176 @@
+use crate::protobuf::wallguard_rd::{
+    AgentMessage, Feature, Hello, ViewerMessage, agent_message, control, input_event,
+    viewer_message,
+};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Version of the remote desktop protocol spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest version of the remote desktop protocol still understood.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Largest message accepted over the RD tunnel; keyframes of big screens are the largest ones.
+pub const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+pub fn hello(features: &[Feature]) -> Hello {
+    Hello {
+        protocol_version: PROTOCOL_VERSION,
+        features: features.iter().map(|feature| *feature as i32).collect(),
+    }
+}
+
+/// Agrees on the highest version and the features supported by both ends.
+pub fn negotiate(local: &Hello, remote: &Hello) -> Result<Hello, Error> {
+    let protocol_version = local.protocol_version.min(remote.protocol_version);
+
+    if protocol_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "Unsupported remote desktop protocol version {protocol_version}"
+        ))
+        .handle_err(location!());
+    }
+
+    let features = local
+        .features
+        .iter()
+        .filter(|feature| remote.features.contains(feature))
+        .copied()
+        .collect();
+
+    Ok(Hello {
+        protocol_version,
+        features,
+    })
+}
+
+pub fn has_feature(hello: &Hello, feature: Feature) -> bool {
+    hello.features.contains(&(feature as i32))
+}
+
+/// The feature that must be negotiated for `message` to be sent, if any.
+pub fn agent_message_feature(message: &AgentMessage) -> Option<Feature> {
+    match message.message.as_ref()? {
+        agent_message::Message::Hello(_) | agent_message::Message::VideoFrame(_) => None,
+        agent_message::Message::Cursor(_) => Some(Feature::Cursor),
+        agent_message::Message::Clipboard(_) => Some(Feature::Clipboard),
+        agent_message::Message::ScreenLayout(_) => Some(Feature::ScreenLayout),
+        agent_message::Message::ControlState(_) | agent_message::Message::ControlRequest(_) => {
+            Some(Feature::Control)
+        }
+    }
+}
+
+/// The feature that must be negotiated for `message` to be sent, if any.
+pub fn viewer_message_feature(message: &ViewerMessage) -> Option<Feature> {
+    match message.message.as_ref()? {
+        viewer_message::Message::Hello(_) => None,
+        viewer_message::Message::Input(input) => match input.event.as_ref()? {
+            input_event::Event::Text(_) => Some(Feature::TextInput),
+            _ => None,
+        },
+        viewer_message::Message::Clipboard(_) => Some(Feature::Clipboard),
+        viewer_message::Message::Control(message) => match message.action? {
+            control::Action::RequestKeyframe(_) => None,
+            control::Action::SetQuality(_) => Some(Feature::Quality),
+            control::Action::SelectMonitor(_) => Some(Feature::ScreenLayout),
+            control::Action::RequestControl(_)
+            | control::Action::GrantControl(_)
+            | control::Action::ReleaseControl(_) => Some(Feature::Control),
+        },
+    }
+}
+
+/// Encodes `message` for the RD tunnel, prefixed with its length as u32 LE.
+pub fn encode_framed(message: &impl prost::Message) -> Vec<u8> {
+    let len = message.encoded_len();
+    let mut bytes = Vec::with_capacity(4 + len);
+    bytes.extend_from_slice(&(len as u32).to_le_bytes());
+    message.encode_raw(&mut bytes);
+    bytes
+}
+
+/// Reads the next length-prefixed message from the RD tunnel, without decoding it.
+pub async fn read_framed<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = reader.read_u32_le().await.handle_err(location!())? as usize;
+
+    if len > MAX_MESSAGE_LEN {
+        return Err(format!("Message of {len} bytes exceeds the limit")).handle_err(location!());
+    }
+
+    let mut message = vec![0u8; len];
+    reader
+        .read_exact(&mut message)
+        .await
+        .handle_err(location!())?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::wallguard_rd::{Clipboard, InputEvent, KeyEvent, TextInput};
+    use prost::Message;
+
+    #[test]
+    fn test_negotiate() {
+        let agent = hello(&[Feature::Clipboard, Feature::Cursor]);
+        let viewer = Hello {
+            protocol_version: PROTOCOL_VERSION + 1,
+            features: vec![Feature::Cursor as i32, Feature::Quality as i32],
+        };
+
+        let negotiated = negotiate(&agent, &viewer).unwrap();
+        assert_eq!(negotiated.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(negotiated.features, vec![Feature::Cursor as i32]);
+
+        let outdated = Hello {
+            protocol_version: MIN_PROTOCOL_VERSION - 1,
+            features: vec![],
+        };
+        assert!(negotiate(&agent, &outdated).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_framing() {
+        let message = AgentMessage {
+            message: Some(agent_message::Message::Clipboard(Clipboard {
+                text: String::from("héllo"),
+            })),
+        };
+
+        let bytes = encode_framed(&message);
+        assert_eq!(
+            u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize,
+            bytes.len() - 4
+        );
+
+        let payload = read_framed(&mut bytes.as_slice()).await.unwrap();
+        let decoded = AgentMessage::decode(payload.as_slice()).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(agent_message_feature(&decoded), Some(Feature::Clipboard));
+    }
+
+    #[test]
+    fn test_input_features() {
+        let input = |event| ViewerMessage {
+            message: Some(viewer_message::Message::Input(InputEvent {
+                event: Some(event),
+            })),
+        };
+
+        let key = input(input_event::Event::Key(KeyEvent {
+            key: String::from("q"),
+            code: String::from("KeyA"),
+            pressed: true,
+        }));
+        assert_eq!(viewer_message_feature(&key), None);
+
+        let text = input(input_event::Event::Text(TextInput {
+            text: String::from("Grüße"),
+        }));
+        assert_eq!(viewer_message_feature(&text), Some(Feature::TextInput));
+    }
+}
@@ -5,6 +5,8 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 use tonic::Request;
 use tonic::Streaming;
+use tonic::codec::CompressionEncoding;
+use tonic::codegen::tokio_stream;
 use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::{Channel, Endpoint};
 
@@ -12,8 +14,8 @@ use crate::protobuf::wallguard_commands::{ClientMessage, ServerMessage};
 use crate::protobuf::wallguard_service::ServicesMessage;
 use crate::protobuf::wallguard_service::wall_guard_client::WallGuardClient;
 use crate::protobuf::wallguard_service::{
-    ConfigSnapshot, ConnectionsData, DeviceSettingsRequest, DeviceSettingsResponse,
-    SystemResourcesData,
+    ConfigSnapshot, ConnectionsBatch, ConnectionsData, DeviceSettingsRequest,
+    DeviceSettingsResponse, SystemResourcesBatch, SystemResourcesData,
 };
 
 /// `Endpoint::timeout` only bounds individual requests made over an
@@ -27,6 +29,15 @@ async fn connect_with_timeout(ep: Endpoint) -> Result<Channel, Error> {
     }
 }
 
+fn authorized_request<T>(token: &str, message: T) -> Result<Request<T>, Error> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {token}").parse().handle_err(location!())?,
+    );
+    Ok(request)
+}
+
 #[derive(Clone, Debug)]
 pub struct WallGuardGrpcInterface {
     client: WallGuardClient<Channel>,
@@ -45,7 +56,10 @@ impl WallGuardGrpcInterface {
             connect_with_timeout(ep).await?
         };
 
-        let client = WallGuardClient::new(channel).max_decoding_message_size(50 * 1024 * 1024);
+        let client = WallGuardClient::new(channel)
+            .max_decoding_message_size(50 * 1024 * 1024)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
 
         Ok(Self { client })
     }
@@ -62,7 +76,10 @@ impl WallGuardGrpcInterface {
             connect_with_timeout(ep).await?
         };
 
-        let client = WallGuardClient::new(channel).max_decoding_message_size(50 * 1024 * 1024);
+        let client = WallGuardClient::new(channel)
+            .max_decoding_message_size(50 * 1024 * 1024)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
 
         Ok(Self { client })
     }
@@ -104,6 +121,44 @@ impl WallGuardGrpcInterface {
             .map(|response| response.into_inner())
     }
 
+    /// Streams the batches over a single zstd-compressed call.
+    ///
+    /// Returns the sequence number of the last batch the server acknowledged as stored.
+    pub async fn stream_connections_data(
+        &self,
+        token: &str,
+        batches: Vec<ConnectionsBatch>,
+    ) -> Result<u64, Error> {
+        let request = authorized_request(token, tokio_stream::iter(batches))?;
+
+        self.client
+            .clone()
+            .send_compressed(CompressionEncoding::Zstd)
+            .stream_connections_data(request)
+            .await
+            .handle_err(location!())
+            .map(|response| response.into_inner().last_sequence)
+    }
+
+    /// Streams the batches over a single zstd-compressed call.
+    ///
+    /// Returns the sequence number of the last batch the server acknowledged as stored.
+    pub async fn stream_system_resources_data(
+        &self,
+        token: &str,
+        batches: Vec<SystemResourcesBatch>,
+    ) -> Result<u64, Error> {
+        let request = authorized_request(token, tokio_stream::iter(batches))?;
+
+        self.client
+            .clone()
+            .send_compressed(CompressionEncoding::Zstd)
+            .stream_system_resources_data(request)
+            .await
+            .handle_err(location!())
+            .map(|response| response.into_inner().last_sequence)
+    }
+
     pub async fn get_device_settings(
         &self,
         request: DeviceSettingsRequest,
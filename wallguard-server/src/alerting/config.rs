@@ -0,0 +1,156 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const DEFAULT_COOLDOWN_MINUTES: u64 = 30;
+
+/// The alerting rules and the notification sinks they deliver to.
+///
+/// Loaded from the JSON file pointed to by `ALERT_RULES_FILE`, e.g.:
+///
+/// ```json
+/// {
+///   "sinks": {
+///     "ops": { "type": "webhook", "url": "https://hooks.example.com/wallguard", "secret": "s3cr3t" },
+///     "mail": { "type": "smtp", "host": "smtp.example.com", "from": "wallguard@example.com", "to": ["noc@example.com"] },
+///     "siem": { "type": "syslog", "address": "10.0.0.5:514" }
+///   },
+///   "rules": [
+///     { "name": "Device offline", "condition": { "type": "device_offline", "minutes": 5 }, "sinks": ["ops", "mail"] },
+///     { "name": "High CPU", "condition": { "type": "cpu_above", "percent": 90 }, "sinks": ["siem"], "cooldown_minutes": 60 }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertingConfig {
+    #[serde(default)]
+    pub(crate) sinks: HashMap<String, SinkConfig>,
+    pub(crate) rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleConfig {
+    pub(crate) name: String,
+    pub(crate) condition: Condition,
+    /// Devices the rule applies to; all devices if empty.
+    #[serde(default)]
+    pub(crate) devices: Vec<String>,
+    pub(crate) sinks: Vec<String>,
+    /// Minimum time between two notifications of the rule for the same device.
+    #[serde(default = "default_cooldown_minutes")]
+    pub(crate) cooldown_minutes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    DeviceOffline { minutes: u64 },
+    CpuAbove { percent: f32 },
+    DiskAbove { percent: f32 },
+    ConfigChanged,
+    DraftUnapplied { minutes: u64 },
+    TunnelOpened,
+    StatesAbove { percent: f32 },
+    MbufsAbove { percent: f32 },
+    GatewayDown,
+    HaStateChanged,
+}
+
+impl Condition {
+    /// Whether the condition describes a state, notified once while it holds.
+    pub fn is_state(&self) -> bool {
+        !matches!(
+            self,
+            Condition::ConfigChanged | Condition::TunnelOpened | Condition::HaStateChanged
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Webhook(WebhookConfig),
+    Smtp(SmtpConfig),
+    Syslog(SyslogConfig),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    pub(crate) url: String,
+    /// Key of the HMAC-SHA256 signature sent in the `X-WallGuard-Signature` header.
+    pub(crate) secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    pub(crate) host: String,
+    pub(crate) port: Option<u16>,
+    #[serde(default)]
+    pub(crate) tls: SmtpTls,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) from: String,
+    pub(crate) to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    None,
+    #[default]
+    Starttls,
+    Implicit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyslogConfig {
+    pub(crate) address: String,
+    #[serde(default)]
+    pub(crate) protocol: SyslogProtocol,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+impl AlertingConfig {
+    /// Reads the alerting configuration; alerting is disabled unless `ALERT_RULES_FILE` is set.
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var("ALERT_RULES_FILE").ok()?;
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                log::error!("Failed to read alert rules file {path}: {err}");
+                return None;
+            }
+        };
+
+        let mut config: Self = match serde_json::from_str(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("Failed to parse alert rules file {path}: {err}");
+                return None;
+            }
+        };
+
+        for rule in &mut config.rules {
+            rule.sinks.retain(|sink| {
+                let known = config.sinks.contains_key(sink);
+                if !known {
+                    log::warn!("Alert rule '{}' refers to unknown sink '{sink}'", rule.name);
+                }
+                known
+            });
+        }
+
+        Some(config)
+    }
+}
+
+fn default_cooldown_minutes() -> u64 {
+    DEFAULT_COOLDOWN_MINUTES
+}
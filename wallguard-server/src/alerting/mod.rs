@@ -0,0 +1,183 @@
+mod config;
+mod rules;
+mod sinks;
+
+use crate::app_context::AppContext;
+use config::{AlertingConfig, SinkConfig};
+use futures_util::future::join_all;
+use nullnet_liberror::{ErrorHandler, Location, location};
+use rules::{Alert, RuleEngine};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+pub use rules::AlertEvent;
+
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Entry point of the alerting rules engine.
+///
+/// Server components report what happens through `emit`; the events are evaluated
+/// against the configured rules by `run_alerting`, which delivers the resulting
+/// notifications to the sinks of each rule.
+#[derive(Debug, Clone, Default)]
+pub struct AlertEngine {
+    config: Option<Arc<AlertingConfig>>,
+    sender: Option<UnboundedSender<AlertEvent>>,
+    receiver: Arc<Mutex<Option<UnboundedReceiver<AlertEvent>>>>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self {
+        let Some(config) = AlertingConfig::from_env() else {
+            return Self::default();
+        };
+
+        let (sender, receiver) = unbounded_channel();
+
+        Self {
+            config: Some(Arc::new(config)),
+            sender: Some(sender),
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        }
+    }
+
+    pub fn emit(&self, event: AlertEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+pub async fn run_alerting(context: AppContext) {
+    let engine = &context.alert_engine;
+
+    let receiver = engine
+        .receiver
+        .lock()
+        .ok()
+        .and_then(|mut receiver| receiver.take());
+
+    let (Some(config), Some(mut receiver)) = (engine.config.clone(), receiver) else {
+        log::info!("Alerting is disabled");
+        return std::future::pending().await;
+    };
+
+    log::info!("Alerting enabled with {} rules", config.rules.len());
+
+    // A hung webhook would keep its alert pending, and so muted, forever
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .handle_err(location!())
+    else {
+        return std::future::pending().await;
+    };
+    let mut rules = RuleEngine::new(config.rules.clone());
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    let (delivered_tx, mut delivered_rx) = unbounded_channel::<(usize, String, bool)>();
+
+    seed_offline_devices(&context, &mut rules).await;
+
+    loop {
+        let alerts = tokio::select! {
+            Some(event) = receiver.recv() => rules.handle(&event, now_ms()),
+            Some((rule_index, device_id, sent)) = delivered_rx.recv() => {
+                rules.delivered(rule_index, &device_id, sent, now_ms());
+                continue;
+            }
+            _ = ticker.tick() => rules.tick(now_ms()),
+        };
+
+        for alert in alerts {
+            log::info!(
+                "Alert '{}' for device {}: {}",
+                alert.rule,
+                alert.device_id,
+                alert.message
+            );
+
+            let sinks: Vec<(String, SinkConfig)> = rules.rules()[alert.rule_index]
+                .sinks
+                .iter()
+                .filter_map(|name| Some((name.clone(), config.sinks.get(name)?.clone())))
+                .collect();
+
+            let client = client.clone();
+            let delivered_tx = delivered_tx.clone();
+
+            tokio::spawn(async move {
+                let sent = deliver(&client, &sinks, &alert).await;
+                let _ = delivered_tx.send((alert.rule_index, alert.device_id, sent));
+            });
+        }
+    }
+}
+
+/// Delivers the alert to every sink, returning whether it reached any of them.
+async fn deliver(client: &reqwest::Client, sinks: &[(String, SinkConfig)], alert: &Alert) -> bool {
+    if sinks.is_empty() {
+        return true;
+    }
+
+    let results = join_all(sinks.iter().map(|(name, sink)| async move {
+        let result = sinks::deliver(client, sink, alert).await;
+        if let Err(err) = &result {
+            log::error!(
+                "Failed to deliver alert '{}' to sink '{name}': {}",
+                alert.rule,
+                err.to_str()
+            );
+        }
+        result.is_ok()
+    }))
+    .await;
+
+    results.into_iter().any(|sent| sent)
+}
+
+/// Starts tracking the devices that were already offline, from their last heartbeat.
+async fn seed_offline_devices(context: &AppContext, rules: &mut RuleEngine) {
+    let result = async {
+        let token = context.root_token_provider.get().await?;
+        let devices = context
+            .datastore
+            .obtain_offline_devices(&token.jwt, true)
+            .await?;
+
+        let mut last_seen = Vec::with_capacity(devices.len());
+        for device_id in devices {
+            let heartbeat = context
+                .datastore
+                .obtain_last_heartbeat(&token.jwt, &device_id, true)
+                .await?;
+            let since_ms = heartbeat
+                .and_then(|heartbeat| {
+                    chrono::DateTime::parse_from_rfc3339(&heartbeat.timestamp).ok()
+                })
+                .map_or_else(now_ms, |at| at.timestamp_millis().max(0) as u64);
+            last_seen.push((device_id, since_ms));
+        }
+
+        Ok::<_, nullnet_liberror::Error>(last_seen)
+    }
+    .await;
+
+    match result {
+        Ok(last_seen) => {
+            log::info!("Tracking {} devices already offline", last_seen.len());
+            for (device_id, since_ms) in last_seen {
+                rules.seed_offline(&device_id, since_ms);
+            }
+        }
+        Err(err) => log::error!("Failed to obtain the offline devices: {}", err.to_str()),
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,497 @@
+use super::config::{Condition, RuleConfig};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use wallguard_common::protobuf::wallguard_service::FirewallHealth;
+
+/// Something that happened on the server that alert rules may react to.
+#[derive(Debug, Clone)]
+pub enum AlertEvent {
+    DeviceStatus {
+        device_id: String,
+        online: bool,
+    },
+    Resources {
+        device_id: String,
+        cpu_percent: f32,
+        disk_percent: f32,
+    },
+    Config {
+        device_id: String,
+        digest: String,
+        changed: bool,
+        draft: bool,
+    },
+    TunnelOpened {
+        device_id: String,
+        tunnel_id: String,
+        tunnel_type: String,
+    },
+    FirewallHealth {
+        device_id: String,
+        health: FirewallHealth,
+    },
+}
+
+/// A notification produced by a rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    #[serde(skip)]
+    pub(crate) rule_index: usize,
+    pub(crate) rule: String,
+    pub(crate) device_id: String,
+    pub(crate) title: String,
+    pub(crate) message: String,
+    pub(crate) timestamp: String,
+    pub(crate) details: Value,
+}
+
+/// Evaluates the rules against the incoming events.
+///
+/// Conditions describing a state (offline, above a threshold, unapplied draft) only
+/// notify when they start holding and are re-armed once they clear; every notification
+/// of a rule for a device is further throttled by the rule cooldown.
+///
+/// An alert is pending until `delivered` reports its outcome: only a notification that
+/// was actually sent marks the state as firing and starts the cooldown.
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<RuleConfig>,
+    offline_since: HashMap<String, u64>,
+    draft_since: HashMap<String, (u64, String)>,
+    ha_states: HashMap<(String, String), String>,
+    pending: HashSet<(usize, String)>,
+    firing: HashSet<(usize, String)>,
+    last_notified: HashMap<(usize, String), u64>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: Vec<RuleConfig>) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn rules(&self) -> &[RuleConfig] {
+        &self.rules
+    }
+
+    /// Records a device known to be offline since `since_ms`, unless already tracked.
+    pub fn seed_offline(&mut self, device_id: &str, since_ms: u64) {
+        self.offline_since
+            .entry(device_id.to_string())
+            .or_insert(since_ms);
+    }
+
+    /// Reports whether the notification of a pending alert reached any sink.
+    pub fn delivered(&mut self, rule_index: usize, device_id: &str, sent: bool, now_ms: u64) {
+        let key = (rule_index, device_id.to_string());
+        let still_holding = self.pending.remove(&key);
+
+        if !sent {
+            return;
+        }
+
+        self.last_notified.insert(key.clone(), now_ms);
+
+        // A state that cleared while the notification was in flight stays re-armed
+        if still_holding && self.rules[rule_index].condition.is_state() {
+            self.firing.insert(key);
+        }
+    }
+
+    pub fn handle(&mut self, event: &AlertEvent, now_ms: u64) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        match event {
+            AlertEvent::DeviceStatus { device_id, online } => {
+                if *online {
+                    self.offline_since.remove(device_id);
+                    self.clear(device_id, |c| matches!(c, Condition::DeviceOffline { .. }));
+                } else {
+                    self.offline_since
+                        .entry(device_id.clone())
+                        .or_insert(now_ms);
+                }
+            }
+            AlertEvent::Resources {
+                device_id,
+                cpu_percent,
+                disk_percent,
+            } => {
+                for index in 0..self.rules.len() {
+                    if !self.applies(index, device_id) {
+                        continue;
+                    }
+
+                    let (kind, value, threshold) = match self.rules[index].condition {
+                        Condition::CpuAbove { percent } => ("CPU", *cpu_percent, percent),
+                        Condition::DiskAbove { percent } => ("Disk", *disk_percent, percent),
+                        _ => continue,
+                    };
+
+                    if value > threshold {
+                        let alert = self.alert(
+                            index,
+                            device_id,
+                            format!("{kind} usage above {threshold}%"),
+                            format!("{kind} usage of device {device_id} is {value:.1}%"),
+                            json!({ "value": value, "threshold": threshold }),
+                        );
+                        alerts.extend(self.fire_state(alert, now_ms));
+                    } else {
+                        self.rearm(index, device_id);
+                    }
+                }
+            }
+            AlertEvent::Config {
+                device_id,
+                digest,
+                changed,
+                draft,
+            } => {
+                if *changed {
+                    for index in 0..self.rules.len() {
+                        if self.rules[index].condition == Condition::ConfigChanged
+                            && self.applies(index, device_id)
+                        {
+                            let alert = self.alert(
+                                index,
+                                device_id,
+                                String::from("Configuration changed"),
+                                format!("The configuration of device {device_id} changed"),
+                                json!({ "digest": digest, "draft": draft }),
+                            );
+                            alerts.extend(self.fire_event(alert, now_ms));
+                        }
+                    }
+                }
+
+                if *draft {
+                    self.draft_since
+                        .entry(device_id.clone())
+                        .or_insert_with(|| (now_ms, digest.clone()));
+                } else {
+                    self.draft_since.remove(device_id);
+                    self.clear(device_id, |c| matches!(c, Condition::DraftUnapplied { .. }));
+                }
+            }
+            AlertEvent::TunnelOpened {
+                device_id,
+                tunnel_id,
+                tunnel_type,
+            } => {
+                for index in 0..self.rules.len() {
+                    if self.rules[index].condition == Condition::TunnelOpened
+                        && self.applies(index, device_id)
+                    {
+                        let alert = self.alert(
+                            index,
+                            device_id,
+                            format!("{} tunnel opened", tunnel_type.to_uppercase()),
+                            format!("A {tunnel_type} tunnel was opened to device {device_id}"),
+                            json!({ "tunnel_id": tunnel_id, "tunnel_type": tunnel_type }),
+                        );
+                        alerts.extend(self.fire_event(alert, now_ms));
+                    }
+                }
+            }
+            AlertEvent::FirewallHealth { device_id, health } => {
+                alerts.extend(self.handle_firewall_health(device_id, health, now_ms));
+            }
+        }
+
+        alerts
+    }
+
+    fn handle_firewall_health(
+        &mut self,
+        device_id: &str,
+        health: &FirewallHealth,
+        now_ms: u64,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        let ha_changes: Vec<(String, String, String)> = health
+            .ha
+            .iter()
+            .filter_map(|ha| {
+                let key = format!("{} {} vhid {}", ha.protocol, ha.interface, ha.vhid);
+                let previous = self
+                    .ha_states
+                    .insert((device_id.to_string(), key.clone()), ha.state.clone())?;
+                (previous != ha.state).then(|| (key, previous, ha.state.clone()))
+            })
+            .collect();
+
+        let down_gateways: Vec<&str> = health
+            .gateways
+            .iter()
+            .filter(|gateway| gateway.status == "down")
+            .map(|gateway| gateway.name.as_str())
+            .collect();
+
+        for index in 0..self.rules.len() {
+            if !self.applies(index, device_id) {
+                continue;
+            }
+
+            match self.rules[index].condition {
+                Condition::StatesAbove { percent } | Condition::MbufsAbove { percent } => {
+                    let (kind, used, limit) = match self.rules[index].condition {
+                        Condition::StatesAbove { .. } => {
+                            ("State table", health.states, health.states_limit)
+                        }
+                        _ => ("Mbuf", health.mbufs, health.mbufs_limit),
+                    };
+
+                    if limit == 0 {
+                        continue;
+                    }
+
+                    let value = used as f32 * 100.0 / limit as f32;
+                    if value > percent {
+                        let alert = self.alert(
+                            index,
+                            device_id,
+                            format!("{kind} usage above {percent}%"),
+                            format!(
+                                "{kind} usage of device {device_id} is {used}/{limit} ({value:.1}%)"
+                            ),
+                            json!({ "used": used, "limit": limit, "threshold": percent }),
+                        );
+                        alerts.extend(self.fire_state(alert, now_ms));
+                    } else {
+                        self.rearm(index, device_id);
+                    }
+                }
+                Condition::GatewayDown => {
+                    if down_gateways.is_empty() {
+                        self.rearm(index, device_id);
+                        continue;
+                    }
+
+                    let alert = self.alert(
+                        index,
+                        device_id,
+                        String::from("Gateway down"),
+                        format!(
+                            "Gateways of device {device_id} are down: {}",
+                            down_gateways.join(", ")
+                        ),
+                        json!({ "gateways": health.gateways }),
+                    );
+                    alerts.extend(self.fire_state(alert, now_ms));
+                }
+                Condition::HaStateChanged => {
+                    for (key, previous, state) in &ha_changes {
+                        let alert = self.alert(
+                            index,
+                            device_id,
+                            format!("HA state changed to {state}"),
+                            format!("{key} of device {device_id} went from {previous} to {state}"),
+                            json!({ "address": key, "previous": previous, "state": state }),
+                        );
+                        alerts.extend(self.fire_event(alert, now_ms));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        alerts
+    }
+
+    /// Evaluates the conditions that depend on how long a state has been holding.
+    pub fn tick(&mut self, now_ms: u64) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for index in 0..self.rules.len() {
+            let (since, minutes) = match self.rules[index].condition {
+                Condition::DeviceOffline { minutes } => (
+                    self.offline_since
+                        .iter()
+                        .map(|(device, since)| (device.clone(), *since, None))
+                        .collect::<Vec<_>>(),
+                    minutes,
+                ),
+                Condition::DraftUnapplied { minutes } => (
+                    self.draft_since
+                        .iter()
+                        .map(|(device, (since, digest))| {
+                            (device.clone(), *since, Some(digest.clone()))
+                        })
+                        .collect(),
+                    minutes,
+                ),
+                _ => continue,
+            };
+
+            for (device_id, since, digest) in since {
+                let elapsed_minutes = now_ms.saturating_sub(since) / 60_000;
+                if elapsed_minutes < minutes || !self.applies(index, &device_id) {
+                    continue;
+                }
+
+                let alert = match digest {
+                    None => self.alert(
+                        index,
+                        &device_id,
+                        String::from("Device offline"),
+                        format!("Device {device_id} has been offline for {elapsed_minutes} minutes"),
+                        json!({ "offline_minutes": elapsed_minutes }),
+                    ),
+                    Some(digest) => self.alert(
+                        index,
+                        &device_id,
+                        String::from("Configuration draft not applied"),
+                        format!(
+                            "Device {device_id} has had an unapplied configuration draft for {elapsed_minutes} minutes"
+                        ),
+                        json!({ "digest": digest, "draft_minutes": elapsed_minutes }),
+                    ),
+                };
+
+                alerts.extend(self.fire_state(alert, now_ms));
+            }
+        }
+
+        alerts
+    }
+
+    fn applies(&self, index: usize, device_id: &str) -> bool {
+        let devices = &self.rules[index].devices;
+        devices.is_empty() || devices.iter().any(|d| d == device_id)
+    }
+
+    fn alert(
+        &self,
+        index: usize,
+        device_id: &str,
+        title: String,
+        message: String,
+        details: Value,
+    ) -> Alert {
+        Alert {
+            rule_index: index,
+            rule: self.rules[index].name.clone(),
+            device_id: device_id.to_string(),
+            title,
+            message,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            details,
+        }
+    }
+
+    fn fire_state(&mut self, alert: Alert, now_ms: u64) -> Option<Alert> {
+        let key = (alert.rule_index, alert.device_id.clone());
+        if self.firing.contains(&key) {
+            return None;
+        }
+
+        self.fire_event(alert, now_ms)
+    }
+
+    fn fire_event(&mut self, alert: Alert, now_ms: u64) -> Option<Alert> {
+        let key = (alert.rule_index, alert.device_id.clone());
+        let cooldown_ms = self.rules[alert.rule_index].cooldown_minutes * 60_000;
+
+        if self.pending.contains(&key)
+            || self
+                .last_notified
+                .get(&key)
+                .is_some_and(|at| now_ms.saturating_sub(*at) < cooldown_ms)
+        {
+            return None;
+        }
+
+        self.pending.insert(key);
+        Some(alert)
+    }
+
+    fn rearm(&mut self, index: usize, device_id: &str) {
+        let key = (index, device_id.to_string());
+        self.firing.remove(&key);
+        self.pending.remove(&key);
+    }
+
+    fn clear(&mut self, device_id: &str, condition: fn(&Condition) -> bool) {
+        let rules = &self.rules;
+        let holds = |(index, device): &(usize, String)| {
+            device == device_id && condition(&rules[*index].condition)
+        };
+        self.firing.retain(|key| !holds(key));
+        self.pending.retain(|key| !holds(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(condition: Condition, cooldown_minutes: u64) -> RuleConfig {
+        RuleConfig {
+            name: String::from("rule"),
+            condition,
+            devices: vec![],
+            sinks: vec![],
+            cooldown_minutes,
+        }
+    }
+
+    fn resources(cpu_percent: f32) -> AlertEvent {
+        AlertEvent::Resources {
+            device_id: String::from("dev"),
+            cpu_percent,
+            disk_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_state_conditions_are_deduplicated_and_throttled() {
+        let mut engine = RuleEngine::new(vec![
+            rule(Condition::CpuAbove { percent: 90.0 }, 10),
+            rule(Condition::DeviceOffline { minutes: 5 }, 0),
+        ]);
+
+        assert_eq!(engine.handle(&resources(95.0), 0).len(), 1);
+        // still pending delivery
+        assert!(engine.handle(&resources(96.0), 1_000).is_empty());
+        engine.delivered(0, "dev", true, 1_000);
+        // still above the threshold: already notified
+        assert!(engine.handle(&resources(97.0), 60_000).is_empty());
+        // cleared and above again, but within the cooldown
+        assert!(engine.handle(&resources(50.0), 120_000).is_empty());
+        assert!(engine.handle(&resources(95.0), 180_000).is_empty());
+        // cleared and above again after the cooldown
+        assert!(engine.handle(&resources(50.0), 700_000).is_empty());
+        assert_eq!(engine.handle(&resources(95.0), 710_000).len(), 1);
+
+        let offline = AlertEvent::DeviceStatus {
+            device_id: String::from("dev"),
+            online: false,
+        };
+        assert!(engine.handle(&offline, 0).is_empty());
+        assert!(engine.tick(4 * 60_000).is_empty());
+        assert_eq!(engine.tick(5 * 60_000).len(), 1);
+        assert!(engine.tick(6 * 60_000).is_empty());
+    }
+
+    #[test]
+    fn test_failed_deliveries_are_retried() {
+        let mut engine = RuleEngine::new(vec![rule(Condition::DeviceOffline { minutes: 5 }, 60)]);
+
+        // Known to be offline since before the server started
+        engine.seed_offline("dev", 0);
+        let alerts = engine.tick(10 * 60_000);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].details["offline_minutes"], 10);
+
+        engine.delivered(0, "dev", false, 10 * 60_000);
+        assert_eq!(engine.tick(11 * 60_000).len(), 1);
+
+        engine.delivered(0, "dev", true, 11 * 60_000);
+        assert!(engine.tick(12 * 60_000).is_empty());
+    }
+}
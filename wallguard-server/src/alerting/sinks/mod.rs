@@ -0,0 +1,19 @@
+mod smtp;
+mod syslog;
+mod webhook;
+
+use super::config::SinkConfig;
+use super::rules::Alert;
+use nullnet_liberror::Error;
+
+pub async fn deliver(
+    client: &reqwest::Client,
+    sink: &SinkConfig,
+    alert: &Alert,
+) -> Result<(), Error> {
+    match sink {
+        SinkConfig::Webhook(config) => webhook::send(client, config, alert).await,
+        SinkConfig::Smtp(config) => smtp::send(config, alert).await,
+        SinkConfig::Syslog(config) => syslog::send(config, alert).await,
+    }
+}
@@ -0,0 +1,69 @@
+use crate::alerting::config::{SmtpConfig, SmtpTls};
+use crate::alerting::rules::Alert;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Mails the alert to the configured recipients.
+pub async fn send(config: &SmtpConfig, alert: &Alert) -> Result<(), Error> {
+    let message = build_message(config, alert)?;
+
+    transport(config)?
+        .send(message)
+        .await
+        .handle_err(location!())?;
+
+    Ok(())
+}
+
+fn transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, Error> {
+    let mut builder = match config.tls {
+        SmtpTls::None => {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(config.host.as_str())
+        }
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .handle_err(location!())?,
+        SmtpTls::Implicit => {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host).handle_err(location!())?
+        }
+    };
+
+    builder = builder.timeout(Some(TIMEOUT));
+
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+
+    if let Some(username) = &config.username {
+        let password = config.password.clone().unwrap_or_default();
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+
+    Ok(builder.build())
+}
+
+fn build_message(config: &SmtpConfig, alert: &Alert) -> Result<Message, Error> {
+    let body = format!(
+        "{}\n\nRule: {}\nDevice: {}\nTime: {}\n\n{}",
+        alert.message,
+        alert.rule,
+        alert.device_id,
+        alert.timestamp,
+        serde_json::to_string_pretty(&alert.details).unwrap_or_default()
+    );
+
+    let mut builder = Message::builder()
+        .from(config.from.parse().handle_err(location!())?)
+        .subject(format!("[WallGuard] {}", alert.title))
+        .header(ContentType::TEXT_PLAIN);
+
+    for recipient in &config.to {
+        builder = builder.to(recipient.parse().handle_err(location!())?);
+    }
+
+    builder.body(body).handle_err(location!())
+}
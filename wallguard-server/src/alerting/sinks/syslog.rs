@@ -0,0 +1,61 @@
+use crate::alerting::config::{SyslogConfig, SyslogProtocol};
+use crate::alerting::rules::Alert;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+
+const APP_NAME: &str = "wallguard-server";
+// local0.warning
+const PRIORITY: u8 = 16 * 8 + 4;
+const TCP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends the alert as an RFC 5424 message, with octet-counting framing over TCP.
+pub async fn send(config: &SyslogConfig, alert: &Alert) -> Result<(), Error> {
+    let message = format_message(alert);
+
+    match config.protocol {
+        SyslogProtocol::Udp => {
+            let socket = UdpSocket::bind("0.0.0.0:0").await.handle_err(location!())?;
+            socket
+                .send_to(message.as_bytes(), &config.address)
+                .await
+                .handle_err(location!())?;
+        }
+        SyslogProtocol::Tcp => {
+            let frame = format!("{} {message}", message.len());
+            tokio::time::timeout(TCP_TIMEOUT, send_tcp(&config.address, &frame))
+                .await
+                .handle_err(location!())??;
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_tcp(address: &str, frame: &str) -> Result<(), Error> {
+    let mut stream = TcpStream::connect(address).await.handle_err(location!())?;
+    stream
+        .write_all(frame.as_bytes())
+        .await
+        .handle_err(location!())?;
+    stream.shutdown().await.handle_err(location!())
+}
+
+fn format_message(alert: &Alert) -> String {
+    format!(
+        "<{PRIORITY}>1 {} - {APP_NAME} {} alert [wallguard@32473 rule=\"{}\" device=\"{}\"] {}",
+        alert.timestamp,
+        std::process::id(),
+        escape_param(&alert.rule),
+        escape_param(&alert.device_id),
+        alert.message
+    )
+}
+
+fn escape_param(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
@@ -0,0 +1,40 @@
+use crate::alerting::config::WebhookConfig;
+use crate::alerting::rules::Alert;
+use hmac::{Hmac, Mac};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use sha2::Sha256;
+
+const SIGNATURE_HEADER: &str = "X-WallGuard-Signature";
+
+/// POSTs the alert as JSON, signed with HMAC-SHA256 when a secret is configured.
+pub async fn send(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    alert: &Alert,
+) -> Result<(), Error> {
+    let body = serde_json::to_vec(alert).handle_err(location!())?;
+
+    let mut request = client
+        .post(&config.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+    if let Some(secret) = &config.secret {
+        request = request.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, &body)?));
+    }
+
+    request
+        .body(body)
+        .send()
+        .await
+        .handle_err(location!())?
+        .error_for_status()
+        .handle_err(location!())?;
+
+    Ok(())
+}
+
+fn sign(secret: &str, body: &[u8]) -> Result<String, Error> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).handle_err(location!())?;
+    mac.update(body);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
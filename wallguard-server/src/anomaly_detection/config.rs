@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct AnomalyDetectionConfig {
+    /// Length of the sliding window the scans and the fan-out are detected over.
+    pub(crate) window: Duration,
+    /// Distinct destination hosts contacted on the same port by one source.
+    pub(crate) horizontal_scan_hosts: usize,
+    /// Distinct ports contacted on the same destination host by one source.
+    pub(crate) vertical_scan_ports: usize,
+    /// Distinct destination hosts contacted by one source.
+    pub(crate) fan_out_hosts: usize,
+    /// How many times the learned per-window volume must be exceeded to report a spike.
+    pub(crate) volume_factor: f64,
+    /// Period during which the outbound ports of a device are learned without being reported.
+    pub(crate) learning_period: Duration,
+    /// Minimum time between two reports of the same finding.
+    pub(crate) cooldown: Duration,
+}
+
+impl Default for AnomalyDetectionConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            horizontal_scan_hosts: 50,
+            vertical_scan_ports: 100,
+            fan_out_hosts: 250,
+            volume_factor: 5.0,
+            learning_period: Duration::from_secs(24 * 60 * 60),
+            cooldown: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl AnomalyDetectionConfig {
+    /// Reads the anomaly detection configuration from the environment.
+    ///
+    /// Detection is disabled unless `ANOMALY_DETECTION_ENABLED` is `true`;
+    /// every threshold falls back to its default when unset or invalid.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("ANOMALY_DETECTION_ENABLED")
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "true" | "1"))
+            .unwrap_or(false);
+
+        if !enabled {
+            return None;
+        }
+
+        let default = Self::default();
+
+        Some(Self {
+            window: read_env("ANOMALY_WINDOW_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(default.window),
+            horizontal_scan_hosts: read_env("ANOMALY_HORIZONTAL_SCAN_HOSTS")
+                .unwrap_or(default.horizontal_scan_hosts),
+            vertical_scan_ports: read_env("ANOMALY_VERTICAL_SCAN_PORTS")
+                .unwrap_or(default.vertical_scan_ports),
+            fan_out_hosts: read_env("ANOMALY_FAN_OUT_HOSTS").unwrap_or(default.fan_out_hosts),
+            volume_factor: read_env("ANOMALY_VOLUME_FACTOR").unwrap_or(default.volume_factor),
+            learning_period: read_env("ANOMALY_LEARNING_HOURS")
+                .map(|hours: u64| Duration::from_secs(hours * 60 * 60))
+                .unwrap_or(default.learning_period),
+            cooldown: read_env("ANOMALY_COOLDOWN_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(default.cooldown),
+        })
+    }
+}
+
+fn read_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let raw = std::env::var(name).ok()?;
+
+    match raw.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            log::warn!("{name} is set to {raw:?}, which is not valid; using the default");
+            None
+        }
+    }
+}
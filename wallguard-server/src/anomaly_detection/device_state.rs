@@ -0,0 +1,467 @@
+use crate::anomaly_detection::config::AnomalyDetectionConfig;
+use nullnet_libipinfo::get_ip_to_lookup;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+const MAX_WINDOW_FLOWS: usize = 50_000;
+const MAX_ATTACHED_FLOWS: usize = 20;
+const MAX_REPORTED_FINDINGS: usize = 10_000;
+const BASELINE_MIN_WINDOWS: u32 = 10;
+const BASELINE_ALPHA: f64 = 0.1;
+const MIN_SPIKE_BYTES: u64 = 10 * 1024 * 1024;
+/// Start of the range operating systems pick client ports from (the lowest default, Linux's).
+const EPHEMERAL_PORT_START: u16 = 32768;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    HorizontalScan,
+    VerticalScan,
+    FanOut,
+    VolumeSpike,
+    NewOutboundPort,
+}
+
+impl FindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FindingKind::HorizontalScan => "horizontal_port_scan",
+            FindingKind::VerticalScan => "vertical_port_scan",
+            FindingKind::FanOut => "fan_out",
+            FindingKind::VolumeSpike => "volume_spike",
+            FindingKind::NewOutboundPort => "new_outbound_port",
+        }
+    }
+
+    pub fn severity(self) -> i32 {
+        match self {
+            FindingKind::HorizontalScan | FindingKind::VerticalScan => 2,
+            FindingKind::FanOut | FindingKind::VolumeSpike => 3,
+            FindingKind::NewOutboundPort => 4,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub key: String,
+    pub title: String,
+    pub description: String,
+    pub details: Value,
+    pub flows: Vec<Connection>,
+}
+
+#[derive(Debug)]
+struct FlowSample {
+    at_ms: u64,
+    source_ip: IpAddr,
+    destination_ip: IpAddr,
+    destination_port: u16,
+    bytes: u64,
+    connection: Connection,
+}
+
+impl FlowSample {
+    fn new(connection: &Connection, at_ms: u64) -> Option<Self> {
+        Some(Self {
+            at_ms,
+            source_ip: connection.source_ip.parse().ok()?,
+            destination_ip: connection.destination_ip.parse().ok()?,
+            destination_port: connection.destination_port.unwrap_or(0) as u16,
+            bytes: connection.total_byte,
+            connection: connection.clone(),
+        })
+    }
+
+    fn protocol(&self) -> &str {
+        &self.connection.protocol
+    }
+
+    fn has_ports(&self) -> bool {
+        matches!(self.protocol(), "tcp" | "udp") && self.destination_port != 0
+    }
+
+    /// Whether the flow looks like it was opened by its source.
+    ///
+    /// Flows carry no TCP flags, so the replies of a busy server are told apart by
+    /// their destination port, which is then a client port of the ephemeral range.
+    fn is_initiator(&self) -> bool {
+        self.has_ports() && self.destination_port < EPHEMERAL_PORT_START
+    }
+}
+
+#[derive(Debug, Default)]
+struct VolumeBaseline {
+    window_start_ms: u64,
+    window_bytes: u64,
+    baseline: f64,
+    windows: u32,
+}
+
+/// Per-device analytics state: the flows of the sliding window,
+/// the learned traffic volume and the learned outbound ports.
+#[derive(Debug)]
+pub struct DeviceState {
+    flows: VecDeque<FlowSample>,
+    first_seen_ms: u64,
+    known_ports: HashSet<(String, u16)>,
+    volume: VolumeBaseline,
+    reported: HashMap<(FindingKind, String), u64>,
+}
+
+impl DeviceState {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            flows: VecDeque::new(),
+            first_seen_ms: now_ms,
+            known_ports: HashSet::new(),
+            volume: VolumeBaseline {
+                window_start_ms: now_ms,
+                ..Default::default()
+            },
+            reported: HashMap::new(),
+        }
+    }
+
+    /// Adds the flows of a new batch, returning the findings not reported yet.
+    pub fn observe(
+        &mut self,
+        config: &AnomalyDetectionConfig,
+        now_ms: u64,
+        connections: &[Connection],
+    ) -> Vec<Finding> {
+        let window_ms = config.window.as_millis() as u64;
+        let learning = now_ms < self.first_seen_ms + config.learning_period.as_millis() as u64;
+
+        self.roll_volume_window(now_ms, window_ms);
+
+        let mut findings = Vec::new();
+
+        for connection in connections {
+            let Some(sample) = FlowSample::new(connection, now_ms) else {
+                continue;
+            };
+
+            self.volume.window_bytes += sample.bytes;
+
+            if let Some(finding) = self.check_outbound_port(&sample, learning) {
+                findings.push(finding);
+            }
+
+            self.flows.push_back(sample);
+        }
+
+        while self
+            .flows
+            .front()
+            .is_some_and(|s| s.at_ms + window_ms < now_ms || self.flows.len() > MAX_WINDOW_FLOWS)
+        {
+            self.flows.pop_front();
+        }
+
+        findings.extend(self.detect_scans(config));
+        findings.extend(self.check_volume(config));
+
+        findings.retain(|finding| self.should_report(finding, now_ms, config));
+        findings
+    }
+
+    fn detect_scans(&self, config: &AnomalyDetectionConfig) -> Vec<Finding> {
+        let mut horizontal: HashMap<(IpAddr, &str, u16), HashSet<IpAddr>> = HashMap::new();
+        let mut vertical: HashMap<(IpAddr, IpAddr), HashSet<u16>> = HashMap::new();
+        let mut fan_out: HashMap<IpAddr, HashSet<IpAddr>> = HashMap::new();
+
+        for sample in &self.flows {
+            if sample.has_ports() {
+                horizontal
+                    .entry((sample.source_ip, sample.protocol(), sample.destination_port))
+                    .or_default()
+                    .insert(sample.destination_ip);
+            }
+            if sample.is_initiator() {
+                vertical
+                    .entry((sample.source_ip, sample.destination_ip))
+                    .or_default()
+                    .insert(sample.destination_port);
+            }
+            fan_out
+                .entry(sample.source_ip)
+                .or_default()
+                .insert(sample.destination_ip);
+        }
+
+        let window_secs = config.window.as_secs();
+        let mut findings = Vec::new();
+
+        for ((source, protocol, port), hosts) in horizontal {
+            if hosts.len() < config.horizontal_scan_hosts {
+                continue;
+            }
+            findings.push(Finding {
+                kind: FindingKind::HorizontalScan,
+                key: format!("{source}/{protocol}/{port}"),
+                title: format!("Horizontal port scan from {source}"),
+                description: format!(
+                    "{source} contacted {} hosts on {protocol} port {port} within {window_secs}s",
+                    hosts.len()
+                ),
+                details: json!({
+                    "source_ip": source.to_string(),
+                    "protocol": protocol,
+                    "destination_port": port,
+                    "destination_hosts": hosts.len(),
+                }),
+                flows: self.flows_matching(|s| {
+                    s.source_ip == source && s.destination_port == port && s.protocol() == protocol
+                }),
+            });
+        }
+
+        for ((source, destination), ports) in vertical {
+            if ports.len() < config.vertical_scan_ports {
+                continue;
+            }
+            findings.push(Finding {
+                kind: FindingKind::VerticalScan,
+                key: format!("{source}/{destination}"),
+                title: format!("Vertical port scan of {destination} from {source}"),
+                description: format!(
+                    "{source} contacted {} ports of {destination} within {window_secs}s",
+                    ports.len()
+                ),
+                details: json!({
+                    "source_ip": source.to_string(),
+                    "destination_ip": destination.to_string(),
+                    "destination_ports": ports.len(),
+                }),
+                flows: self.flows_matching(|s| {
+                    s.source_ip == source && s.destination_ip == destination && s.is_initiator()
+                }),
+            });
+        }
+
+        for (source, hosts) in fan_out {
+            if hosts.len() < config.fan_out_hosts {
+                continue;
+            }
+            findings.push(Finding {
+                kind: FindingKind::FanOut,
+                key: source.to_string(),
+                title: format!("Sudden fan-out from {source}"),
+                description: format!(
+                    "{source} contacted {} distinct hosts within {window_secs}s",
+                    hosts.len()
+                ),
+                details: json!({
+                    "source_ip": source.to_string(),
+                    "destination_hosts": hosts.len(),
+                }),
+                flows: self.flows_matching(|s| s.source_ip == source),
+            });
+        }
+
+        findings
+    }
+
+    /// Closes the volume window once elapsed, folding it into the learned baseline.
+    fn roll_volume_window(&mut self, now_ms: u64, window_ms: u64) {
+        let elapsed = now_ms.saturating_sub(self.volume.window_start_ms);
+        if window_ms == 0 || elapsed < window_ms {
+            return;
+        }
+
+        // Windows without any traffic count as well, but only a few are needed to settle
+        let idle_windows = (elapsed / window_ms - 1).min(u64::from(BASELINE_MIN_WINDOWS));
+        let closed = std::iter::once(self.volume.window_bytes)
+            .chain(std::iter::repeat_n(0, idle_windows as usize));
+
+        for bytes in closed {
+            let bytes = bytes as f64;
+            self.volume.baseline = if self.volume.windows == 0 {
+                bytes
+            } else {
+                BASELINE_ALPHA * bytes + (1.0 - BASELINE_ALPHA) * self.volume.baseline
+            };
+            self.volume.windows += 1;
+        }
+
+        self.volume.window_start_ms = now_ms - elapsed % window_ms;
+        self.volume.window_bytes = 0;
+    }
+
+    fn check_volume(&self, config: &AnomalyDetectionConfig) -> Option<Finding> {
+        let bytes = self.volume.window_bytes;
+        let baseline = self.volume.baseline;
+
+        if self.volume.windows < BASELINE_MIN_WINDOWS
+            || bytes < MIN_SPIKE_BYTES
+            || (bytes as f64) < baseline * config.volume_factor
+        {
+            return None;
+        }
+
+        let mut flows: Vec<&FlowSample> = self.flows.iter().collect();
+        flows.sort_unstable_by_key(|s| std::cmp::Reverse(s.bytes));
+
+        Some(Finding {
+            kind: FindingKind::VolumeSpike,
+            key: String::new(),
+            title: String::from("Traffic volume spike"),
+            description: format!(
+                "{bytes} bytes in the current {}s window, against a baseline of {baseline:.0}",
+                config.window.as_secs()
+            ),
+            details: json!({
+                "bytes": bytes,
+                "baseline_bytes": baseline.round(),
+            }),
+            flows: flows
+                .into_iter()
+                .take(MAX_ATTACHED_FLOWS)
+                .map(|s| s.connection.clone())
+                .collect(),
+        })
+    }
+
+    /// Learns the ports used to reach the Internet, reporting the new ones after the learning period.
+    fn check_outbound_port(&mut self, sample: &FlowSample, learning: bool) -> Option<Finding> {
+        let outbound = !is_public(sample.source_ip) && is_public(sample.destination_ip);
+        if !outbound || !sample.has_ports() {
+            return None;
+        }
+
+        let protocol = sample.protocol().to_string();
+        let port = sample.destination_port;
+
+        if !self.known_ports.insert((protocol.clone(), port)) || learning {
+            return None;
+        }
+
+        Some(Finding {
+            kind: FindingKind::NewOutboundPort,
+            key: format!("{protocol}/{port}"),
+            title: format!("New outbound port {protocol}/{port}"),
+            description: format!(
+                "{} connected to {} on {protocol} port {port}, never used before by this device",
+                sample.source_ip, sample.destination_ip
+            ),
+            details: json!({
+                "protocol": protocol,
+                "destination_port": port,
+            }),
+            flows: vec![sample.connection.clone()],
+        })
+    }
+
+    fn flows_matching(&self, filter: impl Fn(&FlowSample) -> bool) -> Vec<Connection> {
+        self.flows
+            .iter()
+            .filter(|s| filter(s))
+            .take(MAX_ATTACHED_FLOWS)
+            .map(|s| s.connection.clone())
+            .collect()
+    }
+
+    fn should_report(
+        &mut self,
+        finding: &Finding,
+        now_ms: u64,
+        config: &AnomalyDetectionConfig,
+    ) -> bool {
+        let cooldown_ms = config.cooldown.as_millis() as u64;
+
+        if self.reported.len() > MAX_REPORTED_FINDINGS {
+            self.reported
+                .retain(|_, at_ms| now_ms.saturating_sub(*at_ms) < cooldown_ms);
+        }
+
+        let key = (finding.kind, finding.key.clone());
+        if self
+            .reported
+            .get(&key)
+            .is_some_and(|at_ms| now_ms.saturating_sub(*at_ms) < cooldown_ms)
+        {
+            return false;
+        }
+
+        self.reported.insert(key, now_ms);
+        true
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    get_ip_to_lookup(ip, ip).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(source: &str, destination: &str, port: u32, bytes: u64) -> Connection {
+        Connection {
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            interface: "eth0".to_string(),
+            source_ip: source.to_string(),
+            destination_ip: destination.to_string(),
+            source_port: Some(40000),
+            destination_port: Some(port),
+            protocol: "tcp".to_string(),
+            total_byte: bytes,
+            total_packet: 1,
+        }
+    }
+
+    fn kinds(findings: &[Finding]) -> Vec<FindingKind> {
+        findings.iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn test_detects_scans_spikes_and_new_ports() {
+        let config = AnomalyDetectionConfig {
+            learning_period: std::time::Duration::from_secs(600),
+            ..Default::default()
+        };
+        let mut state = DeviceState::new(0);
+
+        // Learning: the outbound HTTPS port is not reported
+        let learned = [connection("192.168.1.10", "8.8.8.8", 443, 1000)];
+        assert!(state.observe(&config, 0, &learned).is_empty());
+
+        let vertical: Vec<_> = (1..=100)
+            .map(|port| connection("203.0.113.5", "192.168.1.1", port, 60))
+            .collect();
+        let findings = state.observe(&config, 1_000, &vertical);
+        assert_eq!(kinds(&findings), vec![FindingKind::VerticalScan]);
+        assert_eq!(findings[0].flows.len(), MAX_ATTACHED_FLOWS);
+
+        // Reported once per cooldown
+        assert!(state.observe(&config, 2_000, &vertical[..1]).is_empty());
+
+        // The replies of a server to many client ports are not a scan
+        let replies: Vec<_> = (40_000..40_200)
+            .map(|port| connection("192.168.1.1", "203.0.113.7", port, 60))
+            .collect();
+        assert!(state.observe(&config, 2_500, &replies).is_empty());
+
+        let horizontal: Vec<_> = (1..=50)
+            .map(|host| connection("203.0.113.6", &format!("192.168.2.{host}"), 22, 60))
+            .collect();
+        let findings = state.observe(&config, 3_000, &horizontal);
+        assert_eq!(kinds(&findings), vec![FindingKind::HorizontalScan]);
+
+        // Steady traffic builds the volume baseline, then a spike stands out
+        for minute in 1..=12 {
+            state.observe(&config, minute * 60_000, &learned);
+        }
+        let spike = [connection("192.168.1.10", "8.8.8.8", 443, 50 * 1024 * 1024)];
+        let findings = state.observe(&config, 12 * 60_000 + 1, &spike);
+        assert_eq!(kinds(&findings), vec![FindingKind::VolumeSpike]);
+
+        // Past the learning period, new outbound ports are reported
+        let new_port = [connection("192.168.1.10", "8.8.8.8", 4444, 100)];
+        let findings = state.observe(&config, 12 * 60_000 + 2, &new_port);
+        assert_eq!(kinds(&findings), vec![FindingKind::NewOutboundPort]);
+    }
+}
@@ -0,0 +1,139 @@
+mod config;
+mod device_state;
+
+use crate::app_context::AppContext;
+use crate::datastore::SecurityEventModel;
+use config::AnomalyDetectionConfig;
+use device_state::{DeviceState, Finding};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+const EVENT_CATEGORY: &str = "anomaly";
+const QUEUE_CAPACITY: usize = 1024;
+
+type Batch = (String, Vec<Connection>);
+
+/// Streaming analytics over the ingested flows.
+///
+/// Keeps per-device sliding windows to detect port scans, fan-out, volume spikes
+/// and new outbound ports, recording every finding as a security event of the device
+/// with the contributing flows attached.
+///
+/// The flows are analyzed by `run_anomaly_detection`, off the ingestion path.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyDetector {
+    config: Option<Arc<AnomalyDetectionConfig>>,
+    sender: Option<mpsc::Sender<Batch>>,
+    receiver: Arc<Mutex<Option<mpsc::Receiver<Batch>>>>,
+}
+
+impl AnomalyDetector {
+    pub fn new() -> Self {
+        let Some(config) = AnomalyDetectionConfig::from_env() else {
+            log::info!("Anomaly detection is disabled");
+            return Self::default();
+        };
+
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+
+        Self {
+            config: Some(Arc::new(config)),
+            sender: Some(sender),
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        }
+    }
+
+    /// Queues the flows for analysis, dropping them if the detector falls behind.
+    pub fn inspect(&self, device_id: &str, connections: &[Connection]) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        match sender.try_send((device_id.to_string(), connections.to_vec())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::warn!("Anomaly detection is falling behind, dropping flows of {device_id}");
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// Analyzes the queued flows, keeping the per-device state.
+pub async fn run_anomaly_detection(context: AppContext) {
+    let detector = &context.anomaly_detector;
+
+    let (Some(config), Some(mut receiver)) = (
+        detector.config.clone(),
+        detector.receiver.lock().ok().and_then(|mut r| r.take()),
+    ) else {
+        return std::future::pending().await;
+    };
+
+    let mut devices: HashMap<String, DeviceState> = HashMap::new();
+
+    while let Some((device_id, connections)) = receiver.recv().await {
+        let now_ms = now_ms();
+        let findings = devices
+            .entry(device_id.clone())
+            .or_insert_with(|| DeviceState::new(now_ms))
+            .observe(&config, now_ms, &connections);
+
+        if !findings.is_empty() {
+            tokio::spawn(record_findings(context.clone(), device_id, findings));
+        }
+    }
+}
+
+async fn record_findings(context: AppContext, device_id: String, findings: Vec<Finding>) {
+    for finding in &findings {
+        log::warn!("Device {device_id}: {}", finding.description);
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    let events: Vec<SecurityEventModel> = findings
+        .into_iter()
+        .map(|finding| SecurityEventModel {
+            device_id: device_id.clone(),
+            category: EVENT_CATEGORY.to_string(),
+            title: finding.title,
+            description: finding.description,
+            severity: finding.kind.severity(),
+            timestamp: timestamp.clone(),
+            metadata: json!({
+                "kind": finding.kind.as_str(),
+                "details": finding.details,
+                "flows": finding.flows,
+            }),
+        })
+        .collect();
+
+    let result = match context.sysdev_token_provider.get().await {
+        Ok(token) => {
+            context
+                .datastore
+                .create_security_events(&token.jwt, &events)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        log::error!(
+            "Failed to record anomalies for device {device_id}: {}",
+            err.to_str()
+        );
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
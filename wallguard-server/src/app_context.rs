@@ -1,6 +1,11 @@
+use crate::alerting::AlertEngine;
+use crate::anomaly_detection::AnomalyDetector;
 use crate::datastore::Datastore;
+use crate::metrics::Metrics;
 use crate::orchestrator::Orchestrator;
+use crate::rd_recording::RdRecordings;
 use crate::reverse_tunnel::ReverseTunnel;
+use crate::threat_intel::ThreatIntel;
 use crate::token_provider::TokenProvider;
 use crate::tunneling::TunnelsManager;
 
@@ -48,6 +53,11 @@ pub struct AppContext {
     pub sysdev_token_provider: TokenProvider,
 
     pub tunnels_manager: TunnelsManager,
+    pub threat_intel: ThreatIntel,
+    pub anomaly_detector: AnomalyDetector,
+    pub alert_engine: AlertEngine,
+    pub metrics: Metrics,
+    pub rd_recordings: RdRecordings,
 }
 
 impl AppContext {
@@ -71,6 +81,11 @@ impl AppContext {
         );
 
         let tunnels_manager = TunnelsManager::new();
+        let threat_intel = ThreatIntel::new();
+        let anomaly_detector = AnomalyDetector::new();
+        let alert_engine = AlertEngine::new();
+        let metrics = Metrics::new();
+        let rd_recordings = RdRecordings::new();
 
         Ok(Self {
             datastore,
@@ -79,6 +94,11 @@ impl AppContext {
             sysdev_token_provider,
             root_token_provider,
             tunnels_manager,
+            threat_intel,
+            anomaly_detector,
+            alert_engine,
+            metrics,
+            rd_recordings,
         })
     }
 }
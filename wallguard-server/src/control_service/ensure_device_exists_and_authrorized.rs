@@ -1,6 +1,7 @@
 use crate::token::Token;
 use crate::{control_service::service::WallGuardService, datastore::Device};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use tonic::{Status, metadata::MetadataMap};
 
 impl WallGuardService {
     pub(crate) async fn ensure_device_exists_and_authrorized(
@@ -27,4 +28,25 @@ impl WallGuardService {
 
         Ok(device)
     }
+
+    /// Authenticates an ingestion stream through the JWT carried in its `authorization` metadata.
+    pub(crate) async fn authorize_ingest_stream(
+        &self,
+        metadata: &MetadataMap,
+    ) -> Result<(Token, Device), Status> {
+        let jwt = metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing authorization metadata"))?;
+
+        let token = Token::from_jwt(jwt).map_err(|_| Status::internal("Malformed JWT token"))?;
+
+        let device = self
+            .ensure_device_exists_and_authrorized(&token)
+            .await
+            .map_err(|err| Status::internal(err.to_str()))?;
+
+        Ok((token, device))
+    }
 }
@@ -1,3 +1,4 @@
+use crate::alerting::AlertEvent;
 use crate::datastore::Datastore;
 use crate::token::Token;
 use crate::{control_service::service::WallGuardService, datastore::DeviceConfiguration};
@@ -31,38 +32,48 @@ impl WallGuardService {
             .await
             .map_err(|err| Status::internal(err.to_str()))?;
 
-        let start = std::time::Instant::now();
+        let ingestion_start = std::time::Instant::now();
+
+        let datastore_start = std::time::Instant::now();
         let previous = self
             .context
             .datastore
             .obtain_config(&token.jwt, token.account.device_id().unwrap_or_default())
             .await
             .map_err(|err| Status::internal(err.to_str()))?;
-        log::info!("obtain_config: {}ms", start.elapsed().as_millis());
-
-        if let Some(mut prev) = previous {
-            if prev.digest == configuration.digest {
+        self.observe_datastore("obtain_config", datastore_start);
+
+        self.context.alert_engine.emit(AlertEvent::Config {
+            device_id: token.account.device_id().unwrap_or_default().to_string(),
+            digest: configuration.digest.clone(),
+            changed: previous
+                .as_ref()
+                .is_none_or(|prev| prev.digest != configuration.digest),
+            draft: status == ConfigStatus::CsDraft,
+        });
+
+        match previous {
+            Some(mut prev) if prev.digest == configuration.digest => {
                 prev.version += 1;
 
-                let start = std::time::Instant::now();
+                let datastore_start = std::time::Instant::now();
                 self.context
                     .datastore
                     .update_config(&token.jwt, &prev.id, &prev)
                     .await
                     .map_err(|err| Status::internal(err.to_str()))?;
-                log::info!("update_config: {}ms", start.elapsed().as_millis());
+                self.observe_datastore("update_config", datastore_start);
 
-                let start = std::time::Instant::now();
+                let datastore_start = std::time::Instant::now();
                 self.context
                     .datastore
                     .update_rules_status(&token.jwt, &prev.id, status)
                     .await
                     .map_err(|err| Status::internal(err.to_str()))?;
-                log::info!("update_rules_status: {}ms", start.elapsed().as_millis());
-
-                Ok(Response::new(()))
-            } else {
-                let start = std::time::Instant::now();
+                self.observe_datastore("update_rules_status", datastore_start);
+            }
+            _ => {
+                let datastore_start = std::time::Instant::now();
                 insert_new_configuration(
                     self.context.datastore.clone(),
                     &token,
@@ -71,30 +82,22 @@ impl WallGuardService {
                 )
                 .await
                 .map_err(|err| Status::internal(err.to_str()))?;
-                log::info!(
-                    "insert_new_configuration: {}ms",
-                    start.elapsed().as_millis()
-                );
-
-                Ok(Response::new(()))
+                self.observe_datastore("insert_new_configuration", datastore_start);
             }
-        } else {
-            let start = std::time::Instant::now();
-            insert_new_configuration(
-                self.context.datastore.clone(),
-                &token,
-                &configuration,
-                status,
-            )
-            .await
-            .map_err(|err| Status::internal(err.to_str()))?;
-            log::info!(
-                "insert_new_configuration: {}ms",
-                start.elapsed().as_millis()
-            );
-
-            Ok(Response::new(()))
         }
+
+        self.context
+            .metrics
+            .observe_ingestion("config", 1, ingestion_start.elapsed());
+
+        Ok(Response::new(()))
+    }
+
+    fn observe_datastore(&self, operation: &str, datastore_start: std::time::Instant) {
+        log::info!("{operation}: {}ms", datastore_start.elapsed().as_millis());
+        self.context
+            .metrics
+            .observe_datastore(operation, datastore_start.elapsed());
     }
 }
 
@@ -3,7 +3,7 @@ use crate::token::Token;
 use nullnet_libipinfo::get_ip_to_lookup;
 use std::net::IpAddr;
 use tonic::{Request, Response, Status};
-use wallguard_common::protobuf::wallguard_service::ConnectionsData;
+use wallguard_common::protobuf::wallguard_service::{Connection, ConnectionsData};
 
 impl WallGuardService {
     pub(crate) async fn handle_connections_data_impl(
@@ -20,9 +20,22 @@ impl WallGuardService {
             .await
             .map_err(|err| Status::internal(err.to_str()))?;
 
-        let connections_count = data.connections.len();
+        self.store_connections(&token, data.connections).await?;
 
-        for conn in &data.connections {
+        Ok(Response::new(()))
+    }
+
+    /// Stores the connections reported by a device, also queueing
+    /// their remote addresses for the IP information lookup.
+    pub(crate) async fn store_connections(
+        &self,
+        token: &Token,
+        connections: Vec<Connection>,
+    ) -> Result<(), Status> {
+        let ingestion_start = std::time::Instant::now();
+        let connections_count = connections.len();
+
+        for conn in &connections {
             let src: Option<IpAddr> = conn.source_ip.parse().ok();
             let dst: Option<IpAddr> = conn.destination_ip.parse().ok();
             if let (Some(src), Some(dst)) = (src, dst) {
@@ -37,21 +50,41 @@ impl WallGuardService {
             token.account.device_id().unwrap_or("unknown")
         );
 
-        if !data.connections.is_empty() {
-            let start = std::time::Instant::now();
+        if !connections.is_empty() {
+            let datastore_start = std::time::Instant::now();
+            let device_id = token.account.device_id().unwrap_or_default();
+            self.context
+                .threat_intel
+                .inspect(&self.context, device_id, &connections);
+            self.context
+                .anomaly_detector
+                .inspect(device_id, &connections);
+            let data = ConnectionsData {
+                token: String::new(),
+                connections,
+            };
             self.context
                 .datastore
-                .create_connections(&token.jwt, &token, data)
+                .create_connections(&token.jwt, device_id, data)
                 .await
                 .map_err(|e| Status::internal(format!("Datastore operation failed: {e:?}")))?;
+            self.context
+                .metrics
+                .observe_datastore("create_connections", datastore_start.elapsed());
             log::info!(
                 "[{}] create_connections: inserted {} records in {}ms",
                 chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
                 connections_count,
-                start.elapsed().as_millis()
+                datastore_start.elapsed().as_millis()
             );
         }
 
-        Ok(Response::new(()))
+        self.context.metrics.observe_ingestion(
+            "connections",
+            connections_count,
+            ingestion_start.elapsed(),
+        );
+
+        Ok(())
     }
 }
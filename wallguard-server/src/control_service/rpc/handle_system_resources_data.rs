@@ -1,7 +1,8 @@
+use crate::alerting::AlertEvent;
 use crate::control_service::service::WallGuardService;
 use crate::token::Token;
 use tonic::{Request, Response, Status};
-use wallguard_common::protobuf::wallguard_service::SystemResourcesData;
+use wallguard_common::protobuf::wallguard_service::{SystemResource, SystemResourcesData};
 
 impl WallGuardService {
     pub(crate) async fn handle_system_resources_data_impl(
@@ -18,23 +19,76 @@ impl WallGuardService {
             .await
             .map_err(|err| Status::internal(err.to_str()))?;
 
-        let resources_count = data.resources.len();
+        self.store_system_resources(&token, &device.id, data.resources)
+            .await?;
+
+        Ok(Response::new(()))
+    }
+
+    pub(crate) async fn store_system_resources(
+        &self,
+        token: &Token,
+        device_id: &str,
+        resources: Vec<SystemResource>,
+    ) -> Result<(), Status> {
+        let ingestion_start = std::time::Instant::now();
+        let resources_count = resources.len();
         log::info!("Received {} system resources", resources_count);
 
-        if !data.resources.is_empty() {
-            let start = std::time::Instant::now();
+        if let Some(latest) = resources.last() {
+            self.context
+                .metrics
+                .record_device_telemetry(device_id, latest);
+
+            let disk_percent = if latest.total_disk_space > 0 {
+                let used = latest
+                    .total_disk_space
+                    .saturating_sub(latest.available_disk_space);
+                used as f32 * 100.0 / latest.total_disk_space as f32
+            } else {
+                0.0
+            };
+
+            self.context.alert_engine.emit(AlertEvent::Resources {
+                device_id: device_id.to_string(),
+                cpu_percent: latest.global_cpu_usage,
+                disk_percent,
+            });
+        }
+
+        for health in resources
+            .iter()
+            .filter_map(|res| res.firewall_health.clone())
+        {
+            self.context.alert_engine.emit(AlertEvent::FirewallHealth {
+                device_id: device_id.to_string(),
+                health,
+            });
+        }
+
+        if !resources.is_empty() {
+            let datastore_start = std::time::Instant::now();
             self.context
                 .datastore
-                .create_system_resources(&token.jwt, data.resources, device.id)
+                .create_system_resources(&token.jwt, resources, device_id.to_string())
                 .await
                 .map_err(|e| Status::internal(format!("Datastore operation failed: {e:?}")))?;
+            self.context
+                .metrics
+                .observe_datastore("create_system_resources", datastore_start.elapsed());
             log::info!(
                 "create_system_resources: inserted {} records in {}ms",
                 resources_count,
-                start.elapsed().as_millis()
+                datastore_start.elapsed().as_millis()
             );
         }
 
-        Ok(Response::new(()))
+        self.context.metrics.observe_ingestion(
+            "resources",
+            resources_count,
+            ingestion_start.elapsed(),
+        );
+
+        Ok(())
     }
 }
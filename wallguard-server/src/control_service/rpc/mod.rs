@@ -4,4 +4,6 @@ mod handle_config_data;
 mod handle_connections_data;
 mod handle_system_resources_data;
 mod report_services;
+mod stream_connections_data;
+mod stream_system_resources_data;
 // mod request_tunnel;
@@ -0,0 +1,50 @@
+use crate::control_service::service::WallGuardService;
+use tonic::{Request, Response, Status, Streaming};
+use wallguard_common::protobuf::wallguard_service::{ConnectionsBatch, IngestAck};
+
+impl WallGuardService {
+    pub(crate) async fn stream_connections_data_impl(
+        &self,
+        request: Request<Streaming<ConnectionsBatch>>,
+    ) -> Result<Response<IngestAck>, Status> {
+        let (token, _) = self.authorize_ingest_stream(request.metadata()).await?;
+
+        let mut stream = request.into_inner();
+        let mut ack = IngestAck::default();
+
+        // Batches are acknowledged only once stored, so that the agent
+        // keeps everything past the last acknowledged sequence number
+        loop {
+            let batch = match stream.message().await {
+                Ok(Some(batch)) => batch,
+                Ok(None) => break,
+                Err(status) => {
+                    log::warn!("Connections stream interrupted: {}", status.message());
+                    break;
+                }
+            };
+
+            if batch.sequence != ack.last_sequence + 1 {
+                log::warn!(
+                    "Out of order connections batch {} (expected {})",
+                    batch.sequence,
+                    ack.last_sequence + 1
+                );
+                break;
+            }
+
+            if let Err(status) = self.store_connections(&token, batch.connections).await {
+                log::error!(
+                    "Failed to store connections batch {}: {}",
+                    batch.sequence,
+                    status.message()
+                );
+                break;
+            }
+
+            ack.last_sequence = batch.sequence;
+        }
+
+        Ok(Response::new(ack))
+    }
+}
@@ -0,0 +1,51 @@
+use crate::control_service::service::WallGuardService;
+use tonic::{Request, Response, Status, Streaming};
+use wallguard_common::protobuf::wallguard_service::{IngestAck, SystemResourcesBatch};
+
+impl WallGuardService {
+    pub(crate) async fn stream_system_resources_data_impl(
+        &self,
+        request: Request<Streaming<SystemResourcesBatch>>,
+    ) -> Result<Response<IngestAck>, Status> {
+        let (token, device) = self.authorize_ingest_stream(request.metadata()).await?;
+
+        let mut stream = request.into_inner();
+        let mut ack = IngestAck::default();
+
+        loop {
+            let batch = match stream.message().await {
+                Ok(Some(batch)) => batch,
+                Ok(None) => break,
+                Err(status) => {
+                    log::warn!("System resources stream interrupted: {}", status.message());
+                    break;
+                }
+            };
+
+            if batch.sequence != ack.last_sequence + 1 {
+                log::warn!(
+                    "Out of order system resources batch {} (expected {})",
+                    batch.sequence,
+                    ack.last_sequence + 1
+                );
+                break;
+            }
+
+            if let Err(status) = self
+                .store_system_resources(&token, &device.id, batch.resources)
+                .await
+            {
+                log::error!(
+                    "Failed to store system resources batch {}: {}",
+                    batch.sequence,
+                    status.message()
+                );
+                break;
+            }
+
+            ack.last_sequence = batch.sequence;
+        }
+
+        Ok(Response::new(ack))
+    }
+}
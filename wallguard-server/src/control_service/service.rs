@@ -1,8 +1,8 @@
 use crate::app_context::AppContext;
-use crate::traffic_handler::ip_info::ip_info_handler;
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use std::net::{IpAddr, SocketAddr};
 use std::sync::mpsc;
+use tonic::codec::CompressionEncoding;
 use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Server;
 use tonic::{Request, Response, Status, Streaming};
@@ -11,13 +11,10 @@ use wallguard_common::protobuf::wallguard_service::wall_guard_server::{
     WallGuard, WallGuardServer,
 };
 use wallguard_common::protobuf::wallguard_service::{
-    ConfigSnapshot, ConnectionsData, DeviceSettingsRequest, DeviceSettingsResponse,
-    ServicesMessage, SystemResourcesData,
+    ConfigSnapshot, ConnectionsBatch, ConnectionsData, DeviceSettingsRequest,
+    DeviceSettingsResponse, IngestAck, ServicesMessage, SystemResourcesBatch, SystemResourcesData,
 };
 
-// @TODO: Configure through ENV
-const IP_INFO_CACHE_SIZE: usize = 10_000;
-
 #[derive(Debug, Clone)]
 pub struct WallGuardService {
     pub(crate) context: AppContext,
@@ -25,15 +22,7 @@ pub struct WallGuardService {
 }
 
 impl WallGuardService {
-    pub fn new(context: AppContext) -> Self {
-        let (ip_info_tx, ip_info_rx) = mpsc::channel();
-
-        let handle = tokio::runtime::Handle::current();
-        let ctx = context.clone();
-        std::thread::spawn(move || {
-            ip_info_handler(&ip_info_rx, IP_INFO_CACHE_SIZE, &handle, ctx);
-        });
-
+    pub fn new(context: AppContext, ip_info_tx: mpsc::Sender<Option<IpAddr>>) -> Self {
         Self {
             context,
             ip_info_tx,
@@ -42,7 +31,13 @@ impl WallGuardService {
 
     pub async fn serve(self, addr: SocketAddr) -> Result<(), Error> {
         Server::builder()
-            .add_service(WallGuardServer::new(self.clone()))
+            .add_service(
+                WallGuardServer::new(self.clone())
+                    .accept_compressed(CompressionEncoding::Gzip)
+                    .accept_compressed(CompressionEncoding::Zstd)
+                    .send_compressed(CompressionEncoding::Gzip)
+                    .send_compressed(CompressionEncoding::Zstd),
+            )
             .serve(addr)
             .await
             .handle_err(location!())?;
@@ -84,6 +79,20 @@ impl WallGuard for WallGuardService {
         self.handle_system_resources_data_impl(request).await
     }
 
+    async fn stream_connections_data(
+        &self,
+        request: Request<Streaming<ConnectionsBatch>>,
+    ) -> Result<Response<IngestAck>, Status> {
+        self.stream_connections_data_impl(request).await
+    }
+
+    async fn stream_system_resources_data(
+        &self,
+        request: Request<Streaming<SystemResourcesBatch>>,
+    ) -> Result<Response<IngestAck>, Status> {
+        self.stream_system_resources_data_impl(request).await
+    }
+
     async fn get_device_settings(
         &self,
         request: Request<DeviceSettingsRequest>,
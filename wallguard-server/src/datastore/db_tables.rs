@@ -21,6 +21,7 @@ pub enum DBTable {
     DeviceServices,
     Heartbeats,
     DeviceTunnels,
+    Notifications,
 }
 
 impl Display for DBTable {
@@ -45,6 +46,7 @@ impl Display for DBTable {
             DBTable::Heartbeats => "device_heartbeats",
             DBTable::DeviceServices => "device_services",
             DBTable::DeviceTunnels => "device_tunnels",
+            DBTable::Notifications => "notifications",
         };
 
         write!(f, "{table_name}")
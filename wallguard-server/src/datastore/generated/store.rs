@@ -2127,6 +2127,8 @@ pub struct DeviceHeartbeats {
     pub hypertable_timestamp: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(string, optional, tag = "24")]
     pub device_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "25")]
+    pub health: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// IpInfos entity definition
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -3167,6 +3169,12 @@ pub struct SystemResources {
     pub temperature: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(string, optional, tag = "33")]
     pub device_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "34")]
+    pub firewall_health: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "35")]
+    pub interfaces: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "36")]
+    pub processes: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// HttpRequests entity definition
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -4345,6 +4353,12 @@ pub struct TempSystemResources {
     pub temperature: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(string, optional, tag = "33")]
     pub device_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "34")]
+    pub firewall_health: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "35")]
+    pub interfaces: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "36")]
+    pub processes: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// TempDeviceInterfaces entity definition
 #[derive(Clone, PartialEq, ::prost::Message)]
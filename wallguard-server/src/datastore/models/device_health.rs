@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use wallguard_common::protobuf::wallguard_commands::Heartbeat;
+
+/// Health of a device, as reported by a heartbeat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceHealth {
+    pub received_at: String,
+    pub version: String,
+    pub uptime_seconds: u64,
+    pub traffic_monitoring: bool,
+    pub telemetry_monitoring: bool,
+    pub configuration_monitoring: bool,
+    pub services_monitoring: bool,
+    pub queued_items: u64,
+    pub spool_bytes: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
+    /// How far the agent clock is ahead of the server one, including the transit time of the heartbeat.
+    pub clock_offset_ms: i64,
+}
+
+impl DeviceHealth {
+    /// The health reported by a heartbeat received now.
+    pub fn reported(heartbeat: Heartbeat) -> Option<Self> {
+        // Agents predating health reports send empty heartbeats
+        if heartbeat.version.is_empty() {
+            return None;
+        }
+
+        Some(Self::from_heartbeat(heartbeat, Utc::now()))
+    }
+
+    fn from_heartbeat(heartbeat: Heartbeat, received_at: DateTime<Utc>) -> Self {
+        let last_error_at = heartbeat
+            .last_error
+            .as_ref()
+            .and_then(|_| DateTime::from_timestamp(heartbeat.last_error_timestamp, 0))
+            .map(|timestamp| timestamp.to_rfc3339());
+
+        Self {
+            received_at: received_at.to_rfc3339(),
+            version: heartbeat.version,
+            uptime_seconds: heartbeat.uptime_seconds,
+            traffic_monitoring: heartbeat.traffic_monitoring,
+            telemetry_monitoring: heartbeat.telemetry_monitoring,
+            configuration_monitoring: heartbeat.configuration_monitoring,
+            services_monitoring: heartbeat.services_monitoring,
+            queued_items: heartbeat.queued_items,
+            spool_bytes: heartbeat.spool_bytes,
+            last_error: heartbeat.last_error,
+            last_error_at,
+            clock_offset_ms: heartbeat.timestamp - received_at.timestamp_millis(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_from_heartbeat() {
+        let received_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let heartbeat = Heartbeat {
+            uptime_seconds: 3600,
+            traffic_monitoring: true,
+            last_error: Some(String::from("Failed to send connections")),
+            last_error_timestamp: 1_699_999_000,
+            timestamp: 1_700_000_001_500,
+            version: String::from("1.0.0"),
+            ..Default::default()
+        };
+
+        let health = DeviceHealth::from_heartbeat(heartbeat, received_at);
+
+        assert_eq!(health.clock_offset_ms, 1500);
+        assert_eq!(
+            health.last_error_at.as_deref(),
+            Some("2023-11-14T21:56:40+00:00")
+        );
+        assert!(health.traffic_monitoring);
+        assert!(!health.telemetry_monitoring);
+    }
+}
@@ -1,17 +1,23 @@
 mod alias;
 mod device;
 mod device_configuration;
+mod device_health;
 mod device_instance;
 mod heartbeat;
 mod installation_code;
+mod process_snapshot;
+mod security_event;
 mod service;
 mod tunnel;
 
 pub use alias::*;
 pub use device::*;
 pub use device_configuration::*;
+pub use device_health::*;
 pub use device_instance::*;
 pub use heartbeat::*;
 pub use installation_code::*;
+pub use process_snapshot::*;
+pub use security_event::*;
 pub use service::*;
 pub use tunnel::*;
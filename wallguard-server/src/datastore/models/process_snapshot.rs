@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProcessSnapshotModel {
+    pub timestamp: String,
+    pub snapshot: serde_json::Value,
+}
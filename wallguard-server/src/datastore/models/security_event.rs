@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// A security relevant finding about a device, stored as a notification.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SecurityEventModel {
+    pub device_id: String,
+    pub category: String,
+    pub title: String,
+    pub description: String,
+    pub severity: i32,
+    pub timestamp: String,
+    pub metadata: serde_json::Value,
+}
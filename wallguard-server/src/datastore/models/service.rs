@@ -27,6 +27,7 @@ impl ServiceInfo {
                 Ok(ServiceProtocol::Https) => "https".into(),
                 Ok(ServiceProtocol::Tty) => "tty".into(),
                 Ok(ServiceProtocol::Rd) => "rd".into(),
+                Ok(ServiceProtocol::Capture) => "capture".into(),
                 _ => "unknown".into(),
             },
             ..Default::default()
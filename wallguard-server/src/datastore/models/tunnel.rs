@@ -12,6 +12,7 @@ pub enum TunnelType {
     Http,
     Https,
     RemoteDesktop,
+    Capture,
 }
 
 impl TryFrom<&str> for TunnelType {
@@ -24,6 +25,7 @@ impl TryFrom<&str> for TunnelType {
             "https" => Ok(TunnelType::Https),
             "tty" => Ok(TunnelType::Tty),
             "rd" => Ok(TunnelType::RemoteDesktop),
+            "capture" => Ok(TunnelType::Capture),
             other => {
                 Err(format!("Tunnel of type {other} is not supported")).handle_err(location!())
             }
@@ -39,6 +41,7 @@ impl Display for TunnelType {
             TunnelType::Http => "http",
             TunnelType::Https => "https",
             TunnelType::RemoteDesktop => "rd",
+            TunnelType::Capture => "capture",
         };
 
         f.write_str(value)
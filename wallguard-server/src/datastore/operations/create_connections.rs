@@ -6,7 +6,6 @@ use crate::datastore::{
         batch_insert_connections_request,
     },
 };
-use crate::token::Token;
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use nullnet_libipinfo::get_ip_to_lookup;
 use std::net::IpAddr;
@@ -16,15 +15,9 @@ impl Datastore {
     pub async fn create_connections(
         &self,
         token: &str,
-        auth_token: &Token,
+        device_id: &str,
         data: ConnectionsData,
     ) -> Result<(), Error> {
-        let device_id = auth_token
-            .account
-            .device_id()
-            .unwrap_or_default()
-            .to_string();
-
         let records: Vec<Connections> = data
             .connections
             .into_iter()
@@ -37,7 +30,7 @@ impl Datastore {
                     .map(|ip| ip.to_string());
 
                 Connections {
-                    device_id: Some(device_id.clone()),
+                    device_id: Some(device_id.to_string()),
                     interface_name: Some(conn.interface),
                     source_ip: Some(conn.source_ip),
                     destination_ip: Some(conn.destination_ip),
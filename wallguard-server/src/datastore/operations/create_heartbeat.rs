@@ -1,5 +1,5 @@
 use crate::datastore::{
-    Datastore, HeartbeatModel,
+    Datastore, DeviceHealth, HeartbeatModel,
     db_tables::DBTable,
     generated::{CreateDeviceHeartbeatsRequest, CreateParams, CreateQuery, DeviceHeartbeats},
 };
@@ -10,12 +10,18 @@ impl Datastore {
         &self,
         token: &str,
         heartbeat: &HeartbeatModel,
+        health: Option<&DeviceHealth>,
     ) -> Result<(), Error> {
+        let health = health
+            .map(|health| serde_json::to_string(health).handle_err(location!()))
+            .transpose()?;
+
         let request = CreateDeviceHeartbeatsRequest {
             device_heartbeats: Some(DeviceHeartbeats {
                 device_id: Some(heartbeat.device_id.clone()),
                 status: Some(String::from("Active")),
                 timestamp: Some(heartbeat.timestamp.clone()),
+                health,
                 ..Default::default()
             }),
             params: Some(CreateParams {
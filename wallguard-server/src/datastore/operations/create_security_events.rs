@@ -0,0 +1,70 @@
+use crate::datastore::{
+    Datastore, SecurityEventModel,
+    db_tables::DBTable,
+    generated::{
+        BatchInsertNotificationsRequest, BatchInsertParams, BatchInsertQuery, Notifications,
+        batch_insert_notifications_request,
+    },
+};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+
+const EVENT_SOURCE: &str = "wallguard";
+
+impl Datastore {
+    pub async fn create_security_events(
+        &self,
+        token: &str,
+        events: &[SecurityEventModel],
+    ) -> Result<(), Error> {
+        let records: Vec<Notifications> = events
+            .iter()
+            .map(|event| {
+                let mut metadata = event.metadata.clone();
+                if let Some(object) = metadata.as_object_mut() {
+                    object.insert("device_id".into(), event.device_id.clone().into());
+                }
+
+                Notifications {
+                    title: Some(event.title.clone()),
+                    description: Some(event.description.clone()),
+                    event_timestamp: Some(event.timestamp.clone()),
+                    source: Some(EVENT_SOURCE.to_string()),
+                    categories: vec![event.category.clone()],
+                    tags: vec![event.device_id.clone()],
+                    priority_level: Some(event.severity),
+                    metadata: Some(metadata.to_string()),
+                    status: Some(String::from("Active")),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let request = BatchInsertNotificationsRequest {
+            params: Some(BatchInsertParams {
+                table: DBTable::Notifications.into(),
+                r#type: String::new(),
+            }),
+            query: Some(BatchInsertQuery {
+                pluck: String::new(),
+            }),
+            body: Some(batch_insert_notifications_request::BatchBody {
+                notifications: records,
+            }),
+        };
+
+        let mut grpc_request = tonic::Request::new(request);
+        grpc_request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token)
+                .parse()
+                .handle_err(location!())?,
+        );
+
+        self.inner
+            .clone()
+            .batch_insert_notifications(grpc_request)
+            .await
+            .handle_err(location!())
+            .map(|_| ())
+    }
+}
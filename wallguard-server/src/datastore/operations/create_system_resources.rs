@@ -35,6 +35,16 @@ impl Datastore {
                 written_bytes: Some(res.written_bytes.to_string()),
                 temperature: Some(res.temperatures),
                 device_id: Some(device_id.clone()),
+                firewall_health: res
+                    .firewall_health
+                    .as_ref()
+                    .map(|health| serde_json::json!(health).to_string()),
+                interfaces: (!res.interfaces.is_empty())
+                    .then(|| serde_json::json!(res.interfaces).to_string()),
+                processes: res
+                    .processes
+                    .as_ref()
+                    .map(|processes| serde_json::json!(processes).to_string()),
                 status: Some(String::from("Active")),
                 ..Default::default()
             })
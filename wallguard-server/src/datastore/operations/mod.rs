@@ -7,6 +7,7 @@ mod create_heartbeat;
 mod create_interfaces;
 mod create_ip_info;
 mod create_rules;
+mod create_security_events;
 mod create_services;
 mod create_system_resources;
 mod create_tunnel;
@@ -18,7 +19,11 @@ mod login;
 mod mark_all_tunnels_terminated;
 mod obtain_config;
 mod obtain_device;
+mod obtain_device_health;
 mod obtain_installation_code;
+mod obtain_last_heartbeat;
+mod obtain_offline_devices;
+mod obtain_process_snapshots;
 mod obtain_service;
 mod obtain_services;
 mod obtain_tunnel;
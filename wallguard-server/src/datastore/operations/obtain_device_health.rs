@@ -0,0 +1,91 @@
+use crate::datastore::{
+    Datastore, DeviceHealth,
+    db_tables::DBTable,
+    generated::{
+        FilterCriteria, FilterOperator, GetByFilterParams, GetByFilterRequest,
+        get_by_filter_request,
+    },
+};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+
+impl Datastore {
+    /// The health reported by the latest heartbeat of the device that carried one.
+    pub async fn obtain_device_health(
+        &self,
+        token: &str,
+        device_id: &str,
+    ) -> Result<Option<DeviceHealth>, Error> {
+        let request = GetByFilterRequest {
+            params: Some(GetByFilterParams {
+                table: DBTable::Heartbeats.into(),
+                r#type: String::new(),
+            }),
+            body: Some(get_by_filter_request::GetByFilterBody {
+                pluck: vec!["health".to_string()],
+                advance_filters: vec![
+                    FilterCriteria {
+                        r#type: "criteria".to_string(),
+                        field: Some("device_id".to_string()),
+                        entity: Some(DBTable::Heartbeats.into()),
+                        operator: Some(FilterOperator::Equal as i32),
+                        values: vec![format!("\"{}\"", device_id)],
+                        ..Default::default()
+                    },
+                    FilterCriteria {
+                        r#type: "operator".to_string(),
+                        operator: Some(FilterOperator::And as i32),
+                        ..Default::default()
+                    },
+                    FilterCriteria {
+                        r#type: "criteria".to_string(),
+                        field: Some("health".to_string()),
+                        entity: Some(DBTable::Heartbeats.into()),
+                        operator: Some(FilterOperator::IsNotNull as i32),
+                        ..Default::default()
+                    },
+                ],
+                limit: Some(1),
+                order_by: Some("timestamp".to_string()),
+                order_direction: Some("desc".to_string()),
+                ..Default::default()
+            }),
+        };
+
+        let mut grpc_request = tonic::Request::new(request);
+        grpc_request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token)
+                .parse()
+                .handle_err(location!())?,
+        );
+
+        let response = self
+            .inner
+            .clone()
+            .get_by_filter(grpc_request)
+            .await
+            .handle_err(location!())?
+            .into_inner();
+
+        if response.count == 0 {
+            return Ok(None);
+        }
+
+        let data: Vec<serde_json::Value> =
+            serde_json::from_str(&response.data).handle_err(location!())?;
+        let Some(mut first) = data.into_iter().next() else {
+            return Ok(None);
+        };
+
+        // The column holds the JSON encoding of the health
+        let health = match first.get_mut("health").map(serde_json::Value::take) {
+            Some(serde_json::Value::String(encoded)) => {
+                serde_json::from_str(&encoded).handle_err(location!())?
+            }
+            Some(health) => serde_json::from_value(health).handle_err(location!())?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(health))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::datastore::{
+    Datastore,
+    db_tables::DBTable,
+    generated::{
+        FilterCriteria, FilterOperator, GetByFilterParams, GetByFilterRequest,
+        get_by_filter_request,
+    },
+};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+
+impl Datastore {
+    /// Returns the IDs of the devices marked as offline.
+    pub async fn obtain_offline_devices(
+        &self,
+        token: &str,
+        performed_by_root: bool,
+    ) -> Result<Vec<String>, Error> {
+        let request = GetByFilterRequest {
+            params: Some(GetByFilterParams {
+                table: DBTable::Devices.into(),
+                r#type: if performed_by_root {
+                    "root".to_string()
+                } else {
+                    String::new()
+                },
+            }),
+            body: Some(get_by_filter_request::GetByFilterBody {
+                pluck: vec!["id".to_string()],
+                advance_filters: vec![FilterCriteria {
+                    r#type: "criteria".to_string(),
+                    field: Some("is_device_online".to_string()),
+                    entity: Some(DBTable::Devices.into()),
+                    operator: Some(FilterOperator::Equal as i32),
+                    values: vec!["false".to_string()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        };
+
+        let mut grpc_request = tonic::Request::new(request);
+        grpc_request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token)
+                .parse()
+                .handle_err(location!())?,
+        );
+
+        let response = self
+            .inner
+            .clone()
+            .get_by_filter(grpc_request)
+            .await
+            .handle_err(location!())?
+            .into_inner();
+
+        if response.count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let data: Vec<serde_json::Value> =
+            serde_json::from_str(&response.data).handle_err(location!())?;
+
+        Ok(data
+            .into_iter()
+            .filter_map(|device| Some(device.get("id")?.as_str()?.to_string()))
+            .collect())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::datastore::{
+    Datastore, ProcessSnapshotModel,
+    db_tables::DBTable,
+    generated::{
+        FilterCriteria, FilterOperator, GetByFilterParams, GetByFilterRequest,
+        get_by_filter_request,
+    },
+};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+
+impl Datastore {
+    /// The latest `limit` process snapshots of the device, oldest first.
+    pub async fn obtain_process_snapshots(
+        &self,
+        token: &str,
+        device_id: &str,
+        limit: i32,
+    ) -> Result<Vec<ProcessSnapshotModel>, Error> {
+        let request = GetByFilterRequest {
+            params: Some(GetByFilterParams {
+                table: DBTable::SystemResources.into(),
+                r#type: String::new(),
+            }),
+            body: Some(get_by_filter_request::GetByFilterBody {
+                pluck: vec!["timestamp".to_string(), "processes".to_string()],
+                advance_filters: vec![
+                    FilterCriteria {
+                        r#type: "criteria".to_string(),
+                        field: Some("device_id".to_string()),
+                        entity: Some(DBTable::SystemResources.into()),
+                        operator: Some(FilterOperator::Equal as i32),
+                        values: vec![format!("\"{}\"", device_id)],
+                        ..Default::default()
+                    },
+                    FilterCriteria {
+                        r#type: "operator".to_string(),
+                        operator: Some(FilterOperator::And as i32),
+                        ..Default::default()
+                    },
+                    FilterCriteria {
+                        r#type: "criteria".to_string(),
+                        field: Some("processes".to_string()),
+                        entity: Some(DBTable::SystemResources.into()),
+                        operator: Some(FilterOperator::IsNotNull as i32),
+                        ..Default::default()
+                    },
+                ],
+                limit: Some(limit),
+                order_by: Some("timestamp".to_string()),
+                order_direction: Some("desc".to_string()),
+                ..Default::default()
+            }),
+        };
+
+        let mut grpc_request = tonic::Request::new(request);
+        grpc_request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token)
+                .parse()
+                .handle_err(location!())?,
+        );
+
+        let response = self
+            .inner
+            .clone()
+            .get_by_filter(grpc_request)
+            .await
+            .handle_err(location!())?
+            .into_inner();
+
+        if response.count == 0 {
+            return Ok(vec![]);
+        }
+
+        let data: Vec<serde_json::Value> =
+            serde_json::from_str(&response.data).handle_err(location!())?;
+
+        let mut snapshots: Vec<ProcessSnapshotModel> =
+            data.into_iter().filter_map(parse_snapshot).collect();
+        snapshots.reverse();
+
+        Ok(snapshots)
+    }
+}
+
+fn parse_snapshot(mut record: serde_json::Value) -> Option<ProcessSnapshotModel> {
+    let timestamp = record.get("timestamp")?.as_str()?.to_string();
+
+    // The column holds the JSON encoding of the snapshot
+    let processes = record.get_mut("processes")?.take();
+    let snapshot = match processes.as_str() {
+        Some(encoded) => serde_json::from_str(encoded).ok()?,
+        None => processes,
+    };
+
+    Some(ProcessSnapshotModel {
+        timestamp,
+        snapshot,
+    })
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+pub struct FlowCollectorConfig {
+    pub(crate) addr: SocketAddr,
+    pub(crate) exporters: HashMap<IpAddr, String>,
+}
+
+impl FlowCollectorConfig {
+    /// Reads the collector configuration from the environment.
+    ///
+    /// The collector is optional: `None` is returned unless `FLOW_COLLECTOR_PORT` is set.
+    /// Exporters are mapped to registered devices through `FLOW_COLLECTOR_EXPORTERS`,
+    /// a comma separated list of `<exporter ip>=<device id>` pairs.
+    pub fn from_env() -> Option<Self> {
+        let port = std::env::var("FLOW_COLLECTOR_PORT")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok())?;
+
+        let host = std::env::var("FLOW_COLLECTOR_ADDR").unwrap_or_else(|_| "0.0.0.0".into());
+
+        let Ok(addr) = format!("{host}:{port}").parse::<SocketAddr>() else {
+            log::error!("Invalid flow collector address '{host}:{port}'");
+            return None;
+        };
+
+        let exporters = std::env::var("FLOW_COLLECTOR_EXPORTERS")
+            .map(|value| parse_exporters(&value))
+            .unwrap_or_default();
+
+        Some(Self { addr, exporters })
+    }
+}
+
+fn parse_exporters(value: &str) -> HashMap<IpAddr, String> {
+    let mut retval = HashMap::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry
+            .split_once('=')
+            .and_then(|(ip, device)| Some((ip.trim().parse().ok()?, device.trim())))
+            .filter(|(_, device)| !device.is_empty());
+
+        match parsed {
+            Some((ip, device)) => {
+                retval.insert(ip, device.to_string());
+            }
+            None => log::warn!("Ignoring malformed flow exporter mapping '{entry}'"),
+        }
+    }
+
+    retval
+}
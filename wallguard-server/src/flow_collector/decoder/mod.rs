@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+mod netflow_v5;
+mod sflow;
+mod template;
+
+pub use template::TemplateCache;
+
+/// A single flow as reported by an exporter, regardless of the export protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowRecord {
+    pub timestamp_ms: u64,
+    pub interface: u32,
+    pub source_ip: IpAddr,
+    pub destination_ip: IpAddr,
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub protocol: u8,
+    pub bytes: u64,
+    pub packets: u64,
+}
+
+impl FlowRecord {
+    /// Normalises the record into the shape the agents report connections in.
+    pub fn into_connection(self) -> Connection {
+        let timestamp = DateTime::<Utc>::from_timestamp_millis(self.timestamp_ms as i64)
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339();
+
+        let protocol = match self.protocol {
+            1 => "icmpv4".to_string(),
+            6 => "tcp".to_string(),
+            17 => "udp".to_string(),
+            58 => "icmpv6".to_string(),
+            other => other.to_string(),
+        };
+
+        let has_ports = matches!(self.protocol, 6 | 17);
+
+        Connection {
+            timestamp,
+            interface: format!("ifindex{}", self.interface),
+            source_ip: self.source_ip.to_string(),
+            destination_ip: self.destination_ip.to_string(),
+            source_port: has_ports.then_some(u32::from(self.source_port)),
+            destination_port: has_ports.then_some(u32::from(self.destination_port)),
+            protocol,
+            total_byte: self.bytes,
+            total_packet: u32::try_from(self.packets).unwrap_or(u32::MAX),
+        }
+    }
+}
+
+/// Decodes a NetFlow v5, NetFlow v9, IPFIX or sFlow v5 datagram.
+///
+/// Template based protocols need the templates previously announced by the same
+/// exporter, which are kept in `templates`.
+pub fn decode(
+    datagram: &[u8],
+    exporter: IpAddr,
+    templates: &mut TemplateCache,
+    now_ms: u64,
+) -> Option<Vec<FlowRecord>> {
+    let mut reader = Reader::new(datagram);
+
+    match reader.u16()? {
+        5 => netflow_v5::decode(datagram),
+        9 => templates.decode_netflow_v9(datagram, exporter),
+        10 => templates.decode_ipfix(datagram, exporter),
+        // sFlow uses a 32 bit version field
+        0 if reader.u16()? == 5 => sflow::decode(datagram, now_ms),
+        _ => None,
+    }
+}
+
+/// Bounds-checked big-endian reader over a datagram.
+pub(super) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn ipv4(&mut self) -> Option<IpAddr> {
+        let b = self.bytes(4)?;
+        Some(IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3])))
+    }
+
+    pub fn ipv6(&mut self) -> Option<IpAddr> {
+        let b: [u8; 16] = self.bytes(16)?.try_into().ok()?;
+        Some(IpAddr::V6(Ipv6Addr::from(b)))
+    }
+}
+
+/// Reads an unsigned integer encoded in up to 8 big-endian bytes,
+/// as allowed by the reduced-size encoding of NetFlow v9 and IPFIX.
+pub(super) fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .take(8)
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | u64::from(*b))
+}
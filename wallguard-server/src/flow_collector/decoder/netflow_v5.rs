@@ -0,0 +1,57 @@
+use super::{FlowRecord, Reader};
+
+const HEADER_LEN: usize = 24;
+const RECORD_LEN: usize = 48;
+
+pub fn decode(datagram: &[u8]) -> Option<Vec<FlowRecord>> {
+    let mut reader = Reader::new(datagram);
+
+    let _version = reader.u16()?;
+    let count = usize::from(reader.u16()?);
+    let sys_uptime = reader.u32()?;
+    let unix_secs = reader.u32()?;
+    let unix_nsecs = reader.u32()?;
+    reader.skip(HEADER_LEN - 16)?;
+
+    if reader.remaining() < count * RECORD_LEN {
+        return None;
+    }
+
+    // The switched times are relative to the exporter's uptime
+    let export_ms = u64::from(unix_secs) * 1000 + u64::from(unix_nsecs) / 1_000_000;
+    let boot_ms = export_ms.saturating_sub(u64::from(sys_uptime));
+
+    let mut records = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let source_ip = reader.ipv4()?;
+        let destination_ip = reader.ipv4()?;
+        let _next_hop = reader.u32()?;
+        let interface = u32::from(reader.u16()?);
+        let _output = reader.u16()?;
+        let packets = u64::from(reader.u32()?);
+        let bytes = u64::from(reader.u32()?);
+        let first = u64::from(reader.u32()?);
+        let _last = reader.u32()?;
+        let source_port = reader.u16()?;
+        let destination_port = reader.u16()?;
+        let _pad = reader.u8()?;
+        let _tcp_flags = reader.u8()?;
+        let protocol = reader.u8()?;
+        reader.skip(RECORD_LEN - 39)?;
+
+        records.push(FlowRecord {
+            timestamp_ms: boot_ms + first,
+            interface,
+            source_ip,
+            destination_ip,
+            source_port,
+            destination_port,
+            protocol,
+            bytes,
+            packets,
+        });
+    }
+
+    Some(records)
+}
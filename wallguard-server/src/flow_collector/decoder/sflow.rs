@@ -0,0 +1,201 @@
+use super::{FlowRecord, Reader};
+use std::net::IpAddr;
+
+const FLOW_SAMPLE: u32 = 1;
+const EXPANDED_FLOW_SAMPLE: u32 = 3;
+
+const RAW_PACKET_HEADER: u32 = 1;
+const IPV4_DATA: u32 = 3;
+const IPV6_DATA: u32 = 4;
+
+const HEADER_PROTOCOL_ETHERNET: u32 = 1;
+const HEADER_PROTOCOL_IPV4: u32 = 11;
+const HEADER_PROTOCOL_IPV6: u32 = 12;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+
+/// Decodes the flow samples of an sFlow v5 datagram.
+///
+/// Every sample stands for `sampling_rate` packets, so the counters are scaled
+/// accordingly. Counter samples are ignored.
+pub fn decode(datagram: &[u8], now_ms: u64) -> Option<Vec<FlowRecord>> {
+    let mut reader = Reader::new(datagram);
+
+    let _version = reader.u32()?;
+    match reader.u32()? {
+        1 => reader.skip(4)?,
+        2 => reader.skip(16)?,
+        _ => return None,
+    }
+    let _sub_agent_id = reader.u32()?;
+    let _sequence = reader.u32()?;
+    let _uptime = reader.u32()?;
+    let samples = reader.u32()?;
+
+    let mut records = Vec::new();
+
+    for _ in 0..samples {
+        let format = reader.u32()?;
+        let length = reader.u32()? as usize;
+        let mut sample = Reader::new(reader.bytes(length)?);
+
+        // Enterprise specific formats are not decoded
+        let (sampling_rate, interface) = match format {
+            FLOW_SAMPLE => {
+                sample.skip(8)?;
+                let sampling_rate = sample.u32()?;
+                sample.skip(8)?;
+                let input = sample.u32()? & 0x3FFF_FFFF;
+                sample.skip(4)?;
+                (sampling_rate, input)
+            }
+            EXPANDED_FLOW_SAMPLE => {
+                sample.skip(12)?;
+                let sampling_rate = sample.u32()?;
+                sample.skip(12)?;
+                let input = sample.u32()?;
+                sample.skip(8)?;
+                (sampling_rate, input)
+            }
+            _ => continue,
+        };
+
+        let flow_records = sample.u32()?;
+
+        for _ in 0..flow_records {
+            let format = sample.u32()?;
+            let length = sample.u32()? as usize;
+            let data = sample.bytes(length.next_multiple_of(4).min(sample.remaining()))?;
+
+            let Some(flow) = decode_flow_record(format, data) else {
+                continue;
+            };
+
+            let sampling_rate = u64::from(sampling_rate.max(1));
+
+            records.push(FlowRecord {
+                timestamp_ms: now_ms,
+                interface,
+                source_ip: flow.source_ip,
+                destination_ip: flow.destination_ip,
+                source_port: flow.source_port,
+                destination_port: flow.destination_port,
+                protocol: flow.protocol,
+                bytes: u64::from(flow.length) * sampling_rate,
+                packets: sampling_rate,
+            });
+
+            // A sample may describe the same packet in several formats, count it once
+            break;
+        }
+    }
+
+    Some(records)
+}
+
+struct SampledPacket {
+    source_ip: IpAddr,
+    destination_ip: IpAddr,
+    source_port: u16,
+    destination_port: u16,
+    protocol: u8,
+    length: u32,
+}
+
+fn decode_flow_record(format: u32, data: &[u8]) -> Option<SampledPacket> {
+    let mut reader = Reader::new(data);
+
+    match format {
+        RAW_PACKET_HEADER => {
+            let header_protocol = reader.u32()?;
+            let frame_length = reader.u32()?;
+            let _stripped = reader.u32()?;
+            let header_length = reader.u32()? as usize;
+            let header = reader.bytes(header_length)?;
+
+            let (ethertype, network) = match header_protocol {
+                HEADER_PROTOCOL_ETHERNET => ethernet_payload(header)?,
+                HEADER_PROTOCOL_IPV4 => (ETHERTYPE_IPV4, header),
+                HEADER_PROTOCOL_IPV6 => (ETHERTYPE_IPV6, header),
+                _ => return None,
+            };
+
+            parse_network(ethertype, network, frame_length)
+        }
+        IPV4_DATA | IPV6_DATA => {
+            let length = reader.u32()?;
+            let protocol = reader.u32()? as u8;
+            let (source_ip, destination_ip) = if format == IPV4_DATA {
+                (reader.ipv4()?, reader.ipv4()?)
+            } else {
+                (reader.ipv6()?, reader.ipv6()?)
+            };
+            let source_port = reader.u32()? as u16;
+            let destination_port = reader.u32()? as u16;
+
+            Some(SampledPacket {
+                source_ip,
+                destination_ip,
+                source_port,
+                destination_port,
+                protocol,
+                length,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn ethernet_payload(frame: &[u8]) -> Option<(u16, &[u8])> {
+    let mut reader = Reader::new(frame);
+    reader.skip(12)?;
+
+    let mut ethertype = reader.u16()?;
+    while matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+        reader.skip(2)?;
+        ethertype = reader.u16()?;
+    }
+
+    Some((ethertype, reader.bytes(reader.remaining())?))
+}
+
+fn parse_network(ethertype: u16, packet: &[u8], frame_length: u32) -> Option<SampledPacket> {
+    let mut reader = Reader::new(packet);
+
+    let (source_ip, destination_ip, protocol) = match ethertype {
+        ETHERTYPE_IPV4 => {
+            let header_len = usize::from(reader.u8()? & 0x0F) * 4;
+            reader.skip(8)?;
+            let protocol = reader.u8()?;
+            reader.skip(2)?;
+            let addresses = (reader.ipv4()?, reader.ipv4()?);
+            reader.skip(header_len.checked_sub(20)?)?;
+            (addresses.0, addresses.1, protocol)
+        }
+        ETHERTYPE_IPV6 => {
+            reader.skip(6)?;
+            let protocol = reader.u8()?;
+            reader.skip(1)?;
+            (reader.ipv6()?, reader.ipv6()?, protocol)
+        }
+        _ => return None,
+    };
+
+    // Ports may have been cut off by the header truncation
+    let (source_port, destination_port) = match protocol {
+        6 | 17 => (reader.u16().unwrap_or(0), reader.u16().unwrap_or(0)),
+        _ => (0, 0),
+    };
+
+    Some(SampledPacket {
+        source_ip,
+        destination_ip,
+        source_port,
+        destination_port,
+        protocol,
+        length: frame_length,
+    })
+}
@@ -0,0 +1,509 @@
+use super::{FlowRecord, Reader, read_uint};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const NETFLOW_V9_TEMPLATE_SET_ID: u16 = 0;
+const IPFIX_TEMPLATE_SET_ID: u16 = 2;
+const MIN_DATA_SET_ID: u16 = 256;
+
+const VARIABLE_LENGTH: u16 = 65535;
+const ENTERPRISE_BIT: u16 = 0x8000;
+
+// Information elements shared by NetFlow v9 and IPFIX
+const IN_BYTES: u16 = 1;
+const IN_PKTS: u16 = 2;
+const PROTOCOL: u16 = 4;
+const L4_SRC_PORT: u16 = 7;
+const IPV4_SRC_ADDR: u16 = 8;
+const INPUT_SNMP: u16 = 10;
+const L4_DST_PORT: u16 = 11;
+const IPV4_DST_ADDR: u16 = 12;
+const FIRST_SWITCHED: u16 = 22;
+const IPV6_SRC_ADDR: u16 = 27;
+const IPV6_DST_ADDR: u16 = 28;
+const SAMPLING_INTERVAL: u16 = 34;
+// IPFIX only
+const OCTET_TOTAL_COUNT: u16 = 85;
+const PACKET_TOTAL_COUNT: u16 = 86;
+const FLOW_START_SECONDS: u16 = 150;
+const FLOW_START_MILLISECONDS: u16 = 152;
+const SYSTEM_INIT_TIME_MILLISECONDS: u16 = 160;
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+struct TemplateKey {
+    exporter: IpAddr,
+    ipfix: bool,
+    domain: u32,
+    template_id: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TemplateField {
+    id: u16,
+    length: u16,
+    enterprise: bool,
+}
+
+/// Templates announced by the exporters, scoped by exporter address and
+/// source ID (NetFlow v9) or observation domain (IPFIX).
+#[derive(Debug, Default)]
+pub struct TemplateCache {
+    templates: HashMap<TemplateKey, Vec<TemplateField>>,
+}
+
+impl TemplateCache {
+    pub fn decode_netflow_v9(
+        &mut self,
+        datagram: &[u8],
+        exporter: IpAddr,
+    ) -> Option<Vec<FlowRecord>> {
+        let mut reader = Reader::new(datagram);
+
+        let _version = reader.u16()?;
+        let _count = reader.u16()?;
+        let sys_uptime = reader.u32()?;
+        let unix_secs = reader.u32()?;
+        let _sequence = reader.u32()?;
+        let source_id = reader.u32()?;
+
+        let export_ms = u64::from(unix_secs) * 1000;
+        let time = TimeBase {
+            export_ms,
+            boot_ms: Some(export_ms.saturating_sub(u64::from(sys_uptime))),
+        };
+
+        self.decode_sets(reader, exporter, false, source_id, time)
+    }
+
+    pub fn decode_ipfix(&mut self, datagram: &[u8], exporter: IpAddr) -> Option<Vec<FlowRecord>> {
+        let mut reader = Reader::new(datagram);
+
+        let _version = reader.u16()?;
+        let length = usize::from(reader.u16()?);
+        let export_time = reader.u32()?;
+        let _sequence = reader.u32()?;
+        let domain = reader.u32()?;
+
+        let datagram = datagram.get(..length)?;
+        let mut reader = Reader::new(datagram);
+        reader.skip(16)?;
+
+        let time = TimeBase {
+            export_ms: u64::from(export_time) * 1000,
+            boot_ms: None,
+        };
+
+        self.decode_sets(reader, exporter, true, domain, time)
+    }
+
+    fn decode_sets(
+        &mut self,
+        mut reader: Reader,
+        exporter: IpAddr,
+        ipfix: bool,
+        domain: u32,
+        time: TimeBase,
+    ) -> Option<Vec<FlowRecord>> {
+        let template_set_id = if ipfix {
+            IPFIX_TEMPLATE_SET_ID
+        } else {
+            NETFLOW_V9_TEMPLATE_SET_ID
+        };
+
+        let mut records = Vec::new();
+
+        while reader.remaining() >= 4 {
+            let set_id = reader.u16()?;
+            let set_len = usize::from(reader.u16()?);
+            if set_len < 4 {
+                break;
+            }
+
+            // A truncated set ends the datagram, keeping the records decoded so far
+            let Some(body) = reader.bytes(set_len - 4) else {
+                break;
+            };
+            let body = Reader::new(body);
+
+            let key = TemplateKey {
+                exporter,
+                ipfix,
+                domain,
+                template_id: set_id,
+            };
+
+            if set_id == template_set_id {
+                self.parse_templates(body, key, ipfix);
+            } else if set_id >= MIN_DATA_SET_ID {
+                // Data sets for unknown templates (including options templates)
+                // are skipped until the exporter announces them
+                if let Some(fields) = self.templates.get(&key) {
+                    decode_data_set(body, fields, time, &mut records);
+                }
+            }
+        }
+
+        Some(records)
+    }
+
+    fn parse_templates(&mut self, mut body: Reader, key: TemplateKey, ipfix: bool) {
+        while body.remaining() >= 4 {
+            let (Some(template_id), Some(field_count)) = (body.u16(), body.u16()) else {
+                return;
+            };
+
+            let key = TemplateKey { template_id, ..key };
+
+            // A template with no fields withdraws a previously announced one
+            if field_count == 0 {
+                self.templates.remove(&key);
+                continue;
+            }
+
+            let mut fields = Vec::with_capacity(usize::from(field_count));
+
+            for _ in 0..field_count {
+                let (Some(id), Some(length)) = (body.u16(), body.u16()) else {
+                    return;
+                };
+
+                let enterprise = ipfix && id & ENTERPRISE_BIT != 0;
+                if enterprise && body.skip(4).is_none() {
+                    return;
+                }
+
+                fields.push(TemplateField {
+                    id: id & !ENTERPRISE_BIT,
+                    length,
+                    enterprise,
+                });
+            }
+
+            self.templates.insert(key, fields);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimeBase {
+    export_ms: u64,
+    boot_ms: Option<u64>,
+}
+
+fn decode_data_set(
+    mut body: Reader,
+    fields: &[TemplateField],
+    time: TimeBase,
+    records: &mut Vec<FlowRecord>,
+) {
+    // Variable length fields take at least one byte, the rest of the set may be padding
+    let min_len: usize = fields
+        .iter()
+        .map(|f| match f.length {
+            VARIABLE_LENGTH => 1,
+            len => usize::from(len),
+        })
+        .sum();
+
+    if min_len == 0 {
+        return;
+    }
+
+    while body.remaining() >= min_len {
+        let Some(record) = decode_record(&mut body, fields, time) else {
+            return;
+        };
+        records.extend(record);
+    }
+}
+
+/// Decodes a single data record, returning `Some(None)` for
+/// well-formed records that do not describe an IP flow.
+fn decode_record(
+    body: &mut Reader,
+    fields: &[TemplateField],
+    time: TimeBase,
+) -> Option<Option<FlowRecord>> {
+    let mut source_ip = None;
+    let mut destination_ip = None;
+    let mut source_port = 0;
+    let mut destination_port = 0;
+    let mut protocol = 0;
+    let mut interface = 0;
+    let mut bytes = None;
+    let mut packets = None;
+    let mut total_bytes = None;
+    let mut total_packets = None;
+    let mut sampling_interval = 1;
+    let mut start_ms = None;
+    let mut start_uptime = None;
+    let mut boot_ms = time.boot_ms;
+
+    for field in fields {
+        let length = match field.length {
+            VARIABLE_LENGTH => match body.u8()? {
+                255 => usize::from(body.u16()?),
+                len => usize::from(len),
+            },
+            len => usize::from(len),
+        };
+
+        let value = body.bytes(length)?;
+
+        if field.enterprise {
+            continue;
+        }
+
+        match (field.id, length) {
+            (IPV4_SRC_ADDR, 4) => source_ip = Reader::new(value).ipv4(),
+            (IPV4_DST_ADDR, 4) => destination_ip = Reader::new(value).ipv4(),
+            (IPV6_SRC_ADDR, 16) => source_ip = Reader::new(value).ipv6(),
+            (IPV6_DST_ADDR, 16) => destination_ip = Reader::new(value).ipv6(),
+            (L4_SRC_PORT, _) => source_port = read_uint(value) as u16,
+            (L4_DST_PORT, _) => destination_port = read_uint(value) as u16,
+            (PROTOCOL, _) => protocol = read_uint(value) as u8,
+            (INPUT_SNMP, _) => interface = read_uint(value) as u32,
+            (IN_BYTES, _) => bytes = Some(read_uint(value)),
+            (IN_PKTS, _) => packets = Some(read_uint(value)),
+            (OCTET_TOTAL_COUNT, _) => total_bytes = Some(read_uint(value)),
+            (PACKET_TOTAL_COUNT, _) => total_packets = Some(read_uint(value)),
+            (SAMPLING_INTERVAL, _) => sampling_interval = read_uint(value).max(1),
+            (FIRST_SWITCHED, _) => start_uptime = Some(read_uint(value)),
+            (FLOW_START_SECONDS, _) => start_ms = Some(read_uint(value).saturating_mul(1000)),
+            (FLOW_START_MILLISECONDS, _) => start_ms = Some(read_uint(value)),
+            (SYSTEM_INIT_TIME_MILLISECONDS, _) => boot_ms = Some(read_uint(value)),
+            _ => {}
+        }
+    }
+
+    let (Some(source_ip), Some(destination_ip)) = (source_ip, destination_ip) else {
+        return Some(None);
+    };
+
+    // Every value comes from the exporter, so the arithmetic must not overflow
+    let timestamp_ms = start_ms
+        .or_else(|| Some(boot_ms?.saturating_add(start_uptime?)))
+        .unwrap_or(time.export_ms);
+
+    Some(Some(FlowRecord {
+        timestamp_ms,
+        interface,
+        source_ip,
+        destination_ip,
+        source_port,
+        destination_port,
+        protocol,
+        bytes: bytes
+            .or(total_bytes)
+            .unwrap_or_default()
+            .saturating_mul(sampling_interval),
+        packets: packets
+            .or(total_packets)
+            .unwrap_or_default()
+            .saturating_mul(sampling_interval),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const EXPORTER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    fn set(id: u16, body: &[u8]) -> Vec<u8> {
+        let mut set = Vec::new();
+        set.extend_from_slice(&id.to_be_bytes());
+        set.extend_from_slice(&((body.len() + 4) as u16).to_be_bytes());
+        set.extend_from_slice(body);
+        set
+    }
+
+    fn fields(template_id: u16, fields: &[(u16, u16)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&template_id.to_be_bytes());
+        body.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+        for (id, len) in fields {
+            body.extend_from_slice(&id.to_be_bytes());
+            body.extend_from_slice(&len.to_be_bytes());
+        }
+        body
+    }
+
+    fn netflow_v9(sets: &[Vec<u8>]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&9u16.to_be_bytes());
+        packet.extend_from_slice(&(sets.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&10_000u32.to_be_bytes());
+        packet.extend_from_slice(&1_700_000_000u32.to_be_bytes());
+        packet.extend_from_slice(&0u32.to_be_bytes());
+        packet.extend_from_slice(&7u32.to_be_bytes());
+        for set in sets {
+            packet.extend_from_slice(set);
+        }
+        packet
+    }
+
+    #[test]
+    fn test_netflow_v9_template_then_data() {
+        let mut cache = TemplateCache::default();
+
+        let template = set(
+            0,
+            &fields(
+                256,
+                &[
+                    (IPV4_SRC_ADDR, 4),
+                    (IPV4_DST_ADDR, 4),
+                    (L4_SRC_PORT, 2),
+                    (L4_DST_PORT, 2),
+                    (PROTOCOL, 1),
+                    (IN_BYTES, 4),
+                    (IN_PKTS, 4),
+                    (FIRST_SWITCHED, 4),
+                ],
+            ),
+        );
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&1234u16.to_be_bytes());
+        data.extend_from_slice(&443u16.to_be_bytes());
+        data.push(6);
+        data.extend_from_slice(&1500u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend_from_slice(&4_000u32.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0]);
+
+        // Data arriving before its template cannot be decoded
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[set(256, &data)]), EXPORTER)
+            .unwrap();
+        assert!(records.is_empty());
+
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[template, set(256, &data)]), EXPORTER)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].source_ip, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(records[0].destination_port, 443);
+        assert_eq!(records[0].protocol, 6);
+        assert_eq!(records[0].bytes, 1500);
+        assert_eq!(records[0].packets, 3);
+        assert_eq!(records[0].timestamp_ms, 1_700_000_000_000 - 10_000 + 4_000);
+
+        // Templates are scoped by exporter
+        let other = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[set(256, &data)]), other)
+            .unwrap();
+        assert!(records.is_empty());
+
+        // A truncated last set keeps the records decoded before it
+        let mut truncated = set(256, &data);
+        truncated.truncate(truncated.len() - 8);
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[set(256, &data), truncated]), EXPORTER)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn test_ipfix_enterprise_and_variable_length_fields() {
+        let mut cache = TemplateCache::default();
+
+        let mut template = fields(
+            300,
+            &[
+                (IPV4_SRC_ADDR, 4),
+                (IPV4_DST_ADDR, 4),
+                (PROTOCOL, 1),
+                (OCTET_TOTAL_COUNT, 8),
+                (FLOW_START_MILLISECONDS, 8),
+            ],
+        );
+        // Enterprise specific, variable length field
+        template[2..4].copy_from_slice(&6u16.to_be_bytes());
+        template.extend_from_slice(&(ENTERPRISE_BIT | 1).to_be_bytes());
+        template.extend_from_slice(&VARIABLE_LENGTH.to_be_bytes());
+        template.extend_from_slice(&29305u32.to_be_bytes());
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        data.push(17);
+        data.extend_from_slice(&4096u64.to_be_bytes());
+        data.extend_from_slice(&1_700_000_000_123u64.to_be_bytes());
+        data.extend_from_slice(&[3, b'a', b'b', b'c']);
+
+        let sets = [set(2, &template), set(300, &data)];
+        let len: usize = 16 + sets.iter().map(Vec::len).sum::<usize>();
+
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&10u16.to_be_bytes());
+        packet.extend_from_slice(&(len as u16).to_be_bytes());
+        packet.extend_from_slice(&1_700_000_001u32.to_be_bytes());
+        packet.extend_from_slice(&0u32.to_be_bytes());
+        packet.extend_from_slice(&1u32.to_be_bytes());
+        for set in &sets {
+            packet.extend_from_slice(set);
+        }
+
+        let records = cache.decode_ipfix(&packet, EXPORTER).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].protocol, 17);
+        assert_eq!(records[0].bytes, 4096);
+        assert_eq!(records[0].packets, 0);
+        assert_eq!(records[0].timestamp_ms, 1_700_000_000_123);
+    }
+
+    #[test]
+    fn test_hostile_template_saturates() {
+        let mut cache = TemplateCache::default();
+
+        let template = set(
+            0,
+            &fields(
+                257,
+                &[
+                    (IPV4_SRC_ADDR, 4),
+                    (IPV4_DST_ADDR, 4),
+                    (IN_BYTES, 8),
+                    (IN_PKTS, 8),
+                    (SAMPLING_INTERVAL, 8),
+                    (FLOW_START_SECONDS, 8),
+                ],
+            ),
+        );
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        for _ in 0..4 {
+            data.extend_from_slice(&u64::MAX.to_be_bytes());
+        }
+
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[template, set(257, &data)]), EXPORTER)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].bytes, u64::MAX);
+        assert_eq!(records[0].packets, u64::MAX);
+        assert_eq!(records[0].timestamp_ms, u64::MAX);
+
+        // Uptime relative start times cannot overflow either
+        let template = set(
+            0,
+            &fields(
+                258,
+                &[(IPV4_SRC_ADDR, 4), (IPV4_DST_ADDR, 4), (FIRST_SWITCHED, 8)],
+            ),
+        );
+        let mut data = Vec::new();
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+
+        let records = cache
+            .decode_netflow_v9(&netflow_v9(&[template, set(258, &data)]), EXPORTER)
+            .unwrap();
+        assert_eq!(records[0].timestamp_ms, u64::MAX);
+    }
+}
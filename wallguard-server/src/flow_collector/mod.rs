@@ -0,0 +1,239 @@
+mod config;
+mod decoder;
+
+use crate::app_context::AppContext;
+use config::FlowCollectorConfig;
+use decoder::TemplateCache;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use nullnet_libipinfo::get_ip_to_lookup;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use wallguard_common::protobuf::wallguard_service::{Connection, ConnectionsData};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_PENDING_CONNECTIONS: usize = 5_000;
+const DEVICE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Starts the optional NetFlow v5/v9, IPFIX and sFlow collector.
+///
+/// Flows exported by devices that cannot run the agent are normalised into
+/// connections and stored exactly like the ones reported by the agents.
+/// Unlike the control service, the collector is not critical: if it's disabled
+/// or cannot start, the rest of the server keeps running.
+pub async fn run_flow_collector(context: AppContext, ip_info_tx: mpsc::Sender<Option<IpAddr>>) {
+    let Some(config) = FlowCollectorConfig::from_env() else {
+        log::info!("Flow collector is disabled");
+        return std::future::pending().await;
+    };
+
+    let socket = match UdpSocket::bind(config.addr).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            log::error!("Failed to start flow collector on {}: {err}", config.addr);
+            return std::future::pending().await;
+        }
+    };
+
+    if config.exporters.is_empty() {
+        log::warn!("Flow collector has no exporters configured, all flows will be dropped");
+    }
+
+    log::info!(
+        "Flow collector running on {} ({} exporters)",
+        config.addr,
+        config.exporters.len()
+    );
+
+    FlowCollector::new(context, config.exporters, ip_info_tx)
+        .run(socket)
+        .await;
+}
+
+struct FlowCollector {
+    context: AppContext,
+    exporters: HashMap<IpAddr, String>,
+    templates: TemplateCache,
+    pending: HashMap<String, Vec<Connection>>,
+    /// Whether each device is monitored, shared with the tasks storing its flows
+    devices: Arc<Mutex<HashMap<String, (bool, Instant)>>>,
+    unknown_exporters: HashSet<IpAddr>,
+    ip_info_tx: mpsc::Sender<Option<IpAddr>>,
+}
+
+impl FlowCollector {
+    fn new(
+        context: AppContext,
+        exporters: HashMap<IpAddr, String>,
+        ip_info_tx: mpsc::Sender<Option<IpAddr>>,
+    ) -> Self {
+        Self {
+            context,
+            exporters,
+            templates: TemplateCache::default(),
+            pending: HashMap::new(),
+            devices: Default::default(),
+            unknown_exporters: HashSet::new(),
+            ip_info_tx,
+        }
+    }
+
+    async fn run(mut self, socket: UdpSocket) {
+        let mut buffer = vec![0u8; u16::MAX as usize];
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                result = socket.recv_from(&mut buffer) => match result {
+                    Ok((len, from)) => {
+                        self.handle_datagram(&buffer[..len], from.ip().to_canonical());
+
+                        if self.pending.values().map(Vec::len).sum::<usize>() >= MAX_PENDING_CONNECTIONS {
+                            self.flush();
+                        }
+                    }
+                    Err(err) => log::error!("Flow collector failed to receive datagram: {err}"),
+                },
+                _ = ticker.tick() => self.flush(),
+            }
+        }
+    }
+
+    fn handle_datagram(&mut self, datagram: &[u8], exporter: IpAddr) {
+        let Some(device_id) = self.exporters.get(&exporter) else {
+            if self.unknown_exporters.insert(exporter) {
+                log::warn!("Dropping flows from unregistered exporter {exporter}");
+            }
+            return;
+        };
+
+        let Some(records) = decoder::decode(datagram, exporter, &mut self.templates, now_ms())
+        else {
+            log::debug!("Failed to decode flow datagram from {exporter}");
+            return;
+        };
+
+        if records.is_empty() {
+            return;
+        }
+
+        let pending = self.pending.entry(device_id.clone()).or_default();
+
+        for record in records {
+            let _ = self
+                .ip_info_tx
+                .send(get_ip_to_lookup(record.source_ip, record.destination_ip));
+            pending.push(record.into_connection());
+        }
+    }
+
+    /// Hands the pending flows over to one task per device, so that the socket
+    /// keeps being drained while the datastore is queried.
+    fn flush(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+
+        for (device_id, connections) in pending {
+            let context = self.context.clone();
+            let devices = self.devices.clone();
+
+            tokio::spawn(async move {
+                match is_device_monitored(&context, &devices, &device_id).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        log::debug!(
+                            "Dropping flows for device {device_id}: not authorized or not monitored"
+                        );
+                        return;
+                    }
+                    Err(err) => {
+                        log::error!("Failed to obtain device {device_id}: {}", err.to_str());
+                        return;
+                    }
+                }
+
+                context
+                    .threat_intel
+                    .inspect(&context, &device_id, &connections);
+                context.anomaly_detector.inspect(&device_id, &connections);
+
+                let ingestion_start = Instant::now();
+                let count = connections.len();
+                let data = ConnectionsData {
+                    token: String::new(),
+                    connections,
+                };
+
+                let result = match context.sysdev_token_provider.get().await {
+                    Ok(token) => {
+                        let datastore_start = Instant::now();
+                        let result = context
+                            .datastore
+                            .create_connections(&token.jwt, &device_id, data)
+                            .await;
+                        context
+                            .metrics
+                            .observe_datastore("create_connections", datastore_start.elapsed());
+                        result
+                    }
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(()) => {
+                        context.metrics.observe_ingestion(
+                            "flows",
+                            count,
+                            ingestion_start.elapsed(),
+                        );
+                        log::debug!("Stored {count} collected flows for device {device_id}");
+                    }
+                    Err(err) => log::error!(
+                        "Failed to store collected flows for device {device_id}: {}",
+                        err.to_str()
+                    ),
+                }
+            });
+        }
+    }
+}
+
+async fn is_device_monitored(
+    context: &AppContext,
+    devices: &Mutex<HashMap<String, (bool, Instant)>>,
+    device_id: &str,
+) -> Result<bool, Error> {
+    let cached = devices
+        .lock()
+        .handle_err(location!())?
+        .get(device_id)
+        .filter(|(_, checked_at)| checked_at.elapsed() < DEVICE_CACHE_TTL)
+        .map(|(monitored, _)| *monitored);
+
+    if let Some(monitored) = cached {
+        return Ok(monitored);
+    }
+
+    let token = context.root_token_provider.get().await?;
+
+    let monitored = context
+        .datastore
+        .obtain_device_by_id(&token.jwt, device_id, true)
+        .await?
+        .is_some_and(|device| device.authorized && device.traffic_monitoring);
+
+    devices
+        .lock()
+        .handle_err(location!())?
+        .insert(device_id.to_string(), (monitored, Instant::now()));
+
+    Ok(monitored)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
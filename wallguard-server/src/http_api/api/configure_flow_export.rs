@@ -0,0 +1,97 @@
+use crate::app_context::AppContext;
+use crate::http_api::utilities::authorization;
+use crate::http_api::utilities::error_json::ErrorJson;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+
+use actix_web::web::Data;
+use actix_web::web::Json;
+use serde::Deserialize;
+use serde_json::json;
+use wallguard_common::protobuf::wallguard_commands::{FlowExportProtocol, FlowExportSettings};
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct RequestPayload {
+    device_id: String,
+    instance_id: String,
+    enable: bool,
+    #[serde(default)]
+    protocol: Option<String>,
+    #[serde(default)]
+    collectors: Vec<String>,
+    #[serde(default)]
+    active_timeout: u32,
+    #[serde(default)]
+    idle_timeout: u32,
+    #[serde(default)]
+    observation_domain_id: u32,
+}
+
+pub async fn configure_flow_export(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    body: Json<RequestPayload>,
+) -> impl Responder {
+    let Some(jwt) = authorization::extract_authorization_token(&request) else {
+        return HttpResponse::Unauthorized().json(ErrorJson::from("Missing Authorization header"));
+    };
+
+    let protocol = match body
+        .protocol
+        .as_deref()
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        None | Some("netflow") | Some("netflow_v9") => FlowExportProtocol::NetflowV9,
+        Some("ipfix") => FlowExportProtocol::Ipfix,
+        Some(_) => {
+            return HttpResponse::BadRequest()
+                .json(ErrorJson::from("Unsupported flow export protocol"));
+        }
+    };
+
+    if body.enable && body.collectors.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorJson::from("No collectors specified"));
+    }
+
+    let Ok(device) = context
+        .datastore
+        .obtain_device_by_id(&jwt, &body.device_id, false)
+        .await
+    else {
+        return HttpResponse::InternalServerError()
+            .json(ErrorJson::from("Failed to fetch device record"));
+    };
+
+    let Some(device) = device else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Device not found"));
+    };
+
+    if !device.authorized {
+        return HttpResponse::BadRequest().json(ErrorJson::from("Device is not authorized yet"));
+    }
+
+    let Some(client) = context
+        .orchestractor
+        .get_client(&device.id, &body.instance_id)
+        .await
+    else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Device is not online"));
+    };
+
+    let settings = FlowExportSettings {
+        enabled: body.enable,
+        protocol: protocol.into(),
+        collectors: body.collectors.clone(),
+        active_timeout_seconds: body.active_timeout,
+        idle_timeout_seconds: body.idle_timeout,
+        observation_domain_id: body.observation_domain_id,
+    };
+
+    if let Err(err) = client.lock().await.configure_flow_export(settings).await {
+        return HttpResponse::InternalServerError().json(ErrorJson::from(err));
+    }
+
+    HttpResponse::Ok().json(json!({}))
+}
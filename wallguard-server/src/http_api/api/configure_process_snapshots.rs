@@ -0,0 +1,94 @@
+use crate::app_context::AppContext;
+use crate::http_api::utilities::authorization;
+use crate::http_api::utilities::error_json::ErrorJson;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+
+use actix_web::web::Data;
+use actix_web::web::Json;
+use serde::Deserialize;
+use serde_json::json;
+use wallguard_common::protobuf::wallguard_commands::ProcessSnapshotSettings;
+
+const DEFAULT_INTERVAL_SECONDS: u32 = 60;
+const DEFAULT_TOP_N: u32 = 10;
+const MIN_INTERVAL_SECONDS: u32 = 5;
+const MAX_TOP_N: u32 = 50;
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct RequestPayload {
+    device_id: String,
+    instance_id: String,
+    enable: bool,
+    #[serde(default)]
+    interval: Option<u32>,
+    #[serde(default)]
+    top_n: Option<u32>,
+}
+
+pub async fn configure_process_snapshots(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    body: Json<RequestPayload>,
+) -> impl Responder {
+    let Some(jwt) = authorization::extract_authorization_token(&request) else {
+        return HttpResponse::Unauthorized().json(ErrorJson::from("Missing Authorization header"));
+    };
+
+    let interval_seconds = body.interval.unwrap_or(DEFAULT_INTERVAL_SECONDS);
+    if interval_seconds < MIN_INTERVAL_SECONDS {
+        return HttpResponse::BadRequest().json(ErrorJson::from(format!(
+            "Interval must be at least {MIN_INTERVAL_SECONDS} seconds"
+        )));
+    }
+
+    let top_n = body.top_n.unwrap_or(DEFAULT_TOP_N);
+    if !(1..=MAX_TOP_N).contains(&top_n) {
+        return HttpResponse::BadRequest().json(ErrorJson::from(format!(
+            "Top N must be between 1 and {MAX_TOP_N}"
+        )));
+    }
+
+    let Ok(device) = context
+        .datastore
+        .obtain_device_by_id(&jwt, &body.device_id, false)
+        .await
+    else {
+        return HttpResponse::InternalServerError()
+            .json(ErrorJson::from("Failed to fetch device record"));
+    };
+
+    let Some(device) = device else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Device not found"));
+    };
+
+    if !device.authorized {
+        return HttpResponse::BadRequest().json(ErrorJson::from("Device is not authorized yet"));
+    }
+
+    let Some(client) = context
+        .orchestractor
+        .get_client(&device.id, &body.instance_id)
+        .await
+    else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Device is not online"));
+    };
+
+    let settings = ProcessSnapshotSettings {
+        enabled: body.enable,
+        interval_seconds,
+        top_n,
+    };
+
+    if let Err(err) = client
+        .lock()
+        .await
+        .configure_process_snapshots(settings)
+        .await
+    {
+        return HttpResponse::InternalServerError().json(ErrorJson::from(err));
+    }
+
+    HttpResponse::Ok().json(json!({}))
+}
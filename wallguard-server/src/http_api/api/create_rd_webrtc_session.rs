@@ -0,0 +1,60 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Data, Json},
+};
+use serde::Deserialize;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+use crate::{
+    app_context::AppContext,
+    http_api::{
+        rd_gateway_v2::webrtc_relay,
+        utilities::{authorization, error_json::ErrorJson},
+    },
+    tunneling::tunnel_common::WallguardTunnel,
+};
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct RequestPayload {
+    tunnel_id: String,
+    /// SDP offer of the viewer, as `RTCPeerConnection.localDescription`
+    offer: RTCSessionDescription,
+}
+
+/// WebRTC signaling for remote desktop viewers: answers their offer with the
+/// server's, candidates included. The WebSocket gateway stays the fallback.
+pub async fn create_rd_webrtc_session(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    body: Json<RequestPayload>,
+) -> impl Responder {
+    let Some(jwt) = authorization::extract_authorization_token(&request) else {
+        return HttpResponse::Unauthorized().json(ErrorJson::from("Missing Authorization header"));
+    };
+
+    let tunnel_id = body.tunnel_id.to_ascii_uppercase();
+
+    match context
+        .datastore
+        .obtain_tunnel(&jwt, &tunnel_id, false)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().json(ErrorJson::from("Tunnel not found")),
+        Err(_) => {
+            return HttpResponse::InternalServerError()
+                .json(ErrorJson::from("Failed to fetch session"));
+        }
+    }
+
+    let Some(WallguardTunnel::Rd(rd_tunnel)) = context.tunnels_manager.get(&tunnel_id).await else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Tunnel not found"));
+    };
+
+    let Json(RequestPayload { offer, .. }) = body;
+
+    match webrtc_relay::accept_offer(context.into_inner(), rd_tunnel, offer).await {
+        Ok(answer) => HttpResponse::Ok().json(answer),
+        Err(_) => HttpResponse::BadRequest().json(ErrorJson::from("Failed to negotiate WebRTC")),
+    }
+}
@@ -7,12 +7,20 @@ use actix_web::{
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{app_context::AppContext, http_api::utilities::authorization};
+use crate::{
+    app_context::AppContext,
+    http_api::utilities::authorization,
+    tunneling::{capture::CaptureParameters, rd::RdParameters},
+};
 
 #[derive(Deserialize)]
 pub(in crate::http_api) struct RequestPayload {
     device_id: String,
     service_id: String,
+    #[serde(default)]
+    capture: Option<CaptureParameters>,
+    #[serde(default)]
+    remote_desktop: Option<RdParameters>,
 }
 
 pub async fn create_tunnel(
@@ -30,6 +38,8 @@ pub async fn create_tunnel(
             &jwt,
             &body.device_id,
             &body.service_id,
+            body.capture.clone(),
+            body.remote_desktop,
             context.clone().into_inner(),
         )
         .await
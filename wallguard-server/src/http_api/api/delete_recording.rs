@@ -0,0 +1,43 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Data, Query},
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    app_context::AppContext,
+    http_api::utilities::{error_json::ErrorJson, request_handling},
+};
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct QueryParams {
+    id: String,
+}
+
+pub async fn delete_recording(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    query: Query<QueryParams>,
+) -> impl Responder {
+    let Some(info) = context.rd_recordings.get(&query.id).await else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Recording not found"));
+    };
+
+    if let Err(response) =
+        request_handling::authorized_device(&request, &context, &info.device_id).await
+    {
+        return response;
+    }
+
+    if context.rd_recordings.is_active(&info.id) {
+        return HttpResponse::Conflict().json(ErrorJson::from("Recording is in progress"));
+    }
+
+    if context.rd_recordings.delete(&info.id).await.is_err() {
+        return HttpResponse::InternalServerError()
+            .json(ErrorJson::from("Failed to delete recording"));
+    }
+
+    HttpResponse::Ok().json(json!({}))
+}
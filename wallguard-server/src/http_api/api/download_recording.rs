@@ -0,0 +1,73 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{Bytes, Data, Query},
+};
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+
+use crate::{
+    app_context::AppContext,
+    http_api::utilities::{error_json::ErrorJson, request_handling},
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct QueryParams {
+    id: String,
+    /// Download the input events instead of the video
+    #[serde(default)]
+    events: bool,
+}
+
+pub async fn download_recording(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    query: Query<QueryParams>,
+) -> impl Responder {
+    let Some(info) = context.rd_recordings.get(&query.id).await else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Recording not found"));
+    };
+
+    if let Err(response) =
+        request_handling::authorized_device(&request, &context, &info.device_id).await
+    {
+        return response;
+    }
+
+    let Some(path) = context.rd_recordings.path(&info.id, query.events) else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Recording not found"));
+    };
+
+    let Ok(file) = tokio::fs::File::open(&path).await else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Recording not found"));
+    };
+
+    let (content_type, filename) = if query.events {
+        ("application/x-ndjson", format!("{}.events.jsonl", info.id))
+    } else {
+        ("video/mp4", format!("{}.mp4", info.id))
+    };
+
+    // Recordings still in progress can be downloaded too, up to what has been written
+    let body = futures_util::stream::unfold(file, |mut file| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(len) => {
+                chunk.truncate(len);
+                Some((Ok::<_, std::io::Error>(Bytes::from(chunk)), file))
+            }
+            Err(err) => Some((Err(err), file)),
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(body)
+}
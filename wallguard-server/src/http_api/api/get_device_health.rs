@@ -0,0 +1,39 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Data, Query},
+};
+use serde::Deserialize;
+
+use crate::{
+    app_context::AppContext,
+    http_api::utilities::{error_json::ErrorJson, request_handling},
+};
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct QueryParams {
+    device_id: String,
+}
+
+pub async fn get_device_health(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    query: Query<QueryParams>,
+) -> impl Responder {
+    let (jwt, device) =
+        match request_handling::authorized_device(&request, &context, &query.device_id).await {
+            Ok(authorized) => authorized,
+            Err(response) => return response,
+        };
+
+    match context
+        .datastore
+        .obtain_device_health(&jwt, &device.id)
+        .await
+    {
+        Ok(Some(health)) => HttpResponse::Ok().json(health),
+        Ok(None) => {
+            HttpResponse::NotFound().json(ErrorJson::from("No health reported by the device"))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(ErrorJson::from("Datastore error")),
+    }
+}
@@ -0,0 +1,9 @@
+use actix_web::{HttpResponse, Responder, web::Data};
+
+use crate::app_context::AppContext;
+
+pub async fn get_metrics(context: Data<AppContext>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(context.metrics.render(&context).await)
+}
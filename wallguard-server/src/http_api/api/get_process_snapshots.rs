@@ -0,0 +1,55 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Data, Query},
+};
+use serde::Deserialize;
+
+use crate::{
+    app_context::AppContext,
+    http_api::utilities::{error_json::ErrorJson, request_handling},
+};
+
+const DEFAULT_LIMIT: i32 = 60;
+const MAX_LIMIT: i32 = 1000;
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct QueryParams {
+    device_id: String,
+    /// How many of the latest snapshots to return
+    limit: Option<i32>,
+}
+
+pub async fn get_process_snapshots(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    query: Query<QueryParams>,
+) -> impl Responder {
+    let (jwt, device) =
+        match request_handling::authorized_device(&request, &context, &query.device_id).await {
+            Ok(authorized) => authorized,
+            Err(response) => return response,
+        };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    match context
+        .datastore
+        .obtain_process_snapshots(&jwt, &device.id, limit)
+        .await
+    {
+        Ok(snapshots) => {
+            let snapshots: Vec<serde_json::Value> = snapshots
+                .into_iter()
+                .map(|snapshot| {
+                    let mut sample = snapshot.snapshot;
+                    if let Some(object) = sample.as_object_mut() {
+                        object.insert("timestamp".into(), snapshot.timestamp.into());
+                    }
+                    sample
+                })
+                .collect();
+            HttpResponse::Ok().json(snapshots)
+        }
+        Err(_) => HttpResponse::InternalServerError().json(ErrorJson::from("Datastore error")),
+    }
+}
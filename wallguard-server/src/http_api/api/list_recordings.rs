@@ -0,0 +1,25 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Data, Query},
+};
+use serde::Deserialize;
+
+use crate::{app_context::AppContext, http_api::utilities::request_handling};
+
+#[derive(Deserialize)]
+pub(in crate::http_api) struct QueryParams {
+    device_id: String,
+}
+
+pub async fn list_recordings(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    query: Query<QueryParams>,
+) -> impl Responder {
+    match request_handling::authorized_device(&request, &context, &query.device_id).await {
+        Ok((_, device)) => {
+            HttpResponse::Ok().json(context.rd_recordings.list(Some(&device.id)).await)
+        }
+        Err(response) => response,
+    }
+}
@@ -1,21 +1,39 @@
 mod authorize_device;
+mod configure_flow_export;
+mod configure_process_snapshots;
 mod create_alias;
 mod create_filter_rule;
 mod create_nat_rule;
+mod create_rd_webrtc_session;
 mod create_tunnel;
+mod delete_recording;
 mod delete_tunnel;
+mod download_recording;
 mod enable_config_monitoring;
 mod enable_telemetry_monitoring;
 mod enable_traffic_monitoring;
+mod get_device_health;
+mod get_metrics;
+mod get_process_snapshots;
 mod get_services;
+mod list_recordings;
 
 pub use authorize_device::*;
+pub use configure_flow_export::*;
+pub use configure_process_snapshots::*;
 pub use create_alias::*;
 pub use create_filter_rule::*;
 pub use create_nat_rule::*;
+pub use create_rd_webrtc_session::*;
 pub use create_tunnel::*;
+pub use delete_recording::*;
 pub use delete_tunnel::*;
+pub use download_recording::*;
 pub use enable_config_monitoring::*;
 pub use enable_telemetry_monitoring::*;
 pub use enable_traffic_monitoring::*;
+pub use get_device_health::*;
+pub use get_metrics::*;
+pub use get_process_snapshots::*;
 pub use get_services::*;
+pub use list_recordings::*;
@@ -0,0 +1,94 @@
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header;
+use actix_web::rt;
+use actix_web::web::{Data, Payload};
+use futures_util::stream;
+
+use super::utilities::error_json::ErrorJson;
+use super::utilities::request_handling;
+use crate::app_context::AppContext;
+use crate::http_api::capture_gateway::websocket_relay::websocket_relay;
+use crate::tunneling::tunnel_common::WallguardTunnel;
+
+mod websocket_relay;
+
+/// Serves a capture session either as a live WebSocket stream or,
+/// for plain HTTP requests, as a downloadable pcapng file.
+pub(super) async fn open_capture_session(
+    request: HttpRequest,
+    context: Data<AppContext>,
+    body: Payload,
+) -> impl Responder {
+    let tunnel_id = match request_handling::extract_session_token(&request) {
+        Ok(tunnel_id) => tunnel_id.to_ascii_uppercase(),
+        Err(response) => return response,
+    };
+
+    let Some(WallguardTunnel::Capture(capture_tunnel)) =
+        context.tunnels_manager.get(&tunnel_id).await
+    else {
+        return HttpResponse::NotFound().json(ErrorJson::from("Tunnel not found"));
+    };
+
+    let subscription = {
+        let mut lock = capture_tunnel.lock().await;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let (date, time) = crate::utilities::time::timestamp_to_datetime(timestamp.cast_signed());
+        lock.data.tunnel_data.last_access_date = Some(date);
+        lock.data.tunnel_data.last_access_time = Some(time);
+
+        if let Ok(token) = context.sysdev_token_provider.get().await {
+            let _ = context
+                .datastore
+                .update_tunnel_accessed(&token.jwt, &lock.data.tunnel_data.id, false, timestamp)
+                .await;
+        }
+
+        lock.subscribe().await
+    };
+
+    let subscription = match subscription {
+        Ok(subscription) => subscription,
+        Err(err) => return HttpResponse::InternalServerError().json(ErrorJson::from(err)),
+    };
+
+    if request_handling::is_websocket_upgrade(&request) {
+        let (response, ws_session, stream) =
+            match request_handling::upgrade_to_websocket(request, body) {
+                Ok(r) => r,
+                Err(resp) => return resp,
+            };
+
+        rt::spawn(websocket_relay(stream, ws_session, subscription));
+
+        return response;
+    }
+
+    let body = stream::unfold(subscription, |mut subscription| async move {
+        let chunk = subscription
+            .next_chunk()
+            .await?
+            .map_err(|err| ErrorInternalServerError(err.to_str().to_string()));
+
+        Some((chunk, subscription))
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-pcapng")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{tunnel_id}.pcapng\""),
+        ))
+        .streaming(body)
+}
@@ -0,0 +1,56 @@
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, MessageStream, Session as WSSession};
+use futures_util::StreamExt as _;
+
+use crate::tunneling::capture::CaptureSubscription;
+
+pub async fn websocket_relay(
+    stream: MessageStream,
+    ws_session: WSSession,
+    subscription: CaptureSubscription,
+) {
+    let stream = stream
+        .aggregate_continuations()
+        .max_continuation_size(2_usize.pow(20));
+
+    tokio::select! {
+        _ = handle_messages_from_user(stream, ws_session.clone()) => {
+            log::info!("Capture WebSocket closed by the user.");
+        }
+        _ = relay_capture_to_user(ws_session.clone(), subscription) => {
+            log::info!("Capture → WebSocket relay ended.");
+        }
+    }
+
+    let _ = ws_session.close(None).await;
+}
+
+/// The capture stream is read-only, only control frames are handled.
+async fn handle_messages_from_user(mut stream: AggregatedMessageStream, mut ws_session: WSSession) {
+    while let Some(msg) = stream.next().await {
+        match msg {
+            Ok(AggregatedMessage::Ping(msg)) => {
+                if ws_session.pong(&msg).await.is_err() {
+                    return;
+                }
+            }
+            Ok(AggregatedMessage::Close(_)) | Err(_) => return,
+            Ok(_) => continue,
+        }
+    }
+}
+
+async fn relay_capture_to_user(mut ws_session: WSSession, mut subscription: CaptureSubscription) {
+    while let Some(chunk) = subscription.next_chunk().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                log::error!("Cannot read the capture: {}", err.to_str());
+                return;
+            }
+        };
+
+        if ws_session.binary(chunk).await.is_err() {
+            return;
+        }
+    }
+}
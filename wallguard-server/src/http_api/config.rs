@@ -32,3 +32,38 @@ impl Default for HttpApiConfig {
         Self { addr }
     }
 }
+
+/// Where the Prometheus metrics are served, apart from the API so that they are
+/// not exposed along with it.
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    pub(crate) addr: SocketAddr,
+}
+
+impl MetricsConfig {
+    /// Constructs a `MetricsConfig` from the environment variables
+    /// `METRICS_HOST` and `METRICS_PORT`.
+    ///
+    /// Falls back to `Default` if either environment variable is missing or invalid.
+    pub fn from_env() -> Self {
+        let host = std::env::var("METRICS_HOST").ok();
+        let port = std::env::var("METRICS_PORT")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok());
+
+        if let (Some(host), Some(port)) = (host, port)
+            && let Ok(addr) = format!("{host}:{port}").parse::<SocketAddr>()
+        {
+            return Self { addr };
+        }
+
+        Self::default()
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        let addr = "127.0.0.1:9464".parse().unwrap();
+        Self { addr }
+    }
+}
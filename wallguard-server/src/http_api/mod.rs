@@ -1,21 +1,31 @@
 use crate::app_context::AppContext;
 
 use crate::http_api::api::authorize_device;
+use crate::http_api::api::configure_flow_export;
+use crate::http_api::api::configure_process_snapshots;
 use crate::http_api::api::create_alias;
 use crate::http_api::api::create_filter_rule;
 use crate::http_api::api::create_nat_rule;
+use crate::http_api::api::create_rd_webrtc_session;
 use crate::http_api::api::create_tunnel;
+use crate::http_api::api::delete_recording;
 use crate::http_api::api::delete_tunnel;
+use crate::http_api::api::download_recording;
 use crate::http_api::api::enable_config_monitoring;
 use crate::http_api::api::enable_telemetry_monitoring;
 use crate::http_api::api::enable_traffic_monitoring;
+use crate::http_api::api::get_device_health;
+use crate::http_api::api::get_metrics;
+use crate::http_api::api::get_process_snapshots;
 use crate::http_api::api::get_services;
+use crate::http_api::api::list_recordings;
 
 use actix_cors::Cors;
 use actix_web::{App, HttpServer, http, web};
-use config::HttpApiConfig;
+use config::{HttpApiConfig, MetricsConfig};
 
 mod api;
+pub mod capture_gateway;
 mod config;
 pub mod rd_gateway_v2;
 pub mod ssh_gateway_v2;
@@ -76,6 +86,38 @@ pub async fn run_http_api(context: AppContext) {
                 "/wallguard/api/v1/enable_config_monitoring",
                 web::post().to(enable_config_monitoring),
             )
+            .route(
+                "/wallguard/api/v1/flow_export",
+                web::post().to(configure_flow_export),
+            )
+            .route(
+                "/wallguard/api/v1/process_snapshots",
+                web::post().to(configure_process_snapshots),
+            )
+            .route(
+                "/wallguard/api/v1/process_snapshots",
+                web::get().to(get_process_snapshots),
+            )
+            .route(
+                "/wallguard/api/v1/device_health",
+                web::get().to(get_device_health),
+            )
+            .route(
+                "/wallguard/api/v1/rd_webrtc",
+                web::post().to(create_rd_webrtc_session),
+            )
+            .route(
+                "/wallguard/api/v1/recordings",
+                web::get().to(list_recordings),
+            )
+            .route(
+                "/wallguard/api/v1/recording",
+                web::get().to(download_recording),
+            )
+            .route(
+                "/wallguard/api/v1/recording",
+                web::delete().to(delete_recording),
+            )
             .route(
                 "/wallguard/gateway/ssh",
                 web::to(ssh_gateway_v2::open_ssh_session),
@@ -88,6 +130,10 @@ pub async fn run_http_api(context: AppContext) {
                 "/wallguard/gateway/rd",
                 web::to(rd_gateway_v2::open_rd_session),
             )
+            .route(
+                "/wallguard/gateway/capture",
+                web::to(capture_gateway::open_capture_session),
+            )
             .route("/wallguard/rule/filter", web::to(create_filter_rule))
             .route("/wallguard/rule/nat", web::to(create_nat_rule))
             .route("/wallguard/alias", web::to(create_alias))
@@ -98,3 +144,31 @@ pub async fn run_http_api(context: AppContext) {
     .await
     .unwrap()
 }
+
+pub async fn run_metrics_api(context: AppContext) {
+    let config = MetricsConfig::from_env();
+    let context = web::Data::new(context);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(context.clone())
+            .route("/metrics", web::get().to(get_metrics))
+    })
+    .workers(1)
+    .bind(config.addr);
+
+    match server {
+        Ok(server) => {
+            log::info!("Metrics served on {}", config.addr);
+            if let Err(err) = server.run().await {
+                log::error!("Metrics server failed: {err}");
+            }
+        }
+        Err(err) => log::error!(
+            "Failed to bind the metrics server to {}: {err}",
+            config.addr
+        ),
+    }
+
+    std::future::pending().await
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
@@ -6,16 +8,29 @@ use actix_web::HttpResponse;
 use actix_web::Responder;
 use actix_web::rt;
 use actix_web::web::{Data, Payload};
+use prost::Message;
+use tokio::sync::Mutex;
+use wallguard_common::protobuf::wallguard_rd::{
+    Control, Hello, RequestKeyframe, ViewerMessage, control, viewer_message,
+};
+use wallguard_common::rd_protocol;
 
 use super::utilities::error_json::ErrorJson;
 use super::utilities::request_handling;
 use crate::app_context::AppContext;
 use crate::datastore::TunnelStatus;
 use crate::http_api::rd_gateway_v2::websocket_relay::websocket_relay;
+use crate::tunneling::rd::RdTunnel;
+use crate::tunneling::rd::session::SessionDataSender;
 use crate::tunneling::tunnel_common::WallguardTunnel;
 
+mod webrtc_config;
+pub mod webrtc_relay;
 mod websocket_relay;
 
+/// How long the viewer and the agent have to send their `Hello`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub(super) async fn open_rd_session(
     request: HttpRequest,
     context: Data<AppContext>,
@@ -30,35 +45,7 @@ pub(super) async fn open_rd_session(
         return HttpResponse::NotFound().json(ErrorJson::from("Tunnel not found"));
     };
 
-    {
-        let mut lock = rd_tunnel.lock().await;
-
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        let (date, time) = crate::utilities::time::timestamp_to_datetime(timestamp.cast_signed());
-        lock.data.tunnel_data.last_access_date = Some(date);
-        lock.data.tunnel_data.last_access_time = Some(time);
-
-        if let Ok(token) = context.sysdev_token_provider.get().await {
-            let _ = context
-                .datastore
-                .update_tunnel_accessed(&token.jwt, &lock.data.tunnel_data.id, false, timestamp)
-                .await;
-
-            let _ = context
-                .datastore
-                .update_tunnel_status(
-                    &token.jwt,
-                    &lock.data.tunnel_data.id,
-                    TunnelStatus::Active,
-                    token.account.is_root_account,
-                )
-                .await;
-        }
-    }
+    mark_tunnel_active(&context, &rd_tunnel).await;
 
     let (response, ws_session, stream) = match request_handling::upgrade_to_websocket(request, body)
     {
@@ -75,3 +62,83 @@ pub(super) async fn open_rd_session(
 
     response
 }
+
+/// Records the access to the tunnel and marks it as active.
+async fn mark_tunnel_active(context: &AppContext, rd_tunnel: &Mutex<RdTunnel>) {
+    let mut lock = rd_tunnel.lock().await;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let (date, time) = crate::utilities::time::timestamp_to_datetime(timestamp.cast_signed());
+    lock.data.tunnel_data.last_access_date = Some(date);
+    lock.data.tunnel_data.last_access_time = Some(time);
+
+    if let Ok(token) = context.sysdev_token_provider.get().await {
+        let _ = context
+            .datastore
+            .update_tunnel_accessed(&token.jwt, &lock.data.tunnel_data.id, false, timestamp)
+            .await;
+
+        let _ = context
+            .datastore
+            .update_tunnel_status(
+                &token.jwt,
+                &lock.data.tunnel_data.id,
+                TunnelStatus::Active,
+                token.account.is_root_account,
+            )
+            .await;
+    }
+}
+
+/// Marks the tunnel as idle once its last viewer is gone.
+async fn mark_tunnel_idle_if_unwatched(context: &AppContext, rd_tunnel: &Mutex<RdTunnel>) {
+    let tunnel_lock = rd_tunnel.lock().await;
+
+    if !tunnel_lock.has_active_viewers() {
+        let tunnel_id = tunnel_lock.data.tunnel_data.id.clone();
+        drop(tunnel_lock);
+
+        if let Ok(token) = context.sysdev_token_provider.get().await {
+            let _ = context
+                .datastore
+                .update_tunnel_status(&token.jwt, &tunnel_id, TunnelStatus::Idle, false)
+                .await;
+        }
+    }
+}
+
+/// Agrees on the protocol with a viewer, whatever carries its messages.
+///
+/// Waits for the `Hello` of the agent and returns the one to answer the viewer
+/// with; once it is sent, a keyframe should be requested so that the viewer
+/// gets a complete picture right away.
+async fn negotiate_with_agent(
+    rd_tunnel: &Arc<Mutex<RdTunnel>>,
+    viewer_hello: &Hello,
+) -> Result<Hello, String> {
+    let mut agent_hello = rd_tunnel.lock().await.get_agent_hello();
+
+    let agent_hello =
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, agent_hello.wait_for(Option::is_some))
+            .await
+            .map_err(|_| String::from("Timed out waiting for the agent Hello"))?
+            .map_err(|_| String::from("Remote desktop session closed"))?
+            .clone()
+            .unwrap_or_default();
+
+    rd_protocol::negotiate(&agent_hello, viewer_hello).map_err(|err| err.to_str().to_string())
+}
+
+async fn request_keyframe(sender: &SessionDataSender) -> Result<(), ()> {
+    let message = ViewerMessage {
+        message: Some(viewer_message::Message::Control(Control {
+            action: Some(control::Action::RequestKeyframe(RequestKeyframe {})),
+        })),
+    };
+
+    sender.send(message.encode_to_vec()).await.map_err(|_| ())
+}
@@ -0,0 +1,50 @@
+#[derive(Debug, Clone, Default)]
+pub struct WebRtcConfig {
+    /// STUN / TURN server URLs
+    pub(crate) ice_servers: Vec<String>,
+    pub(crate) ice_username: String,
+    pub(crate) ice_credential: String,
+    /// Public addresses advertised in place of the local ones, for 1:1 NAT
+    pub(crate) public_ips: Vec<String>,
+    /// UDP ports the peer connections bind to, any if `None`
+    pub(crate) udp_ports: Option<(u16, u16)>,
+}
+
+impl WebRtcConfig {
+    /// Reads the remote desktop WebRTC configuration from the environment.
+    ///
+    /// Every variable is optional: with none of them set, only the host
+    /// candidates of the server are gathered.
+    pub fn from_env() -> Self {
+        let ice_servers = list_var("RD_WEBRTC_ICE_SERVERS");
+        let ice_username = std::env::var("RD_WEBRTC_ICE_USERNAME").unwrap_or_default();
+        let ice_credential = std::env::var("RD_WEBRTC_ICE_CREDENTIAL").unwrap_or_default();
+        let public_ips = list_var("RD_WEBRTC_PUBLIC_IPS");
+
+        let udp_ports = std::env::var("RD_WEBRTC_UDP_PORTS")
+            .ok()
+            .and_then(|value| {
+                let (min, max) = value.trim().split_once('-')?;
+                Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+            })
+            .filter(|(min, max)| min <= max);
+
+        Self {
+            ice_servers,
+            ice_username,
+            ice_credential,
+            public_ips,
+            udp_ports,
+        }
+    }
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
+}
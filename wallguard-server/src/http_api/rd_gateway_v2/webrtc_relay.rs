@@ -0,0 +1,428 @@
+use std::sync::{Arc, LazyLock};
+
+use super::webrtc_config::WebRtcConfig;
+use super::{
+    HANDSHAKE_TIMEOUT, mark_tunnel_active, mark_tunnel_idle_if_unwatched, negotiate_with_agent,
+    request_keyframe,
+};
+use crate::app_context::AppContext;
+use crate::tunneling::rd::RdTunnel;
+use crate::tunneling::rd::session::SessionDataSender;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use prost::Message;
+use prost::bytes::Bytes;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, Notify, mpsc, watch};
+use wallguard_common::protobuf::wallguard_rd::{
+    AgentMessage, Codec, Hello, ViewerMessage, agent_message, viewer_message,
+};
+use wallguard_common::rd_protocol;
+use webrtc::api::APIBuilder;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MIME_TYPE_H264, MediaEngine};
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::ice::udp_network::{EphemeralUDP, UDPNetwork};
+use webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtp::codecs::h264::H264Payloader;
+use webrtc::rtp::packetizer::{Packetizer, new_packetizer};
+use webrtc::rtp::sequence::new_random_sequencer;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::track::track_local::TrackLocalWriter;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+
+static WEBRTC_CONFIG: LazyLock<WebRtcConfig> = LazyLock::new(WebRtcConfig::from_env);
+
+/// Label of the data channel the viewer opens for everything but the video.
+const DATA_CHANNEL_LABEL: &str = "rd";
+
+const H264_CLOCK_RATE: u32 = 90_000;
+// Constrained Baseline, as produced by the agent, at level 5.1: the answer is
+// settled before the first SPS, and whole desktops exceed the 720p of level 3.1
+const H264_FMTP: &str = "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e033";
+const RTP_MTU: usize = 1200;
+
+/// The data channel opened by the viewer, hooked as soon as it is announced
+/// so that its first message, the `Hello`, is not missed.
+struct ViewerChannel {
+    channel: Arc<RTCDataChannel>,
+    messages: mpsc::Receiver<Bytes>,
+    closed: Arc<Notify>,
+}
+
+/// Answers the SDP offer of a viewer and relays the session to it over WebRTC.
+///
+/// The video goes on an H.264 track. Every other message, starting with the
+/// `Hello` exchange, goes over the `rd` data channel the viewer must open,
+/// encoded exactly as over the WebSocket gateway.
+pub async fn accept_offer(
+    context: Arc<AppContext>,
+    rd_tunnel: Arc<Mutex<RdTunnel>>,
+    offer: RTCSessionDescription,
+) -> Result<RTCSessionDescription, Error> {
+    let peer = Arc::new(new_peer_connection(&WEBRTC_CONFIG).await?);
+
+    let (channel_sender, channels) = mpsc::channel(1);
+    peer.on_data_channel(Box::new(move |channel| {
+        let channel_sender = channel_sender.clone();
+        Box::pin(async move {
+            if channel.label() == DATA_CHANNEL_LABEL {
+                let _ = channel_sender.send(hook_channel(channel)).await;
+            }
+        })
+    }));
+
+    let (state_sender, state) = watch::channel(RTCPeerConnectionState::New);
+    peer.on_peer_connection_state_change(Box::new(move |new_state| {
+        state_sender.send_replace(new_state);
+        Box::pin(async {})
+    }));
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_H264.to_owned(),
+            clock_rate: H264_CLOCK_RATE,
+            sdp_fmtp_line: H264_FMTP.to_owned(),
+            ..Default::default()
+        },
+        String::from("video"),
+        String::from("wallguard-rd"),
+    ));
+
+    let (answer, rtp_sender) = match answer_offer(&peer, track.clone(), offer).await {
+        Ok(answer) => answer,
+        Err(err) => {
+            let _ = peer.close().await;
+            return Err(err);
+        }
+    };
+
+    mark_tunnel_active(&context, &rd_tunnel).await;
+
+    tokio::spawn(async move {
+        // Held until the end so the tunnel counts this viewer as active
+        let sender = rd_tunnel.lock().await.get_data_send_channel();
+
+        let link = Link {
+            rd_tunnel: rd_tunnel.clone(),
+            sender,
+            track,
+            rtp_sender,
+        };
+
+        webrtc_relay(link, channels, state).await;
+
+        let _ = peer.close().await;
+        mark_tunnel_idle_if_unwatched(&context, &rd_tunnel).await;
+    });
+
+    Ok(answer)
+}
+
+async fn new_peer_connection(config: &WebRtcConfig) -> Result<RTCPeerConnection, Error> {
+    let mut media_engine = MediaEngine::default();
+    media_engine
+        .register_default_codecs()
+        .handle_err(location!())?;
+
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)
+        .handle_err(location!())?;
+
+    let mut setting_engine = SettingEngine::default();
+    if !config.public_ips.is_empty() {
+        setting_engine.set_nat_1to1_ips(config.public_ips.clone(), RTCIceCandidateType::Host);
+    }
+    if let Some((min, max)) = config.udp_ports {
+        let ephemeral = EphemeralUDP::new(min, max).handle_err(location!())?;
+        setting_engine.set_udp_network(UDPNetwork::Ephemeral(ephemeral));
+    }
+
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .with_setting_engine(setting_engine)
+        .build();
+
+    let mut configuration = RTCConfiguration::default();
+    if !config.ice_servers.is_empty() {
+        configuration.ice_servers = vec![RTCIceServer {
+            urls: config.ice_servers.clone(),
+            username: config.ice_username.clone(),
+            credential: config.ice_credential.clone(),
+        }];
+    }
+
+    api.new_peer_connection(configuration)
+        .await
+        .handle_err(location!())
+}
+
+/// Answers `offer` once every local candidate has been gathered, so that no trickle ICE is needed.
+async fn answer_offer(
+    peer: &RTCPeerConnection,
+    track: Arc<TrackLocalStaticRTP>,
+    offer: RTCSessionDescription,
+) -> Result<(RTCSessionDescription, Arc<RTCRtpSender>), Error> {
+    let rtp_sender = peer.add_track(track).await.handle_err(location!())?;
+
+    peer.set_remote_description(offer)
+        .await
+        .handle_err(location!())?;
+
+    let answer = peer.create_answer(None).await.handle_err(location!())?;
+    let mut gathered = peer.gathering_complete_promise().await;
+    peer.set_local_description(answer)
+        .await
+        .handle_err(location!())?;
+
+    let _ = tokio::time::timeout(HANDSHAKE_TIMEOUT, gathered.recv()).await;
+
+    let answer = peer
+        .local_description()
+        .await
+        .ok_or("Missing local description")
+        .handle_err(location!())?;
+
+    Ok((answer, rtp_sender))
+}
+
+fn hook_channel(channel: Arc<RTCDataChannel>) -> ViewerChannel {
+    let (message_sender, messages) = mpsc::channel(128);
+    channel.on_message(Box::new(move |message: DataChannelMessage| {
+        let message_sender = message_sender.clone();
+        Box::pin(async move {
+            // Same protocol as the WebSocket: binary protobuf only
+            if !message.is_string {
+                let _ = message_sender.send(message.data).await;
+            }
+        })
+    }));
+
+    let closed = Arc::new(Notify::new());
+    let notify = closed.clone();
+    channel.on_close(Box::new(move || {
+        notify.notify_one();
+        Box::pin(async {})
+    }));
+
+    ViewerChannel {
+        channel,
+        messages,
+        closed,
+    }
+}
+
+/// What the relay of one viewer needs, besides its data channel.
+struct Link {
+    rd_tunnel: Arc<Mutex<RdTunnel>>,
+    sender: SessionDataSender,
+    track: Arc<TrackLocalStaticRTP>,
+    rtp_sender: Arc<RTCRtpSender>,
+}
+
+async fn webrtc_relay(
+    link: Link,
+    mut channels: mpsc::Receiver<ViewerChannel>,
+    mut state: watch::Receiver<RTCPeerConnectionState>,
+) {
+    let viewer = tokio::select! {
+        viewer = tokio::time::timeout(HANDSHAKE_TIMEOUT, channels.recv()) => viewer.ok().flatten(),
+        _ = disconnected(&mut state) => None,
+    };
+
+    let Some(mut viewer) = viewer else {
+        log::warn!("RD WebRTC: the viewer did not open the '{DATA_CHANNEL_LABEL}' data channel");
+        return;
+    };
+
+    let negotiated = match handshake(&mut viewer, &link).await {
+        Ok(negotiated) => negotiated,
+        Err(reason) => {
+            log::warn!("RD handshake with WebRTC viewer failed: {reason}");
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = relay_user_to_rd(&mut viewer.messages, &link.sender, &negotiated) => {
+            log::info!("WebRTC → RD relay ended.");
+        }
+        _ = relay_rd_to_user(&viewer.channel, &link, &negotiated) => {
+            log::info!("RD → WebRTC relay ended.");
+        }
+        _ = relay_keyframe_requests(&link.rtp_sender, &link.sender) => {
+            log::info!("WebRTC RTCP relay ended.");
+        }
+        _ = viewer.closed.notified() => {
+            log::info!("WebRTC data channel closed.");
+        }
+        _ = disconnected(&mut state) => {
+            log::info!("WebRTC peer connection closed.");
+        }
+    }
+}
+
+async fn disconnected(state: &mut watch::Receiver<RTCPeerConnectionState>) {
+    let _ = state
+        .wait_for(|state| {
+            matches!(
+                state,
+                RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed
+            )
+        })
+        .await;
+}
+
+/// Same exchange as over the WebSocket, on the data channel.
+async fn handshake(viewer: &mut ViewerChannel, link: &Link) -> Result<Hello, String> {
+    let message = tokio::time::timeout(HANDSHAKE_TIMEOUT, viewer.messages.recv())
+        .await
+        .map_err(|_| String::from("Timed out waiting for the viewer Hello"))?
+        .ok_or_else(|| String::from("Viewer disconnected"))?;
+
+    let viewer_hello = match ViewerMessage::decode(message) {
+        Ok(ViewerMessage {
+            message: Some(viewer_message::Message::Hello(hello)),
+        }) => hello,
+        _ => return Err(String::from("Expected a Hello as the first message")),
+    };
+
+    let negotiated = negotiate_with_agent(&link.rd_tunnel, &viewer_hello).await?;
+
+    let reply = AgentMessage {
+        message: Some(agent_message::Message::Hello(negotiated.clone())),
+    };
+
+    viewer
+        .channel
+        .send(&Bytes::from(reply.encode_to_vec()))
+        .await
+        .map_err(|_| String::from("Viewer disconnected"))?;
+
+    request_keyframe(&link.sender)
+        .await
+        .map_err(|_| String::from("Remote desktop session closed"))?;
+
+    Ok(negotiated)
+}
+
+async fn relay_user_to_rd(
+    messages: &mut mpsc::Receiver<Bytes>,
+    sender: &SessionDataSender,
+    negotiated: &Hello,
+) {
+    while let Some(bytes) = messages.recv().await {
+        let Ok(message) = ViewerMessage::decode(bytes.clone()) else {
+            log::warn!("WebRTC → RD: dropping malformed viewer message");
+            continue;
+        };
+
+        if let Some(viewer_message::Message::Hello(_)) = message.message {
+            continue;
+        }
+
+        if let Some(feature) = rd_protocol::viewer_message_feature(&message)
+            && !rd_protocol::has_feature(negotiated, feature)
+        {
+            log::debug!(
+                "WebRTC → RD: dropping message of not negotiated feature {}",
+                feature.as_str_name()
+            );
+            continue;
+        }
+
+        if sender.send(bytes.to_vec()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends the video frames on the track, and every other message on the data channel.
+async fn relay_rd_to_user(channel: &RTCDataChannel, link: &Link, negotiated: &Hello) {
+    let mut reader = link.rd_tunnel.lock().await.get_data_recv_channel();
+
+    let mut packetizer = new_packetizer(
+        RTP_MTU,
+        0, // the payload type and SSRC are set by the track binding
+        0,
+        Box::new(H264Payloader::default()),
+        Box::new(new_random_sequencer()),
+        H264_CLOCK_RATE,
+    );
+
+    loop {
+        let message = match reader.recv().await {
+            Ok(message) => message,
+            Err(RecvError::Lagged(n)) => {
+                log::warn!("RD → WebRTC: receiver lagged, skipped {n} frame(s)");
+                if request_keyframe(&link.sender).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        if let Some(feature) = message.feature
+            && !rd_protocol::has_feature(negotiated, feature)
+        {
+            continue;
+        }
+
+        let decoded = AgentMessage::decode(Bytes::clone(&message.bytes));
+        if let Ok(AgentMessage {
+            message: Some(agent_message::Message::VideoFrame(frame)),
+        }) = decoded
+        {
+            if frame.codec() != Codec::H264 {
+                continue;
+            }
+
+            let Ok(packets) = packetizer.packetize(&Bytes::from(frame.data), 0) else {
+                log::warn!("RD → WebRTC: failed to packetize a frame");
+                continue;
+            };
+
+            // RTP timestamps follow the capture time of the agent, whatever the frame rate
+            let timestamp = frame
+                .timestamp_ms
+                .wrapping_mul(u64::from(H264_CLOCK_RATE) / 1000);
+            for mut packet in packets {
+                packet.header.timestamp = timestamp as u32;
+                if link.track.write_rtp(&packet).await.is_err() {
+                    return;
+                }
+            }
+
+            continue;
+        }
+
+        if channel.send(&message.bytes).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Asks the agent for a keyframe whenever the browser reports a lost picture.
+async fn relay_keyframe_requests(rtp_sender: &RTCRtpSender, sender: &SessionDataSender) {
+    while let Ok((packets, _)) = rtp_sender.read_rtcp().await {
+        let lost = packets.iter().any(|packet| {
+            let packet = packet.as_any();
+            packet.is::<PictureLossIndication>() || packet.is::<FullIntraRequest>()
+        });
+
+        if lost && request_keyframe(sender).await.is_err() {
+            return;
+        }
+    }
+}
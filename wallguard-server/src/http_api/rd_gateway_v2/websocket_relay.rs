@@ -1,61 +1,144 @@
 use std::sync::Arc;
 
-use crate::{app_context::AppContext, datastore::TunnelStatus, tunneling::rd::RdTunnel};
-use actix_ws::{AggregatedMessage, AggregatedMessageStream, MessageStream, Session as WSSession};
+use super::{
+    HANDSHAKE_TIMEOUT, mark_tunnel_idle_if_unwatched, negotiate_with_agent, request_keyframe,
+};
+use crate::{app_context::AppContext, tunneling::rd::RdTunnel};
+use actix_ws::{
+    AggregatedMessage, AggregatedMessageStream, CloseCode, CloseReason, MessageStream,
+    Session as WSSession,
+};
 use futures_util::StreamExt as _;
+use prost::Message;
 use prost::bytes::Bytes;
 use tokio::sync::Mutex;
 use tokio::sync::broadcast::error::RecvError;
+use wallguard_common::protobuf::wallguard_rd::{
+    AgentMessage, Hello, ViewerMessage, agent_message, viewer_message,
+};
+use wallguard_common::rd_protocol;
 
 pub async fn websocket_relay(
     stream: MessageStream,
-    ws_session: WSSession,
+    mut ws_session: WSSession,
     rd_tunnel: Arc<Mutex<RdTunnel>>,
     context: Arc<AppContext>,
 ) {
-    let stream = stream
+    let mut stream = stream
         .aggregate_continuations()
         .max_continuation_size(2_usize.pow(20));
 
-    tokio::select! {
-        _ = relay_user_to_rd(stream, rd_tunnel.clone(), ws_session.clone()) => {
-            log::info!("WebSocket → RD relay ended.");
+    match handshake(&mut stream, &mut ws_session, &rd_tunnel).await {
+        Ok(negotiated) => {
+            tokio::select! {
+                _ = relay_user_to_rd(stream, rd_tunnel.clone(), ws_session.clone(), &negotiated) => {
+                    log::info!("WebSocket → RD relay ended.");
+                }
+                _ = relay_rd_to_user(ws_session, rd_tunnel.clone(), &negotiated) => {
+                    log::info!("RD → WebSocket relay ended.");
+                }
+            }
         }
-        _ = relay_rd_to_user(ws_session, rd_tunnel.clone()) => {
-            log::info!("RD → WebSocket relay ended.");
+        Err(reason) => {
+            log::warn!("RD handshake with viewer failed: {reason}");
+            let reason = CloseReason {
+                code: CloseCode::Policy,
+                description: Some(reason),
+            };
+            let _ = ws_session.close(Some(reason)).await;
         }
     }
 
-    let tunnel_lock = rd_tunnel.lock().await;
+    mark_tunnel_idle_if_unwatched(&context, &rd_tunnel).await;
+}
+
+/// Negotiates the protocol version and features with a new viewer.
+///
+/// The viewer opens with its `Hello` and is answered with the one agreed
+/// with the agent; a keyframe is then requested so that it gets a complete
+/// picture right away.
+async fn handshake(
+    stream: &mut AggregatedMessageStream,
+    ws_session: &mut WSSession,
+    rd_tunnel: &Arc<Mutex<RdTunnel>>,
+) -> Result<Hello, String> {
+    let viewer_hello = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_viewer_hello(stream))
+        .await
+        .map_err(|_| String::from("Timed out waiting for the viewer Hello"))??;
+
+    let sender = rd_tunnel.lock().await.get_data_send_channel();
+    let negotiated = negotiate_with_agent(rd_tunnel, &viewer_hello).await?;
+
+    let reply = AgentMessage {
+        message: Some(agent_message::Message::Hello(negotiated.clone())),
+    };
 
-    if !tunnel_lock.has_active_viewers() {
-        let tunnel_id = tunnel_lock.data.tunnel_data.id.clone();
-        drop(tunnel_lock);
+    ws_session
+        .binary(reply.encode_to_vec())
+        .await
+        .map_err(|_| String::from("Viewer disconnected"))?;
 
-        if let Ok(token) = context.sysdev_token_provider.get().await {
-            let _ = context
-                .datastore
-                .update_tunnel_status(&token.jwt, &tunnel_id, TunnelStatus::Idle, false)
-                .await;
+    request_keyframe(&sender)
+        .await
+        .map_err(|_| String::from("Remote desktop session closed"))?;
+
+    Ok(negotiated)
+}
+
+async fn read_viewer_hello(stream: &mut AggregatedMessageStream) -> Result<Hello, String> {
+    while let Some(msg) = stream.next().await {
+        match msg {
+            Ok(AggregatedMessage::Binary(bin)) => {
+                return match ViewerMessage::decode(bin) {
+                    Ok(ViewerMessage {
+                        message: Some(viewer_message::Message::Hello(hello)),
+                    }) => Ok(hello),
+                    _ => Err(String::from("Expected a Hello as the first message")),
+                };
+            }
+            Ok(AggregatedMessage::Text(_)) => {
+                return Err(String::from(
+                    "Unsupported protocol: messages must be binary protobuf",
+                ));
+            }
+            Ok(_) => continue,
+            Err(_) => break,
         }
     }
+
+    Err(String::from("Viewer disconnected"))
 }
 
 async fn relay_user_to_rd(
     mut stream: AggregatedMessageStream,
     rd_tunnel: Arc<Mutex<RdTunnel>>,
     mut ws_session: WSSession,
+    negotiated: &Hello,
 ) {
     let sender = rd_tunnel.lock().await.get_data_send_channel();
 
     while let Some(msg) = stream.next().await {
         match msg {
-            Ok(AggregatedMessage::Text(text)) => {
-                if sender.send(text.as_bytes().to_vec()).await.is_err() {
-                    return;
-                }
-            }
             Ok(AggregatedMessage::Binary(bin)) => {
+                let Ok(message) = ViewerMessage::decode(bin.clone()) else {
+                    log::warn!("WebSocket → RD: dropping malformed viewer message");
+                    continue;
+                };
+
+                if let Some(viewer_message::Message::Hello(_)) = message.message {
+                    continue;
+                }
+
+                if let Some(feature) = rd_protocol::viewer_message_feature(&message)
+                    && !rd_protocol::has_feature(negotiated, feature)
+                {
+                    log::debug!(
+                        "WebSocket → RD: dropping message of not negotiated feature {}",
+                        feature.as_str_name()
+                    );
+                    continue;
+                }
+
                 if sender.send(bin.to_vec()).await.is_err() {
                     return;
                 }
@@ -71,24 +154,42 @@ async fn relay_user_to_rd(
     }
 }
 
-async fn relay_rd_to_user(mut ws_session: WSSession, rd_tunnel: Arc<Mutex<RdTunnel>>) {
-    let mut reader = rd_tunnel.lock().await.get_data_recv_channel();
+async fn relay_rd_to_user(
+    mut ws_session: WSSession,
+    rd_tunnel: Arc<Mutex<RdTunnel>>,
+    negotiated: &Hello,
+) {
+    let (mut reader, sender) = {
+        let lock = rd_tunnel.lock().await;
+        (lock.get_data_recv_channel(), lock.get_data_send_channel())
+    };
 
     loop {
         match reader.recv().await {
             Ok(message) => {
-                let message = Bytes::copy_from_slice(&message);
-                if ws_session.binary(message).await.is_err() {
+                if let Some(feature) = message.feature
+                    && !rd_protocol::has_feature(negotiated, feature)
+                {
+                    continue;
+                }
+
+                if ws_session
+                    .binary(Bytes::clone(&message.bytes))
+                    .await
+                    .is_err()
+                {
                     break;
                 }
             }
             Err(RecvError::Lagged(n)) => {
                 // The viewer fell behind the ring buffer; skip the dropped frames
                 // and resume from the oldest still-available frame.  This can
-                // happen under high load or when a viewer connects long after the
-                // stream started.  A keyframe will arrive within KEYFRAME_INTERVAL
-                // frames so the decoder can recover.
+                // happen under high load or on a slow link.  Ask the agent for a
+                // keyframe so the decoder can recover right away.
                 log::warn!("RD → WebSocket: receiver lagged, skipped {n} frame(s)");
+                if request_keyframe(&sender).await.is_err() {
+                    break;
+                }
             }
             Err(RecvError::Closed) => break,
         }
@@ -1,3 +1,5 @@
+use crate::app_context::AppContext;
+use crate::datastore::Device;
 use crate::http_api::utilities::authorization;
 use crate::http_api::utilities::error_json::ErrorJson;
 use actix_web::HttpRequest;
@@ -31,6 +33,13 @@ pub fn extract_session_token(req: &HttpRequest) -> Result<String, HttpResponse>
         })
 }
 
+pub fn is_websocket_upgrade(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(actix_web::http::header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
 pub fn upgrade_to_websocket(
     request: HttpRequest,
     body: Payload,
@@ -38,3 +47,27 @@ pub fn upgrade_to_websocket(
     actix_ws::handle(&request, body)
         .map_err(|err| HttpResponse::InternalServerError().json(ErrorJson::from(err.to_string())))
 }
+
+/// Looks the device up with the credentials of the caller, for the endpoints serving data
+/// the datastore does not scope by itself. Returns the caller JWT along with the device.
+pub async fn authorized_device(
+    request: &HttpRequest,
+    context: &AppContext,
+    device_id: &str,
+) -> Result<(String, Device), HttpResponse> {
+    let Some(jwt) = authorization::extract_authorization_token(request) else {
+        return Err(
+            HttpResponse::Unauthorized().json(ErrorJson::from("Missing Authorization header"))
+        );
+    };
+
+    match context
+        .datastore
+        .obtain_device_by_id(&jwt, device_id, false)
+        .await
+    {
+        Ok(Some(device)) => Ok((jwt, device)),
+        Ok(None) => Err(HttpResponse::NotFound().json(ErrorJson::from("Device not found"))),
+        Err(_) => Err(HttpResponse::InternalServerError().json(ErrorJson::from("Datastore error"))),
+    }
+}
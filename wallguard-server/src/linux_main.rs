@@ -1,8 +1,14 @@
+use crate::alerting::run_alerting;
+use crate::anomaly_detection::run_anomaly_detection;
 use crate::app_context::AppContext;
 use crate::control_service::run_control_service;
-use crate::http_api::run_http_api;
+use crate::flow_collector::run_flow_collector;
+use crate::http_api::{run_http_api, run_metrics_api};
 use crate::http_proxy_v2::run_http_proxy;
+use crate::rd_recording::run_rd_recording_retention;
 use crate::reverse_tunnel::run_tunnel_acceptor;
+use crate::threat_intel::run_threat_intel;
+use crate::traffic_handler::ip_info::spawn_ip_info_handler;
 use nullnet_liberror::Error;
 
 pub async fn linux_main() {
@@ -37,12 +43,21 @@ pub async fn linux_main() {
         .tunnels_manager
         .spawn_timeout_controller(app_context.clone());
 
+    // Agents and flow exporters share one handler, so an IP is looked up once
+    let ip_info_tx = spawn_ip_info_handler(app_context.clone());
+
     tokio::select! {
         _ = tokio::signal::ctrl_c() => {},
-        _ = run_control_service(app_context.clone()) => {},
+        _ = run_control_service(app_context.clone(), ip_info_tx.clone()) => {},
         _ = run_http_api(app_context.clone()) => {},
+        _ = run_metrics_api(app_context.clone()) => {},
         _ = run_http_proxy(app_context.clone()) => {},
         _ = run_tunnel_acceptor(app_context.clone()) => {},
+        _ = run_flow_collector(app_context.clone(), ip_info_tx) => {},
+        _ = run_threat_intel(app_context.clone()) => {},
+        _ = run_anomaly_detection(app_context.clone()) => {},
+        _ = run_alerting(app_context.clone()) => {},
+        _ = run_rd_recording_retention(app_context.clone()) => {},
     }
 }
 
@@ -1,12 +1,18 @@
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
+        mod alerting;
+        mod anomaly_detection;
         mod app_context;
         mod control_service;
         mod datastore;
+        mod flow_collector;
         mod http_api;
         mod http_proxy_v2;
+        mod metrics;
         mod orchestrator;
+        mod rd_recording;
         mod reverse_tunnel;
+        mod threat_intel;
         mod token;
         mod token_provider;
         mod traffic_handler;
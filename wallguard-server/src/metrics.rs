@@ -0,0 +1,217 @@
+use crate::app_context::AppContext;
+use prometheus::{
+    GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::time::Duration;
+use wallguard_common::protobuf::wallguard_service::SystemResource;
+
+const DEVICE_METRICS: [&str; 5] = [
+    "cpu_usage_percent",
+    "memory_used_bytes",
+    "memory_total_bytes",
+    "disk_used_bytes",
+    "disk_total_bytes",
+];
+
+/// Prometheus metrics of the server, served on `/metrics`.
+///
+/// Counters and histograms are updated as things happen, while the gauges
+/// describing the connected devices and the open tunnels are computed at scrape time.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Registry,
+
+    connected_devices: IntGauge,
+    connected_instances: IntGauge,
+    active_tunnels: IntGaugeVec,
+
+    ingested_records: IntCounterVec,
+    ingestion_duration: HistogramVec,
+    datastore_duration: HistogramVec,
+    ip_info_lookups: IntCounterVec,
+    tunnel_connections: IntCounterVec,
+
+    /// Latest telemetry of every device, only if `METRICS_DEVICE_TELEMETRY` is enabled.
+    device_telemetry: Option<GaugeVec>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some(String::from("wallguard")), None).unwrap();
+
+        let connected_devices = IntGauge::new(
+            "connected_devices",
+            "Devices with at least one connected instance",
+        )
+        .unwrap();
+        let connected_instances =
+            IntGauge::new("connected_instances", "Connected agent instances").unwrap();
+        let active_tunnels = IntGaugeVec::new(
+            Opts::new("active_tunnels", "Open tunnels by type"),
+            &["type"],
+        )
+        .unwrap();
+        let ingested_records = IntCounterVec::new(
+            Opts::new("ingested_records_total", "Records received from the agents"),
+            &["kind"],
+        )
+        .unwrap();
+        let ingestion_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "ingestion_duration_seconds",
+                "Time spent handling a batch of records received from an agent",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+        let datastore_duration = HistogramVec::new(
+            HistogramOpts::new("datastore_duration_seconds", "Latency of datastore calls"),
+            &["operation"],
+        )
+        .unwrap();
+        let ip_info_lookups = IntCounterVec::new(
+            Opts::new("ip_info_lookups_total", "IP information lookups by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let tunnel_connections = IntCounterVec::new(
+            Opts::new(
+                "tunnel_connections_total",
+                "Connections to the tunnel acceptor by result",
+            ),
+            &["result"],
+        )
+        .unwrap();
+
+        let device_telemetry = std::env::var("METRICS_DEVICE_TELEMETRY")
+            .is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "true" | "1"))
+            .then(|| {
+                GaugeVec::new(
+                    Opts::new("device_telemetry", "Latest telemetry reported by a device"),
+                    &["device_id", "metric"],
+                )
+                .unwrap()
+            });
+
+        registry
+            .register(Box::new(connected_devices.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(connected_instances.clone()))
+            .unwrap();
+        registry.register(Box::new(active_tunnels.clone())).unwrap();
+        registry
+            .register(Box::new(ingested_records.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(ingestion_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datastore_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(ip_info_lookups.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(tunnel_connections.clone()))
+            .unwrap();
+        if let Some(device_telemetry) = &device_telemetry {
+            registry
+                .register(Box::new(device_telemetry.clone()))
+                .unwrap();
+        }
+
+        Self {
+            registry,
+            connected_devices,
+            connected_instances,
+            active_tunnels,
+            ingested_records,
+            ingestion_duration,
+            datastore_duration,
+            ip_info_lookups,
+            tunnel_connections,
+            device_telemetry,
+        }
+    }
+
+    /// Records a batch of `count` records of the given kind (`connections`, `flows`, `resources`, `config`).
+    pub fn observe_ingestion(&self, kind: &str, count: usize, elapsed: Duration) {
+        self.ingested_records
+            .with_label_values(&[kind])
+            .inc_by(count as u64);
+        self.ingestion_duration
+            .with_label_values(&[kind])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_datastore(&self, operation: &str, elapsed: Duration) {
+        self.datastore_duration
+            .with_label_values(&[operation])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Counts an IP information lookup: `known`, `resolved` or `failed`.
+    pub fn count_ip_info_lookup(&self, outcome: &str) {
+        self.ip_info_lookups.with_label_values(&[outcome]).inc();
+    }
+
+    /// Counts a connection to the tunnel acceptor: `accepted` or `rejected`.
+    pub fn count_tunnel_connection(&self, result: &str) {
+        self.tunnel_connections.with_label_values(&[result]).inc();
+    }
+
+    pub fn record_device_telemetry(&self, device_id: &str, resource: &SystemResource) {
+        let Some(device_telemetry) = &self.device_telemetry else {
+            return;
+        };
+
+        let values = [
+            f64::from(resource.global_cpu_usage),
+            resource.used_memory as f64,
+            resource.total_memory as f64,
+            resource
+                .total_disk_space
+                .saturating_sub(resource.available_disk_space) as f64,
+            resource.total_disk_space as f64,
+        ];
+
+        for (metric, value) in DEVICE_METRICS.into_iter().zip(values) {
+            device_telemetry
+                .with_label_values(&[device_id, metric])
+                .set(value);
+        }
+    }
+
+    /// Drops the telemetry of a device that went offline, so that it does not linger.
+    pub fn forget_device(&self, device_id: &str) {
+        let Some(device_telemetry) = &self.device_telemetry else {
+            return;
+        };
+
+        for metric in DEVICE_METRICS {
+            let _ = device_telemetry.remove_label_values(&[device_id, metric]);
+        }
+    }
+
+    /// Refreshes the scrape-time gauges and renders every metric in the text exposition format.
+    pub async fn render(&self, context: &AppContext) -> String {
+        let (devices, instances) = context.orchestractor.count_connected().await;
+        self.connected_devices.set(devices as i64);
+        self.connected_instances.set(instances as i64);
+
+        self.active_tunnels.reset();
+        for (kind, count) in context.tunnels_manager.count_by_type().await {
+            self.active_tunnels
+                .with_label_values(&[kind])
+                .set(count as i64);
+        }
+
+        let mut buffer = String::new();
+        if let Err(err) = TextEncoder::new().encode_utf8(&self.registry.gather(), &mut buffer) {
+            log::error!("Failed to encode metrics: {err}");
+        }
+        buffer
+    }
+}
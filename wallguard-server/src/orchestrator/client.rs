@@ -10,11 +10,15 @@ use wallguard_common::protobuf::wallguard_models::NatRule;
 use crate::app_context::AppContext;
 use crate::orchestrator::control_stream::control_stream;
 use wallguard_common::protobuf::wallguard_commands::AuthenticationData;
+use wallguard_common::protobuf::wallguard_commands::CaptureSessionData;
 use wallguard_common::protobuf::wallguard_commands::ClientMessage;
+use wallguard_common::protobuf::wallguard_commands::RemoteDesktopRole;
+use wallguard_common::protobuf::wallguard_commands::RemoteDesktopSessionData;
 use wallguard_common::protobuf::wallguard_commands::ServerMessage;
 use wallguard_common::protobuf::wallguard_commands::SshSessionData;
 use wallguard_common::protobuf::wallguard_commands::UiSessionData;
 use wallguard_common::protobuf::wallguard_commands::server_message::Message;
+use wallguard_common::protobuf::wallguard_commands::{FlowExportSettings, ProcessSnapshotSettings};
 
 pub(crate) type OutboundStream = mpsc::Sender<Result<ServerMessage, Status>>;
 pub(crate) type InboundStream = Streaming<ClientMessage>;
@@ -104,6 +108,43 @@ impl Instance {
             .handle_err(location!())
     }
 
+    pub async fn configure_flow_export(&self, settings: FlowExportSettings) -> Result<(), Error> {
+        log::info!(
+            "Sending ConfigureFlowExportCommand to the client with device ID {}, Instance {}",
+            self.device_id,
+            self.instance_id
+        );
+
+        let message = ServerMessage {
+            message: Some(Message::ConfigureFlowExportCommand(settings)),
+        };
+
+        self.outbound
+            .send(Ok(message))
+            .await
+            .handle_err(location!())
+    }
+
+    pub async fn configure_process_snapshots(
+        &self,
+        settings: ProcessSnapshotSettings,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Sending ConfigureProcessSnapshotsCommand to the client with device ID {}, Instance {}",
+            self.device_id,
+            self.instance_id
+        );
+
+        let message = ServerMessage {
+            message: Some(Message::ConfigureProcessSnapshotsCommand(settings)),
+        };
+
+        self.outbound
+            .send(Ok(message))
+            .await
+            .handle_err(location!())
+    }
+
     pub async fn enable_telemetry_monitoring(&self, enable: bool) -> Result<(), Error> {
         log::info!(
             "Sending EnableTelemetryMonitoringCommand to the client with device ID {}, Instance {}",
@@ -215,9 +256,46 @@ impl Instance {
             .handle_err(location!())
     }
 
+    pub async fn request_capture_session(
+        &self,
+        tunnel_token: impl Into<String>,
+        interface: impl Into<String>,
+        bpf_filter: impl Into<String>,
+        duration_seconds: u32,
+        max_bytes: u64,
+        promiscuous: bool,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Sending OpenCaptureSessionCommand to the client with device ID {}, Instance {}",
+            self.device_id,
+            self.instance_id
+        );
+
+        let capture_session_data = CaptureSessionData {
+            tunnel_token: tunnel_token.into(),
+            interface: interface.into(),
+            bpf_filter: bpf_filter.into(),
+            duration_seconds,
+            max_bytes,
+            promiscuous,
+        };
+
+        let message = ServerMessage {
+            message: Some(Message::OpenCaptureSessionCommand(capture_session_data)),
+        };
+
+        self.outbound
+            .send(Ok(message))
+            .await
+            .handle_err(location!())
+    }
+
     pub async fn request_remote_desktop_session(
         &self,
         tunnel_token: impl Into<String>,
+        clipboard_to_host: bool,
+        clipboard_to_viewer: bool,
+        role: RemoteDesktopRole,
     ) -> Result<(), Error> {
         log::info!(
             "Sending OpenRemoteDesktopSessionCommand to the client with device ID {}, Instance {}",
@@ -225,9 +303,16 @@ impl Instance {
             self.instance_id
         );
 
+        let remote_desktop_session_data = RemoteDesktopSessionData {
+            tunnel_token: tunnel_token.into(),
+            disable_clipboard_to_host: !clipboard_to_host,
+            disable_clipboard_to_viewer: !clipboard_to_viewer,
+            role: role.into(),
+        };
+
         let message = ServerMessage {
             message: Some(Message::OpenRemoteDesktopSessionCommand(
-                tunnel_token.into(),
+                remote_desktop_session_data,
             )),
         };
 
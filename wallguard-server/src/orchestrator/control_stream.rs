@@ -1,8 +1,9 @@
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use std::time::Duration;
 
+use crate::alerting::AlertEvent;
 use crate::app_context::AppContext;
-use crate::datastore::HeartbeatModel;
+use crate::datastore::{DeviceHealth, HeartbeatModel};
 use crate::orchestrator::client::{InboundStream, OutboundStream};
 use crate::token_provider::TokenProvider;
 use wallguard_common::protobuf::wallguard_commands::{
@@ -20,6 +21,11 @@ pub(crate) async fn control_stream(
 ) {
     log::info!("Starting a control stream for device ID {device_id}, Instance {instance_id}");
 
+    context.alert_engine.emit(AlertEvent::DeviceStatus {
+        device_id: device_id.clone(),
+        online: true,
+    });
+
     if let Ok(token) = context.sysdev_token_provider.get().await {
         if context
             .datastore
@@ -57,6 +63,15 @@ pub(crate) async fn control_stream(
             .does_client_have_connected_instances(&device_id)
             .await;
 
+        context.alert_engine.emit(AlertEvent::DeviceStatus {
+            device_id: device_id.clone(),
+            online: is_online,
+        });
+
+        if !is_online {
+            context.metrics.forget_device(&device_id);
+        }
+
         if context
             .datastore
             .update_device_online_status(&token.jwt, &device_id, is_online)
@@ -122,14 +137,16 @@ async fn authstream(
                         };
 
                         match msg {
-                            client_message::Message::Heartbeat(()) => {
+                            client_message::Message::Heartbeat(heartbeat) => {
                                 log::debug!("Received a heartbeat from {device_id}");
 
+                                let health = DeviceHealth::reported(heartbeat);
+
                                 if let Ok(token) = context.sysdev_token_provider.get().await {
                                     let data = HeartbeatModel::from_device_id(device_id.clone());
                                     if context
                                         .datastore
-                                        .create_heartbeat(&token.jwt, &data)
+                                        .create_heartbeat(&token.jwt, &data, health.as_ref())
                                         .await
                                         .is_err()
                                     {
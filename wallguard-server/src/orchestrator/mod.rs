@@ -95,6 +95,22 @@ impl Orchestrator {
         None
     }
 
+    /// Number of devices with at least one connected instance, and of instances.
+    pub async fn count_connected(&self) -> (usize, usize) {
+        let mut devices = 0;
+        let mut instances = 0;
+
+        for vec in self.clients.lock().await.values() {
+            let count = vec.lock().await.len();
+            if count > 0 {
+                devices += 1;
+                instances += count;
+            }
+        }
+
+        (devices, instances)
+    }
+
     pub async fn does_client_have_connected_instances(&self, device_id: &str) -> bool {
         if let Some(vec) = self.clients.lock().await.get(device_id) {
             !vec.lock().await.is_empty()
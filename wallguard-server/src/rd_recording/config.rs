@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    pub(crate) dir: PathBuf,
+    /// Recordings older than this are deleted, `None` to keep them forever
+    pub(crate) retention: Option<Duration>,
+    /// The oldest recordings are deleted beyond this total size, `None` for no limit
+    pub(crate) max_total_bytes: Option<u64>,
+}
+
+impl RecordingConfig {
+    /// Reads the remote desktop recording configuration from the environment.
+    ///
+    /// Recording is optional: `None` is returned unless `RD_RECORDING_DIR` is set.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("RD_RECORDING_DIR")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())?;
+
+        let retention_days = std::env::var("RD_RECORDING_RETENTION_DAYS")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+        let max_total_mb = std::env::var("RD_RECORDING_MAX_TOTAL_MB")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+
+        Some(Self {
+            dir: PathBuf::from(dir),
+            retention: (retention_days > 0).then(|| Duration::from_secs(retention_days * 86400)),
+            max_total_bytes: (max_total_mb > 0).then(|| max_total_mb * 1024 * 1024),
+        })
+    }
+}
@@ -0,0 +1,296 @@
+//! Fragmented MP4 muxing of the H.264 stream of the agent, one fragment per frame.
+//!
+//! The sample entry is `avc3`, so the parameter sets the encoder repeats before
+//! every keyframe stay in band. The track header and the sample entry still
+//! hold a single resolution, so a new SPS starts a new file.
+
+/// Milliseconds, as the timestamps of the agent
+const TIMESCALE: u32 = 1000;
+const TRACK_ID: u32 = 1;
+const MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+
+/// Splits an Annex B byte stream into NAL units, without their start codes.
+pub fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(start) = start {
+                units.push(trim_trailing_zeros(&data[start..i]));
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(start) = start {
+        units.push(trim_trailing_zeros(&data[start..]));
+    }
+
+    units.retain(|unit| !unit.is_empty());
+    units
+}
+
+/// The first SPS and PPS among `units`.
+pub fn parameter_sets<'a>(units: &[&'a [u8]]) -> Option<(&'a [u8], &'a [u8])> {
+    let find = |kind| units.iter().find(|unit| unit[0] & 0x1f == kind).copied();
+    let sps = find(NAL_SPS).filter(|sps| sps.len() >= 4)?;
+    Some((sps, find(NAL_PPS)?))
+}
+
+/// `ftyp` and `moov`, describing a single H.264 track.
+pub fn init_segment(width: u16, height: u16, sps: &[u8], pps: &[u8]) -> Vec<u8> {
+    let ftyp = mp4_box(
+        b"ftyp",
+        &[
+            b"iso5".as_slice(),
+            &512u32.to_be_bytes(),
+            b"iso5",
+            b"iso6",
+            b"avc1",
+            b"mp41",
+        ]
+        .concat(),
+    );
+
+    let moov = mp4_box(
+        b"moov",
+        &[mvhd(), trak(width, height, sps, pps), mvex()].concat(),
+    );
+
+    [ftyp, moov].concat()
+}
+
+/// `moof` and `mdat` holding one frame, `decode_time` and `duration` in milliseconds.
+pub fn fragment(
+    sequence: u32,
+    decode_time: u64,
+    duration: u32,
+    keyframe: bool,
+    units: &[&[u8]],
+) -> Vec<u8> {
+    let mut sample = Vec::new();
+    for unit in units {
+        sample.extend((unit.len() as u32).to_be_bytes());
+        sample.extend_from_slice(unit);
+    }
+
+    // sample_depends_on = 2 for keyframes, otherwise 1 and sample_is_non_sync_sample
+    let flags: u32 = if keyframe { 0x0200_0000 } else { 0x0101_0000 };
+
+    let moof = |data_offset: u32| {
+        let mfhd = full_box(b"mfhd", 0, 0, &sequence.to_be_bytes());
+        // default-base-is-moof
+        let tfhd = full_box(b"tfhd", 0, 0x02_0000, &TRACK_ID.to_be_bytes());
+        let tfdt = full_box(b"tfdt", 1, 0, &decode_time.to_be_bytes());
+
+        let mut run = Vec::new();
+        for value in [1, data_offset, duration, sample.len() as u32, flags] {
+            run.extend(value.to_be_bytes());
+        }
+        // data offset, sample duration, size and flags present
+        let trun = full_box(b"trun", 0, 0x00_0701, &run);
+
+        let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+        mp4_box(b"moof", &[mfhd, traf].concat())
+    };
+
+    // The sample starts right after the moof and the mdat header
+    let moof_len = moof(0).len() as u32;
+    [moof(moof_len + 8), mp4_box(b"mdat", &sample)].concat()
+}
+
+fn mvhd() -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend(0u32.to_be_bytes()); // creation time
+    payload.extend(0u32.to_be_bytes()); // modification time
+    payload.extend(TIMESCALE.to_be_bytes());
+    payload.extend(0u32.to_be_bytes()); // duration, given by the fragments
+    payload.extend(0x0001_0000u32.to_be_bytes()); // rate
+    payload.extend(0x0100u16.to_be_bytes()); // volume
+    payload.extend([0u8; 10]);
+    MATRIX.iter().for_each(|v| payload.extend(v.to_be_bytes()));
+    payload.extend([0u8; 24]);
+    payload.extend((TRACK_ID + 1).to_be_bytes()); // next track ID
+    full_box(b"mvhd", 0, 0, &payload)
+}
+
+fn trak(width: u16, height: u16, sps: &[u8], pps: &[u8]) -> Vec<u8> {
+    let mut tkhd = Vec::new();
+    tkhd.extend(0u32.to_be_bytes()); // creation time
+    tkhd.extend(0u32.to_be_bytes()); // modification time
+    tkhd.extend(TRACK_ID.to_be_bytes());
+    tkhd.extend(0u32.to_be_bytes());
+    tkhd.extend(0u32.to_be_bytes()); // duration
+    tkhd.extend([0u8; 8]);
+    tkhd.extend([0u8; 8]); // layer, alternate group, volume, reserved
+    MATRIX.iter().for_each(|v| tkhd.extend(v.to_be_bytes()));
+    tkhd.extend((u32::from(width) << 16).to_be_bytes());
+    tkhd.extend((u32::from(height) << 16).to_be_bytes());
+    // enabled, in movie
+    let tkhd = full_box(b"tkhd", 0, 0x03, &tkhd);
+
+    let mut mdhd = Vec::new();
+    mdhd.extend(0u32.to_be_bytes());
+    mdhd.extend(0u32.to_be_bytes());
+    mdhd.extend(TIMESCALE.to_be_bytes());
+    mdhd.extend(0u32.to_be_bytes());
+    mdhd.extend(0x55c4u16.to_be_bytes()); // "und"
+    mdhd.extend(0u16.to_be_bytes());
+    let mdhd = full_box(b"mdhd", 0, 0, &mdhd);
+
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &[
+            0u32.to_be_bytes().as_slice(),
+            b"vide",
+            &[0u8; 12],
+            b"VideoHandler\0",
+        ]
+        .concat(),
+    );
+
+    let vmhd = full_box(b"vmhd", 0, 1, &[0u8; 8]);
+    let url = full_box(b"url ", 0, 1, &[]);
+    let dref = full_box(
+        b"dref",
+        0,
+        0,
+        &[1u32.to_be_bytes().as_slice(), &url].concat(),
+    );
+    let dinf = mp4_box(b"dinf", &dref);
+
+    let stsd = full_box(
+        b"stsd",
+        0,
+        0,
+        &[
+            1u32.to_be_bytes().as_slice(),
+            &avc3(width, height, sps, pps),
+        ]
+        .concat(),
+    );
+    let stbl = mp4_box(
+        b"stbl",
+        &[
+            stsd,
+            full_box(b"stts", 0, 0, &0u32.to_be_bytes()),
+            full_box(b"stsc", 0, 0, &0u32.to_be_bytes()),
+            full_box(b"stsz", 0, 0, &[0u8; 8]),
+            full_box(b"stco", 0, 0, &0u32.to_be_bytes()),
+        ]
+        .concat(),
+    );
+
+    let minf = mp4_box(b"minf", &[vmhd, dinf, stbl].concat());
+    let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+    mp4_box(b"trak", &[tkhd, mdia].concat())
+}
+
+fn avc3(width: u16, height: u16, sps: &[u8], pps: &[u8]) -> Vec<u8> {
+    let mut avcc = vec![1, sps[1], sps[2], sps[3], 0xff, 0xe1];
+    avcc.extend((sps.len() as u16).to_be_bytes());
+    avcc.extend_from_slice(sps);
+    avcc.push(1);
+    avcc.extend((pps.len() as u16).to_be_bytes());
+    avcc.extend_from_slice(pps);
+
+    let mut entry = Vec::new();
+    entry.extend([0u8; 6]);
+    entry.extend(1u16.to_be_bytes()); // data reference index
+    entry.extend([0u8; 16]);
+    entry.extend(width.to_be_bytes());
+    entry.extend(height.to_be_bytes());
+    entry.extend(0x0048_0000u32.to_be_bytes()); // 72 dpi
+    entry.extend(0x0048_0000u32.to_be_bytes());
+    entry.extend(0u32.to_be_bytes());
+    entry.extend(1u16.to_be_bytes()); // frame count
+    entry.extend([0u8; 32]); // compressor name
+    entry.extend(0x0018u16.to_be_bytes()); // depth
+    entry.extend(0xffffu16.to_be_bytes());
+    entry.extend(mp4_box(b"avcC", &avcc));
+    mp4_box(b"avc3", &entry)
+}
+
+fn mvex() -> Vec<u8> {
+    let mut trex = Vec::new();
+    for value in [TRACK_ID, 1, 0, 0, 0] {
+        trex.extend(value.to_be_bytes());
+    }
+    mp4_box(b"mvex", &full_box(b"trex", 0, 0, &trex))
+}
+
+fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut retval = Vec::with_capacity(8 + payload.len());
+    retval.extend((8 + payload.len() as u32).to_be_bytes());
+    retval.extend_from_slice(kind);
+    retval.extend_from_slice(payload);
+    retval
+}
+
+fn full_box(kind: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+    let header = [
+        version,
+        (flags >> 16) as u8,
+        (flags >> 8) as u8,
+        flags as u8,
+    ];
+    mp4_box(kind, &[header.as_slice(), payload].concat())
+}
+
+fn trim_trailing_zeros(unit: &[u8]) -> &[u8] {
+    let len = unit
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |i| i + 1);
+    &unit[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragmented_mp4() {
+        let stream = [
+            &[0, 0, 0, 1, 0x67, 0x42, 0xc0, 0x1f, 0xda][..],
+            &[0, 0, 0, 1, 0x68, 0xce, 0x3c, 0x80],
+            &[0, 0, 1, 0x65, 0x88, 0x84, 0x00],
+        ]
+        .concat();
+
+        let units = nal_units(&stream);
+        assert_eq!(units.len(), 3);
+        assert_eq!(units[2], &[0x65, 0x88, 0x84]);
+
+        let (sps, pps) = parameter_sets(&units).unwrap();
+        assert_eq!(sps, &[0x67, 0x42, 0xc0, 0x1f, 0xda]);
+        assert_eq!(pps, &[0x68, 0xce, 0x3c, 0x80]);
+
+        let init = init_segment(1920, 1080, sps, pps);
+        assert_eq!(&init[4..8], b"ftyp");
+        let ftyp_len = u32::from_be_bytes(init[..4].try_into().unwrap()) as usize;
+        let moov_len = u32::from_be_bytes(init[ftyp_len..ftyp_len + 4].try_into().unwrap());
+        assert_eq!(ftyp_len + moov_len as usize, init.len());
+
+        // The data offset of the trun points at the first sample in the mdat
+        let fragment = fragment(1, 0, 33, true, &units[2..]);
+        let moof_len = u32::from_be_bytes(fragment[..4].try_into().unwrap()) as usize;
+        assert_eq!(&fragment[moof_len + 4..moof_len + 8], b"mdat");
+        let offset = u32::from_be_bytes(fragment[moof_len - 16..moof_len - 12].try_into().unwrap());
+        assert_eq!(offset as usize, moof_len + 8);
+        assert_eq!(
+            &fragment[offset as usize..],
+            &[0, 0, 0, 3, 0x65, 0x88, 0x84]
+        );
+    }
+}
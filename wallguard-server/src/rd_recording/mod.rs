@@ -0,0 +1,288 @@
+mod config;
+mod fmp4;
+mod recorder;
+
+use crate::app_context::AppContext;
+use config::RecordingConfig;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use recorder::Recorder;
+
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Metadata of a recording, stored next to it as `<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub tunnel_id: String,
+    pub device_id: String,
+    /// Role of the recorded tunnel, `controller` or `view_only`
+    pub role: String,
+    /// Unix timestamps, in seconds
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    pub duration_ms: u64,
+    /// Size of the video file
+    #[serde(default, skip_deserializing)]
+    pub size_bytes: u64,
+}
+
+/// Remote desktop recordings kept on the server storage.
+#[derive(Debug, Clone, Default)]
+pub struct RdRecordings {
+    config: Option<Arc<RecordingConfig>>,
+    /// IDs of the recordings still being written
+    active: Arc<Mutex<HashSet<String>>>,
+}
+
+impl RdRecordings {
+    pub fn new() -> Self {
+        Self {
+            config: RecordingConfig::from_env().map(Arc::new),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub async fn start(
+        &self,
+        tunnel_id: &str,
+        device_id: &str,
+        role: &str,
+    ) -> Result<Recorder, Error> {
+        let dir = self.dir()?;
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .handle_err(location!())?;
+
+        // Milliseconds keep the IDs of recordings started within a second apart
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let id = format!("{tunnel_id}-{}", now.as_millis());
+
+        let info = RecordingInfo {
+            id: id.clone(),
+            tunnel_id: tunnel_id.to_string(),
+            device_id: device_id.to_string(),
+            role: role.to_string(),
+            started_at: now.as_secs(),
+            ended_at: None,
+            duration_ms: 0,
+            size_bytes: 0,
+        };
+
+        if !is_valid_id(&info.id) {
+            return Err(format!("Invalid recording ID '{}'", info.id)).handle_err(location!());
+        }
+
+        self.on_started(&info.id);
+        log::info!("RD recording {} started", info.id);
+
+        Recorder::create(self.clone(), info, dir)
+            .await
+            .inspect_err(|_| self.on_finished(&id))
+    }
+
+    /// Recordings of the device, or of every device, oldest first.
+    pub async fn list(&self, device_id: Option<&str>) -> Vec<RecordingInfo> {
+        let Ok(dir) = self.dir() else {
+            return vec![];
+        };
+
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            return vec![];
+        };
+
+        let mut recordings = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(id) = name.strip_suffix(".json") else {
+                continue;
+            };
+
+            let Some(info) = self.get(id).await else {
+                continue;
+            };
+
+            if device_id.is_none_or(|device_id| info.device_id == device_id) {
+                recordings.push(info);
+            }
+        }
+
+        recordings.sort_by_key(|info| info.started_at);
+        recordings
+    }
+
+    pub async fn get(&self, id: &str) -> Option<RecordingInfo> {
+        if !is_valid_id(id) {
+            return None;
+        }
+
+        let dir = self.dir().ok()?;
+        let json = tokio::fs::read(dir.join(format!("{id}.json"))).await.ok()?;
+        let mut info: RecordingInfo = serde_json::from_slice(&json).ok()?;
+
+        info.size_bytes = tokio::fs::metadata(dir.join(format!("{id}.mp4")))
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+
+        Some(info)
+    }
+
+    /// Path of the video, or of the input events with `events`.
+    pub fn path(&self, id: &str, events: bool) -> Option<PathBuf> {
+        if !is_valid_id(id) {
+            return None;
+        }
+
+        let extension = if events { "events.jsonl" } else { "mp4" };
+        Some(self.dir().ok()?.join(format!("{id}.{extension}")))
+    }
+
+    pub fn is_active(&self, id: &str) -> bool {
+        self.active.lock().unwrap().contains(id)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), Error> {
+        if self.is_active(id) {
+            return Err(format!("Recording {id} is in progress")).handle_err(location!());
+        }
+
+        let dir = self.dir()?;
+        for extension in ["mp4", "events.jsonl", "json"] {
+            let path = dir.join(format!("{id}.{extension}"));
+            if let Err(err) = tokio::fs::remove_file(&path).await
+                && err.kind() != std::io::ErrorKind::NotFound
+            {
+                return Err(err).handle_err(location!());
+            }
+        }
+
+        log::info!("RD recording {id} deleted");
+        Ok(())
+    }
+
+    fn on_started(&self, id: &str) {
+        self.active.lock().unwrap().insert(id.to_string());
+    }
+
+    fn on_finished(&self, id: &str) {
+        self.active.lock().unwrap().remove(id);
+    }
+
+    fn dir(&self) -> Result<PathBuf, Error> {
+        self.config
+            .as_ref()
+            .map(|config| config.dir.clone())
+            .ok_or("Remote desktop recording is disabled")
+            .handle_err(location!())
+    }
+
+    /// Deletes the recordings past the retention period, then the oldest ones beyond the size limit.
+    async fn apply_retention(&self, config: &RecordingConfig) {
+        let now = unix_timestamp();
+        let mut recordings: Vec<_> = self
+            .list(None)
+            .await
+            .into_iter()
+            .filter(|info| !self.is_active(&info.id))
+            .collect();
+
+        if let Some(retention) = config.retention {
+            let (expired, kept): (Vec<_>, Vec<_>) = recordings
+                .into_iter()
+                .partition(|info| now.saturating_sub(info.started_at) > retention.as_secs());
+
+            for info in expired {
+                if let Err(err) = self.delete(&info.id).await {
+                    log::error!(
+                        "Failed to delete RD recording {}: {}",
+                        info.id,
+                        err.to_str()
+                    );
+                }
+            }
+
+            recordings = kept;
+        }
+
+        if let Some(max_total_bytes) = config.max_total_bytes {
+            let mut total: u64 = recordings.iter().map(|info| info.size_bytes).sum();
+
+            for info in recordings {
+                if total <= max_total_bytes {
+                    break;
+                }
+
+                match self.delete(&info.id).await {
+                    Ok(()) => total -= info.size_bytes,
+                    Err(err) => {
+                        log::error!(
+                            "Failed to delete RD recording {}: {}",
+                            info.id,
+                            err.to_str()
+                        )
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Periodically deletes the remote desktop recordings past their retention.
+pub async fn run_rd_recording_retention(context: AppContext) {
+    let Some(config) = context.rd_recordings.config.clone() else {
+        log::info!("Remote desktop recording is disabled");
+        return std::future::pending().await;
+    };
+
+    log::info!(
+        "Remote desktop recordings are stored in {}",
+        config.dir.display()
+    );
+
+    let mut ticker = tokio::time::interval(RETENTION_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+        context.rd_recordings.apply_retention(&config).await;
+    }
+}
+
+/// Recording IDs end up in file names: no separators, no dots.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_ids() {
+        assert!(is_valid_id("4F2A-9C1B-1760000000"));
+        assert!(!is_valid_id("../etc/passwd"));
+        assert!(!is_valid_id("a.b"));
+        assert!(!is_valid_id(""));
+    }
+}
@@ -0,0 +1,467 @@
+use super::{RdRecordings, RecordingInfo, fmp4, unix_timestamp};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use wallguard_common::protobuf::wallguard_rd::{
+    AgentMessage, Codec, MouseButton, VideoFrame, ViewerMessage, agent_message, control,
+    input_event, viewer_message,
+};
+
+/// Entries waiting for the writer task, beyond which they are dropped.
+const QUEUE_LEN: usize = 256;
+
+/// What the relay hands over to the writer task.
+enum Entry {
+    Frame(VideoFrame),
+    /// An event and when it happened
+    Event(Value, Instant),
+}
+
+/// Frame waiting for the next one, which gives its duration.
+struct PendingFrame {
+    timestamp_ms: u64,
+    keyframe: bool,
+    data: Vec<u8>,
+}
+
+/// Records one remote desktop tunnel: the relayed H.264 stream to fragmented
+/// MP4 files, the input of the viewers and the control handoffs to JSON lines files.
+///
+/// A task of its own writes the files, so a slow disk never holds up the relay.
+pub struct Recorder {
+    id: String,
+    sender: mpsc::Sender<Entry>,
+    writer: JoinHandle<()>,
+    /// Set once a frame was dropped: the following ones are too, until a keyframe
+    awaiting_keyframe: bool,
+    dropping: bool,
+}
+
+impl Recorder {
+    pub(super) async fn create(
+        recordings: RdRecordings,
+        info: RecordingInfo,
+        dir: PathBuf,
+    ) -> Result<Self, Error> {
+        let id = info.id.clone();
+        let part = Part::create(&dir, info).await?;
+
+        let writer = Writer {
+            recordings,
+            dir,
+            base_id: id.clone(),
+            part_number: 1,
+            part,
+            format: None,
+            origin: None,
+            pending: None,
+            sequence: 0,
+            last_duration: 33,
+            failed: false,
+        };
+
+        let (sender, receiver) = mpsc::channel(QUEUE_LEN);
+
+        Ok(Self {
+            id,
+            sender,
+            writer: tokio::spawn(writer.run(receiver)),
+            awaiting_keyframe: false,
+            dropping: false,
+        })
+    }
+
+    pub fn on_agent_message(&mut self, message: &AgentMessage) {
+        match &message.message {
+            Some(agent_message::Message::VideoFrame(frame)) => {
+                if frame.codec() != Codec::H264 {
+                    return;
+                }
+
+                // Deltas can't be decoded without the frames they follow
+                if self.awaiting_keyframe && !frame.keyframe {
+                    return;
+                }
+
+                self.awaiting_keyframe = !self.push(Entry::Frame(frame.clone()));
+            }
+            Some(agent_message::Message::ControlState(state)) => {
+                let event = json!({
+                    "type": "control_state",
+                    "session": state.session,
+                    "holder": state.holder,
+                });
+                self.push(Entry::Event(event, Instant::now()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Records what the viewers send, including input the agent drops for view-only sessions.
+    pub fn on_viewer_message(&mut self, message: &ViewerMessage) {
+        if let Some(event) = viewer_event(message) {
+            self.push(Entry::Event(event, Instant::now()));
+        }
+    }
+
+    /// Waits for the queued entries and the final metadata to be written.
+    pub async fn finish(self) {
+        drop(self.sender);
+
+        if let Err(err) = self.writer.await {
+            log::error!("RD recording {} writer failed: {err}", self.id);
+        }
+    }
+
+    /// Queues an entry without waiting, returning whether it fit.
+    fn push(&mut self, entry: Entry) -> bool {
+        let queued = self.sender.try_send(entry).is_ok();
+
+        if !queued && !self.dropping {
+            log::warn!(
+                "RD recording {} can't keep up with the session, dropping data",
+                self.id
+            );
+        }
+
+        self.dropping = !queued;
+        queued
+    }
+}
+
+/// The files of one recording. A session is split in several recordings,
+/// or parts, when the resolution changes.
+struct Part {
+    info: RecordingInfo,
+    info_path: PathBuf,
+    video: File,
+    events: File,
+}
+
+impl Part {
+    async fn create(dir: &Path, info: RecordingInfo) -> Result<Self, Error> {
+        let info_path = dir.join(format!("{}.json", info.id));
+        let video = File::create(dir.join(format!("{}.mp4", info.id)))
+            .await
+            .handle_err(location!())?;
+        let events = File::create(dir.join(format!("{}.events.jsonl", info.id)))
+            .await
+            .handle_err(location!())?;
+
+        let part = Self {
+            info,
+            info_path,
+            video,
+            events,
+        };
+
+        part.write_info().await?;
+        Ok(part)
+    }
+
+    async fn write_info(&self) -> Result<(), Error> {
+        let json = serde_json::to_vec_pretty(&self.info).handle_err(location!())?;
+        tokio::fs::write(&self.info_path, json)
+            .await
+            .handle_err(location!())
+    }
+}
+
+struct Writer {
+    recordings: RdRecordings,
+    dir: PathBuf,
+    /// ID of the first part, which the next ones extend with their number
+    base_id: String,
+    part_number: u32,
+    part: Part,
+    /// Width, height and SPS the init segment of the part was written for
+    format: Option<(u32, u32, Vec<u8>)>,
+    /// Agent timestamp and local time of the first frame of the part
+    origin: Option<(u64, Instant)>,
+    pending: Option<PendingFrame>,
+    sequence: u32,
+    last_duration: u32,
+    failed: bool,
+}
+
+impl Writer {
+    async fn run(mut self, mut receiver: mpsc::Receiver<Entry>) {
+        while let Some(entry) = receiver.recv().await {
+            let result = match entry {
+                Entry::Frame(frame) => self.on_video_frame(frame).await,
+                Entry::Event(event, at) => self.write_event(event, at).await,
+            };
+
+            self.check(result);
+        }
+
+        self.finish_part().await;
+    }
+
+    async fn on_video_frame(&mut self, frame: VideoFrame) -> Result<(), Error> {
+        if frame.keyframe {
+            let units = fmp4::nal_units(&frame.data);
+
+            if let Some((sps, pps)) = fmp4::parameter_sets(&units) {
+                let format = (frame.width, frame.height, sps.to_vec());
+
+                // The init segment holds the resolution and the SPS: when they
+                // change, the frames go to a new part with its own
+                if self.format.as_ref() != Some(&format) {
+                    if self.format.is_some() {
+                        self.next_part().await?;
+                    }
+
+                    let init =
+                        fmp4::init_segment(frame.width as u16, frame.height as u16, sps, pps);
+                    self.part
+                        .video
+                        .write_all(&init)
+                        .await
+                        .handle_err(location!())?;
+                    self.format = Some(format);
+                    self.origin = Some((frame.timestamp_ms, Instant::now()));
+                }
+            }
+        }
+
+        // The file can only start with a keyframe carrying the parameter sets
+        if self.origin.is_none() {
+            return Ok(());
+        }
+
+        let next = PendingFrame {
+            timestamp_ms: frame.timestamp_ms,
+            keyframe: frame.keyframe,
+            data: frame.data,
+        };
+
+        if let Some(frame) = self.pending.replace(next) {
+            let duration = self.pending.as_ref().map_or(0, |next| {
+                next.timestamp_ms.saturating_sub(frame.timestamp_ms) as u32
+            });
+            self.last_duration = duration.max(1);
+            self.write_frame(frame, self.last_duration).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_frame(&mut self, frame: PendingFrame, duration: u32) -> Result<(), Error> {
+        let Some((first_timestamp, _)) = self.origin else {
+            return Ok(());
+        };
+
+        self.sequence += 1;
+        let decode_time = frame.timestamp_ms.saturating_sub(first_timestamp);
+        self.part.info.duration_ms = decode_time + u64::from(duration);
+
+        let units = fmp4::nal_units(&frame.data);
+        let fragment = fmp4::fragment(self.sequence, decode_time, duration, frame.keyframe, &units);
+
+        self.part
+            .video
+            .write_all(&fragment)
+            .await
+            .handle_err(location!())
+    }
+
+    async fn write_event(&mut self, mut event: Value, at: Instant) -> Result<(), Error> {
+        // Milliseconds into the video of the part
+        let elapsed = self.origin.map_or(0, |(_, origin)| {
+            at.saturating_duration_since(origin).as_millis() as u64
+        });
+        event["t_ms"] = json!(elapsed);
+
+        let mut line = event.to_string();
+        line.push('\n');
+        self.part
+            .events
+            .write_all(line.as_bytes())
+            .await
+            .handle_err(location!())
+    }
+
+    /// Finishes the current part and opens the next one, which waits for its init segment.
+    async fn next_part(&mut self) -> Result<(), Error> {
+        self.finish_part().await;
+
+        self.part_number += 1;
+        let info = RecordingInfo {
+            id: format!("{}-{}", self.base_id, self.part_number),
+            started_at: unix_timestamp(),
+            ended_at: None,
+            duration_ms: 0,
+            size_bytes: 0,
+            ..self.part.info.clone()
+        };
+
+        // Frames are skipped until the part is open, the next keyframe retries otherwise
+        self.origin = None;
+        self.sequence = 0;
+
+        self.recordings.on_started(&info.id);
+        log::info!(
+            "RD recording {} continues in {} after a resolution change",
+            self.part.info.id,
+            info.id
+        );
+
+        let id = info.id.clone();
+        self.part = Part::create(&self.dir, info)
+            .await
+            .inspect_err(|_| self.recordings.on_finished(&id))?;
+        self.format = None;
+
+        Ok(())
+    }
+
+    /// Writes the last frame and the final metadata of the current part.
+    async fn finish_part(&mut self) {
+        if let Some(frame) = self.pending.take() {
+            let result = self.write_frame(frame, self.last_duration).await;
+            self.check(result);
+        }
+
+        let _ = self.part.video.flush().await;
+        let _ = self.part.events.flush().await;
+
+        self.part.info.ended_at = Some(unix_timestamp());
+        if let Err(err) = self.part.write_info().await {
+            log::error!(
+                "Failed to finalize RD recording {}: {}",
+                self.part.info.id,
+                err.to_str()
+            );
+        }
+
+        log::info!("RD recording {} finished", self.part.info.id);
+        self.recordings.on_finished(&self.part.info.id);
+    }
+
+    fn check(&mut self, result: Result<(), Error>) {
+        if let Err(err) = result
+            && !self.failed
+        {
+            self.failed = true;
+            log::error!(
+                "RD recording {} failed: {}",
+                self.part.info.id,
+                err.to_str()
+            );
+        }
+    }
+}
+
+fn viewer_event(message: &ViewerMessage) -> Option<Value> {
+    let event = match message.message.as_ref()? {
+        viewer_message::Message::Input(input) => match input.event.as_ref()? {
+            input_event::Event::MouseMove(mv) => {
+                json!({"type": "mouse_move", "x": mv.x, "y": mv.y})
+            }
+            input_event::Event::MouseButton(button) => json!({
+                "type": "mouse_button",
+                "button": MouseButton::try_from(button.button)
+                    .map_or("unknown", |button| button.as_str_name()),
+                "pressed": button.pressed,
+            }),
+            input_event::Event::Key(key) => json!({
+                "type": "key",
+                "key": key.key,
+                "code": key.code,
+                "pressed": key.pressed,
+            }),
+            // Only the length, like the clipboard
+            input_event::Event::Text(text) => {
+                json!({"type": "text", "chars": text.text.chars().count()})
+            }
+        },
+        // Only the size: pasted text often holds credentials
+        viewer_message::Message::Clipboard(clipboard) => {
+            json!({"type": "clipboard", "bytes": clipboard.text.len()})
+        }
+        viewer_message::Message::Control(message) => match message.action? {
+            control::Action::SelectMonitor(selection) => {
+                json!({"type": "select_monitor", "monitor": selection.id})
+            }
+            control::Action::RequestControl(_) => json!({"type": "request_control"}),
+            control::Action::GrantControl(grant) => {
+                json!({"type": "grant_control", "session": grant.session})
+            }
+            control::Action::ReleaseControl(_) => json!({"type": "release_control"}),
+            control::Action::RequestKeyframe(_) | control::Action::SetQuality(_) => return None,
+        },
+        viewer_message::Message::Hello(_) => return None,
+    };
+
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::RecordingConfig;
+    use super::*;
+    use std::sync::Arc;
+
+    fn frame(timestamp_ms: u64, width: u32, sps: &[u8]) -> AgentMessage {
+        let keyframe = !sps.is_empty();
+        let data = if keyframe {
+            [
+                &[0, 0, 0, 1],
+                sps,
+                &[0, 0, 0, 1, 0x68, 0xce, 0x3c, 0x80, 0, 0, 1, 0x65, 0x88],
+            ]
+            .concat()
+        } else {
+            vec![0, 0, 0, 1, 0x41, 0x9a]
+        };
+
+        AgentMessage {
+            message: Some(agent_message::Message::VideoFrame(VideoFrame {
+                codec: Codec::H264.into(),
+                width,
+                height: width * 9 / 16,
+                keyframe,
+                timestamp_ms,
+                data,
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_part_on_resolution_change() {
+        let dir = std::env::temp_dir().join(format!("wallguard-rd-{}", std::process::id()));
+        let recordings = RdRecordings {
+            config: Some(Arc::new(RecordingConfig {
+                dir: dir.clone(),
+                retention: None,
+                max_total_bytes: None,
+            })),
+            ..Default::default()
+        };
+
+        let mut recorder = recordings
+            .start("tunnel", "device", "controller")
+            .await
+            .unwrap();
+        recorder.on_agent_message(&frame(0, 1920, &[0x67, 0x42, 0xc0, 0x28, 0xda]));
+        recorder.on_agent_message(&frame(33, 1920, &[]));
+        recorder.on_agent_message(&frame(66, 960, &[0x67, 0x42, 0xc0, 0x1f, 0xda]));
+        recorder.on_agent_message(&frame(99, 960, &[]));
+        recorder.finish().await;
+
+        let mut parts = recordings.list(None).await;
+        parts.sort_by(|a, b| a.id.cmp(&b.id));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].id, format!("{}-2", parts[0].id));
+        assert_eq!(parts[0].duration_ms, 66);
+        assert!(parts.iter().all(|part| !recordings.is_active(&part.id)));
+    }
+}
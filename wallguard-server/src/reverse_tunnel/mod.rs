@@ -77,6 +77,7 @@ pub async fn run_tunnel_acceptor(context: AppContext) -> Result<(), Error> {
 
             let Ok(hash) = TokenHash::read_from_stream(&mut stream).await else {
                 log::error!("Failed to read token hash from newely accepted TCP stream");
+                ctx.metrics.count_tunnel_connection("rejected");
                 let _ = stream.shutdown().await;
                 return;
             };
@@ -85,12 +86,14 @@ pub async fn run_tunnel_acceptor(context: AppContext) -> Result<(), Error> {
 
             match ctx.tunnel.listeners.lock().await.remove(&hash) {
                 Some(channel) => {
+                    ctx.metrics.count_tunnel_connection("accepted");
                     if let Err(mut tunnel) = channel.send(tunnel) {
                         let _ = tunnel.shutdown().await;
                         log::error!("Failed to send tunnel instance");
                     }
                 }
                 None => {
+                    ctx.metrics.count_tunnel_connection("rejected");
                     log::warn!(
                         "Received tunnel connection with unknown token hash: {:?}",
                         hash
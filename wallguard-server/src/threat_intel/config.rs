@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+const DEFAULT_REFRESH_MINUTES: u64 = 60;
+const DEFAULT_ALIAS_NAME: &str = "wallguard_threats";
+const DEFAULT_ALIAS_TABLE: &str = "filter";
+
+#[derive(Debug, Clone)]
+pub struct ThreatIntelConfig {
+    pub(crate) feeds: Vec<FeedConfig>,
+    pub(crate) refresh_interval: Duration,
+    pub(crate) alias: Option<AliasConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    pub(crate) name: String,
+    pub(crate) source: FeedSource,
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedSource {
+    Url(String),
+    File(String),
+}
+
+/// Where the matched addresses are pushed on the firewall.
+#[derive(Debug, Clone)]
+pub struct AliasConfig {
+    pub(crate) name: String,
+    /// nftables table holding the set; unused on pfSense and OPNsense.
+    pub(crate) table: String,
+}
+
+impl ThreatIntelConfig {
+    /// Reads the threat intelligence configuration from the environment.
+    ///
+    /// The subsystem is optional: `None` is returned unless `THREAT_INTEL_FEEDS` is set
+    /// to a comma separated list of `<name>=<url or path>` pairs.
+    pub fn from_env() -> Option<Self> {
+        let feeds = std::env::var("THREAT_INTEL_FEEDS")
+            .map(|value| parse_feeds(&value))
+            .unwrap_or_default();
+
+        if feeds.is_empty() {
+            return None;
+        }
+
+        let refresh_minutes = std::env::var("THREAT_INTEL_REFRESH_MINUTES")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|minutes| *minutes > 0)
+            .unwrap_or(DEFAULT_REFRESH_MINUTES);
+
+        let push_alias = std::env::var("THREAT_INTEL_PUSH_ALIAS")
+            .is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "true" | "1"));
+
+        let alias = push_alias.then(|| AliasConfig {
+            name: std::env::var("THREAT_INTEL_ALIAS_NAME")
+                .unwrap_or_else(|_| DEFAULT_ALIAS_NAME.into()),
+            table: std::env::var("THREAT_INTEL_ALIAS_TABLE")
+                .unwrap_or_else(|_| DEFAULT_ALIAS_TABLE.into()),
+        });
+
+        Some(Self {
+            feeds,
+            refresh_interval: Duration::from_secs(refresh_minutes * 60),
+            alias,
+        })
+    }
+}
+
+fn parse_feeds(value: &str) -> Vec<FeedConfig> {
+    let mut retval = Vec::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let Some((name, source)) = entry
+            .split_once('=')
+            .map(|(name, source)| (name.trim(), source.trim()))
+            .filter(|(name, source)| !name.is_empty() && !source.is_empty())
+        else {
+            log::warn!("Ignoring malformed threat intel feed '{entry}'");
+            continue;
+        };
+
+        let source = if source.starts_with("http://") || source.starts_with("https://") {
+            FeedSource::Url(source.to_string())
+        } else {
+            FeedSource::File(source.to_string())
+        };
+
+        retval.push(FeedConfig {
+            name: name.to_string(),
+            source,
+        });
+    }
+
+    retval
+}
@@ -0,0 +1,39 @@
+use crate::threat_intel::config::{FeedConfig, FeedSource};
+use crate::threat_intel::indicators::{Indicator, parse_feed};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::time::Duration;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Downloads or reads a feed, returning the indicators it lists.
+pub async fn load_feed(
+    client: &reqwest::Client,
+    feed: &FeedConfig,
+) -> Result<Vec<Indicator>, Error> {
+    let content = match &feed.source {
+        FeedSource::Url(url) => client
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await
+            .handle_err(location!())?
+            .error_for_status()
+            .handle_err(location!())?
+            .text()
+            .await
+            .handle_err(location!())?,
+        FeedSource::File(path) => tokio::fs::read_to_string(path)
+            .await
+            .handle_err(location!())?,
+    };
+
+    let indicators = tokio::task::spawn_blocking(move || parse_feed(&content))
+        .await
+        .handle_err(location!())?;
+
+    if indicators.is_empty() {
+        return Err(format!("Feed '{}' contains no indicators", feed.name)).handle_err(location!());
+    }
+
+    Ok(indicators)
+}
@@ -0,0 +1,189 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An IP network listed by a feed, as address and prefix length.
+pub type Indicator = (IpAddr, u8);
+
+/// The indicators of all the feeds, indexed by prefix length for longest-prefix matching.
+#[derive(Debug, Default)]
+pub struct IndicatorSet {
+    feeds: Vec<String>,
+    v4: BTreeMap<u8, HashMap<u32, usize>>,
+    v6: BTreeMap<u8, HashMap<u128, usize>>,
+    len: usize,
+}
+
+/// The most specific indicator matching an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndicatorMatch {
+    pub feed: String,
+    pub indicator: String,
+}
+
+impl IndicatorSet {
+    pub fn add_feed(&mut self, name: &str, indicators: &[Indicator]) {
+        let feed = self.feeds.len();
+        self.feeds.push(name.to_string());
+
+        for &(addr, prefix) in indicators {
+            // Networks listed by several feeds are attributed to the first one
+            let vacant = match addr {
+                IpAddr::V4(addr) => {
+                    let networks = self.v4.entry(prefix).or_default();
+                    insert_vacant(networks, mask_v4(addr, prefix), feed)
+                }
+                IpAddr::V6(addr) => {
+                    let networks = self.v6.entry(prefix).or_default();
+                    insert_vacant(networks, mask_v6(addr, prefix), feed)
+                }
+            };
+
+            if vacant {
+                self.len += 1;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<IndicatorMatch> {
+        let (network, prefix, feed) = match ip.to_canonical() {
+            IpAddr::V4(addr) => self.v4.iter().rev().find_map(|(&prefix, networks)| {
+                let network = mask_v4(addr, prefix);
+                let feed = networks.get(&network)?;
+                Some((IpAddr::V4(network.into()), prefix, *feed))
+            })?,
+            IpAddr::V6(addr) => self.v6.iter().rev().find_map(|(&prefix, networks)| {
+                let network = mask_v6(addr, prefix);
+                let feed = networks.get(&network)?;
+                Some((IpAddr::V6(network.into()), prefix, *feed))
+            })?,
+        };
+
+        Some(IndicatorMatch {
+            feed: self.feeds[feed].clone(),
+            indicator: format!("{network}/{prefix}"),
+        })
+    }
+}
+
+/// Extracts the IP/CIDR indicators from a feed.
+///
+/// Rather than supporting each format explicitly, the first token of every line that
+/// parses as an address or network is taken. This covers plain lists (FireHOL netsets,
+/// abuse.ch blocklists), Spamhaus DROP in both its text and JSON flavours, and CSV files
+/// whatever the column holding the address. Comment lines start with `#`, `;` or `//`.
+pub fn parse_feed(content: &str) -> Vec<Indicator> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with(';')
+                && !line.starts_with("//")
+        })
+        .filter_map(|line| {
+            line.split(|c: char| {
+                c.is_whitespace() || matches!(c, ',' | ';' | '"' | '\'' | '{' | '}' | '[' | ']')
+            })
+            .find_map(parse_indicator)
+        })
+        .collect()
+}
+
+fn parse_indicator(token: &str) -> Option<Indicator> {
+    let (addr, prefix) = match token.split_once('/') {
+        Some((addr, prefix)) => (
+            addr.parse::<IpAddr>().ok()?,
+            Some(prefix.parse::<u8>().ok()?),
+        ),
+        None => (token.parse::<IpAddr>().ok()?, None),
+    };
+
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+
+    (prefix <= max_prefix).then_some((addr, prefix))
+}
+
+fn insert_vacant<K: Eq + Hash>(networks: &mut HashMap<K, usize>, network: K, feed: usize) -> bool {
+    match networks.entry(network) {
+        Entry::Vacant(entry) => {
+            entry.insert(feed);
+            true
+        }
+        Entry::Occupied(_) => false,
+    }
+}
+
+fn mask_v4(addr: Ipv4Addr, prefix: u8) -> u32 {
+    let bits = u32::from(addr);
+    bits.checked_shr(32 - u32::from(prefix))
+        .and_then(|bits| bits.checked_shl(32 - u32::from(prefix)))
+        .unwrap_or(0)
+}
+
+fn mask_v6(addr: Ipv6Addr, prefix: u8) -> u128 {
+    let bits = u128::from(addr);
+    bits.checked_shr(128 - u32::from(prefix))
+        .and_then(|bits| bits.checked_shl(128 - u32::from(prefix)))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_feed_formats_and_match() {
+        let firehol = "# FireHOL level1\n1.10.16.0/20\n5.188.10.0/23\n";
+        let drop = "; Spamhaus DROP List\n5.188.10.0/23 ; SBL402741\n{\"cidr\":\"2001:db8::/32\",\"sblid\":\"SBL1\"}\n";
+        let feodo =
+            "first_seen_utc,dst_ip,dst_port\n\"2025-01-01 00:00:00\",\"203.0.113.7\",\"447\"\n";
+
+        assert_eq!(parse_feed(firehol).len(), 2);
+        assert_eq!(parse_feed(drop).len(), 2);
+        assert_eq!(
+            parse_feed(feodo),
+            vec![("203.0.113.7".parse().unwrap(), 32)]
+        );
+
+        let mut set = IndicatorSet::default();
+        set.add_feed("firehol", &parse_feed(firehol));
+        set.add_feed("drop", &parse_feed(drop));
+        set.add_feed("feodo", &parse_feed(feodo));
+
+        assert_eq!(set.len(), 4);
+        assert_eq!(
+            set.lookup("1.10.20.1".parse().unwrap()),
+            Some(IndicatorMatch {
+                feed: "firehol".into(),
+                indicator: "1.10.16.0/20".into()
+            })
+        );
+        assert_eq!(
+            set.lookup("5.188.11.255".parse().unwrap()).unwrap().feed,
+            "firehol"
+        );
+        assert_eq!(
+            set.lookup("2001:db8::1".parse().unwrap())
+                .unwrap()
+                .indicator,
+            "2001:db8::/32"
+        );
+        assert_eq!(
+            set.lookup("203.0.113.7".parse().unwrap()).unwrap().feed,
+            "feodo"
+        );
+        assert_eq!(set.lookup("203.0.113.8".parse().unwrap()), None);
+    }
+}
@@ -0,0 +1,353 @@
+mod config;
+mod feed;
+mod indicators;
+
+use crate::app_context::AppContext;
+use crate::datastore::SecurityEventModel;
+use config::{AliasConfig, ThreatIntelConfig};
+use feed::load_feed;
+use indicators::{Indicator, IndicatorMatch, IndicatorSet};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use nullnet_libipinfo::get_ip_to_lookup;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use wallguard_common::protobuf::wallguard_models::Alias;
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+const REPORT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+const MAX_REPORTED: usize = 50_000;
+const EVENT_CATEGORY: &str = "threat_intel";
+const EVENT_SEVERITY: i32 = 1;
+const ALIAS_PUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Matches the ingested flows against IP/CIDR threat intelligence feeds.
+///
+/// Every remote address found in a feed is recorded as a security event of the device
+/// (at most once per `REPORT_COOLDOWN`), and optionally pushed to the firewall as an alias.
+/// Alias pushes are batched every `ALIAS_PUSH_INTERVAL`, each one reloading the firewall.
+#[derive(Debug, Clone, Default)]
+pub struct ThreatIntel {
+    config: Option<Arc<ThreatIntelConfig>>,
+    indicators: Arc<RwLock<IndicatorSet>>,
+    reported: Arc<Mutex<HashMap<(String, IpAddr), Instant>>>,
+    pending_aliases: Arc<Mutex<HashMap<String, BTreeSet<IpAddr>>>>,
+}
+
+#[derive(Debug)]
+struct ThreatMatch {
+    ip: IpAddr,
+    indicator: IndicatorMatch,
+    connection: Connection,
+}
+
+impl ThreatIntel {
+    pub fn new() -> Self {
+        Self {
+            config: ThreatIntelConfig::from_env().map(Arc::new),
+            ..Default::default()
+        }
+    }
+
+    pub fn inspect(&self, context: &AppContext, device_id: &str, connections: &[Connection]) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        let matches = self.find_matches(device_id, connections);
+        if matches.is_empty() {
+            return;
+        }
+
+        if config.alias.is_some() {
+            self.queue_alias_addresses(device_id, matches.iter().map(|m| m.ip));
+        }
+
+        tokio::spawn(record_matches(
+            context.clone(),
+            device_id.to_string(),
+            matches,
+        ));
+    }
+
+    fn queue_alias_addresses(&self, device_id: &str, addresses: impl IntoIterator<Item = IpAddr>) {
+        if let Ok(mut pending) = self.pending_aliases.lock() {
+            pending
+                .entry(device_id.to_string())
+                .or_default()
+                .extend(addresses);
+        }
+    }
+
+    fn take_pending_aliases(&self) -> HashMap<String, BTreeSet<IpAddr>> {
+        self.pending_aliases
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    fn find_matches(&self, device_id: &str, connections: &[Connection]) -> Vec<ThreatMatch> {
+        let mut matches = Vec::new();
+
+        let Ok(indicators) = self.indicators.read() else {
+            return matches;
+        };
+
+        if indicators.is_empty() {
+            return matches;
+        }
+
+        let Ok(mut reported) = self.reported.lock() else {
+            return matches;
+        };
+
+        let now = Instant::now();
+
+        for connection in connections {
+            for ip in [&connection.source_ip, &connection.destination_ip] {
+                let Ok(ip) = ip.parse::<IpAddr>() else {
+                    continue;
+                };
+
+                // Some feeds (e.g. FireHOL level 1) also list the private ranges
+                if get_ip_to_lookup(ip, ip).is_none() {
+                    continue;
+                }
+
+                let Some(indicator) = indicators.lookup(ip) else {
+                    continue;
+                };
+
+                let key = (device_id.to_string(), ip);
+                if reported
+                    .get(&key)
+                    .is_some_and(|at| now.duration_since(*at) < REPORT_COOLDOWN)
+                {
+                    continue;
+                }
+                reported.insert(key, now);
+
+                matches.push(ThreatMatch {
+                    ip,
+                    indicator,
+                    connection: connection.clone(),
+                });
+            }
+        }
+
+        if reported.len() > MAX_REPORTED {
+            reported.retain(|_, at| now.duration_since(*at) < REPORT_COOLDOWN);
+        }
+
+        matches
+    }
+
+    fn replace_indicators(&self, indicators: IndicatorSet) {
+        if let Ok(mut current) = self.indicators.write() {
+            *current = indicators;
+        }
+    }
+}
+
+/// Periodically reloads the threat intelligence feeds.
+///
+/// Feeds that fail to load keep their previous indicators until the next refresh.
+pub async fn run_threat_intel(context: AppContext) {
+    let Some(config) = context.threat_intel.config.clone() else {
+        log::info!("Threat intelligence is disabled");
+        return std::future::pending().await;
+    };
+
+    if let Some(alias) = config.alias.clone() {
+        tokio::spawn(push_aliases(context.clone(), alias));
+    }
+
+    let client = reqwest::Client::new();
+    let mut loaded: HashMap<String, Vec<Indicator>> = HashMap::new();
+    let mut ticker = tokio::time::interval(config.refresh_interval);
+
+    loop {
+        ticker.tick().await;
+
+        for feed in &config.feeds {
+            match load_feed(&client, feed).await {
+                Ok(indicators) => {
+                    log::info!(
+                        "Loaded {} indicators from threat intel feed '{}'",
+                        indicators.len(),
+                        feed.name
+                    );
+                    loaded.insert(feed.name.clone(), indicators);
+                }
+                Err(err) => log::error!(
+                    "Failed to load threat intel feed '{}': {}",
+                    feed.name,
+                    err.to_str()
+                ),
+            }
+        }
+
+        let mut indicators = IndicatorSet::default();
+        for feed in &config.feeds {
+            if let Some(feed_indicators) = loaded.get(&feed.name) {
+                indicators.add_feed(&feed.name, feed_indicators);
+            }
+        }
+
+        log::info!("Threat intel holds {} indicators", indicators.len());
+        context.threat_intel.replace_indicators(indicators);
+    }
+}
+
+async fn record_matches(context: AppContext, device_id: String, matches: Vec<ThreatMatch>) {
+    log::warn!(
+        "{} flows of device {device_id} matched threat intel feeds",
+        matches.len()
+    );
+
+    let events: Vec<SecurityEventModel> = matches
+        .iter()
+        .map(|m| SecurityEventModel {
+            device_id: device_id.clone(),
+            category: EVENT_CATEGORY.to_string(),
+            title: format!("Traffic with listed address {}", m.ip),
+            description: format!(
+                "{} matched {} from threat intel feed '{}'",
+                m.ip, m.indicator.indicator, m.indicator.feed
+            ),
+            severity: EVENT_SEVERITY,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            metadata: json!({
+                "ip": m.ip.to_string(),
+                "feed": m.indicator.feed,
+                "indicator": m.indicator.indicator,
+                "connection": m.connection,
+            }),
+        })
+        .collect();
+
+    let result = match context.sysdev_token_provider.get().await {
+        Ok(token) => {
+            context
+                .datastore
+                .create_security_events(&token.jwt, &events)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        log::error!(
+            "Failed to record threat intel events for device {device_id}: {}",
+            err.to_str()
+        );
+    }
+}
+
+/// Periodically pushes the addresses matched since the last push to the firewall aliases.
+///
+/// Addresses that could not be pushed, e.g. because the device is offline, are kept
+/// for the next round.
+async fn push_aliases(context: AppContext, config: AliasConfig) {
+    let mut ticker = tokio::time::interval(ALIAS_PUSH_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        for (device_id, addresses) in context.threat_intel.take_pending_aliases() {
+            match push_alias(&context, &device_id, &config, &addresses).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::debug!("Device {device_id} is offline, threat intel alias not pushed");
+                    context
+                        .threat_intel
+                        .queue_alias_addresses(&device_id, addresses);
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to push threat intel alias to device {device_id}: {}",
+                        err.to_str()
+                    );
+                    context
+                        .threat_intel
+                        .queue_alias_addresses(&device_id, addresses);
+                }
+            }
+        }
+    }
+}
+
+/// Adds the matched addresses to the firewall alias through the `CreateAlias` command,
+/// which merges them into the existing alias. Returns whether the device was online.
+async fn push_alias(
+    context: &AppContext,
+    device_id: &str,
+    config: &AliasConfig,
+    addresses: &BTreeSet<IpAddr>,
+) -> Result<bool, Error> {
+    let Some(client) = context
+        .orchestractor
+        .get_any_client_instance(device_id)
+        .await
+    else {
+        return Ok(false);
+    };
+
+    let token = context.root_token_provider.get().await?;
+
+    let device = context
+        .datastore
+        .obtain_device_by_id(&token.jwt, device_id, true)
+        .await?
+        .ok_or("Device not found")
+        .handle_err(location!())?;
+
+    for alias in build_aliases(&device.r#type, config, addresses) {
+        client.lock().await.create_alias(alias).await?;
+    }
+
+    Ok(true)
+}
+
+fn build_aliases(platform: &str, config: &AliasConfig, addresses: &BTreeSet<IpAddr>) -> Vec<Alias> {
+    let join = |filter: fn(&IpAddr) -> bool| {
+        addresses
+            .iter()
+            .filter(|ip| filter(ip))
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let description = String::from("Addresses matched by WallGuard threat intel feeds");
+
+    // nftables sets hold a single address family
+    if platform == "nftables" {
+        [
+            ("ip", config.name.clone(), join(IpAddr::is_ipv4)),
+            ("ip6", format!("{}_v6", config.name), join(IpAddr::is_ipv6)),
+        ]
+        .into_iter()
+        .filter(|(_, _, value)| !value.is_empty())
+        .map(|(r#type, name, value)| Alias {
+            r#type: r#type.to_string(),
+            name,
+            value,
+            description: description.clone(),
+            table: config.table.clone(),
+            family: String::from("inet"),
+        })
+        .collect()
+    } else {
+        vec![Alias {
+            r#type: String::from("host"),
+            name: config.name.clone(),
+            value: join(|_| true),
+            description,
+            table: String::new(),
+            family: String::new(),
+        }]
+    }
+}
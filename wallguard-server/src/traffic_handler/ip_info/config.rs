@@ -0,0 +1,99 @@
+use crate::utilities::data_dir::data_dir;
+use std::path::PathBuf;
+
+const DEFAULT_CACHE_FILE: &str = "ip_info_cache.json";
+const DEFAULT_API_RATE_LIMIT: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProviderKind {
+    /// Local City/ASN MMDB files (`MaxMind` GeoLite2 or DB-IP lite).
+    Mmdb,
+    /// The online ipapi.co API.
+    Api,
+}
+
+pub(crate) struct IpInfoConfig {
+    pub(crate) providers: Vec<ProviderKind>,
+    pub(crate) city_db: Option<PathBuf>,
+    pub(crate) asn_db: Option<PathBuf>,
+    pub(crate) cache_file: Option<PathBuf>,
+    pub(crate) api_rate_limit: u32,
+}
+
+impl IpInfoConfig {
+    /// Reads the IP information lookup configuration from the environment.
+    ///
+    /// Providers are queried in the order given by `IP_INFO_PROVIDERS` (default `mmdb,api`),
+    /// until one of them returns a non-empty result.
+    pub(crate) fn from_env() -> Self {
+        let city_db = path_from_env("GEOIP_CITY_DB");
+        let asn_db = path_from_env("GEOIP_ASN_DB");
+
+        let providers = std::env::var("IP_INFO_PROVIDERS")
+            .map(|value| parse_providers(&value))
+            .unwrap_or_else(|_| vec![ProviderKind::Mmdb, ProviderKind::Api]);
+
+        let cache_file = match std::env::var("IP_INFO_CACHE_FILE") {
+            Ok(value) if value.trim().is_empty() => None,
+            Ok(value) => Some(PathBuf::from(value.trim())),
+            Err(_) => Some(data_dir().join(DEFAULT_CACHE_FILE)),
+        };
+
+        let api_rate_limit = match std::env::var("IP_INFO_API_RATE_LIMIT") {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                log::warn!(
+                    "Invalid IP_INFO_API_RATE_LIMIT '{value}', using {DEFAULT_API_RATE_LIMIT}"
+                );
+                DEFAULT_API_RATE_LIMIT
+            }),
+            Err(_) => DEFAULT_API_RATE_LIMIT,
+        };
+
+        if std::env::var("IP_INFO_PROVIDERS").is_ok()
+            && providers.contains(&ProviderKind::Mmdb)
+            && city_db.is_none()
+            && asn_db.is_none()
+        {
+            log::warn!(
+                "MMDB IP info provider enabled, but neither GEOIP_CITY_DB nor GEOIP_ASN_DB is set"
+            );
+        }
+
+        Self {
+            providers,
+            city_db,
+            asn_db,
+            cache_file,
+            api_rate_limit,
+        }
+    }
+}
+
+fn path_from_env(name: &str) -> Option<PathBuf> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn parse_providers(value: &str) -> Vec<ProviderKind> {
+    let mut retval = Vec::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let provider = match entry.to_ascii_lowercase().as_str() {
+            "mmdb" => ProviderKind::Mmdb,
+            "api" => ProviderKind::Api,
+            _ => {
+                log::warn!("Ignoring unknown IP info provider '{entry}'");
+                continue;
+            }
+        };
+
+        if !retval.contains(&provider) {
+            retval.push(provider);
+        }
+    }
+
+    retval
+}
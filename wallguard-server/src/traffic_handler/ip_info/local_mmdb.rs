@@ -0,0 +1,153 @@
+use maxminddb::Reader;
+use maxminddb::geoip2::{Asn, City};
+use nullnet_libipinfo::IpInfo;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Offline IP information provider backed by local City and ASN MMDB files.
+///
+/// Both `MaxMind` GeoLite2 and DB-IP lite databases are supported, since they share
+/// the same record layout. The files are watched and reloaded whenever they change,
+/// so that they can be updated (e.g. by `geoipupdate`) without restarting the server.
+pub(crate) struct LocalMmdb {
+    city: Option<Arc<MmdbFile>>,
+    asn: Option<Arc<MmdbFile>>,
+}
+
+impl LocalMmdb {
+    pub(crate) fn new(city_db: Option<PathBuf>, asn_db: Option<PathBuf>) -> Self {
+        let city = city_db.map(MmdbFile::open);
+        let asn = asn_db.map(MmdbFile::open);
+
+        let watched: Vec<Arc<MmdbFile>> = city.iter().chain(asn.iter()).cloned().collect();
+        if !watched.is_empty() {
+            std::thread::spawn(move || {
+                loop {
+                    std::thread::sleep(RELOAD_CHECK_INTERVAL);
+                    for file in &watched {
+                        file.reload_if_changed();
+                    }
+                }
+            });
+        }
+
+        Self { city, asn }
+    }
+
+    pub(crate) fn lookup(&self, ip: IpAddr) -> IpInfo {
+        let mut ip_info = IpInfo::default();
+
+        if let Some(file) = &self.city {
+            file.with_reader(|reader| {
+                if let Some(city) = decode::<City>(reader, ip) {
+                    fill_city(&mut ip_info, &city);
+                }
+            });
+        }
+
+        if let Some(file) = &self.asn {
+            file.with_reader(|reader| {
+                if let Some(asn) = decode::<Asn>(reader, ip) {
+                    ip_info.asn = asn.autonomous_system_number.map(|n| n.to_string());
+                    ip_info.org = asn.autonomous_system_organization.map(ToString::to_string);
+                }
+            });
+        }
+
+        ip_info
+    }
+}
+
+struct MmdbFile {
+    path: PathBuf,
+    state: RwLock<MmdbState>,
+}
+
+#[derive(Default)]
+struct MmdbState {
+    reader: Option<Reader<Vec<u8>>>,
+    modified: Option<SystemTime>,
+}
+
+impl MmdbFile {
+    fn open(path: PathBuf) -> Arc<Self> {
+        let file = Arc::new(Self {
+            path,
+            state: RwLock::default(),
+        });
+        file.reload_if_changed();
+        file
+    }
+
+    fn reload_if_changed(&self) {
+        let modified = match std::fs::metadata(&self.path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(err) => {
+                log::warn!("Cannot access MMDB file {}: {err}", self.path.display());
+                return;
+            }
+        };
+
+        let unchanged = self
+            .state
+            .read()
+            .is_ok_and(|state| state.modified == Some(modified));
+        if unchanged {
+            return;
+        }
+
+        // Load outside of the lock, lookups keep using the previous database meanwhile
+        match load(&self.path) {
+            Ok(reader) => {
+                log::info!("Loaded MMDB file {}", self.path.display());
+                if let Ok(mut state) = self.state.write() {
+                    state.reader = Some(reader);
+                    state.modified = Some(modified);
+                }
+            }
+            Err(err) => {
+                log::error!("Failed to load MMDB file {}: {err}", self.path.display());
+                // Don't retry the same broken file on every check
+                if let Ok(mut state) = self.state.write() {
+                    state.modified = Some(modified);
+                }
+            }
+        }
+    }
+
+    fn with_reader(&self, f: impl FnOnce(&Reader<Vec<u8>>)) {
+        if let Ok(state) = self.state.read()
+            && let Some(reader) = &state.reader
+        {
+            f(reader);
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Reader<Vec<u8>>, maxminddb::MaxMindDbError> {
+    let reader = Reader::open_readfile(path)?;
+    // A file caught in the middle of being replaced would only fail at lookup time otherwise
+    reader.lookup(IpAddr::from([8, 8, 8, 8]))?;
+    Ok(reader)
+}
+
+fn decode<'a, T: serde::Deserialize<'a>>(reader: &'a Reader<Vec<u8>>, ip: IpAddr) -> Option<T> {
+    reader.lookup(ip).ok()?.decode().ok()?
+}
+
+fn fill_city(ip_info: &mut IpInfo, city: &City) {
+    ip_info.country = city.country.iso_code.map(ToString::to_string);
+    ip_info.continent_code = city.continent.code.map(ToString::to_string);
+    ip_info.city = city.city.names.english.map(ToString::to_string);
+    ip_info.region = city
+        .subdivisions
+        .first()
+        .and_then(|subdivision| subdivision.names.english)
+        .map(ToString::to_string);
+    ip_info.postal = city.postal.code.map(ToString::to_string);
+    ip_info.timezone = city.location.time_zone.map(ToString::to_string);
+}
@@ -0,0 +1,242 @@
+use nullnet_libipinfo::IpInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ENTRY_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const MAX_ENTRIES: usize = 100_000;
+
+/// Lookup results cache, persisted to disk so that a restart
+/// doesn't cause every address to be looked up again.
+pub(crate) struct LookupCache {
+    path: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    entries: HashMap<IpAddr, CachedIpInfo>,
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedIpInfo {
+    looked_up_at: u64,
+    country: Option<String>,
+    asn: Option<String>,
+    org: Option<String>,
+    continent_code: Option<String>,
+    city: Option<String>,
+    region: Option<String>,
+    postal: Option<String>,
+    timezone: Option<String>,
+}
+
+impl LookupCache {
+    pub(crate) fn new(path: Option<PathBuf>) -> Self {
+        let entries = path.as_deref().map(load).unwrap_or_default();
+
+        if let Some(path) = &path {
+            log::info!(
+                "Loaded {} IP info cache entries from {}",
+                entries.len(),
+                path.display()
+            );
+        }
+
+        Self {
+            path,
+            state: Mutex::new(CacheState {
+                entries,
+                dirty: false,
+            }),
+        }
+    }
+
+    pub(crate) fn get(&self, ip: IpAddr) -> Option<IpInfo> {
+        let state = self.state.lock().ok()?;
+        let entry = state.entries.get(&ip)?;
+
+        if is_expired(entry, now_secs()) {
+            return None;
+        }
+
+        Some(entry.clone().into())
+    }
+
+    pub(crate) fn insert(&self, ip: IpAddr, ip_info: &IpInfo) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let now = now_secs();
+        state.entries.insert(ip, CachedIpInfo::new(ip_info, now));
+        state.dirty = true;
+
+        if state.entries.len() > MAX_ENTRIES {
+            evict(&mut state.entries, now);
+        }
+    }
+
+    /// Writes the cache to disk if it has pending changes.
+    ///
+    /// The entries are only copied under the lock, lookups aren't held up by the write.
+    pub(crate) fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let entries = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+
+            if !state.dirty {
+                return;
+            }
+
+            state.dirty = false;
+            state.entries.clone()
+        };
+
+        if let Err(err) = save(path, &entries) {
+            log::error!("Failed to save IP info cache to {}: {err}", path.display());
+
+            if let Ok(mut state) = self.state.lock() {
+                state.dirty = true;
+            }
+        }
+    }
+}
+
+fn save(path: &Path, entries: &HashMap<IpAddr, CachedIpInfo>) -> std::io::Result<()> {
+    let data = serde_json::to_vec(entries).map_err(std::io::Error::other)?;
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Write to a temporary file first, a crash mid-write must not wipe the cache
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, data)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+fn load(path: &Path) -> HashMap<IpAddr, CachedIpInfo> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+        Err(err) => {
+            log::error!("Failed to read IP info cache {}: {err}", path.display());
+            return HashMap::new();
+        }
+    };
+
+    let mut entries: HashMap<IpAddr, CachedIpInfo> = match serde_json::from_slice(&data) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::error!(
+                "Discarding corrupted IP info cache {}: {err}",
+                path.display()
+            );
+            return HashMap::new();
+        }
+    };
+
+    let now = now_secs();
+    entries.retain(|_, entry| !is_expired(entry, now));
+    entries
+}
+
+/// Drops the expired entries and, if still too many, the oldest ones.
+fn evict(entries: &mut HashMap<IpAddr, CachedIpInfo>, now: u64) {
+    entries.retain(|_, entry| !is_expired(entry, now));
+
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    if excess == 0 {
+        return;
+    }
+
+    let mut by_age: Vec<(u64, IpAddr)> = entries
+        .iter()
+        .map(|(ip, entry)| (entry.looked_up_at, *ip))
+        .collect();
+    by_age.sort_unstable();
+
+    for (_, ip) in by_age.into_iter().take(excess) {
+        entries.remove(&ip);
+    }
+}
+
+fn is_expired(entry: &CachedIpInfo, now: u64) -> bool {
+    now.saturating_sub(entry.looked_up_at) > ENTRY_TTL.as_secs()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl CachedIpInfo {
+    fn new(ip_info: &IpInfo, looked_up_at: u64) -> Self {
+        Self {
+            looked_up_at,
+            country: ip_info.country.clone(),
+            asn: ip_info.asn.clone(),
+            org: ip_info.org.clone(),
+            continent_code: ip_info.continent_code.clone(),
+            city: ip_info.city.clone(),
+            region: ip_info.region.clone(),
+            postal: ip_info.postal.clone(),
+            timezone: ip_info.timezone.clone(),
+        }
+    }
+}
+
+impl From<CachedIpInfo> for IpInfo {
+    fn from(value: CachedIpInfo) -> Self {
+        Self {
+            country: value.country,
+            asn: value.asn,
+            org: value.org,
+            continent_code: value.continent_code,
+            city: value.city,
+            region: value.region,
+            postal: value.postal,
+            timezone: value.timezone,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_survives_restart() {
+        let path = std::env::temp_dir().join(format!("ip_info_cache_{}.json", std::process::id()));
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+        let ip_info = IpInfo {
+            country: Some("US".to_string()),
+            asn: Some("15169".to_string()),
+            org: Some("Google LLC".to_string()),
+            ..IpInfo::default()
+        };
+
+        let cache = LookupCache::new(Some(path.clone()));
+        cache.insert(ip, &ip_info);
+        cache.flush();
+
+        let cache = LookupCache::new(Some(path.clone()));
+        assert_eq!(cache.get(ip), Some(ip_info));
+        assert_eq!(cache.get("1.1.1.1".parse().unwrap()), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
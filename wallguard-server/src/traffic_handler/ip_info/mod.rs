@@ -0,0 +1,258 @@
+mod config;
+mod local_mmdb;
+mod lookup_cache;
+mod rate_limiter;
+
+use crate::app_context::AppContext;
+use config::{IpInfoConfig, ProviderKind};
+use indexmap::IndexSet;
+use local_mmdb::LocalMmdb;
+use lookup_cache::LookupCache;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use nullnet_libipinfo::{ApiFields, IpInfo, IpInfoHandler, IpInfoProvider};
+use rate_limiter::RateLimiter;
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+const CACHE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+// @TODO: Configure through ENV
+const IP_INFO_CACHE_SIZE: usize = 10_000;
+
+/// Starts the IP info handler shared by every source of connections.
+///
+/// Send it the IP to look up for each connection, as given by `get_ip_to_lookup`.
+pub fn spawn_ip_info_handler(context: AppContext) -> Sender<Option<IpAddr>> {
+    let (tx, rx) = mpsc::channel();
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        ip_info_handler(&rx, IP_INFO_CACHE_SIZE, &handle, context);
+    });
+
+    tx
+}
+
+fn ip_info_handler(
+    rx: &Receiver<Option<IpAddr>>,
+    cache_size: usize,
+    rt_handle: &Handle,
+    context: AppContext,
+) {
+    let ip_cache = Arc::new(Mutex::new(IpCache::new(cache_size)));
+    for ip in rx.iter().flatten() {
+        // The IP is marked right away, so that it's looked up once while the request is pending
+        let Ok(is_cached) = ip_cache.lock().map(|mut cache| cache.refresh(ip)) else {
+            log::error!("IP info cache lock poisoned, stopping the IP info handler");
+            return;
+        };
+        if !is_cached {
+            let context = context.clone();
+            let ip_cache = ip_cache.clone();
+            rt_handle.spawn(async move {
+                match get_and_store_ip_info(ip, &context).await {
+                    Ok(outcome) => context.metrics.count_ip_info_lookup(outcome),
+                    Err(e) => {
+                        context.metrics.count_ip_info_lookup("failed");
+                        log::error!("Failed to get/store IP info for {ip}: {e:?}");
+                        // Forget the IP, so that the lookup is retried the next time it's seen
+                        if let Ok(mut cache) = ip_cache.lock() {
+                            cache.remove(ip);
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Returns whether the information was already `known` or has just been `resolved`.
+async fn get_and_store_ip_info(ip: IpAddr, context: &AppContext) -> Result<&'static str, Error> {
+    let ip_str = ip.to_string();
+
+    let token = context.sysdev_token_provider.get().await?;
+
+    let is_stored = context
+        .datastore
+        .is_ip_info_stored(&ip_str, &token.jwt)
+        .await?;
+
+    if is_stored {
+        return Ok("known");
+    }
+
+    let ip_info = RESOLVER.lookup(ip).await?;
+    context
+        .datastore
+        .create_ip_info(&token.jwt, &ip_info, &ip_str)
+        .await?;
+
+    Ok("resolved")
+}
+
+struct IpCache {
+    cache: IndexSet<IpAddr>,
+    size: usize,
+}
+
+impl IpCache {
+    fn new(size: usize) -> Self {
+        Self {
+            cache: IndexSet::new(),
+            size,
+        }
+    }
+
+    /// Moves the IP to the front of the cache, returning whether it was already there.
+    fn refresh(&mut self, ip: IpAddr) -> bool {
+        let is_cached = self.cache.contains(&ip);
+        self.cache.shift_insert(0, ip);
+        while self.cache.len() > self.size {
+            self.cache.pop();
+        }
+        is_cached
+    }
+
+    fn remove(&mut self, ip: IpAddr) {
+        self.cache.shift_remove(&ip);
+    }
+}
+
+/// Looks up IP information through the configured provider chain.
+struct IpInfoResolver {
+    providers: Vec<ProviderKind>,
+    mmdb: Option<LocalMmdb>,
+    cache: LookupCache,
+    api_limiter: RateLimiter,
+}
+
+impl IpInfoResolver {
+    fn from_env() -> Self {
+        let config = IpInfoConfig::from_env();
+
+        log::info!("IP info providers: {:?}", config.providers);
+
+        let mmdb = (config.providers.contains(&ProviderKind::Mmdb)
+            && (config.city_db.is_some() || config.asn_db.is_some()))
+        .then(|| LocalMmdb::new(config.city_db, config.asn_db));
+
+        Self {
+            providers: config.providers,
+            mmdb,
+            cache: LookupCache::new(config.cache_file),
+            api_limiter: RateLimiter::new(config.api_rate_limit),
+        }
+    }
+
+    /// Returns the first non-empty result of the provider chain.
+    ///
+    /// Fails instead of returning an empty result if the API was skipped because of the
+    /// rate limit: the handler then forgets the address, which is looked up again the
+    /// next time it's seen.
+    async fn lookup(&self, ip: IpAddr) -> Result<IpInfo, Error> {
+        if let Some(ip_info) = self.cache.get(ip) {
+            log::debug!("IP information for {ip} found in cache");
+            return Ok(ip_info);
+        }
+
+        let mut throttled = false;
+
+        for provider in &self.providers {
+            let ip_info = match provider {
+                ProviderKind::Mmdb => match &self.mmdb {
+                    Some(mmdb) => mmdb.lookup(ip),
+                    None => continue,
+                },
+                ProviderKind::Api => {
+                    if !self.api_limiter.try_acquire() {
+                        throttled = true;
+                        continue;
+                    }
+                    log::info!("Looking up IP information for {ip}");
+                    API_HANDLER.lookup(&ip.to_string()).await?
+                }
+            };
+
+            if !is_empty(&ip_info) {
+                log::info!("Looked up IP information for {ip} ({provider:?}): {ip_info:?}");
+                self.cache.insert(ip, &ip_info);
+                return Ok(ip_info);
+            }
+        }
+
+        if throttled {
+            return Err("IP info API rate limit reached").handle_err(location!());
+        }
+
+        log::warn!("No IP information found for {ip}");
+        Ok(IpInfo::default())
+    }
+}
+
+fn is_empty(ip_info: &IpInfo) -> bool {
+    ip_info.country.is_none() && ip_info.asn.is_none() && ip_info.org.is_none()
+}
+
+static RESOLVER: LazyLock<IpInfoResolver> = LazyLock::new(|| {
+    let resolver = IpInfoResolver::from_env();
+
+    std::thread::spawn(|| {
+        loop {
+            std::thread::sleep(CACHE_FLUSH_INTERVAL);
+            RESOLVER.cache.flush();
+        }
+    });
+
+    resolver
+});
+
+static API_HANDLER: LazyLock<IpInfoHandler> = LazyLock::new(|| {
+    #[cfg(not(debug_assertions))]
+    let url = "https://ipapi.co/{ip}/json/?key={api_key}";
+    #[cfg(debug_assertions)]
+    let url = "https://ipapi.co/{ip}/json";
+
+    let api_key = std::env::var("IP_INFO_API_KEY").unwrap_or_else(|_| {
+        log::warn!("IP_INFO_API_KEY environment variable not set");
+        String::new()
+    });
+
+    IpInfoHandler::new(vec![IpInfoProvider::new_api_provider(
+        url,
+        &api_key,
+        ApiFields {
+            country: Some("/country"),
+            asn: Some("/asn"),
+            org: Some("/org"),
+            continent_code: Some("/continent_code"),
+            city: Some("/city"),
+            region: Some("/region"),
+            postal: Some("/postal"),
+            timezone: Some("/timezone"),
+        },
+    )])
+    .unwrap()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_cache_forgets_failed_lookups() {
+        let mut cache = IpCache::new(2);
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+
+        assert!(!cache.refresh(ip));
+        assert!(cache.refresh(ip));
+
+        cache.remove(ip);
+        assert!(!cache.refresh(ip));
+
+        cache.refresh("1.1.1.1".parse().unwrap());
+        cache.refresh("9.9.9.9".parse().unwrap());
+        assert!(!cache.refresh(ip));
+    }
+}
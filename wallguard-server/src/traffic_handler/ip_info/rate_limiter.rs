@@ -0,0 +1,76 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Token bucket limiting the requests sent to the online IP info API.
+pub(crate) struct RateLimiter {
+    per_minute: u32,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `per_minute` requests per minute; 0 means unlimited.
+    pub(crate) fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            state: Mutex::new(Bucket {
+                tokens: f64::from(per_minute),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token if one is available, without waiting.
+    pub(crate) fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        if self.per_minute == 0 {
+            return true;
+        }
+
+        let Ok(mut bucket) = self.state.lock() else {
+            return false;
+        };
+
+        let capacity = f64::from(self.per_minute);
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * capacity / 60.0).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_limiter_refills_over_time() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+
+        for _ in 0..60 {
+            assert!(limiter.try_acquire_at(start));
+        }
+        assert!(!limiter.try_acquire_at(start));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(500)));
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(1)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(1)));
+
+        let unlimited = RateLimiter::new(0);
+        assert!((0..1000).all(|_| unlimited.try_acquire()));
+    }
+}
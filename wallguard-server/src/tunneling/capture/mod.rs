@@ -0,0 +1,147 @@
+use nullnet_liberror::Error;
+use serde::Deserialize;
+
+use crate::{
+    app_context::AppContext,
+    datastore::TunnelStatus,
+    reverse_tunnel::TunnelInstance,
+    tunneling::tunnel_common::{TunnelCommonData, TunnelCreateError},
+};
+use std::sync::Arc;
+
+mod session;
+
+pub use session::CaptureSubscription;
+
+const DEFAULT_DURATION_SECONDS: u32 = 60;
+const MAX_DURATION_SECONDS: u32 = 3600;
+const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Extra time the agent is given to flush and close the stream once the
+/// requested duration has elapsed, before the session is forcefully terminated.
+pub const CAPTURE_GRACE_PERIOD: u64 = 15;
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct CaptureParameters {
+    #[serde(default)]
+    pub interface: String,
+    #[serde(default)]
+    pub bpf_filter: String,
+    #[serde(default)]
+    pub duration_seconds: Option<u32>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Also capture traffic not addressed to the host, off unless asked for
+    #[serde(default)]
+    pub promiscuous: bool,
+}
+
+impl CaptureParameters {
+    fn normalized(self) -> Self {
+        let duration_seconds = self
+            .duration_seconds
+            .unwrap_or(DEFAULT_DURATION_SECONDS)
+            .clamp(1, MAX_DURATION_SECONDS);
+
+        let max_bytes = self
+            .max_bytes
+            .unwrap_or(DEFAULT_MAX_BYTES)
+            .clamp(1, MAX_BYTES);
+
+        Self {
+            interface: self.interface,
+            bpf_filter: self.bpf_filter,
+            duration_seconds: Some(duration_seconds),
+            max_bytes: Some(max_bytes),
+            promiscuous: self.promiscuous,
+        }
+    }
+
+    pub fn duration_seconds(&self) -> u32 {
+        self.duration_seconds.unwrap_or(DEFAULT_DURATION_SECONDS)
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes.unwrap_or(DEFAULT_MAX_BYTES)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureTunnel {
+    pub data: TunnelCommonData,
+    pub params: CaptureParameters,
+    context: Arc<AppContext>,
+    session: Arc<session::Session>,
+}
+
+impl CaptureTunnel {
+    pub async fn new(
+        context: Arc<AppContext>,
+        data: TunnelCommonData,
+        params: CaptureParameters,
+    ) -> Result<Self, TunnelCreateError> {
+        let params = params.normalized();
+
+        let tunnel_instance =
+            Self::request_tunnel_stream(&context, &data.tunnel_data.device_id, &params)
+                .await
+                .map_err(|_| TunnelCreateError::CantEstablishATunnel)?;
+
+        let session = session::Session::new(
+            context.clone(),
+            tunnel_instance,
+            data.tunnel_data.id.clone(),
+            params.max_bytes(),
+        )
+        .map_err(|_| TunnelCreateError::CantEstablishATunnel)?;
+
+        Ok(Self {
+            data,
+            params,
+            context,
+            session: Arc::new(session),
+        })
+    }
+
+    async fn request_tunnel_stream(
+        context: &AppContext,
+        device_id: &str,
+        params: &CaptureParameters,
+    ) -> Result<TunnelInstance, Error> {
+        use super::command::establish_tunneled_capture;
+        establish_tunneled_capture(context, device_id, params).await
+    }
+
+    pub async fn subscribe(&self) -> Result<CaptureSubscription, Error> {
+        self.session.subscribe().await
+    }
+
+    pub async fn terminate(&self) -> Result<(), Error> {
+        self.session.signal().await;
+        let token = self.context.sysdev_token_provider.get().await?;
+
+        self.context
+            .datastore
+            .update_tunnel_status(
+                &token.jwt,
+                &self.data.tunnel_data.id,
+                TunnelStatus::Terminated,
+                false,
+            )
+            .await
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.session.is_finished()
+    }
+
+    pub fn has_active_downloads(&self) -> bool {
+        self.session.has_active_downloads()
+    }
+
+    /// Maximum number of seconds the capture itself is allowed to run.
+    pub fn max_lifetime(&self) -> u64 {
+        u64::from(self.params.duration_seconds()) + CAPTURE_GRACE_PERIOD
+    }
+}
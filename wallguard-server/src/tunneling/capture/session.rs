@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use crate::app_context::AppContext;
+use crate::datastore::TunnelStatus;
+use crate::reverse_tunnel::TunnelInstance;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use prost::bytes::Bytes;
+use tempfile::NamedTempFile;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, watch};
+
+/// Largest chunk handed to a subscriber at once.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How far the capture has been written to the spool file.
+#[derive(Debug, Clone, Copy, Default)]
+struct Progress {
+    written: u64,
+    finished: bool,
+}
+
+/// A reader of the capture from its first byte.
+///
+/// The capture is spooled to a temporary file, every subscriber reads it
+/// at its own offset and waits for more data until the capture is finished.
+pub struct CaptureSubscription {
+    file: File,
+    offset: u64,
+    progress: watch::Receiver<Progress>,
+    _guard: Arc<()>,
+}
+
+impl CaptureSubscription {
+    /// The next chunk of the capture, `None` once it has been read entirely.
+    pub async fn next_chunk(&mut self) -> Option<Result<Bytes, Error>> {
+        loop {
+            let progress = *self.progress.borrow_and_update();
+
+            if self.offset < progress.written {
+                let len = (progress.written - self.offset).min(READ_CHUNK_SIZE as u64) as usize;
+                let mut chunk = vec![0u8; len];
+
+                if let Err(err) = self
+                    .file
+                    .read_exact(&mut chunk)
+                    .await
+                    .handle_err(location!())
+                {
+                    return Some(Err(err));
+                }
+
+                self.offset += len as u64;
+                return Some(Ok(Bytes::from(chunk)));
+            }
+
+            if progress.finished {
+                return None;
+            }
+
+            // The writer only goes away once it marked the capture as finished
+            if self.progress.changed().await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Session {
+    /// Removed from the disk when the session is dropped
+    spool: Arc<NamedTempFile>,
+    progress: watch::Receiver<Progress>,
+    downloads: Arc<()>,
+    signal: broadcast::Sender<()>,
+}
+
+impl Session {
+    pub fn new(
+        context: Arc<AppContext>,
+        tunnel: TunnelInstance,
+        tunnel_id: String,
+        max_bytes: u64,
+    ) -> Result<Self, Error> {
+        let spool = Arc::new(NamedTempFile::new().handle_err(location!())?);
+        let writer = spool.reopen().handle_err(location!())?;
+
+        let (progress_sender, progress) = watch::channel(Progress::default());
+        let (terminate, _) = broadcast::channel(2);
+
+        tokio::spawn(capture_relay_impl(
+            context,
+            tunnel,
+            tunnel_id,
+            File::from_std(writer),
+            progress_sender,
+            max_bytes,
+            terminate.subscribe(),
+        ));
+
+        Ok(Self {
+            spool,
+            progress,
+            downloads: Arc::new(()),
+            signal: terminate,
+        })
+    }
+
+    pub async fn subscribe(&self) -> Result<CaptureSubscription, Error> {
+        let file = File::open(self.spool.path())
+            .await
+            .handle_err(location!())?;
+
+        Ok(CaptureSubscription {
+            file,
+            offset: 0,
+            progress: self.progress.clone(),
+            _guard: self.downloads.clone(),
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress.borrow().finished
+    }
+
+    pub async fn signal(&self) {
+        let _ = self.signal.send(());
+    }
+
+    pub fn has_active_downloads(&self) -> bool {
+        Arc::strong_count(&self.downloads) > 1
+    }
+}
+
+async fn capture_relay_impl(
+    context: Arc<AppContext>,
+    tunnel: TunnelInstance,
+    tunnel_id: String,
+    spool: File,
+    progress: watch::Sender<Progress>,
+    max_bytes: u64,
+    mut terminate: broadcast::Receiver<()>,
+) {
+    let completed = tokio::select! {
+        _ = capture_relay(tunnel, spool, &progress, max_bytes) => {
+            log::debug!("Capture session {tunnel_id}: stream finished");
+            true
+        }
+        _ = terminate.recv() => {
+            log::debug!("Capture session {tunnel_id}: TERM signal received");
+            false
+        }
+    };
+
+    progress.send_modify(|progress| progress.finished = true);
+
+    if !completed {
+        return;
+    }
+
+    // The capture is done but remains downloadable until the tunnel expires
+    if let Ok(token) = context.sysdev_token_provider.get().await {
+        let _ = context
+            .datastore
+            .update_tunnel_status(&token.jwt, &tunnel_id, TunnelStatus::Idle, false)
+            .await;
+    }
+}
+
+async fn capture_relay(
+    mut tunnel: TunnelInstance,
+    mut spool: File,
+    progress: &watch::Sender<Progress>,
+    max_bytes: u64,
+) {
+    let mut chunk = [0u8; 16 * 1024];
+    let mut total: u64 = 0;
+
+    loop {
+        match tunnel.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                total += n as u64;
+
+                if total > max_bytes {
+                    log::warn!("Capture exceeded the {max_bytes} bytes limit, closing the stream");
+                    break;
+                }
+
+                // Subscribers are only told about data that reached the file
+                let written = match spool.write_all(&chunk[..n]).await {
+                    Ok(()) => spool.flush().await,
+                    Err(err) => Err(err),
+                };
+
+                if let Err(err) = written {
+                    log::error!("Cannot spool the capture: {err}");
+                    break;
+                }
+
+                progress.send_modify(|progress| progress.written = total);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn subscribe(
+        spool: &NamedTempFile,
+        progress: &watch::Sender<Progress>,
+    ) -> CaptureSubscription {
+        CaptureSubscription {
+            file: File::open(spool.path()).await.unwrap(),
+            offset: 0,
+            progress: progress.subscribe(),
+            _guard: Arc::new(()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_follow_the_spool() {
+        let spool = NamedTempFile::new().unwrap();
+        let mut writer = File::from_std(spool.reopen().unwrap());
+        let (progress, _) = watch::channel(Progress::default());
+
+        let mut early = subscribe(&spool, &progress).await;
+
+        writer.write_all(b"abc").await.unwrap();
+        writer.flush().await.unwrap();
+        progress.send_modify(|progress| progress.written = 3);
+        assert_eq!(early.next_chunk().await.unwrap().unwrap(), &b"abc"[..]);
+
+        writer.write_all(b"de").await.unwrap();
+        writer.flush().await.unwrap();
+        progress.send_modify(|progress| {
+            progress.written = 5;
+            progress.finished = true;
+        });
+        assert_eq!(early.next_chunk().await.unwrap().unwrap(), &b"de"[..]);
+        assert!(early.next_chunk().await.is_none());
+
+        // A late subscriber still gets the capture from its first byte
+        let mut late = subscribe(&spool, &progress).await;
+        assert_eq!(late.next_chunk().await.unwrap().unwrap(), &b"abcde"[..]);
+        assert!(late.next_chunk().await.is_none());
+    }
+}
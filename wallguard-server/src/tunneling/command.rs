@@ -1,4 +1,8 @@
-use crate::{app_context::AppContext, reverse_tunnel::TunnelInstance};
+use crate::{
+    app_context::AppContext,
+    reverse_tunnel::TunnelInstance,
+    tunneling::{capture::CaptureParameters, rd::RdParameters},
+};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use std::time::Duration;
 
@@ -8,9 +12,10 @@ const DEFAULT_TIMEOUT: Duration = Duration::from_millis(5_000);
 enum TunnelType {
     Ssh((String, String)),
     Tty,
-    RemoteDesktop,
+    RemoteDesktop(RdParameters),
     // Local Addr, Local Port, Protocol
     UI((String, u32, String)),
+    Capture(CaptureParameters),
 }
 
 pub async fn establish_tunneled_ssh(
@@ -60,6 +65,7 @@ pub async fn establish_tunneled_tty(
 pub async fn establish_tunneled_rd(
     context: &AppContext,
     device_id: &str,
+    params: RdParameters,
 ) -> Result<TunnelInstance, Error> {
     let instance_id = context
         .orchestractor
@@ -72,7 +78,13 @@ pub async fn establish_tunneled_rd(
         .instance_id
         .clone();
 
-    establish_tunneled_channel(context, device_id, &instance_id, TunnelType::RemoteDesktop).await
+    establish_tunneled_channel(
+        context,
+        device_id,
+        &instance_id,
+        TunnelType::RemoteDesktop(params),
+    )
+    .await
 }
 
 pub async fn establish_tunneled_ui(
@@ -102,6 +114,31 @@ pub async fn establish_tunneled_ui(
     .await
 }
 
+pub async fn establish_tunneled_capture(
+    context: &AppContext,
+    device_id: &str,
+    params: &CaptureParameters,
+) -> Result<TunnelInstance, Error> {
+    let instance_id = context
+        .orchestractor
+        .get_any_client_instance(device_id)
+        .await
+        .ok_or("device not found")
+        .handle_err(location!())?
+        .lock()
+        .await
+        .instance_id
+        .clone();
+
+    establish_tunneled_channel(
+        context,
+        device_id,
+        &instance_id,
+        TunnelType::Capture(params.clone()),
+    )
+    .await
+}
+
 async fn establish_tunneled_channel(
     context: &AppContext,
     device_id: &str,
@@ -126,12 +163,33 @@ async fn establish_tunneled_channel(
                 .await?
         }
         TunnelType::Tty => client.request_tty_session(token.clone()).await?,
-        TunnelType::RemoteDesktop => client.request_remote_desktop_session(token.clone()).await?,
+        TunnelType::RemoteDesktop(params) => {
+            client
+                .request_remote_desktop_session(
+                    token.clone(),
+                    params.clipboard_to_host,
+                    params.clipboard_to_viewer,
+                    params.role.into(),
+                )
+                .await?
+        }
         TunnelType::UI((addr, port, protocol)) => {
             client
                 .request_ui_session(token.clone(), addr, port, protocol)
                 .await?
         }
+        TunnelType::Capture(params) => {
+            client
+                .request_capture_session(
+                    token.clone(),
+                    params.interface.clone(),
+                    params.bpf_filter.clone(),
+                    params.duration_seconds(),
+                    params.max_bytes(),
+                    params.promiscuous,
+                )
+                .await?
+        }
     };
 
     tokio::select! {
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::app_context::AppContext;
 use crate::datastore::TunnelStatus;
@@ -10,22 +11,34 @@ use nullnet_liberror::Error;
 pub struct HttpTunnel {
     pub data: TunnelCommonData,
     context: Arc<AppContext>,
+    /// Set once the agent has accepted a first stream
+    opened: Arc<AtomicBool>,
 }
 
 impl HttpTunnel {
     pub fn new(context: Arc<AppContext>, data: TunnelCommonData) -> Self {
-        Self { context, data }
+        Self {
+            context,
+            data,
+            opened: Default::default(),
+        }
     }
 
     pub async fn request_stream(&self) -> Result<TunnelInstance, Error> {
-        super::command::establish_tunneled_ui(
+        let stream = super::command::establish_tunneled_ui(
             &self.context,
             &self.data.tunnel_data.device_id,
             &self.data.service_data.protocol,
             &self.data.service_data.address,
             self.data.service_data.port as u32,
         )
-        .await
+        .await?;
+
+        if !self.opened.swap(true, Ordering::Relaxed) {
+            self.context.alert_engine.emit(self.data.opened_event());
+        }
+
+        Ok(stream)
     }
 
     pub async fn terminate(&self) -> Result<(), Error> {
@@ -1,8 +1,9 @@
 use crate::{
     app_context::AppContext,
     tunneling::{
+        capture::{CaptureParameters, CaptureTunnel},
         http::HttpTunnel,
-        rd::RdTunnel,
+        rd::{RdParameters, RdTunnel},
         ssh::SshTunnel,
         timeout_controller::TimeoutController,
         tty::TtyTunnel,
@@ -12,6 +13,7 @@ use crate::{
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::Mutex;
 
+pub mod capture;
 mod command;
 pub mod http;
 pub mod rd;
@@ -37,13 +39,17 @@ impl TunnelsManager {
         jwt: &str,
         device_id: &str,
         service_id: &str,
+        capture: Option<CaptureParameters>,
+        remote_desktop: Option<RdParameters>,
         context: Arc<AppContext>,
     ) -> Result<String, TunnelCreateError> {
         let data = TunnelCommonData::create(context.clone(), jwt, device_id, service_id).await?;
 
         let tunnel_id = data.tunnel_data.id.clone();
+        let opened = data.opened_event();
 
-        let tunnel = match Self::request_inner(data, context.clone()).await {
+        let tunnel = match Self::request_inner(data, capture, remote_desktop, context.clone()).await
+        {
             Ok(tunnel) => tunnel,
             Err(err) => {
                 let _ = context.datastore.delete_tunnel(jwt, &tunnel_id).await;
@@ -51,6 +57,11 @@ impl TunnelsManager {
             }
         };
 
+        // HTTP tunnels only reach the agent with their first stream
+        if !matches!(tunnel, WallguardTunnel::Http(_)) {
+            context.alert_engine.emit(opened);
+        }
+
         self.tunnels.lock().await.insert(tunnel_id.clone(), tunnel);
 
         Ok(tunnel_id)
@@ -58,6 +69,8 @@ impl TunnelsManager {
 
     async fn request_inner(
         data: TunnelCommonData,
+        capture: Option<CaptureParameters>,
+        remote_desktop: Option<RdParameters>,
         context: Arc<AppContext>,
     ) -> Result<WallguardTunnel, TunnelCreateError> {
         use crate::datastore::TunnelType;
@@ -76,9 +89,15 @@ impl TunnelsManager {
                 Ok(WallguardTunnel::Tty(Arc::new(Mutex::new(tunnel))))
             }
             TunnelType::RemoteDesktop => {
-                let tunnel = RdTunnel::new(context, data).await?;
+                let params = remote_desktop.unwrap_or_default();
+                let tunnel = RdTunnel::new(context, data, params).await?;
                 Ok(WallguardTunnel::Rd(Arc::new(Mutex::new(tunnel))))
             }
+            TunnelType::Capture => {
+                let params = capture.unwrap_or_default();
+                let tunnel = CaptureTunnel::new(context, data, params).await?;
+                Ok(WallguardTunnel::Capture(Arc::new(Mutex::new(tunnel))))
+            }
         }
     }
 
@@ -86,6 +105,14 @@ impl TunnelsManager {
         self.tunnels.lock().await.get(tunnel_id).cloned()
     }
 
+    pub async fn count_by_type(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::new();
+        for tunnel in self.tunnels.lock().await.values() {
+            *counts.entry(tunnel.type_name()).or_default() += 1;
+        }
+        counts
+    }
+
     pub async fn on_tunnel_terminated(&self, tunnel_id: &str) {
         if let Some(tunnel) = self.tunnels.lock().await.remove(tunnel_id) {
             let _ = tunnel.terminate().await;
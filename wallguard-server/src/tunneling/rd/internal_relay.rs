@@ -1,9 +1,15 @@
 use std::sync::Arc;
 
-use super::session::{ChannelReader, ChannelWriter, UserDataReceiver, UserDataSender};
+use super::session::{
+    ChannelReader, ChannelWriter, RelayedMessage, UserDataReceiver, UserDataSender,
+};
 use crate::app_context::AppContext;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::broadcast;
+use crate::rd_recording::Recorder;
+use prost::Message;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, broadcast, watch};
+use wallguard_common::protobuf::wallguard_rd::{AgentMessage, Hello, ViewerMessage, agent_message};
+use wallguard_common::rd_protocol::{self, MIN_PROTOCOL_VERSION};
 
 pub(crate) struct InternalRelay {
     context: Arc<AppContext>,
@@ -14,6 +20,8 @@ pub(crate) struct InternalRelay {
 
     data_sender: UserDataSender,
     data_receiver: UserDataReceiver,
+    agent_hello: watch::Sender<Option<Hello>>,
+    recorder: Option<Arc<Mutex<Recorder>>>,
 
     terminate: broadcast::Receiver<()>,
 }
@@ -28,6 +36,8 @@ impl InternalRelay {
         data_receiver: UserDataReceiver,
         terminate: broadcast::Receiver<()>,
     ) -> Self {
+        let (agent_hello, _) = watch::channel(None);
+
         Self {
             context,
             tunnel_id,
@@ -35,60 +45,81 @@ impl InternalRelay {
             channel_writer,
             data_sender,
             data_receiver,
+            agent_hello,
+            recorder: None,
             terminate,
         }
     }
 
+    /// Records the relayed session.
+    pub fn with_recorder(mut self, recorder: Option<Recorder>) -> Self {
+        self.recorder = recorder.map(|recorder| Arc::new(Mutex::new(recorder)));
+        self
+    }
+
+    /// The `Hello` of the agent, set once it has been received.
+    pub fn agent_hello(&self) -> watch::Receiver<Option<Hello>> {
+        self.agent_hello.subscribe()
+    }
+
     pub fn spawn(self) {
-        tokio::spawn(internal_relay_impl(
-            self.context,
-            self.tunnel_id,
-            self.channel_reader,
-            self.channel_writer,
-            self.data_sender,
-            self.data_receiver,
-            self.terminate,
-        ));
+        tokio::spawn(internal_relay_impl(self));
     }
 }
 
-async fn internal_relay_impl(
-    context: Arc<AppContext>,
-    tunnel_id: String,
-    channel_reader: ChannelReader,
-    channel_writer: ChannelWriter,
-    data_sender: UserDataSender,
-    data_receiver: UserDataReceiver,
-    mut terminate: broadcast::Receiver<()>,
-) {
+async fn internal_relay_impl(relay: InternalRelay) {
+    let InternalRelay {
+        context,
+        tunnel_id,
+        channel_reader,
+        channel_writer,
+        data_sender,
+        data_receiver,
+        agent_hello,
+        recorder,
+        mut terminate,
+    } = relay;
+
     tokio::select! {
-        _ = from_users_to_channel(data_receiver, channel_writer) => {
+        _ = from_users_to_channel(data_receiver, channel_writer, recorder.clone()) => {
             log::debug!("RD Internal Relay: input relay finished");
         }
-        _ = from_channel_to_users(channel_reader, data_sender) => {
-            log::debug!("RD Internal Relay: frame relay finished");
+        _ = from_channel_to_users(channel_reader, data_sender, agent_hello, recorder.clone()) => {
+            log::debug!("RD Internal Relay: agent relay finished");
         }
         _ = terminate.recv() => {
             log::debug!("RD Internal Relay: TERM signal received");
         }
     }
 
+    // The relay halves are dropped by now, leaving the only reference
+    if let Some(recorder) = recorder.and_then(Arc::into_inner) {
+        recorder.into_inner().finish().await;
+    }
+
     let _ = context
         .tunnels_manager
         .on_tunnel_terminated(&tunnel_id)
         .await;
 }
 
-/// Reads input events from viewers and writes them to the agent tunnel.
+/// Reads messages from viewers and writes them to the agent tunnel.
 ///
-/// Each message is framed as `[u32 LE length][payload bytes]` so the agent
-/// can reconstruct complete JSON events regardless of how TCP segments the
-/// stream.
+/// Each message is an encoded `ViewerMessage`, prefixed with its length as
+/// u32 LE so the agent can reconstruct complete messages regardless of how
+/// TCP segments the stream.
 async fn from_users_to_channel(
     mut data_receiver: UserDataReceiver,
     mut channel_writer: ChannelWriter,
+    recorder: Option<Arc<Mutex<Recorder>>>,
 ) {
     while let Some(message) = data_receiver.recv().await {
+        if let Some(recorder) = &recorder
+            && let Ok(decoded) = ViewerMessage::decode(message.as_slice())
+        {
+            recorder.lock().await.on_viewer_message(&decoded);
+        }
+
         let len = (message.len() as u32).to_le_bytes();
         if channel_writer.write_all(&len).await.is_err() {
             break;
@@ -99,38 +130,57 @@ async fn from_users_to_channel(
     }
 }
 
-/// Reads H264 frames from the agent tunnel and broadcasts each complete frame
-/// to all connected viewers.
-///
-/// The agent wraps every frame in a `TimestampedPacket`:
-///   bytes  0–15  : duration as u128 LE (16 bytes)
-///   bytes 16–19  : payload length as u32 LE (4 bytes)
-///   bytes 20..   : H264 payload
+/// Reads messages from the agent tunnel and broadcasts them to all connected viewers.
 ///
-/// We reassemble the full packet before broadcasting so each item in the
-/// channel — and therefore each binary WebSocket message sent to viewers —
-/// is exactly one decodable frame.
-async fn from_channel_to_users(mut channel_reader: ChannelReader, data_sender: UserDataSender) {
-    const HEADER_LEN: usize = 20; // 16-byte duration + 4-byte length field
-
+/// The agent opens with its `Hello`, which is kept for the handshake with
+/// each viewer; every other message is relayed as is, so each binary
+/// WebSocket message sent to viewers is exactly one `AgentMessage`.
+async fn from_channel_to_users(
+    mut channel_reader: ChannelReader,
+    data_sender: UserDataSender,
+    agent_hello: watch::Sender<Option<Hello>>,
+    recorder: Option<Arc<Mutex<Recorder>>>,
+) {
     loop {
-        let mut header = [0u8; HEADER_LEN];
-        if channel_reader.read_exact(&mut header).await.is_err() {
-            break;
+        let bytes = match rd_protocol::read_framed(&mut channel_reader).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                log::debug!("RD Internal Relay: agent stream ended: {}", err.to_str());
+                break;
+            }
+        };
+
+        let message = match AgentMessage::decode(bytes.as_slice()) {
+            Ok(message) => message,
+            Err(err) => {
+                log::error!("RD Internal Relay: malformed agent message: {err}");
+                break;
+            }
+        };
+
+        if let Some(agent_message::Message::Hello(hello)) = message.message {
+            if hello.protocol_version < MIN_PROTOCOL_VERSION {
+                log::error!(
+                    "RD Internal Relay: unsupported agent protocol version {}",
+                    hello.protocol_version
+                );
+                break;
+            }
+
+            agent_hello.send_replace(Some(hello));
+            continue;
         }
 
-        let data_len = u32::from_le_bytes(header[16..20].try_into().unwrap()) as usize;
-
-        let mut payload = vec![0u8; data_len];
-        if channel_reader.read_exact(&mut payload).await.is_err() {
-            break;
+        if let Some(recorder) = &recorder {
+            recorder.lock().await.on_agent_message(&message);
         }
 
-        let mut packet = Vec::with_capacity(HEADER_LEN + data_len);
-        packet.extend_from_slice(&header);
-        packet.extend_from_slice(&payload);
+        let message = RelayedMessage {
+            feature: rd_protocol::agent_message_feature(&message),
+            bytes: bytes.into(),
+        };
 
-        if data_sender.send(packet).is_err() {
+        if data_sender.send(message).is_err() {
             break;
         }
     }
@@ -1,4 +1,5 @@
 use nullnet_liberror::Error;
+use serde::Deserialize;
 
 use crate::{
     app_context::AppContext,
@@ -10,10 +11,73 @@ use crate::{
     },
 };
 use std::sync::Arc;
+use tokio::sync::watch;
+use wallguard_common::protobuf::wallguard_commands::RemoteDesktopRole;
+use wallguard_common::protobuf::wallguard_rd::Hello;
 
 mod internal_relay;
 pub mod session;
 
+/// Per-session options of a remote desktop tunnel.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RdParameters {
+    /// Whether the viewer may paste into the host clipboard
+    #[serde(default = "enabled")]
+    pub clipboard_to_host: bool,
+    /// Whether the host clipboard is sent to the viewer
+    #[serde(default = "enabled")]
+    pub clipboard_to_viewer: bool,
+    /// What the viewers of this tunnel may do
+    #[serde(default)]
+    pub role: RdRole,
+    /// Whether the session is recorded on the server
+    #[serde(default)]
+    pub record: bool,
+}
+
+impl Default for RdParameters {
+    fn default() -> Self {
+        Self {
+            clipboard_to_host: true,
+            clipboard_to_viewer: true,
+            role: RdRole::default(),
+            record: false,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RdRole {
+    /// May send input while holding control, and request it from other tunnels
+    #[default]
+    Controller,
+    /// Only watches, input is dropped by the agent
+    ViewOnly,
+}
+
+impl RdRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RdRole::Controller => "controller",
+            RdRole::ViewOnly => "view_only",
+        }
+    }
+}
+
+impl From<RdRole> for RemoteDesktopRole {
+    fn from(role: RdRole) -> Self {
+        match role {
+            RdRole::Controller => RemoteDesktopRole::Controller,
+            RdRole::ViewOnly => RemoteDesktopRole::ViewOnly,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RdTunnel {
     pub data: TunnelCommonData,
@@ -25,15 +89,37 @@ impl RdTunnel {
     pub async fn new(
         context: Arc<AppContext>,
         data: TunnelCommonData,
+        params: RdParameters,
     ) -> Result<Self, TunnelCreateError> {
-        let tunnel_instance = Self::request_tunnel_stream(&context, &data.tunnel_data.device_id)
-            .await
-            .map_err(|_| TunnelCreateError::CantEstablishATunnel)?;
+        if params.record && !context.rd_recordings.is_enabled() {
+            return Err(TunnelCreateError::RecordingDisabled);
+        }
+
+        let tunnel_instance =
+            Self::request_tunnel_stream(&context, &data.tunnel_data.device_id, params)
+                .await
+                .map_err(|_| TunnelCreateError::CantEstablishATunnel)?;
+
+        let recorder = if params.record {
+            let recorder = context
+                .rd_recordings
+                .start(
+                    &data.tunnel_data.id,
+                    &data.tunnel_data.device_id,
+                    params.role.as_str(),
+                )
+                .await
+                .map_err(|_| TunnelCreateError::RecordingFailed)?;
+            Some(recorder)
+        } else {
+            None
+        };
 
         let session = session::Session::new(
             context.clone(),
             tunnel_instance,
             data.tunnel_data.id.clone(),
+            recorder,
         )
         .await
         .map_err(|_| TunnelCreateError::CantEstablishATunnel)?;
@@ -48,9 +134,10 @@ impl RdTunnel {
     async fn request_tunnel_stream(
         context: &AppContext,
         device_id: &str,
+        params: RdParameters,
     ) -> Result<TunnelInstance, Error> {
         use super::command::establish_tunneled_rd;
-        establish_tunneled_rd(context, device_id).await
+        establish_tunneled_rd(context, device_id, params).await
     }
 
     pub fn get_data_send_channel(&self) -> SessionDataSender {
@@ -61,6 +148,10 @@ impl RdTunnel {
         self.session.get_data_recv_channel()
     }
 
+    pub fn get_agent_hello(&self) -> watch::Receiver<Option<Hello>> {
+        self.session.get_agent_hello()
+    }
+
     pub async fn terminate(&self) -> Result<(), Error> {
         self.session.signal().await;
         let token = self.context.sysdev_token_provider.get().await?;
@@ -2,17 +2,29 @@ use std::sync::Arc;
 
 use super::internal_relay::InternalRelay;
 use crate::app_context::AppContext;
+use crate::rd_recording::Recorder;
 use crate::reverse_tunnel::TunnelInstance;
 use nullnet_liberror::Error;
+use prost::bytes::Bytes;
 use tokio::io::{ReadHalf, WriteHalf};
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, watch};
+use wallguard_common::protobuf::wallguard_rd::{Feature, Hello};
 
 pub type ChannelReader = ReadHalf<TunnelInstance>;
 pub type ChannelWriter = WriteHalf<TunnelInstance>;
 pub type SessionDataSender = mpsc::Sender<Vec<u8>>;
 pub type UserDataReceiver = mpsc::Receiver<Vec<u8>>;
-pub type UserDataSender = broadcast::Sender<Vec<u8>>;
-pub type SessionDataReceiver = broadcast::Receiver<Vec<u8>>;
+pub type UserDataSender = broadcast::Sender<RelayedMessage>;
+pub type SessionDataReceiver = broadcast::Receiver<RelayedMessage>;
+
+/// A message of the agent, as relayed to the viewers.
+#[derive(Debug, Clone)]
+pub struct RelayedMessage {
+    /// Feature a viewer must have negotiated to receive it
+    pub feature: Option<Feature>,
+    /// The encoded `AgentMessage`
+    pub bytes: Bytes,
+}
 
 #[derive(Debug)]
 pub struct Session {
@@ -29,6 +41,9 @@ pub struct Session {
     /// has had a chance to connect.
     _sentinel: SessionDataReceiver,
 
+    /// Hello sent by the agent when the session opened, to negotiate with viewers.
+    agent_hello: watch::Receiver<Option<Hello>>,
+
     signal: broadcast::Sender<()>,
 }
 
@@ -37,6 +52,7 @@ impl Session {
         context: Arc<AppContext>,
         tunnel: TunnelInstance,
         tunnel_id: String,
+        recorder: Option<Recorder>,
     ) -> Result<Self, Error> {
         let (session_reader, session_writer) = tokio::io::split(tunnel);
 
@@ -45,7 +61,7 @@ impl Session {
 
         let (terminate, _) = broadcast::channel(2);
 
-        InternalRelay::new(
+        let relay = InternalRelay::new(
             context,
             tunnel_id,
             session_reader,
@@ -54,12 +70,16 @@ impl Session {
             from_users_receiver,
             terminate.subscribe(),
         )
-        .spawn();
+        .with_recorder(recorder);
+
+        let agent_hello = relay.agent_hello();
+        relay.spawn();
 
         Ok(Self {
             data_sender: from_users_sender,
             data_source: to_users_sender,
             _sentinel: sentinel,
+            agent_hello,
             signal: terminate,
         })
     }
@@ -78,6 +98,10 @@ impl Session {
         self.data_source.subscribe()
     }
 
+    pub fn get_agent_hello(&self) -> watch::Receiver<Option<Hello>> {
+        self.agent_hello.clone()
+    }
+
     pub async fn signal(&self) {
         let _ = self.signal.send(());
     }
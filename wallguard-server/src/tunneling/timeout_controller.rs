@@ -193,6 +193,50 @@ impl TimeoutController {
                                     || is_lifetime_expired(tun.data.created_at, self.hard_timeout)
                             };
 
+                            if expired {
+                                expired_ids.push(tun.data.tunnel_data.id.clone());
+                            }
+                        }
+                        WallguardTunnel::Capture(capture_tunnel) => {
+                            let tun = capture_tunnel.lock().await;
+
+                            let last_accessed_timestamp =
+                                crate::utilities::time::datetime_to_timestamp(
+                                    &tun.data
+                                        .tunnel_data
+                                        .last_access_date
+                                        .clone()
+                                        .unwrap_or_default(),
+                                    &tun.data
+                                        .tunnel_data
+                                        .last_access_time
+                                        .clone()
+                                        .unwrap_or_default(),
+                                )
+                                .unwrap_or_default()
+                                .cast_unsigned();
+
+                            let timestamp = if last_accessed_timestamp != 0 {
+                                last_accessed_timestamp
+                            } else {
+                                tun.data.created_at
+                            };
+
+                            // A running capture is bounded by its requested duration;
+                            // a finished one stays downloadable until it goes idle.
+                            let expired = if !tun.is_finished() {
+                                is_lifetime_expired(tun.data.created_at, Some(tun.max_lifetime()))
+                                    || is_lifetime_expired(tun.data.created_at, self.hard_timeout)
+                            } else if tun.has_active_downloads() {
+                                is_lifetime_expired(
+                                    tun.data.created_at,
+                                    self.active_terminal_timeout,
+                                ) || is_lifetime_expired(tun.data.created_at, self.hard_timeout)
+                            } else {
+                                is_idle_expired(timestamp, self.idle_timeout)
+                                    || is_lifetime_expired(tun.data.created_at, self.hard_timeout)
+                            };
+
                             if expired {
                                 expired_ids.push(tun.data.tunnel_data.id.clone());
                             }
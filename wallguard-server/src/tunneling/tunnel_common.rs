@@ -1,8 +1,10 @@
 use nullnet_liberror::Error;
 use tokio::sync::Mutex;
 
+use crate::alerting::AlertEvent;
 use crate::app_context::AppContext;
 use crate::datastore::{ServiceInfo, TunnelModel, TunnelStatus, TunnelType};
+use crate::tunneling::capture::CaptureTunnel;
 use crate::tunneling::http::HttpTunnel;
 use crate::tunneling::rd::RdTunnel;
 use crate::tunneling::ssh::SshTunnel;
@@ -21,6 +23,8 @@ pub enum TunnelCreateError {
     DatastoreError,
     SshKeygenError,
     SshSessionFailed,
+    RecordingDisabled,
+    RecordingFailed,
 }
 
 impl Display for TunnelCreateError {
@@ -35,6 +39,8 @@ impl Display for TunnelCreateError {
             Self::CantEstablishATunnel => "failed to establish a tunnel",
             Self::SshKeygenError => "ssh key generation failed",
             Self::SshSessionFailed => "failed to create ssh session",
+            Self::RecordingDisabled => "recording is not enabled on the server",
+            Self::RecordingFailed => "failed to start the recording",
         };
 
         f.write_str(msg)
@@ -53,6 +59,8 @@ impl TunnelCreateError {
             Self::DatastoreError => 500,
             Self::SshKeygenError => 500,
             Self::SshSessionFailed => 500,
+            Self::RecordingDisabled => 400,
+            Self::RecordingFailed => 500,
         }
     }
 }
@@ -132,6 +140,15 @@ impl TunnelCommonData {
                 .as_secs(),
         })
     }
+
+    /// The alert to raise once the agent has accepted the tunnel.
+    pub fn opened_event(&self) -> AlertEvent {
+        AlertEvent::TunnelOpened {
+            device_id: self.tunnel_data.device_id.clone(),
+            tunnel_id: self.tunnel_data.id.clone(),
+            tunnel_type: self.tunnel_data.tunnel_type.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -140,15 +157,29 @@ pub enum WallguardTunnel {
     Ssh(Arc<Mutex<SshTunnel>>),
     Tty(Arc<Mutex<TtyTunnel>>),
     Rd(Arc<Mutex<RdTunnel>>),
+    Capture(Arc<Mutex<CaptureTunnel>>),
 }
 
 impl WallguardTunnel {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            WallguardTunnel::Http(_) => "http",
+            WallguardTunnel::Ssh(_) => "ssh",
+            WallguardTunnel::Tty(_) => "tty",
+            WallguardTunnel::Rd(_) => "rd",
+            WallguardTunnel::Capture(_) => "capture",
+        }
+    }
+
     pub async fn terminate(self) -> Result<(), Error> {
         match self {
             WallguardTunnel::Http(http_tunnel) => http_tunnel.lock().await.terminate().await,
             WallguardTunnel::Ssh(ssh_tunnel) => ssh_tunnel.lock().await.terminate().await,
             WallguardTunnel::Tty(tty_tunnel) => tty_tunnel.lock().await.terminate().await,
             WallguardTunnel::Rd(rd_tunnel) => rd_tunnel.lock().await.terminate().await,
+            WallguardTunnel::Capture(capture_tunnel) => {
+                capture_tunnel.lock().await.terminate().await
+            }
         }
     }
 
@@ -158,6 +189,7 @@ impl WallguardTunnel {
             WallguardTunnel::Ssh(tun) => tun.lock().await.data.service_data.id.clone(),
             WallguardTunnel::Tty(tun) => tun.lock().await.data.service_data.id.clone(),
             WallguardTunnel::Rd(tun) => tun.lock().await.data.service_data.id.clone(),
+            WallguardTunnel::Capture(tun) => tun.lock().await.data.service_data.id.clone(),
         }
     }
 
@@ -167,6 +199,7 @@ impl WallguardTunnel {
             WallguardTunnel::Ssh(tun) => tun.lock().await.data.tunnel_data.id.clone(),
             WallguardTunnel::Tty(tun) => tun.lock().await.data.tunnel_data.id.clone(),
             WallguardTunnel::Rd(tun) => tun.lock().await.data.tunnel_data.id.clone(),
+            WallguardTunnel::Capture(tun) => tun.lock().await.data.tunnel_data.id.clone(),
         }
     }
 }
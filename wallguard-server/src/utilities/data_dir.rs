@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+const DEFAULT_DATA_DIR: &str = "/var/lib/wallguard-server";
+
+/// Directory holding the state the server keeps across restarts, from `DATA_DIR`.
+pub fn data_dir() -> PathBuf {
+    std::env::var("DATA_DIR")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_DATA_DIR), PathBuf::from)
+}
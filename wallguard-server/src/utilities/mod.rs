@@ -1,3 +1,4 @@
+pub mod data_dir;
 pub mod hash;
 pub mod random;
 pub mod ssh;
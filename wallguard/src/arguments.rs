@@ -1,5 +1,6 @@
 use crate::constants::BATCH_SIZE;
 use clap::Parser;
+use std::net::IpAddr;
 
 /// Application configuration from CLI arguments
 #[derive(Parser, Debug, Clone, Default)]
@@ -19,4 +20,12 @@ pub struct Arguments {
     /// Maximum number of packets per batch sent to the server
     #[arg(long, default_value_t = BATCH_SIZE)]
     pub batch_size: usize,
+
+    /// Port to serve the agent metrics on in the OpenMetrics format; disabled if unset
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
+
+    /// Address to serve the agent metrics on, localhost if unset
+    #[arg(long)]
+    pub metrics_host: Option<IpAddr>,
 }
@@ -15,9 +15,19 @@ pub const QUEUE_SIZE_RESOURCES: usize = 10;
 #[cfg(not(debug_assertions))]
 pub const QUEUE_SIZE_RESOURCES: usize = 60;
 
+pub const INGEST_BATCH_SIZE: usize = 1000;
+
+pub const FIREWALL_HEALTH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+pub const LINK_INFO_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
 pub const SNAPLEN: usize = 96;
 
+pub const CAPTURE_SNAPLEN: i32 = 65535;
+pub const CAPTURE_MAX_DURATION_SECONDS: u64 = 3600;
+pub const CAPTURE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
 pub const DUMP_DIR: &str = "dumps";
+pub const DUMP_ZSTD_LEVEL: i32 = 3;
 
 pub static DISK_SIZE: std::sync::LazyLock<u64> = std::sync::LazyLock::new(|| {
     let disks = sysinfo::Disks::new_with_refreshed_list();
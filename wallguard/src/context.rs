@@ -3,6 +3,7 @@ use crate::constants::DISK_SIZE;
 use crate::daemon::Daemon;
 use crate::data_transmission::dump_dir::DumpDir;
 use crate::data_transmission::transmission_manager::TransmissionManager;
+use crate::remote_desktop::RemoteDesktopManager;
 use crate::reverse_tunnel::ReverseTunnel;
 use crate::server_data::ServerData;
 use crate::token_provider::TokenProvider;
@@ -19,6 +20,8 @@ pub struct Context {
     pub daemon: Arc<Mutex<Daemon>>,
     pub client_data: ClientData,
     pub(crate) transmission_manager: Arc<Mutex<TransmissionManager>>,
+    /// Shared by the remote desktop sessions open at the same time.
+    pub(crate) remote_desktop: Arc<Mutex<Option<RemoteDesktopManager>>>,
 }
 
 impl Context {
@@ -55,6 +58,7 @@ impl Context {
             daemon,
             client_data,
             transmission_manager: Arc::new(Mutex::new(transmission_manager)),
+            remote_desktop: Arc::default(),
         })
     }
 }
@@ -0,0 +1,66 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use prost::Message as _;
+use wallguard_common::protobuf::wallguard_commands::FlowExportSettings;
+
+use crate::context::Context;
+use crate::control_channel::command::ExecutableCommand;
+use crate::storage::{Secret, Storage};
+
+pub struct ConfigureFlowExportCommand {
+    context: Context,
+    settings: FlowExportSettings,
+}
+
+impl ConfigureFlowExportCommand {
+    pub fn new(context: Context, settings: FlowExportSettings) -> Self {
+        Self { context, settings }
+    }
+}
+
+impl ExecutableCommand for ConfigureFlowExportCommand {
+    async fn execute(self) -> Result<(), nullnet_liberror::Error> {
+        log::debug!(
+            "Executing ConfigureFlowExportCommand command: enabled={}, collectors={:?}",
+            self.settings.enabled,
+            self.settings.collectors
+        );
+
+        // Persisted so that the exporter is restored after a restart
+        let encoded = STANDARD.encode(self.settings.encode_to_vec());
+        Storage::set_value(Secret::FlowExportSettings, &encoded).await?;
+
+        self.context
+            .transmission_manager
+            .lock()
+            .await
+            .configure_flow_export(&self.settings)
+            .await
+    }
+}
+
+/// Restores the flow export settings last received from the server.
+pub async fn restore_flow_export(context: &Context) {
+    let Some(encoded) = Storage::get_value(Secret::FlowExportSettings).await else {
+        return;
+    };
+
+    let Some(settings) = STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| FlowExportSettings::decode(bytes.as_slice()).ok())
+    else {
+        log::warn!("Stored flow export settings are malformed, ignoring them");
+        return;
+    };
+
+    if let Err(err) = context
+        .transmission_manager
+        .lock()
+        .await
+        .configure_flow_export(&settings)
+        .await
+    {
+        log::error!("Failed to restore flow export: {}", err.to_str());
+    }
+}
@@ -0,0 +1,64 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use prost::Message as _;
+use wallguard_common::protobuf::wallguard_commands::ProcessSnapshotSettings;
+
+use crate::context::Context;
+use crate::control_channel::command::ExecutableCommand;
+use crate::storage::{Secret, Storage};
+
+pub struct ConfigureProcessSnapshotsCommand {
+    context: Context,
+    settings: ProcessSnapshotSettings,
+}
+
+impl ConfigureProcessSnapshotsCommand {
+    pub fn new(context: Context, settings: ProcessSnapshotSettings) -> Self {
+        Self { context, settings }
+    }
+}
+
+impl ExecutableCommand for ConfigureProcessSnapshotsCommand {
+    async fn execute(self) -> Result<(), nullnet_liberror::Error> {
+        log::debug!(
+            "Executing ConfigureProcessSnapshotsCommand command: enabled={}, interval={}s, top_n={}",
+            self.settings.enabled,
+            self.settings.interval_seconds,
+            self.settings.top_n
+        );
+
+        // Persisted so that the snapshots are restored after a restart
+        let encoded = STANDARD.encode(self.settings.encode_to_vec());
+        Storage::set_value(Secret::ProcessSnapshotSettings, &encoded).await?;
+
+        self.context
+            .transmission_manager
+            .lock()
+            .await
+            .configure_process_snapshots(self.settings);
+
+        Ok(())
+    }
+}
+
+/// Restores the process snapshot settings last received from the server.
+pub async fn restore_process_snapshots(context: &Context) {
+    let Some(encoded) = Storage::get_value(Secret::ProcessSnapshotSettings).await else {
+        return;
+    };
+
+    let Some(settings) = STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| ProcessSnapshotSettings::decode(bytes.as_slice()).ok())
+    else {
+        log::warn!("Stored process snapshot settings are malformed, ignoring them");
+        return;
+    };
+
+    context
+        .transmission_manager
+        .lock()
+        .await
+        .configure_process_snapshots(settings);
+}
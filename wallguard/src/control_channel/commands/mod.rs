@@ -1,9 +1,12 @@
+mod configure_flow_export_command;
+mod configure_process_snapshots_command;
 mod create_alias_command;
 mod create_filter_rule_command;
 mod create_nat_rule_command;
 mod enable_configuration_monitoring_command;
 mod enable_network_monitoring_command;
 mod enable_telemtry_monitoring_command;
+mod open_capture_session_command;
 mod open_ssh_session_command;
 mod open_tty_session_command;
 mod open_ui_session_command;
@@ -12,12 +15,15 @@ mod update_token_command;
 mod open_remote_desktop_session_command;
 pub use open_remote_desktop_session_command::*;
 
+pub use configure_flow_export_command::*;
+pub use configure_process_snapshots_command::*;
 pub use create_alias_command::*;
 pub use create_filter_rule_command::*;
 pub use create_nat_rule_command::*;
 pub use enable_configuration_monitoring_command::*;
 pub use enable_network_monitoring_command::*;
 pub use enable_telemtry_monitoring_command::*;
+pub use open_capture_session_command::*;
 pub use open_ssh_session_command::*;
 pub use open_tty_session_command::*;
 pub use open_ui_session_command::*;
@@ -0,0 +1,186 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use wallguard_common::protobuf::wallguard_commands::CaptureSessionData;
+
+use crate::constants::{CAPTURE_MAX_BYTES, CAPTURE_MAX_DURATION_SECONDS, CAPTURE_SNAPLEN};
+use crate::context::Context;
+use crate::control_channel::command::ExecutableCommand;
+use crate::metrics::METRICS;
+use crate::utilities::pcapng;
+
+const READ_TIMEOUT_MS: i32 = 500;
+
+pub struct OpenCaptureSessionCommand {
+    context: Context,
+    data: CaptureSessionData,
+}
+
+impl OpenCaptureSessionCommand {
+    pub fn new(context: Context, data: CaptureSessionData) -> Self {
+        Self { context, data }
+    }
+}
+
+impl ExecutableCommand for OpenCaptureSessionCommand {
+    async fn execute(self) -> Result<(), Error> {
+        log::debug!("Received OpenCaptureSessionCommand");
+
+        if !self.context.client_data.platform.can_monitor_traffic() {
+            return Err("Platform does not support traffic capture").handle_err(location!());
+        }
+
+        let duration = Duration::from_secs(
+            u64::from(self.data.duration_seconds).clamp(1, CAPTURE_MAX_DURATION_SECONDS),
+        );
+
+        let max_bytes = if self.data.max_bytes == 0 {
+            CAPTURE_MAX_BYTES
+        } else {
+            self.data.max_bytes.min(CAPTURE_MAX_BYTES)
+        };
+
+        let filter = capture_filter(&self.data.bpf_filter, self.context.tunnel.addr());
+
+        let Ok(mut tunnel) = self
+            .context
+            .tunnel
+            .request_channel(&self.data.tunnel_token)
+            .await
+        else {
+            return Err("Cant establish tunnel connection").handle_err(location!());
+        };
+
+        let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(256);
+        let interface = self.data.interface;
+        let promiscuous = self.data.promiscuous;
+
+        tokio::task::spawn_blocking(move || {
+            let capture = capture(
+                &interface,
+                &filter,
+                promiscuous,
+                duration,
+                max_bytes,
+                sender,
+            );
+            if let Err(err) = capture {
+                log::error!("Capture session failed: {}", err.to_str());
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(block) = receiver.recv().await {
+                if tunnel.write_all(&block).await.is_err() {
+                    break;
+                }
+            }
+
+            let _ = tunnel.shutdown().await;
+            log::debug!("Capture session finished");
+        });
+
+        Ok(())
+    }
+}
+
+/// Combines the operator supplied expression with a clause excluding the
+/// tunnel connection itself, otherwise the capture would record its own
+/// stream and feed back into it.
+fn capture_filter(user_filter: &str, tunnel_addr: SocketAddr) -> String {
+    let exclusion = format!(
+        "not (host {} and tcp port {})",
+        tunnel_addr.ip(),
+        tunnel_addr.port()
+    );
+
+    let user_filter = user_filter.trim();
+
+    if user_filter.is_empty() {
+        exclusion
+    } else {
+        format!("({user_filter}) and {exclusion}")
+    }
+}
+
+fn capture(
+    interface: &str,
+    filter: &str,
+    promiscuous: bool,
+    duration: Duration,
+    max_bytes: u64,
+    sender: mpsc::Sender<Vec<u8>>,
+) -> Result<(), Error> {
+    let device = if interface.is_empty() {
+        pcap::Device::lookup()
+            .handle_err(location!())?
+            .ok_or("No capture device available")
+            .handle_err(location!())?
+    } else {
+        pcap::Device::from(interface)
+    };
+
+    log::info!(
+        "Starting capture on {} with filter '{filter}' for {}s, up to {max_bytes} bytes",
+        device.name,
+        duration.as_secs()
+    );
+
+    let mut cap = pcap::Capture::from_device(device)
+        .handle_err(location!())?
+        .promisc(promiscuous)
+        .snaplen(CAPTURE_SNAPLEN)
+        .timeout(READ_TIMEOUT_MS)
+        .open()
+        .handle_err(location!())?;
+
+    cap.filter(filter, true).handle_err(location!())?;
+
+    let link_type = cap.get_datalink().0 as u16;
+
+    let mut header = pcapng::section_header_block();
+    header.extend(pcapng::interface_description_block(
+        link_type,
+        CAPTURE_SNAPLEN as u32,
+    ));
+
+    let mut written = header.len() as u64;
+    if written > max_bytes || sender.blocking_send(header).is_err() {
+        return Ok(());
+    }
+
+    let deadline = Instant::now() + duration;
+
+    while Instant::now() < deadline {
+        let packet = match cap.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::TimeoutExpired) => continue,
+            Err(err) => return Err(err).handle_err(location!()),
+        };
+
+        let timestamp = Duration::from_secs(packet.header.ts.tv_sec as u64)
+            + Duration::from_micros(packet.header.ts.tv_usec as u64);
+
+        let block = pcapng::enhanced_packet_block(0, timestamp, packet.data, packet.header.len);
+
+        written += block.len() as u64;
+        if written > max_bytes {
+            log::info!("Capture session reached the {max_bytes} bytes limit");
+            break;
+        }
+
+        if sender.blocking_send(block).is_err() {
+            // Tunnel closed by the server
+            break;
+        }
+    }
+
+    if let Ok(stats) = cap.stats() {
+        METRICS.add_capture_drops(u64::from(stats.dropped) + u64::from(stats.if_dropped));
+    }
+
+    Ok(())
+}
@@ -1,18 +1,21 @@
 use crate::control_channel::command::ExecutableCommand;
-use crate::remote_desktop::RemoteDesktopManager;
+use crate::remote_desktop::{RemoteDesktopManager, SessionOptions};
 use crate::{context::Context, reverse_tunnel::TunnelInstance};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::io::{AsyncWriteExt, ReadHalf, WriteHalf};
 use tokio::sync::mpsc;
+use wallguard_common::protobuf::wallguard_commands::{RemoteDesktopRole, RemoteDesktopSessionData};
+use wallguard_common::protobuf::wallguard_rd::Role;
+use wallguard_common::rd_protocol;
 
 pub struct OpenRemoteDesktopSessionCommand {
     context: Context,
-    token: String,
+    data: RemoteDesktopSessionData,
 }
 
 impl OpenRemoteDesktopSessionCommand {
-    pub fn new(context: Context, token: String) -> Self {
-        Self { context, token }
+    pub fn new(context: Context, data: RemoteDesktopSessionData) -> Self {
+        Self { context, data }
     }
 }
 
@@ -20,23 +23,55 @@ impl ExecutableCommand for OpenRemoteDesktopSessionCommand {
     async fn execute(self) -> Result<(), Error> {
         log::debug!("Received OpenRemoteDesktopSessionCommand");
 
-        // Create a fresh RemoteDesktopManager for this session.  Doing this
-        // on-demand (rather than caching it in Context) means:
+        let role = match self.data.role() {
+            RemoteDesktopRole::Controller => Role::Controller,
+            RemoteDesktopRole::ViewOnly => Role::ViewOnly,
+        };
+
+        let options = SessionOptions {
+            role,
+            clipboard_to_host: !self.data.disable_clipboard_to_host,
+            clipboard_to_viewer: !self.data.disable_clipboard_to_viewer,
+        };
+
+        let Ok(tunnel) = self
+            .context
+            .tunnel
+            .request_channel(&self.data.tunnel_token)
+            .await
+        else {
+            return Err("Cant establish tunnel connection").handle_err(location!());
+        };
+
+        let (sender, receiver) = mpsc::channel(64);
+
+        // Sessions open at the same time share a RemoteDesktopManager, so that
+        // only one of them holds control.  Otherwise a fresh one is created
+        // on-demand (rather than at startup), which means:
         //   • The agent never panics at startup when no display is available.
         //   • The first session after a user logs in just works — no restart
         //     needed.
-        let mut rdm = RemoteDesktopManager::new().inspect_err(|err| {
-            log::warn!("Cannot open remote desktop session: {}", err.to_str());
-        })?;
-
-        let Ok(tunnel) = self.context.tunnel.request_channel(&self.token).await else {
-            return Err("Cant establish tunnel connection").handle_err(location!());
+        // The client is registered before the lock is released, so a session
+        // opened meanwhile sees it and joins the same manager.
+        let (mut rdm, id) = {
+            let mut shared = self.context.remote_desktop.lock().await;
+
+            let mut rdm = match shared.as_ref() {
+                Some(rdm) if rdm.has_clients().await => rdm.clone(),
+                _ => {
+                    let rdm = RemoteDesktopManager::new().inspect_err(|err| {
+                        log::warn!("Cannot open remote desktop session: {}", err.to_str());
+                    })?;
+                    *shared = Some(rdm.clone());
+                    rdm
+                }
+            };
+
+            let id = rdm.on_client_connected(sender, options).await;
+            (rdm, id)
         };
 
         tokio::spawn(async move {
-            let (sender, receiver) = mpsc::channel(64);
-            let id = rdm.on_client_connected(sender).await;
-
             let (reader, writer) = tokio::io::split(tunnel);
 
             tokio::select! {
@@ -54,23 +89,11 @@ impl ExecutableCommand for OpenRemoteDesktopSessionCommand {
 async fn stream_to_system(
     mut reader: ReadHalf<TunnelInstance>,
     remote_desktop_manager: RemoteDesktopManager,
-    client_id: u128,
+    client_id: u32,
 ) -> Result<(), Error> {
-    loop {
-        // The server prefixes every input event with a 4-byte LE length so we
-        // always read exactly one complete JSON message, never a partial one.
-        let mut len_buf = [0u8; 4];
-        if reader.read_exact(&mut len_buf).await.is_err() {
-            break;
-        }
-
-        let len = u32::from_le_bytes(len_buf) as usize;
-
-        let mut message = vec![0u8; len];
-        if reader.read_exact(&mut message).await.is_err() {
-            break;
-        }
-
+    // The server prefixes every viewer message with its length, so we always
+    // read exactly one complete message, never a partial one.
+    while let Ok(message) = rd_protocol::read_framed(&mut reader).await {
         if let Err(err) = remote_desktop_manager
             .on_client_message(client_id, message)
             .await
@@ -0,0 +1,22 @@
+use crate::metrics::{METRICS, Monitoring};
+use crate::{STARTED_AT, VERSION};
+use wallguard_common::protobuf::wallguard_commands::Heartbeat;
+
+/// Collects the health of the agent to be sent along with a heartbeat.
+pub fn heartbeat() -> Heartbeat {
+    let (last_error_timestamp, last_error) = METRICS.last_error().unzip();
+
+    Heartbeat {
+        uptime_seconds: STARTED_AT.elapsed().as_secs(),
+        traffic_monitoring: METRICS.is_monitoring(Monitoring::Traffic),
+        telemetry_monitoring: METRICS.is_monitoring(Monitoring::Telemetry),
+        configuration_monitoring: METRICS.is_monitoring(Monitoring::Configuration),
+        services_monitoring: METRICS.is_monitoring(Monitoring::Services),
+        queued_items: METRICS.queued_items(),
+        spool_bytes: METRICS.spool_bytes(),
+        last_error,
+        last_error_timestamp: last_error_timestamp.unwrap_or_default(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        version: VERSION.to_string(),
+    }
+}
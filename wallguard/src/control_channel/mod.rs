@@ -1,19 +1,21 @@
 use crate::context::Context;
 use crate::control_channel::command::ExecutableCommand;
 use crate::control_channel::commands::{
-    CreateAliasCommand, CreateFilterRuleCommand, CreateNatRuleCommand,
-    EnableConfigurationMonitoringCommand, EnableNetworkMonitoringCommand,
-    EnableTelemetryMonitoringCommand, OpenTtySessionCommand, OpenUiSessionCommand,
-    UpdateTokenCommand,
+    ConfigureFlowExportCommand, ConfigureProcessSnapshotsCommand, CreateAliasCommand,
+    CreateFilterRuleCommand, CreateNatRuleCommand, EnableConfigurationMonitoringCommand,
+    EnableNetworkMonitoringCommand, EnableTelemetryMonitoringCommand, OpenCaptureSessionCommand,
+    OpenTtySessionCommand, OpenUiSessionCommand, UpdateTokenCommand,
 };
 
 use crate::control_channel::commands::OpenRemoteDesktopSessionCommand;
 
 use crate::control_channel::post_startup::post_startup;
 use crate::daemon::Daemon;
+use crate::metrics::METRICS;
 use crate::storage::{Secret, Storage};
 use await_authorization::await_authorization;
 use commands::OpenSshSessionCommand;
+use heartbeat::heartbeat;
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use send_authenticate::send_authenticate;
 use std::sync::Arc;
@@ -27,6 +29,7 @@ use wallguard_common::protobuf::wallguard_commands::{
 mod await_authorization;
 mod command;
 mod commands;
+mod heartbeat;
 mod post_startup;
 mod send_authenticate;
 
@@ -60,6 +63,7 @@ impl ControlChannel {
         manager.terminate_resource_monitoring();
         manager.terminate_sysconfig_monitoring();
         manager.terminate_services_monitoring();
+        manager.terminate_flow_export();
 
         drop(manager);
 
@@ -82,7 +86,9 @@ async fn stream_wrapper(
     tokio::select! {
         _ = terminate.recv() => {}
         result = control_stream(context.clone(), &installation_code) => {
-            if result.is_err() {
+            if let Err(err) = result {
+                METRICS.record_error(format!("Control channel failed: {}", err.to_str()));
+
                 // Reset backoff after a stable connection; otherwise accumulate.
                 let next_attempt = if connected_at.elapsed() >= Duration::from_secs(60) {
                     1
@@ -148,7 +154,7 @@ async fn healthcheck(outbound: OutboundStream) -> Result<(), Error> {
 
     loop {
         let heartbeat = ClientMessage {
-            message: Some(Message::Heartbeat(())),
+            message: Some(Message::Heartbeat(heartbeat())),
         };
 
         log::info!("Sending heartbeat");
@@ -260,6 +266,8 @@ async fn handle_incoming_messages(inbound: InboundStream, context: Context) -> R
                     Message::DeviceDeauthorizedMessage(_) => {
                         _ = Storage::delete_value(Secret::AppId).await;
                         _ = Storage::delete_value(Secret::AppSecret).await;
+                        _ = Storage::delete_value(Secret::FlowExportSettings).await;
+                        _ = Storage::delete_value(Secret::ProcessSnapshotSettings).await;
 
                         let ctx = context.clone();
                         let _ = tokio::spawn(async move {
@@ -268,8 +276,11 @@ async fn handle_incoming_messages(inbound: InboundStream, context: Context) -> R
                         .await;
                     }
 
-                    Message::OpenRemoteDesktopSessionCommand(token) => {
-                        let cmd = OpenRemoteDesktopSessionCommand::new(context.clone(), token);
+                    Message::OpenRemoteDesktopSessionCommand(remote_desktop_session_data) => {
+                        let cmd = OpenRemoteDesktopSessionCommand::new(
+                            context.clone(),
+                            remote_desktop_session_data,
+                        );
 
                         if let Err(err) = cmd.execute().await {
                             log::error!(
@@ -278,6 +289,37 @@ async fn handle_incoming_messages(inbound: InboundStream, context: Context) -> R
                             );
                         }
                     }
+                    Message::OpenCaptureSessionCommand(capture_session_data) => {
+                        let cmd =
+                            OpenCaptureSessionCommand::new(context.clone(), capture_session_data);
+
+                        if let Err(err) = cmd.execute().await {
+                            log::error!(
+                                "OpenCaptureSessionCommand execution failed: {}",
+                                err.to_str()
+                            );
+                        }
+                    }
+                    Message::ConfigureFlowExportCommand(settings) => {
+                        let cmd = ConfigureFlowExportCommand::new(context.clone(), settings);
+
+                        if let Err(err) = cmd.execute().await {
+                            log::error!(
+                                "ConfigureFlowExportCommand execution failed: {}",
+                                err.to_str()
+                            );
+                        }
+                    }
+                    Message::ConfigureProcessSnapshotsCommand(settings) => {
+                        let cmd = ConfigureProcessSnapshotsCommand::new(context.clone(), settings);
+
+                        if let Err(err) = cmd.execute().await {
+                            log::error!(
+                                "ConfigureProcessSnapshotsCommand execution failed: {}",
+                                err.to_str()
+                            );
+                        }
+                    }
                     Message::AuthorizationRejectedMessage(_) => {
                         Err("Unexpected message").handle_err(location!())?
                     }
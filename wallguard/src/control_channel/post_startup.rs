@@ -1,3 +1,4 @@
+use crate::control_channel::commands::{restore_flow_export, restore_process_snapshots};
 use crate::{context::Context, token_provider::RetrievalStrategy};
 use std::time::Duration;
 use wallguard_common::protobuf::wallguard_service::DeviceSettingsRequest;
@@ -63,6 +64,9 @@ pub async fn post_startup(context: Context) {
             .start_packet_capture();
     }
 
+    restore_flow_export(&context).await;
+    restore_process_snapshots(&context).await;
+
     context
         .transmission_manager
         .lock()
@@ -7,6 +7,7 @@ use crate::context::Context;
 use crate::control_channel::ControlChannel;
 use crate::daemon::cli_server::CliServer;
 use crate::daemon::state::DaemonState;
+use crate::metrics::METRICS;
 use crate::server_data::ServerData;
 use crate::storage::{Secret, Storage};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
@@ -199,11 +200,14 @@ impl Daemon {
     }
 
     pub(crate) async fn on_error(this: Arc<Mutex<Daemon>>, reason: impl Into<String>) {
+        let reason = reason.into();
+        METRICS.record_error(reason.clone());
+
         if let DaemonState::Connected(control_channel) = this.lock().await.state.clone() {
             control_channel.terminate().await
         }
 
-        this.lock().await.state = DaemonState::Error(reason.into());
+        this.lock().await.state = DaemonState::Error(reason);
     }
 
     pub(crate) async fn connect(context: Context, attempt: u32) {
@@ -213,6 +217,7 @@ impl Daemon {
 
         let backoff = reconnect_backoff(attempt);
         if !backoff.is_zero() {
+            METRICS.add_reconnect_attempt();
             log::info!(
                 "Reconnecting in {}s (attempt {})",
                 backoff.as_secs(),
@@ -1,81 +1,439 @@
-use crate::constants::DUMP_DIR;
+use crate::constants::{DUMP_DIR, DUMP_ZSTD_LEVEL};
+use crate::metrics::METRICS;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use prost::Message;
+use std::io::Read;
 use std::ops::RangeTo;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
-use wallguard_common::protobuf::wallguard_service::{ConnectionsData, SystemResourcesData};
+use tokio::sync::Mutex;
+use wallguard_common::protobuf::wallguard_service::{
+    Connection, ConnectionsData, SystemResource, SystemResourcesData,
+};
 
+const CONNECTIONS_EXTENSION: &str = "connections";
+const RESOURCES_EXTENSION: &str = "resources";
+const TMP_EXTENSION: &str = "tmp";
+
+/// On-disk spool for the items that could not be delivered to the server.
+///
+/// Each file holds a single batch, stored as zstd-compressed length-delimited
+/// protobuf records. Files are named after their creation time, so that sorting
+/// them by name yields the order in which they have to be retransmitted.
 #[derive(Clone, Debug)]
 pub(crate) struct DumpDir {
-    path: &'static str,
+    path: PathBuf,
     max_size: u64,
+    state: Arc<Mutex<SpoolState>>,
+}
+
+#[derive(Debug, Default)]
+struct SpoolState {
+    size: u64,
+    sequence: u64,
 }
 
 impl DumpDir {
     pub(crate) async fn new(max_size: u64) -> Self {
-        fs::create_dir(DUMP_DIR).await.unwrap_or_default();
-        Self {
-            path: DUMP_DIR,
+        let path = wallguard_common::single_instance::state_dir().join(DUMP_DIR);
+
+        if let Err(err) = fs::create_dir_all(&path).await {
+            log::error!("Failed to create dumps directory {}: {err}", path.display());
+        }
+
+        let dump_dir = Self {
+            path,
             max_size,
+            state: Arc::default(),
+        };
+
+        let size = dump_dir.scan().await;
+        dump_dir.state.lock().await.size = size;
+        METRICS.set_spool_size(size);
+
+        dump_dir.migrate_legacy_dumps().await;
+        log::info!(
+            "Dumps directory {} holds {size} bytes (limit {max_size})",
+            dump_dir.path.display()
+        );
+
+        dump_dir
+    }
+
+    /// Computes the size of the spool, removing the leftovers of interrupted writes.
+    async fn scan(&self) -> u64 {
+        let mut size = 0;
+
+        for file in self.list_files().await {
+            let Ok(meta) = fs::metadata(&file).await else {
+                continue;
+            };
+
+            if file.extension().is_some_and(|ext| ext == TMP_EXTENSION) {
+                log::warn!("Removing partially written dump file {}", file.display());
+                let _ = fs::remove_file(&file).await;
+            } else {
+                size += meta.len();
+            }
+        }
+
+        size
+    }
+
+    /// Moves the JSON dumps that older versions left in the working directory into the spool.
+    async fn migrate_legacy_dumps(&self) {
+        let legacy = Path::new(DUMP_DIR);
+
+        if !fs::try_exists(legacy).await.unwrap_or(false) {
+            return;
         }
+
+        // Nothing to migrate when the agent runs from its state directory
+        if let (Ok(legacy), Ok(current)) = (
+            fs::canonicalize(legacy).await,
+            fs::canonicalize(&self.path).await,
+        ) && legacy == current
+        {
+            return;
+        }
+
+        let mut migrated = 0;
+
+        for file in list_dir(legacy).await {
+            let item = match fs::read(&file).await {
+                Ok(data) => parse_legacy_dump(&file, &data),
+                Err(err) => {
+                    log::warn!("Failed to read legacy dump file {}: {err}", file.display());
+                    None
+                }
+            };
+
+            if let Some((timestamp, kind, item)) = item {
+                let mut state = self.state.lock().await;
+                let file_path = self.path.join(file_name(&mut state, timestamp, kind));
+
+                match self.write_file(&mut state, &file_path, item).await {
+                    Ok(()) => migrated += 1,
+                    Err(err) => log::error!("Failed to migrate dump file: {}", err.to_str()),
+                }
+            } else {
+                log::warn!("Discarding legacy dump file {}", file.display());
+            }
+
+            let _ = fs::remove_file(&file).await;
+        }
+
+        if let Err(err) = fs::remove_dir(legacy).await {
+            log::warn!("Failed to remove legacy dumps directory: {err}");
+        }
+
+        log::info!("Migrated {migrated} legacy dump files");
     }
 
-    pub(crate) async fn get_files_sorted(&self) -> Vec<fs::DirEntry> {
-        let mut dir = fs::read_dir(self.path)
+    async fn list_files(&self) -> Vec<PathBuf> {
+        list_dir(&self.path).await
+    }
+
+    pub(crate) async fn get_files_sorted(&self) -> Vec<PathBuf> {
+        self.list_files()
             .await
-            .expect("Failed to read packets and resources dumps directory");
-        let mut files = Vec::new();
-        while let Ok(Some(file)) = dir.next_entry().await {
-            files.push(file);
+            .into_iter()
+            .filter(|file| DumpKind::from_path(file).is_some())
+            .collect()
+    }
+
+    pub(crate) async fn dump_item_to_file(&self, dump_item: DumpItem) {
+        let Some(kind) = DumpKind::from_item(&dump_item) else {
+            return;
+        };
+
+        let mut state = self.state.lock().await;
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let file_path = self.path.join(file_name(&mut state, timestamp, kind));
+
+        if let Err(err) = self.write_file(&mut state, &file_path, dump_item).await {
+            log::error!("Failed to write dump file: {}", err.to_str());
         }
-        files.sort_by_key(fs::DirEntry::file_name);
-        files
     }
 
-    pub(crate) async fn is_full(&self) -> bool {
-        let mut size = 0;
-        let mut dir = fs::read_dir(self.path)
+    pub(crate) async fn update_items_dump_file(&self, file_path: PathBuf, dump: DumpItem) {
+        let mut state = self.state.lock().await;
+
+        // The file may have been evicted in the meantime, nothing to update then
+        let Ok(meta) = fs::metadata(&file_path).await else {
+            return;
+        };
+
+        // The file is replaced atomically, its current content stops counting right away
+        state.size = state.size.saturating_sub(meta.len());
+
+        if let Err(err) = self.write_file(&mut state, &file_path, dump).await {
+            log::error!("Failed to update dump file: {}", err.to_str());
+            state.size += meta.len();
+        }
+    }
+
+    pub(crate) async fn remove_file(&self, file_path: &Path) {
+        let mut state = self.state.lock().await;
+        Self::remove_locked(&mut state, file_path).await;
+    }
+
+    /// Reads a dump file back.
+    ///
+    /// Truncated or corrupted files are salvaged up to the last record that could be
+    /// decoded; files with nothing to salvage are removed and `None` is returned.
+    pub(crate) async fn read_item(&self, file_path: &Path) -> Option<DumpItem> {
+        let kind = DumpKind::from_path(file_path)?;
+
+        let data = match fs::read(file_path).await {
+            Ok(data) => data,
+            Err(err) => {
+                log::error!("Failed to read dump file {}: {err}", file_path.display());
+                return None;
+            }
+        };
+
+        let (item, complete) = tokio::task::spawn_blocking(move || decode_item(kind, &data))
             .await
-            .expect("Failed to read packets and resources dumps directory");
-        while let Ok(Some(file)) = dir.next_entry().await {
-            if let Ok(meta) = file.metadata().await {
-                size += meta.len();
+            .ok()?;
+
+        if !complete {
+            log::warn!(
+                "Dump file {} is corrupted, salvaged {} items",
+                file_path.display(),
+                item.size()
+            );
+        }
+
+        if item.size() == 0 {
+            self.remove_file(file_path).await;
+            return None;
+        }
+
+        Some(item)
+    }
+
+    async fn write_file(
+        &self,
+        state: &mut SpoolState,
+        file_path: &Path,
+        dump_item: DumpItem,
+    ) -> Result<(), Error> {
+        let data = tokio::task::spawn_blocking(move || encode_item(&dump_item))
+            .await
+            .handle_err(location!())??;
+
+        let len = data.len() as u64;
+        if len > self.max_size {
+            return Err("Dump item exceeds the maximum dumps size").handle_err(location!());
+        }
+
+        self.evict(state, len, file_path).await;
+
+        // Write to a temporary file first: a crash mid-write must not leave a partial dump behind
+        let tmp_path = file_path.with_extension(TMP_EXTENSION);
+        fs::write(&tmp_path, data).await.handle_err(location!())?;
+        fs::rename(&tmp_path, file_path)
+            .await
+            .handle_err(location!())?;
+
+        state.size += len;
+        METRICS.set_spool_size(state.size);
+
+        Ok(())
+    }
+
+    /// Removes the oldest dumps until `incoming` more bytes fit in the spool,
+    /// sparing the file that is about to be written.
+    async fn evict(&self, state: &mut SpoolState, incoming: u64, keep: &Path) {
+        if state.size + incoming <= self.max_size {
+            return;
+        }
+
+        let mut evicted = 0;
+
+        for file in self.get_files_sorted().await {
+            if state.size + incoming <= self.max_size {
+                break;
+            }
+            if file == keep {
+                continue;
             }
+            Self::remove_locked(state, &file).await;
+            evicted += 1;
         }
-        size >= self.max_size
+
+        METRICS.add_spool_evictions(evicted);
+        log::warn!("Dump size maximum limit reached, evicted the {evicted} oldest dump files");
     }
 
-    fn get_file_path(&self, time: &str, dump_item: &DumpItem) -> String {
-        match dump_item {
-            DumpItem::Connections(_) => format!("{}/{time}_connections", self.path),
-            DumpItem::Resources(_) => format!("{}/{time}_resources", self.path),
-            DumpItem::Empty => format!("{}/{time}_empty", self.path),
+    async fn remove_locked(state: &mut SpoolState, file_path: &Path) {
+        let len = fs::metadata(file_path).await.map(|m| m.len()).unwrap_or(0);
+
+        match fs::remove_file(file_path).await {
+            Ok(()) => {
+                state.size = state.size.saturating_sub(len);
+                METRICS.set_spool_size(state.size);
+            }
+            Err(err) => log::error!("Failed to remove dump file {}: {err}", file_path.display()),
         }
     }
+}
 
-    pub(crate) async fn dump_item_to_file(&self, dump_item: DumpItem) {
-        let now = chrono::Utc::now().to_rfc3339();
-        let file_path = self.get_file_path(&now, &dump_item);
-        tokio::fs::write(
-            file_path,
-            serde_json::to_string(&dump_item).expect("Failed to serialize item"),
-        )
-        .await
-        .expect("Failed to write dump file");
-    }
-
-    pub(crate) async fn update_items_dump_file(&self, file_path: PathBuf, mut dump: DumpItem) {
-        dump.set_token(String::new());
-        tokio::fs::write(
-            file_path,
-            serde_json::to_string(&dump).expect("Failed to serialize items"),
-        )
-        .await
-        .expect("Failed to write dump file");
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DumpKind {
+    Connections,
+    Resources,
+}
+
+impl DumpKind {
+    fn from_item(item: &DumpItem) -> Option<Self> {
+        match item {
+            DumpItem::Connections(_) => Some(DumpKind::Connections),
+            DumpItem::Resources(_) => Some(DumpKind::Resources),
+            DumpItem::Empty => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            CONNECTIONS_EXTENSION => Some(DumpKind::Connections),
+            RESOURCES_EXTENSION => Some(DumpKind::Resources),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            DumpKind::Connections => CONNECTIONS_EXTENSION,
+            DumpKind::Resources => RESOURCES_EXTENSION,
+        }
+    }
+}
+
+async fn list_dir(path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    let mut dir = match fs::read_dir(path).await {
+        Ok(dir) => dir,
+        Err(err) => {
+            log::error!("Failed to read dumps directory {}: {err}", path.display());
+            return files;
+        }
+    };
+
+    while let Ok(Some(file)) = dir.next_entry().await {
+        files.push(file.path());
     }
+
+    files.sort();
+    files
+}
+
+fn file_name(state: &mut SpoolState, timestamp_ms: i64, kind: DumpKind) -> String {
+    let name = format!(
+        "{timestamp_ms:013}-{:06}.{}",
+        state.sequence,
+        kind.extension()
+    );
+    state.sequence += 1;
+    name
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default)]
-#[serde(untagged)]
+/// Parses a dump written by older versions, named `<RFC 3339 time>_<kind>` and holding JSON.
+fn parse_legacy_dump(path: &Path, data: &[u8]) -> Option<(i64, DumpKind, DumpItem)> {
+    let (time, kind) = path.file_name()?.to_str()?.rsplit_once('_')?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(time)
+        .ok()?
+        .timestamp_millis();
+
+    let (kind, item) = match kind {
+        CONNECTIONS_EXTENSION => {
+            let mut data: ConnectionsData = serde_json::from_slice(data).ok()?;
+            data.token.clear();
+            (DumpKind::Connections, DumpItem::Connections(data))
+        }
+        RESOURCES_EXTENSION => {
+            let mut data: SystemResourcesData = serde_json::from_slice(data).ok()?;
+            data.token.clear();
+            (DumpKind::Resources, DumpItem::Resources(data))
+        }
+        _ => return None,
+    };
+
+    Some((timestamp, kind, item))
+}
+
+fn encode_item(dump_item: &DumpItem) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+
+    match dump_item {
+        DumpItem::Connections(data) => {
+            for connection in &data.connections {
+                connection
+                    .encode_length_delimited(&mut buffer)
+                    .handle_err(location!())?;
+            }
+        }
+        DumpItem::Resources(data) => {
+            for resource in &data.resources {
+                resource
+                    .encode_length_delimited(&mut buffer)
+                    .handle_err(location!())?;
+            }
+        }
+        DumpItem::Empty => {}
+    }
+
+    zstd::encode_all(buffer.as_slice(), DUMP_ZSTD_LEVEL).handle_err(location!())
+}
+
+/// Decodes as many records as possible, also reporting whether the whole file was valid.
+fn decode_item(kind: DumpKind, data: &[u8]) -> (DumpItem, bool) {
+    let mut buffer = Vec::new();
+    let mut complete = zstd::Decoder::new(data)
+        .and_then(|mut decoder| decoder.read_to_end(&mut buffer))
+        .is_ok();
+
+    let mut cursor = buffer.as_slice();
+
+    let item = match kind {
+        DumpKind::Connections => {
+            let mut connections = Vec::new();
+            while !cursor.is_empty() {
+                let Ok(connection) = Connection::decode_length_delimited(&mut cursor) else {
+                    complete = false;
+                    break;
+                };
+                connections.push(connection);
+            }
+            DumpItem::Connections(ConnectionsData {
+                token: String::new(),
+                connections,
+            })
+        }
+        DumpKind::Resources => {
+            let mut resources = Vec::new();
+            while !cursor.is_empty() {
+                let Ok(resource) = SystemResource::decode_length_delimited(&mut cursor) else {
+                    complete = false;
+                    break;
+                };
+                resources.push(resource);
+            }
+            DumpItem::Resources(SystemResourcesData {
+                token: String::new(),
+                resources,
+            })
+        }
+    };
+
+    (item, complete)
+}
+
+#[derive(Default)]
 pub(crate) enum DumpItem {
     Connections(ConnectionsData),
     Resources(SystemResourcesData),
@@ -84,14 +442,6 @@ pub(crate) enum DumpItem {
 }
 
 impl DumpItem {
-    pub(crate) fn set_token(&mut self, token: String) {
-        match self {
-            DumpItem::Connections(connections) => connections.token = token,
-            DumpItem::Resources(resources) => resources.token = token,
-            DumpItem::Empty => {}
-        }
-    }
-
     pub(crate) fn size(&self) -> usize {
         match self {
             DumpItem::Connections(connections) => connections.connections.len(),
@@ -112,3 +462,91 @@ impl DumpItem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(port: u32) -> Connection {
+        Connection {
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            interface: "eth0".to_string(),
+            source_ip: "10.0.0.1".to_string(),
+            destination_ip: "10.0.0.2".to_string(),
+            source_port: Some(port),
+            destination_port: Some(443),
+            protocol: "tcp".to_string(),
+            total_byte: 1500,
+            total_packet: 3,
+        }
+    }
+
+    fn connections(item: DumpItem) -> Vec<Connection> {
+        match item {
+            DumpItem::Connections(data) => data.connections,
+            _ => panic!("expected connections"),
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let original: Vec<Connection> = (0..100).map(connection).collect();
+        let item = DumpItem::Connections(ConnectionsData {
+            token: "secret".to_string(),
+            connections: original.clone(),
+        });
+
+        let data = encode_item(&item).unwrap();
+        let (decoded, complete) = decode_item(DumpKind::Connections, &data);
+
+        assert!(complete);
+        assert_eq!(connections(decoded), original);
+    }
+
+    #[test]
+    fn test_decode_salvages_truncated_file() {
+        let original: Vec<Connection> = (0..1000).map(connection).collect();
+        let item = DumpItem::Connections(ConnectionsData {
+            token: String::new(),
+            connections: original.clone(),
+        });
+
+        let data = encode_item(&item).unwrap();
+        let (decoded, complete) = decode_item(DumpKind::Connections, &data[..data.len() / 2]);
+        let decoded = connections(decoded);
+
+        assert!(!complete);
+        assert!(decoded.len() < original.len());
+        assert_eq!(decoded, original[..decoded.len()]);
+
+        let (decoded, complete) = decode_item(DumpKind::Resources, b"not a zstd stream");
+        assert!(!complete);
+        assert_eq!(decoded.size(), 0);
+    }
+
+    #[test]
+    fn test_parse_legacy_dump() {
+        let original: Vec<Connection> = (0..3).map(connection).collect();
+        let data = serde_json::to_vec(&ConnectionsData {
+            token: "secret".to_string(),
+            connections: original.clone(),
+        })
+        .unwrap();
+
+        let path = Path::new("dumps/2025-01-01T00:00:00.5+00:00_connections");
+        let (timestamp, kind, item) = parse_legacy_dump(path, &data).unwrap();
+        assert_eq!(timestamp, 1_735_689_600_500);
+        assert_eq!(kind, DumpKind::Connections);
+
+        let DumpItem::Connections(item) = item else {
+            panic!("expected connections");
+        };
+        assert!(item.token.is_empty());
+        assert_eq!(item.connections, original);
+
+        let path = Path::new("dumps/2025-01-01T00:00:00+00:00_empty");
+        assert!(parse_legacy_dump(path, b"null").is_none());
+        let path = Path::new("dumps/2025-01-01T00:00:00+00:00_resources");
+        assert!(parse_legacy_dump(path, b"{").is_none());
+    }
+}
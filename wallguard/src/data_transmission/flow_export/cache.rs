@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct FlowKey {
+    pub source_ip: IpAddr,
+    pub destination_ip: IpAddr,
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub protocol: u8,
+    pub interface_index: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlowRecord {
+    pub key: FlowKey,
+    pub start_ms: u64,
+    pub last_ms: u64,
+    pub bytes: u64,
+    pub packets: u64,
+}
+
+/// Aggregates the per-batch connection records into long-lived flows,
+/// so that they can be exported according to the active and idle timeouts.
+#[derive(Default)]
+pub struct FlowCache {
+    flows: HashMap<FlowKey, FlowRecord>,
+    interfaces: HashMap<String, u32>,
+}
+
+impl FlowCache {
+    pub fn update(&mut self, connections: Vec<Connection>, now_ms: u64) {
+        for connection in connections {
+            let Some(key) = self.flow_key(&connection) else {
+                continue;
+            };
+
+            let start_ms = chrono::DateTime::parse_from_rfc3339(&connection.timestamp)
+                .map(|ts| ts.timestamp_millis().max(0) as u64)
+                .unwrap_or(now_ms);
+
+            self.flows
+                .entry(key)
+                .and_modify(|flow| {
+                    flow.bytes += connection.total_byte;
+                    flow.packets += u64::from(connection.total_packet);
+                    flow.last_ms = now_ms;
+                })
+                .or_insert(FlowRecord {
+                    key,
+                    start_ms,
+                    last_ms: now_ms,
+                    bytes: connection.total_byte,
+                    packets: u64::from(connection.total_packet),
+                });
+        }
+    }
+
+    /// Removes and returns the flows that have been idle for `idle_timeout_ms`
+    /// or active for longer than `active_timeout_ms`.
+    pub fn expire(
+        &mut self,
+        now_ms: u64,
+        active_timeout_ms: u64,
+        idle_timeout_ms: u64,
+    ) -> Vec<FlowRecord> {
+        let expired: Vec<FlowKey> = self
+            .flows
+            .values()
+            .filter(|flow| {
+                now_ms.saturating_sub(flow.last_ms) >= idle_timeout_ms
+                    || now_ms.saturating_sub(flow.start_ms) >= active_timeout_ms
+            })
+            .map(|flow| flow.key)
+            .collect();
+
+        expired
+            .iter()
+            .filter_map(|key| self.flows.remove(key))
+            .collect()
+    }
+
+    pub fn drain(&mut self) -> Vec<FlowRecord> {
+        self.flows.drain().map(|(_, flow)| flow).collect()
+    }
+
+    fn flow_key(&mut self, connection: &Connection) -> Option<FlowKey> {
+        let protocol = match connection.protocol.as_str() {
+            "tcp" => 6,
+            "udp" => 17,
+            "icmpv4" => 1,
+            "icmpv6" => 58,
+            _ => return None,
+        };
+
+        let interface_index = *self
+            .interfaces
+            .entry(connection.interface.clone())
+            .or_insert_with(|| interface_index(&connection.interface));
+
+        Some(FlowKey {
+            source_ip: connection.source_ip.parse().ok()?,
+            destination_ip: connection.destination_ip.parse().ok()?,
+            source_port: connection.source_port.unwrap_or_default() as u16,
+            destination_port: connection.destination_port.unwrap_or_default() as u16,
+            protocol,
+            interface_index,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> u32 {
+    let Ok(name) = std::ffi::CString::new(name) else {
+        return 0;
+    };
+
+    unsafe { libc::if_nametoindex(name.as_ptr()) }
+}
+
+#[cfg(not(unix))]
+fn interface_index(_: &str) -> u32 {
+    0
+}
@@ -0,0 +1,315 @@
+use super::cache::FlowRecord;
+use std::net::IpAddr;
+
+const NETFLOW_V9_VERSION: u16 = 9;
+const IPFIX_VERSION: u16 = 10;
+
+const NETFLOW_V9_TEMPLATE_SET_ID: u16 = 0;
+const IPFIX_TEMPLATE_SET_ID: u16 = 2;
+
+const IPV4_TEMPLATE_ID: u16 = 256;
+const IPV6_TEMPLATE_ID: u16 = 257;
+
+/// Upper bound for a single export packet, keeps datagrams below a typical MTU.
+const MAX_PACKET_SIZE: usize = 1400;
+
+// Information elements shared by NetFlow v9 and IPFIX
+const IN_BYTES: u16 = 1;
+const IN_PKTS: u16 = 2;
+const PROTOCOL: u16 = 4;
+const L4_SRC_PORT: u16 = 7;
+const IPV4_SRC_ADDR: u16 = 8;
+const INPUT_SNMP: u16 = 10;
+const L4_DST_PORT: u16 = 11;
+const IPV4_DST_ADDR: u16 = 12;
+const IPV6_SRC_ADDR: u16 = 27;
+const IPV6_DST_ADDR: u16 = 28;
+// NetFlow v9 only: milliseconds relative to the exporter's sysUptime
+const LAST_SWITCHED: u16 = 21;
+const FIRST_SWITCHED: u16 = 22;
+// IPFIX only: absolute milliseconds since the UNIX epoch
+const FLOW_START_MILLISECONDS: u16 = 152;
+const FLOW_END_MILLISECONDS: u16 = 153;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    NetflowV9,
+    Ipfix,
+}
+
+impl ExportFormat {
+    fn template_set_id(self) -> u16 {
+        match self {
+            ExportFormat::NetflowV9 => NETFLOW_V9_TEMPLATE_SET_ID,
+            ExportFormat::Ipfix => IPFIX_TEMPLATE_SET_ID,
+        }
+    }
+
+    fn header_len(self) -> usize {
+        match self {
+            ExportFormat::NetflowV9 => 20,
+            ExportFormat::Ipfix => 16,
+        }
+    }
+
+    fn fields(self, ipv6: bool) -> Vec<(u16, u16)> {
+        let (src, dst, addr_len) = if ipv6 {
+            (IPV6_SRC_ADDR, IPV6_DST_ADDR, 16)
+        } else {
+            (IPV4_SRC_ADDR, IPV4_DST_ADDR, 4)
+        };
+
+        let mut fields = vec![
+            (src, addr_len),
+            (dst, addr_len),
+            (L4_SRC_PORT, 2),
+            (L4_DST_PORT, 2),
+            (PROTOCOL, 1),
+            (INPUT_SNMP, 4),
+            (IN_BYTES, 8),
+            (IN_PKTS, 8),
+        ];
+
+        match self {
+            ExportFormat::NetflowV9 => {
+                fields.push((FIRST_SWITCHED, 4));
+                fields.push((LAST_SWITCHED, 4));
+            }
+            ExportFormat::Ipfix => {
+                fields.push((FLOW_START_MILLISECONDS, 8));
+                fields.push((FLOW_END_MILLISECONDS, 8));
+            }
+        }
+
+        fields
+    }
+
+    fn record_len(self, ipv6: bool) -> usize {
+        self.fields(ipv6)
+            .iter()
+            .map(|(_, len)| usize::from(*len))
+            .sum()
+    }
+}
+
+/// Stateful encoder producing NetFlow v9 or IPFIX export packets.
+///
+/// Keeps track of the sequence number, which has different semantics for the
+/// two formats: NetFlow v9 counts export packets while IPFIX counts data records.
+pub struct FlowEncoder {
+    format: ExportFormat,
+    observation_domain_id: u32,
+    sequence: u32,
+    boot_time_ms: u64,
+}
+
+impl FlowEncoder {
+    pub fn new(format: ExportFormat, observation_domain_id: u32, boot_time_ms: u64) -> Self {
+        Self {
+            format,
+            observation_domain_id,
+            sequence: 0,
+            boot_time_ms,
+        }
+    }
+
+    /// Encodes the template set for both address families.
+    pub fn encode_templates(&mut self, now_ms: u64) -> Vec<u8> {
+        let mut set = Vec::new();
+        set.extend_from_slice(&self.format.template_set_id().to_be_bytes());
+        set.extend_from_slice(&0u16.to_be_bytes());
+
+        for (template_id, ipv6) in [(IPV4_TEMPLATE_ID, false), (IPV6_TEMPLATE_ID, true)] {
+            let fields = self.format.fields(ipv6);
+            set.extend_from_slice(&template_id.to_be_bytes());
+            set.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+            for (id, len) in fields {
+                set.extend_from_slice(&id.to_be_bytes());
+                set.extend_from_slice(&len.to_be_bytes());
+            }
+        }
+
+        let set_len = set.len() as u16;
+        set[2..4].copy_from_slice(&set_len.to_be_bytes());
+
+        self.finish_packet(vec![set], 2, 0, now_ms)
+    }
+
+    /// Encodes flow records into as many export packets as needed.
+    pub fn encode_records(&mut self, records: &[FlowRecord], now_ms: u64) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+
+        for ipv6 in [false, true] {
+            let template_id = if ipv6 {
+                IPV6_TEMPLATE_ID
+            } else {
+                IPV4_TEMPLATE_ID
+            };
+
+            let records: Vec<_> = records
+                .iter()
+                .filter(|r| r.key.source_ip.is_ipv6() == ipv6)
+                .collect();
+
+            let per_packet =
+                (MAX_PACKET_SIZE - self.format.header_len() - 4) / self.format.record_len(ipv6);
+
+            for chunk in records.chunks(per_packet.max(1)) {
+                let mut set = Vec::new();
+                set.extend_from_slice(&template_id.to_be_bytes());
+                set.extend_from_slice(&0u16.to_be_bytes());
+
+                for record in chunk {
+                    self.encode_record(&mut set, record);
+                }
+
+                while set.len() % 4 != 0 {
+                    set.push(0);
+                }
+
+                let set_len = set.len() as u16;
+                set[2..4].copy_from_slice(&set_len.to_be_bytes());
+
+                packets.push(self.finish_packet(vec![set], chunk.len(), chunk.len(), now_ms));
+            }
+        }
+
+        packets
+    }
+
+    fn encode_record(&self, buffer: &mut Vec<u8>, record: &FlowRecord) {
+        write_ip(buffer, record.key.source_ip);
+        write_ip(buffer, record.key.destination_ip);
+        buffer.extend_from_slice(&record.key.source_port.to_be_bytes());
+        buffer.extend_from_slice(&record.key.destination_port.to_be_bytes());
+        buffer.push(record.key.protocol);
+        buffer.extend_from_slice(&record.key.interface_index.to_be_bytes());
+        buffer.extend_from_slice(&record.bytes.to_be_bytes());
+        buffer.extend_from_slice(&record.packets.to_be_bytes());
+
+        match self.format {
+            ExportFormat::NetflowV9 => {
+                let first = record.start_ms.saturating_sub(self.boot_time_ms) as u32;
+                let last = record.last_ms.saturating_sub(self.boot_time_ms) as u32;
+                buffer.extend_from_slice(&first.to_be_bytes());
+                buffer.extend_from_slice(&last.to_be_bytes());
+            }
+            ExportFormat::Ipfix => {
+                buffer.extend_from_slice(&record.start_ms.to_be_bytes());
+                buffer.extend_from_slice(&record.last_ms.to_be_bytes());
+            }
+        }
+    }
+
+    fn finish_packet(
+        &mut self,
+        sets: Vec<Vec<u8>>,
+        record_count: usize,
+        data_records: usize,
+        now_ms: u64,
+    ) -> Vec<u8> {
+        let body_len: usize = sets.iter().map(Vec::len).sum();
+        let mut packet = Vec::with_capacity(self.format.header_len() + body_len);
+
+        match self.format {
+            ExportFormat::NetflowV9 => {
+                let uptime = now_ms.saturating_sub(self.boot_time_ms) as u32;
+                packet.extend_from_slice(&NETFLOW_V9_VERSION.to_be_bytes());
+                packet.extend_from_slice(&(record_count as u16).to_be_bytes());
+                packet.extend_from_slice(&uptime.to_be_bytes());
+                packet.extend_from_slice(&((now_ms / 1000) as u32).to_be_bytes());
+                packet.extend_from_slice(&self.sequence.to_be_bytes());
+                packet.extend_from_slice(&self.observation_domain_id.to_be_bytes());
+                self.sequence = self.sequence.wrapping_add(1);
+            }
+            ExportFormat::Ipfix => {
+                let total_len = (self.format.header_len() + body_len) as u16;
+                packet.extend_from_slice(&IPFIX_VERSION.to_be_bytes());
+                packet.extend_from_slice(&total_len.to_be_bytes());
+                packet.extend_from_slice(&((now_ms / 1000) as u32).to_be_bytes());
+                packet.extend_from_slice(&self.sequence.to_be_bytes());
+                packet.extend_from_slice(&self.observation_domain_id.to_be_bytes());
+                self.sequence = self.sequence.wrapping_add(data_records as u32);
+            }
+        }
+
+        for set in sets {
+            packet.extend(set);
+        }
+
+        packet
+    }
+}
+
+fn write_ip(buffer: &mut Vec<u8>, ip: IpAddr) {
+    match ip {
+        IpAddr::V4(ip) => buffer.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => buffer.extend_from_slice(&ip.octets()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_transmission::flow_export::cache::FlowKey;
+    use std::net::Ipv4Addr;
+
+    fn record() -> FlowRecord {
+        FlowRecord {
+            key: FlowKey {
+                source_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                destination_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                source_port: 1234,
+                destination_port: 443,
+                protocol: 6,
+                interface_index: 2,
+            },
+            start_ms: 10_000,
+            last_ms: 12_000,
+            bytes: 1500,
+            packets: 3,
+        }
+    }
+
+    fn read_u16(data: &[u8], offset: usize) -> u16 {
+        u16::from_be_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_netflow_v9_sequence_counts_packets() {
+        let mut encoder = FlowEncoder::new(ExportFormat::NetflowV9, 7, 0);
+
+        let templates = encoder.encode_templates(20_000);
+        assert_eq!(read_u16(&templates, 0), 9);
+        assert_eq!(read_u16(&templates, 2), 2);
+        assert_eq!(read_u32(&templates, 12), 0);
+        assert_eq!(read_u32(&templates, 16), 7);
+
+        let packets = encoder.encode_records(&[record(), record()], 20_000);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(read_u16(&packets[0], 2), 2);
+        assert_eq!(read_u32(&packets[0], 12), 1);
+        assert_eq!(read_u16(&packets[0], 20), IPV4_TEMPLATE_ID);
+        assert_eq!(packets[0].len() % 4, 0);
+    }
+
+    #[test]
+    fn test_ipfix_sequence_counts_records() {
+        let mut encoder = FlowEncoder::new(ExportFormat::Ipfix, 1, 0);
+
+        let templates = encoder.encode_templates(20_000);
+        assert_eq!(read_u16(&templates, 0), 10);
+        assert_eq!(usize::from(read_u16(&templates, 2)), templates.len());
+        assert_eq!(read_u16(&templates, 16), IPFIX_TEMPLATE_SET_ID);
+
+        let first = encoder.encode_records(&[record(), record(), record()], 20_000);
+        let second = encoder.encode_records(&[record()], 21_000);
+        assert_eq!(read_u32(&first[0], 8), 0);
+        assert_eq!(read_u32(&second[0], 8), 3);
+        assert_eq!(usize::from(read_u16(&first[0], 2)), first[0].len());
+    }
+}
@@ -0,0 +1,210 @@
+use cache::{FlowCache, FlowRecord};
+use encoder::{ExportFormat, FlowEncoder};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, mpsc};
+use wallguard_common::protobuf::wallguard_commands::{FlowExportProtocol, FlowExportSettings};
+use wallguard_common::protobuf::wallguard_service::Connection;
+
+mod cache;
+mod encoder;
+
+const DEFAULT_ACTIVE_TIMEOUT_SECONDS: u32 = 60;
+const DEFAULT_IDLE_TIMEOUT_SECONDS: u32 = 15;
+const DEFAULT_COLLECTOR_PORT: u16 = 2055;
+// Collectors may start after us or restart, so templates are periodically resent
+const TEMPLATE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) type FlowExportTap = Arc<Mutex<Option<FlowExporter>>>;
+
+/// Exports the connection records produced by the packet capture to
+/// external NetFlow v9 / IPFIX collectors, alongside the gRPC transmission.
+#[derive(Debug)]
+pub(crate) struct FlowExporter {
+    sender: mpsc::Sender<Vec<Connection>>,
+    terminate: broadcast::Sender<()>,
+}
+
+impl FlowExporter {
+    pub(crate) async fn start(settings: &FlowExportSettings) -> Result<Self, Error> {
+        let collectors = resolve_collectors(&settings.collectors).await?;
+
+        let bind_addr = if collectors.iter().all(SocketAddr::is_ipv4) {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+
+        let socket = UdpSocket::bind(bind_addr).await.handle_err(location!())?;
+
+        let format = match FlowExportProtocol::try_from(settings.protocol) {
+            Ok(FlowExportProtocol::Ipfix) => ExportFormat::Ipfix,
+            _ => ExportFormat::NetflowV9,
+        };
+
+        let active_timeout = timeout_or_default(
+            settings.active_timeout_seconds,
+            DEFAULT_ACTIVE_TIMEOUT_SECONDS,
+        );
+        let idle_timeout =
+            timeout_or_default(settings.idle_timeout_seconds, DEFAULT_IDLE_TIMEOUT_SECONDS);
+
+        log::info!(
+            "Starting {format:?} flow export to {collectors:?} (active timeout {}s, idle timeout {}s)",
+            active_timeout.as_secs(),
+            idle_timeout.as_secs()
+        );
+
+        let (sender, receiver) = mpsc::channel(64);
+        let (terminate, _) = broadcast::channel(1);
+
+        let exporter = Exporter {
+            socket,
+            collectors,
+            encoder: FlowEncoder::new(format, settings.observation_domain_id, now_ms()),
+            templates_sent_at: None,
+            active_timeout,
+            idle_timeout,
+        };
+
+        tokio::spawn(exporter.run(receiver, terminate.subscribe()));
+
+        Ok(Self { sender, terminate })
+    }
+
+    pub(crate) fn submit(&self, connections: &[Connection]) {
+        if connections.is_empty() {
+            return;
+        }
+
+        if self.sender.try_send(connections.to_vec()).is_err() {
+            log::warn!(
+                "Flow exporter is lagging behind, dropping {} records",
+                connections.len()
+            );
+        }
+    }
+
+    pub(crate) fn terminate(&self) {
+        log::info!("Terminating flow export");
+        let _ = self.terminate.send(());
+    }
+}
+
+struct Exporter {
+    socket: UdpSocket,
+    collectors: Vec<SocketAddr>,
+    encoder: FlowEncoder,
+    templates_sent_at: Option<Instant>,
+    active_timeout: Duration,
+    idle_timeout: Duration,
+}
+
+impl Exporter {
+    async fn run(
+        mut self,
+        mut receiver: mpsc::Receiver<Vec<Connection>>,
+        mut terminate: broadcast::Receiver<()>,
+    ) {
+        let mut cache = FlowCache::default();
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+
+        loop {
+            tokio::select! {
+                batch = receiver.recv() => {
+                    let Some(batch) = batch else {
+                        break;
+                    };
+                    cache.update(batch, now_ms());
+                }
+                _ = ticker.tick() => {
+                    let expired = cache.expire(
+                        now_ms(),
+                        self.active_timeout.as_millis() as u64,
+                        self.idle_timeout.as_millis() as u64,
+                    );
+                    self.export(expired).await;
+                }
+                _ = terminate.recv() => break,
+            }
+        }
+
+        // Flush whatever is still cached so no traffic goes unreported
+        let remaining = cache.drain();
+        self.export(remaining).await;
+    }
+
+    async fn export(&mut self, records: Vec<FlowRecord>) {
+        if records.is_empty() {
+            return;
+        }
+
+        let now = now_ms();
+        let mut packets = Vec::new();
+
+        if self
+            .templates_sent_at
+            .is_none_or(|sent_at| sent_at.elapsed() >= TEMPLATE_REFRESH_INTERVAL)
+        {
+            packets.push(self.encoder.encode_templates(now));
+            self.templates_sent_at = Some(Instant::now());
+        }
+
+        packets.extend(self.encoder.encode_records(&records, now));
+
+        for collector in &self.collectors {
+            for packet in &packets {
+                if let Err(err) = self.socket.send_to(packet, collector).await {
+                    log::error!("Failed to export flows to {collector}: {err}");
+                    break;
+                }
+            }
+        }
+
+        log::debug!(
+            "Exported {} flow records to {} collector(s)",
+            records.len(),
+            self.collectors.len()
+        );
+    }
+}
+
+async fn resolve_collectors(collectors: &[String]) -> Result<Vec<SocketAddr>, Error> {
+    let mut retval = Vec::new();
+
+    for collector in collectors {
+        let addr = if let Ok(ip) = collector.parse::<IpAddr>() {
+            SocketAddr::new(ip, DEFAULT_COLLECTOR_PORT).to_string()
+        } else if collector.contains(':') {
+            collector.clone()
+        } else {
+            format!("{collector}:{DEFAULT_COLLECTOR_PORT}")
+        };
+
+        match tokio::net::lookup_host(&addr).await {
+            Ok(mut addrs) => retval.extend(addrs.next()),
+            Err(err) => log::error!("Failed to resolve flow collector {collector}: {err}"),
+        }
+    }
+
+    if retval.is_empty() {
+        return Err("No valid flow collectors configured").handle_err(location!());
+    }
+
+    Ok(retval)
+}
+
+fn timeout_or_default(value: u32, default: u32) -> Duration {
+    let value = if value == 0 { default } else { value };
+    Duration::from_secs(u64::from(value))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
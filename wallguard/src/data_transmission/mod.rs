@@ -1,4 +1,5 @@
 pub(crate) mod dump_dir;
+pub(crate) mod flow_export;
 pub(crate) mod grpc_handler;
 pub(crate) mod item_buffer;
 pub(crate) mod packets;
@@ -1,7 +1,9 @@
 use super::parser::parse_packets;
 use crate::constants::{DATA_TRANSMISSION_INTERVAL_SECONDS, QUEUE_SIZE};
 use crate::data_transmission::dump_dir::{DumpDir, DumpItem};
+use crate::data_transmission::flow_export::FlowExportTap;
 use crate::data_transmission::item_buffer::ItemBuffer;
+use crate::metrics::{METRICS, Upload};
 use crate::timer::Timer;
 use crate::token_provider::TokenProvider;
 use crate::wg_server::WGServer;
@@ -16,6 +18,7 @@ pub(crate) async fn transmit_packets(
     dump_dir: DumpDir,
     client: WGServer,
     batch_size: usize,
+    flow_export: FlowExportTap,
 ) {
     // PacketInfo doesn't implement Clone so we use a plain Vec for the raw accumulation window
     let mut raw_batch: Vec<PacketInfo> = Vec::with_capacity(batch_size);
@@ -28,6 +31,11 @@ pub(crate) async fn transmit_packets(
             timer.reset();
 
             let connections = parse_packets(std::mem::take(&mut raw_batch));
+
+            if let Some(exporter) = flow_export.lock().unwrap().as_ref() {
+                exporter.submit(&connections);
+            }
+
             connection_queue.extend(connections);
 
             send_connections(&client, &mut connection_queue, &token_provider, batch_size).await;
@@ -42,19 +50,9 @@ pub(crate) async fn transmit_packets(
                     token: String::new(),
                 });
                 dump_dir.dump_item_to_file(dump_item).await;
-                if dump_dir.is_full().await {
-                    log::warn!(
-                        "Dump size maximum limit reached. Packets routine entering idle mode...",
-                    );
-
-                    loop {
-                        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                        if client.is_connected().await {
-                            break;
-                        }
-                    }
-                }
             }
+
+            METRICS.set_queue_len(Upload::Connections, connection_queue.len());
         }
     }
 }
@@ -66,37 +64,46 @@ async fn send_connections(
     batch_size: usize,
 ) {
     while !connection_queue.is_empty() {
-        let token = token_provider.get().await;
-
-        if token.is_none() {
+        let Some(token) = token_provider.get().await else {
             log::error!("Failed to obtain token");
             break;
-        }
+        };
 
         let range = ..min(connection_queue.len(), batch_size);
-        let data = ConnectionsData {
-            connections: connection_queue.get(range),
-            token: token.unwrap(),
-        };
+        let connections = connection_queue.get(range);
 
-        let sent = range.end;
+        // Only the connections acknowledged by the server are removed from the queue
+        let stored = match interface.stream_connections(&token, &connections).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                METRICS.record_error(format!("Failed to send connections: {}", e.to_str()));
+                log::error!(
+                    "[{}] Failed to send connections (Queue size {}): {e:?}",
+                    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                    connection_queue.len()
+                );
+                break;
+            }
+        };
 
-        if let Err(e) = interface.handle_connections_data(data).await {
-            log::error!(
-                "[{}] Failed to send connections (Queue size {}): {e:?}",
-                connection_queue.len(),
-                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ")
-            );
-            break;
+        connection_queue.drain(..stored);
+        if stored > 0 {
+            METRICS.record_upload(Upload::Connections);
         }
 
-        connection_queue.drain(range);
-
         log::info!(
             "[{}] Sent {} connections, {} remaining in queue",
             chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
-            sent,
+            stored,
             connection_queue.len()
         );
+
+        if stored < range.end {
+            log::warn!(
+                "Server acknowledged only {stored} of {} connections",
+                range.end
+            );
+            break;
+        }
     }
 }
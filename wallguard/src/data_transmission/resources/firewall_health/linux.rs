@@ -0,0 +1,118 @@
+use std::path::Path;
+use wallguard_common::protobuf::wallguard_service::{FirewallHealth, HaStatus};
+
+const CONNTRACK_COUNT: &str = "/proc/sys/net/netfilter/nf_conntrack_count";
+const CONNTRACK_MAX: &str = "/proc/sys/net/netfilter/nf_conntrack_max";
+const KEEPALIVED_CONF: &str = "/etc/keepalived/keepalived.conf";
+/// Written by the keepalived `notify` script, one file per VRRP instance holding its state.
+const KEEPALIVED_STATE_DIR: &str = "/run/wallguard/keepalived";
+
+pub(super) async fn collect() -> FirewallHealth {
+    FirewallHealth {
+        states: read_counter(CONNTRACK_COUNT).await,
+        states_limit: read_counter(CONNTRACK_MAX).await,
+        ha: read_keepalived_state().await,
+        ..Default::default()
+    }
+}
+
+async fn read_counter(path: &str) -> u64 {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+/// Lists the VRRP instances of the keepalived configuration with the state last
+/// reported for them by the notify script. Instances without a state are left out.
+async fn read_keepalived_state() -> Vec<HaStatus> {
+    let Ok(config) = tokio::fs::read_to_string(KEEPALIVED_CONF).await else {
+        return vec![];
+    };
+
+    let mut instances = parse_keepalived_conf(&config);
+
+    for instance in &mut instances {
+        if instance.instance.contains('/') {
+            continue;
+        }
+
+        let path = Path::new(KEEPALIVED_STATE_DIR).join(&instance.instance);
+        if let Ok(state) = tokio::fs::read_to_string(path).await {
+            instance.state = state.trim().to_uppercase();
+        }
+    }
+
+    instances.retain(|instance| !instance.state.is_empty());
+    instances
+}
+
+fn parse_keepalived_conf(content: &str) -> Vec<HaStatus> {
+    let content = content
+        .lines()
+        .map(|line| line.split(['#', '!']).next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .replace('{', " { ")
+        .replace('}', " } ");
+
+    let mut instances: Vec<HaStatus> = Vec::new();
+    let mut tokens = content.split_whitespace();
+    let mut depth = 0usize;
+    let mut in_instance = false;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "{" => depth += 1,
+            "}" => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    in_instance = false;
+                }
+            }
+            "vrrp_instance" if depth == 0 => {
+                if let Some(name) = tokens.next() {
+                    instances.push(HaStatus {
+                        protocol: String::from("vrrp"),
+                        instance: name.to_string(),
+                        ..Default::default()
+                    });
+                    in_instance = true;
+                }
+            }
+            "interface" if in_instance && depth == 1 => {
+                if let (Some(instance), Some(value)) = (instances.last_mut(), tokens.next()) {
+                    instance.interface = value.to_string();
+                }
+            }
+            "virtual_router_id" if in_instance && depth == 1 => {
+                if let (Some(instance), Some(value)) = (instances.last_mut(), tokens.next()) {
+                    instance.vhid = value.parse().unwrap_or_default();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    instances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keepalived_conf() {
+        let config = "global_defs {\n    router_id LVS_1 # interface eth9\n}\n\nvrrp_instance VI_1 {\n    state BACKUP\n    interface eth0\n    virtual_router_id 51\n    track_interface {\n        eth2\n    }\n    virtual_ipaddress {\n        10.0.0.1/24 dev eth1\n    }\n}\n! vrrp_instance VI_OLD {\nvrrp_instance VI_2 {\n    interface eth1\n    virtual_router_id 52\n}\n";
+        let instances = parse_keepalived_conf(config);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].instance, "VI_1");
+        assert_eq!(instances[0].interface, "eth0");
+        assert_eq!(instances[0].vhid, 51);
+        assert_eq!(instances[1].instance, "VI_2");
+        assert_eq!(instances[1].interface, "eth1");
+        assert_eq!(instances[1].vhid, 52);
+        assert!(instances.iter().all(|instance| instance.state.is_empty()));
+    }
+}
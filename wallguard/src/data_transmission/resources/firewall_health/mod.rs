@@ -0,0 +1,14 @@
+mod linux;
+mod pf;
+
+use crate::client_data::Platform;
+use wallguard_common::protobuf::wallguard_service::FirewallHealth;
+
+/// Collects the firewall vitals of the platform, if it has any.
+pub(crate) async fn collect_firewall_health(platform: Platform) -> Option<FirewallHealth> {
+    match platform {
+        Platform::PfSense | Platform::OpnSense => Some(pf::collect().await),
+        Platform::NfTables => Some(linux::collect().await),
+        Platform::Generic => None,
+    }
+}
@@ -0,0 +1,206 @@
+use crate::utilities::system::command_output;
+use wallguard_common::protobuf::wallguard_service::{FirewallHealth, GatewayHealth, HaStatus};
+
+#[cfg(unix)]
+const DPINGER_DIR: &str = "/var/run";
+
+// pfSense defaults for the gateway monitoring thresholds
+const LATENCY_LOW_MS: f32 = 200.0;
+const LATENCY_HIGH_MS: f32 = 500.0;
+const LOSS_LOW_PERCENT: f32 = 10.0;
+const LOSS_HIGH_PERCENT: f32 = 20.0;
+
+pub(super) async fn collect() -> FirewallHealth {
+    let mut health = FirewallHealth::default();
+
+    if let Some(info) = command_output("pfctl", &["-si"]).await {
+        health.states = parse_state_count(&info).unwrap_or_default();
+    }
+
+    if let Some(limits) = command_output("pfctl", &["-sm"]).await {
+        health.states_limit = parse_state_limit(&limits).unwrap_or_default();
+    }
+
+    if let Some((mbufs, mbufs_limit)) = command_output("netstat", &["-m"])
+        .await
+        .and_then(|m| parse_mbufs(&m))
+    {
+        health.mbufs = mbufs;
+        health.mbufs_limit = mbufs_limit;
+    }
+
+    if let Some(ifconfig) = command_output("ifconfig", &[]).await {
+        health.ha = parse_carp(&ifconfig);
+    }
+
+    health.gateways = read_dpinger_sockets().await;
+
+    health
+}
+
+/// `current entries` of the `State Table` section of `pfctl -si`.
+fn parse_state_count(output: &str) -> Option<u64> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("current entries")
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+/// `states hard limit` of `pfctl -sm`.
+fn parse_state_limit(output: &str) -> Option<u64> {
+    output.lines().find_map(|line| {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["states", "hard", "limit", value] => value.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Current and maximum mbuf clusters from `netstat -m`, e.g.
+/// `1024/1532/2556/1000000 mbuf clusters in use (current/cache/total/max)`.
+fn parse_mbufs(output: &str) -> Option<(u64, u64)> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("mbuf clusters in use"))?;
+
+    let counters: Vec<u64> = line
+        .split_whitespace()
+        .next()?
+        .split('/')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match counters.as_slice() {
+        [current, _, _, max] => Some((*current, *max)),
+        _ => None,
+    }
+}
+
+/// CARP addresses from `ifconfig`, e.g. `carp: MASTER vhid 1 advbase 1 advskew 0`.
+fn parse_carp(output: &str) -> Vec<HaStatus> {
+    let mut statuses = Vec::new();
+    let mut interface = "";
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            interface = line.split(':').next().unwrap_or_default();
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if let ["carp:", state, "vhid", vhid, ..] = tokens.as_slice() {
+            statuses.push(HaStatus {
+                protocol: String::from("carp"),
+                interface: interface.to_string(),
+                vhid: vhid.parse().unwrap_or_default(),
+                state: state.to_string(),
+                instance: String::new(),
+            });
+        }
+    }
+
+    statuses
+}
+
+/// Reads the status line every dpinger instance serves on its control socket:
+/// `<name> <latency us> <stddev us> <loss %>`.
+#[cfg(unix)]
+async fn read_dpinger_sockets() -> Vec<GatewayHealth> {
+    use tokio::io::AsyncReadExt;
+    use tokio::net::UnixStream;
+
+    let Ok(mut entries) = tokio::fs::read_dir(DPINGER_DIR).await else {
+        return vec![];
+    };
+
+    let mut gateways = Vec::new();
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if !file_name.starts_with("dpinger_") || !file_name.ends_with(".sock") {
+            continue;
+        }
+
+        let Ok(mut stream) = UnixStream::connect(entry.path()).await else {
+            continue;
+        };
+
+        let mut line = String::new();
+        if stream.read_to_string(&mut line).await.is_err() {
+            continue;
+        }
+
+        if let Some(gateway) = parse_dpinger_line(&line) {
+            gateways.push(gateway);
+        }
+    }
+
+    gateways
+}
+
+#[cfg(not(unix))]
+async fn read_dpinger_sockets() -> Vec<GatewayHealth> {
+    vec![]
+}
+
+fn parse_dpinger_line(line: &str) -> Option<GatewayHealth> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let [name, latency_us, stddev_us, loss_percent] = tokens.as_slice() else {
+        return None;
+    };
+
+    let latency_ms = latency_us.parse::<f32>().ok()? / 1000.0;
+    let stddev_ms = stddev_us.parse::<f32>().ok()? / 1000.0;
+    let loss_percent = loss_percent.parse::<f32>().ok()?;
+
+    let status = if loss_percent >= LOSS_HIGH_PERCENT || latency_ms >= LATENCY_HIGH_MS {
+        "down"
+    } else if loss_percent >= LOSS_LOW_PERCENT {
+        "loss"
+    } else if latency_ms >= LATENCY_LOW_MS {
+        "delay"
+    } else {
+        "online"
+    };
+
+    Some(GatewayHealth {
+        name: name.to_string(),
+        latency_ms,
+        stddev_ms,
+        loss_percent,
+        status: status.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pf_outputs() {
+        let info = "Status: Enabled for 3 days 01:02:03\n\nState Table                          Total             Rate\n  current entries                      812               \n  searches                        12345678          46.8/s\n";
+        assert_eq!(parse_state_count(info), Some(812));
+
+        let limits = "states        hard limit   400000\nsrc-nodes     hard limit   400000\nfrags         hard limit     5000\n";
+        assert_eq!(parse_state_limit(limits), Some(400_000));
+
+        let mbufs = "3066/2514/5580 mbufs in use (current/cache/total)\n3040/1492/4532/1000000 mbuf clusters in use (current/cache/total/max)\n";
+        assert_eq!(parse_mbufs(mbufs), Some((3040, 1_000_000)));
+
+        let ifconfig = "em1: flags=8943<UP,BROADCAST,RUNNING> metric 0 mtu 1500\n\tinet 10.0.0.2 netmask 0xffffff00 broadcast 10.0.0.255\n\tinet 10.0.0.1 netmask 0xffffff00 broadcast 10.0.0.255 vhid 3\n\tcarp: BACKUP vhid 3 advbase 1 advskew 100\n";
+        let carp = parse_carp(ifconfig);
+        assert_eq!(carp.len(), 1);
+        assert_eq!(carp[0].interface, "em1");
+        assert_eq!(carp[0].state, "BACKUP");
+        assert_eq!(carp[0].vhid, 3);
+
+        let gateway = parse_dpinger_line("WAN_DHCP 23500 1200 15\n").unwrap();
+        assert_eq!(gateway.name, "WAN_DHCP");
+        assert_eq!(gateway.status, "loss");
+    }
+}
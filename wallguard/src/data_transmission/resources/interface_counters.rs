@@ -0,0 +1,176 @@
+const NET_DEV: &str = "/proc/net/dev";
+
+/// Counters of a network interface, cumulative since boot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct InterfaceCounters {
+    pub(crate) rx_bytes: u64,
+    pub(crate) tx_bytes: u64,
+    pub(crate) rx_packets: u64,
+    pub(crate) tx_packets: u64,
+    pub(crate) rx_errors: u64,
+    pub(crate) tx_errors: u64,
+    pub(crate) rx_drops: u64,
+    pub(crate) tx_drops: u64,
+}
+
+impl InterfaceCounters {
+    /// What has been accumulated since `previous`; a counter that went backwards
+    /// (e.g. the interface was recreated) is taken as restarted from zero.
+    pub(crate) fn since(&self, previous: &Self) -> Self {
+        let delta = |current: u64, previous: u64| {
+            if current >= previous {
+                current - previous
+            } else {
+                current
+            }
+        };
+
+        Self {
+            rx_bytes: delta(self.rx_bytes, previous.rx_bytes),
+            tx_bytes: delta(self.tx_bytes, previous.tx_bytes),
+            rx_packets: delta(self.rx_packets, previous.rx_packets),
+            tx_packets: delta(self.tx_packets, previous.tx_packets),
+            rx_errors: delta(self.rx_errors, previous.rx_errors),
+            tx_errors: delta(self.tx_errors, previous.tx_errors),
+            rx_drops: delta(self.rx_drops, previous.rx_drops),
+            tx_drops: delta(self.tx_drops, previous.tx_drops),
+        }
+    }
+}
+
+/// Reads the counters of every interface from `/proc/net/dev` on Linux,
+/// or from the link level `if_data` of `getifaddrs` on the BSDs.
+pub(crate) async fn read_interface_counters() -> Vec<(String, InterfaceCounters)> {
+    if let Ok(net_dev) = tokio::fs::read_to_string(NET_DEV).await {
+        return parse_net_dev(&net_dev);
+    }
+
+    read_if_data()
+}
+
+fn parse_net_dev(content: &str) -> Vec<(String, InterfaceCounters)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let counters: Vec<u64> = counters
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .ok()?;
+
+            // receive: bytes packets errs drop fifo frame compressed multicast
+            // transmit: bytes packets errs drop fifo colls carrier compressed
+            if counters.len() < 16 {
+                return None;
+            }
+
+            Some((
+                name.trim().to_string(),
+                InterfaceCounters {
+                    rx_bytes: counters[0],
+                    rx_packets: counters[1],
+                    rx_errors: counters[2],
+                    rx_drops: counters[3],
+                    tx_bytes: counters[8],
+                    tx_packets: counters[9],
+                    tx_errors: counters[10],
+                    tx_drops: counters[11],
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "macos"))]
+fn read_if_data() -> Vec<(String, InterfaceCounters)> {
+    use std::ffi::CStr;
+
+    let mut addresses: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addresses) } != 0 {
+        return vec![];
+    }
+
+    let mut interfaces = Vec::new();
+    let mut cursor = addresses;
+
+    while !cursor.is_null() {
+        // The list stays valid until it is released by freeifaddrs
+        let address = unsafe { &*cursor };
+        cursor = address.ifa_next;
+
+        // Only the AF_LINK entry of an interface carries its if_data
+        if address.ifa_addr.is_null()
+            || address.ifa_data.is_null()
+            || i32::from(unsafe { (*address.ifa_addr).sa_family }) != libc::AF_LINK
+        {
+            continue;
+        }
+
+        let name = unsafe { CStr::from_ptr(address.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        let data = unsafe { &*(address.ifa_data as *const libc::if_data) };
+
+        interfaces.push((
+            name,
+            InterfaceCounters {
+                rx_bytes: u64::from(data.ifi_ibytes),
+                tx_bytes: u64::from(data.ifi_obytes),
+                rx_packets: u64::from(data.ifi_ipackets),
+                tx_packets: u64::from(data.ifi_opackets),
+                rx_errors: u64::from(data.ifi_ierrors),
+                tx_errors: u64::from(data.ifi_oerrors),
+                rx_drops: u64::from(data.ifi_iqdrops),
+                #[cfg(target_os = "freebsd")]
+                tx_drops: data.ifi_oqdrops,
+                // Not accounted for by Darwin
+                #[cfg(target_os = "macos")]
+                tx_drops: 0,
+            },
+        ));
+    }
+
+    unsafe { libc::freeifaddrs(addresses) };
+
+    interfaces
+}
+
+#[cfg(not(any(target_os = "freebsd", target_os = "macos")))]
+fn read_if_data() -> Vec<(String, InterfaceCounters)> {
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interface_counters() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit\n face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    lo: 1000 10 0 0 0 0 0 0 1000 10 0 0 0 0 0 0\n  eth0:50000 400 1 2 0 0 0 0 40000 300 3 4 0 0 0 0\n";
+        let interfaces = parse_net_dev(net_dev);
+        assert_eq!(interfaces.len(), 2);
+        assert_eq!(interfaces[1].0, "eth0");
+        assert_eq!(
+            interfaces[1].1,
+            InterfaceCounters {
+                rx_bytes: 50000,
+                tx_bytes: 40000,
+                rx_packets: 400,
+                tx_packets: 300,
+                rx_errors: 1,
+                tx_errors: 3,
+                rx_drops: 2,
+                tx_drops: 4,
+            }
+        );
+
+        let previous = InterfaceCounters {
+            rx_bytes: 100,
+            tx_bytes: 600_000,
+            ..Default::default()
+        };
+        let delta = interfaces[1].1.since(&previous);
+        assert_eq!((delta.rx_bytes, delta.tx_bytes), (49_900, 40_000));
+    }
+}
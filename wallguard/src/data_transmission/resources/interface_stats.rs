@@ -0,0 +1,81 @@
+use crate::constants::LINK_INFO_INTERVAL;
+use crate::data_transmission::resources::interface_counters::{
+    InterfaceCounters, read_interface_counters,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+use wallguard_common::os_if::InterfaceSnapshot;
+use wallguard_common::protobuf::wallguard_service::InterfaceStats;
+
+type LinkInfo = (Option<u64>, String);
+
+/// Turns the cumulative interface counters into per-sample statistics.
+#[derive(Debug, Default)]
+pub(crate) struct InterfaceStatsTracker {
+    previous: HashMap<String, InterfaceCounters>,
+    links: HashMap<String, LinkInfo>,
+    links_refreshed_at: Option<Instant>,
+}
+
+impl InterfaceStatsTracker {
+    /// Statistics of every interface since the previous sample; the counters
+    /// of the first sample of an interface are all zeros.
+    pub(crate) async fn sample(&mut self) -> HashMap<String, InterfaceStats> {
+        if self
+            .links_refreshed_at
+            .is_none_or(|at| at.elapsed() >= LINK_INFO_INTERVAL)
+        {
+            self.links_refreshed_at = Some(Instant::now());
+            self.refresh_links().await;
+        }
+
+        let mut stats = HashMap::new();
+        let mut current = HashMap::new();
+
+        for (name, counters) in read_interface_counters().await {
+            let delta = self
+                .previous
+                .get(&name)
+                .map(|previous| counters.since(previous))
+                .unwrap_or_default();
+
+            let (link_speed_mbps, oper_state) = self.links.get(&name).cloned().unwrap_or_default();
+
+            stats.insert(
+                name.clone(),
+                InterfaceStats {
+                    rx_bytes: delta.rx_bytes,
+                    tx_bytes: delta.tx_bytes,
+                    rx_packets: delta.rx_packets,
+                    tx_packets: delta.tx_packets,
+                    rx_errors: delta.rx_errors,
+                    tx_errors: delta.tx_errors,
+                    rx_drops: delta.rx_drops,
+                    tx_drops: delta.tx_drops,
+                    link_speed_mbps,
+                    oper_state,
+                },
+            );
+            current.insert(name, counters);
+        }
+
+        self.previous = current;
+        stats
+    }
+
+    async fn refresh_links(&mut self) {
+        let Ok(snapshots) = tokio::task::spawn_blocking(InterfaceSnapshot::take_all).await else {
+            return;
+        };
+
+        self.links = snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let oper_state = snapshot
+                    .oper_state
+                    .unwrap_or_else(|| String::from(if snapshot.is_up { "up" } else { "down" }));
+                (snapshot.name, (snapshot.link_speed_mbps, oper_state))
+            })
+            .collect();
+    }
+}
@@ -1 +1,5 @@
+pub(crate) mod firewall_health;
+pub(crate) mod interface_counters;
+pub(crate) mod interface_stats;
+pub(crate) mod process_snapshot;
 pub(crate) mod transmitter;
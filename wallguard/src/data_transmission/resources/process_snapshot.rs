@@ -0,0 +1,131 @@
+use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+use wallguard_common::protobuf::wallguard_commands::ProcessSnapshotSettings;
+use wallguard_common::protobuf::wallguard_service::{ProcessInfo, ProcessSnapshot};
+
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_TOP_N: usize = 50;
+
+/// Process snapshot settings last received from the server, shared with the resources transmitter.
+pub(crate) type ProcessSnapshotTap = Arc<Mutex<ProcessSnapshotSettings>>;
+
+/// Samples the processes using the most CPU and memory at the interval requested by the server.
+pub(crate) struct ProcessSampler {
+    settings: ProcessSnapshotTap,
+    system: System,
+    users: Users,
+    last_sample: Option<Instant>,
+}
+
+impl ProcessSampler {
+    pub(crate) fn new(settings: ProcessSnapshotTap) -> Self {
+        Self {
+            settings,
+            system: System::new(),
+            users: Users::new(),
+            last_sample: None,
+        }
+    }
+
+    /// Returns a snapshot once every configured interval, `None` otherwise or when disabled.
+    ///
+    /// The CPU usage of a process is averaged since the previous refresh,
+    /// so the first refresh after enabling only primes the counters.
+    /// Walking the process table blocks, so it runs on the blocking pool.
+    pub(crate) async fn sample(&mut self) -> Option<ProcessSnapshot> {
+        let Ok(settings) = self.settings.lock().map(|settings| *settings) else {
+            return None;
+        };
+
+        if !settings.enabled {
+            self.last_sample = None;
+            return None;
+        }
+
+        let interval = Duration::from_secs(settings.interval_seconds.into()).max(MIN_INTERVAL);
+        if self.last_sample.is_some_and(|at| at.elapsed() < interval) {
+            return None;
+        }
+
+        let primed = self.last_sample.is_some();
+        self.last_sample = Some(Instant::now());
+
+        let top_n = (settings.top_n as usize).clamp(1, MAX_TOP_N);
+        let mut system = std::mem::take(&mut self.system);
+        let mut users = std::mem::take(&mut self.users);
+
+        let sampled = tokio::task::spawn_blocking(move || {
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::All,
+                true,
+                ProcessRefreshKind::nothing()
+                    .with_cpu()
+                    .with_memory()
+                    .with_user(UpdateKind::OnlyIfNotSet),
+            );
+
+            let snapshot = primed.then(|| {
+                users.refresh();
+                top_processes(&system, &users, top_n)
+            });
+
+            (system, users, snapshot)
+        })
+        .await;
+
+        // If the sampling panicked the counters start over from the fresh defaults left behind
+        let Ok((system, users, snapshot)) = sampled else {
+            self.last_sample = None;
+            return None;
+        };
+
+        self.system = system;
+        self.users = users;
+        snapshot
+    }
+}
+
+fn top_processes(system: &System, users: &Users, top_n: usize) -> ProcessSnapshot {
+    let mut processes: Vec<&Process> = system.processes().values().collect();
+
+    processes.sort_by(|a, b| {
+        b.cpu_usage()
+            .partial_cmp(&a.cpu_usage())
+            .unwrap_or(Ordering::Equal)
+    });
+    let top_cpu = processes
+        .iter()
+        .take(top_n)
+        .map(|process| process_info(users, process))
+        .collect();
+
+    processes.sort_by_key(|process| std::cmp::Reverse(process.memory()));
+    let top_memory = processes
+        .iter()
+        .take(top_n)
+        .map(|process| process_info(users, process))
+        .collect();
+
+    ProcessSnapshot {
+        top_cpu,
+        top_memory,
+    }
+}
+
+fn process_info(users: &Users, process: &Process) -> ProcessInfo {
+    let user = process
+        .user_id()
+        .and_then(|uid| users.get_user_by_id(uid))
+        .map(|user| user.name().to_string())
+        .unwrap_or_default();
+
+    ProcessInfo {
+        pid: process.pid().as_u32(),
+        name: process.name().to_string_lossy().into_owned(),
+        user,
+        cpu_usage: process.cpu_usage(),
+        memory: process.memory(),
+    }
+}
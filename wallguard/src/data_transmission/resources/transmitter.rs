@@ -1,10 +1,16 @@
-use crate::constants::QUEUE_SIZE_RESOURCES;
+use crate::client_data::Platform;
+use crate::constants::{FIREWALL_HEALTH_INTERVAL, QUEUE_SIZE_RESOURCES};
 use crate::data_transmission::dump_dir::{DumpDir, DumpItem};
 use crate::data_transmission::item_buffer::ItemBuffer;
+use crate::data_transmission::resources::firewall_health::collect_firewall_health;
+use crate::data_transmission::resources::interface_stats::InterfaceStatsTracker;
+use crate::data_transmission::resources::process_snapshot::{ProcessSampler, ProcessSnapshotTap};
+use crate::metrics::{METRICS, Upload};
 use crate::token_provider::TokenProvider;
 use crate::wg_server::WGServer;
 use async_channel::Receiver;
 use chrono::Utc;
+use std::time::Instant;
 use wallguard_common::protobuf::wallguard_service::{SystemResource, SystemResourcesData};
 
 pub(crate) async fn transmit_system_resources(
@@ -12,9 +18,23 @@ pub(crate) async fn transmit_system_resources(
     token_provider: TokenProvider,
     dump_dir: DumpDir,
     client: WGServer,
+    platform: Platform,
+    process_snapshots: ProcessSnapshotTap,
 ) {
     let mut resources_queue = ItemBuffer::new(QUEUE_SIZE_RESOURCES);
+    let mut last_health: Option<Instant> = None;
+    let mut interface_stats = InterfaceStatsTracker::default();
+    let mut processes = ProcessSampler::new(process_snapshots);
     while let Ok(res) = rx.recv().await {
+        // firewall vitals are more expensive to collect: only sample them every few ticks
+        let firewall_health =
+            if last_health.is_none_or(|at| at.elapsed() >= FIREWALL_HEALTH_INTERVAL) {
+                last_health = Some(Instant::now());
+                collect_firewall_health(platform).await
+            } else {
+                None
+            };
+
         // create proper gRPC object including token and timestamp
         #[allow(clippy::cast_possible_wrap)]
         let resource = SystemResource {
@@ -35,26 +55,34 @@ pub(crate) async fn transmit_system_resources(
                     .filter_map(|(k, v)| v.map(|v| (k, v)))
                     .collect::<Vec<_>>()
             ),
+            firewall_health,
+            interfaces: interface_stats.sample().await,
+            processes: processes.sample().await,
         };
+        METRICS.record_resource(&resource);
         resources_queue.push(resource);
 
         if let Some(token) = token_provider.get().await {
-            let resources = SystemResourcesData {
-                token,
-                resources: resources_queue.get(..resources_queue.len()),
-            };
+            let resources = resources_queue.get(..resources_queue.len());
 
-            // send it to the server
-
-            if client
-                .handle_system_resources_data(resources)
-                .await
-                .is_err()
-            {
-                log::error!("Failed to send system resources");
-            } else {
-                resources_queue.drain(..resources_queue.len());
-                continue;
+            // send it to the server, keeping whatever it did not acknowledge
+            match client.stream_system_resources(&token, &resources).await {
+                Ok(stored) => {
+                    resources_queue.drain(..stored);
+                    if stored > 0 {
+                        METRICS.record_upload(Upload::Resources);
+                    }
+                    METRICS.set_queue_len(Upload::Resources, resources_queue.len());
+                    if resources_queue.is_empty() {
+                        continue;
+                    }
+                    log::error!("Server acknowledged only {stored} system resources");
+                }
+                Err(err) => {
+                    METRICS
+                        .record_error(format!("Failed to send system resources: {}", err.to_str()));
+                    log::error!("Failed to send system resources");
+                }
             }
         } else {
             log::error!("Faild to obtain a token");
@@ -71,18 +99,8 @@ pub(crate) async fn transmit_system_resources(
                 token: String::new(),
             });
             dump_dir.dump_item_to_file(dump_item).await;
-            if dump_dir.is_full().await {
-                log::warn!(
-                    "Dump size maximum limit reached. System resources routine entering idle mode...",
-                );
-                // wait for the server to come up again
-                loop {
-                    tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                    if client.is_connected().await {
-                        break;
-                    }
-                }
-            }
         }
+
+        METRICS.set_queue_len(Upload::Resources, resources_queue.len());
     }
 }
@@ -1,16 +1,21 @@
 use crate::client_data::Platform;
 use crate::constants::SNAPLEN;
+use crate::data_transmission::flow_export::{FlowExportTap, FlowExporter};
 use crate::data_transmission::grpc_handler::handle_connection_and_retransmission;
 use crate::data_transmission::packets::transmitter::transmit_packets;
+use crate::data_transmission::resources::process_snapshot::ProcessSnapshotTap;
 use crate::data_transmission::resources::transmitter::transmit_system_resources;
 use crate::data_transmission::sysconfig;
+use crate::metrics::{METRICS, Monitoring};
 use crate::netinfo::monitor_services;
 use crate::wg_server::WGServer;
 use crate::{data_transmission::dump_dir::DumpDir, token_provider::TokenProvider};
 use async_channel::Receiver;
+use nullnet_liberror::Error;
 use nullnet_libresmon::SystemResources;
 use nullnet_traffic_monitor::PacketInfo;
 use tokio::sync::broadcast;
+use wallguard_common::protobuf::wallguard_commands::{FlowExportSettings, ProcessSnapshotSettings};
 
 #[derive(Debug, Clone)]
 pub(crate) struct TransmissionManager {
@@ -18,6 +23,8 @@ pub(crate) struct TransmissionManager {
     resource_monitoring: Option<Receiver<SystemResources>>,
     sysconf_monitoring: Option<broadcast::Sender<()>>,
     services_monitoring: Option<broadcast::Sender<()>>,
+    flow_export: FlowExportTap,
+    process_snapshots: ProcessSnapshotTap,
 
     interface: WGServer,
     dump_dir: DumpDir,
@@ -42,6 +49,8 @@ impl TransmissionManager {
             resource_monitoring: None,
             sysconf_monitoring: None,
             services_monitoring: None,
+            flow_export: Default::default(),
+            process_snapshots: Default::default(),
 
             interface,
             dump_dir,
@@ -95,12 +104,14 @@ impl TransmissionManager {
         log::info!("Starting packet capture");
         let rx = nullnet_traffic_monitor::monitor_devices(&monitor_config);
         self.packet_capture = Some(rx.clone());
+        METRICS.set_monitoring(Monitoring::Traffic, true);
         let token = self.token_provider.clone();
         let dump_dir = self.dump_dir.clone();
         let interface = self.interface.clone();
         let batch_size = self.batch_size;
+        let flow_export = self.flow_export.clone();
         tokio::spawn(async move {
-            transmit_packets(rx, token, dump_dir, interface, batch_size).await;
+            transmit_packets(rx, token, dump_dir, interface, batch_size, flow_export).await;
         });
     }
 
@@ -117,11 +128,22 @@ impl TransmissionManager {
         log::info!("Starting resource monitoring");
         let rx = nullnet_libresmon::poll_system_resources(1000);
         self.resource_monitoring = Some(rx.clone());
+        METRICS.set_monitoring(Monitoring::Telemetry, true);
         let token_provider = self.token_provider.clone();
         let dump_dir = self.dump_dir.clone();
         let interface = self.interface.clone();
+        let platform = self.platform;
+        let process_snapshots = self.process_snapshots.clone();
         tokio::spawn(async move {
-            transmit_system_resources(rx, token_provider, dump_dir, interface).await;
+            transmit_system_resources(
+                rx,
+                token_provider,
+                dump_dir,
+                interface,
+                platform,
+                process_snapshots,
+            )
+            .await;
         });
     }
 
@@ -143,6 +165,7 @@ impl TransmissionManager {
         let receiver = terminate.subscribe();
 
         self.sysconf_monitoring = Some(terminate);
+        METRICS.set_monitoring(Monitoring::Configuration, true);
 
         tokio::spawn(async move {
             sysconfig::watch_sysconfig(
@@ -164,17 +187,57 @@ impl TransmissionManager {
 
         let interface = self.interface.clone();
         let token_provider = self.token_provider.clone();
+        let platform = self.platform;
         let mut receiver = terminate.subscribe();
         self.services_monitoring = Some(terminate);
+        METRICS.set_monitoring(Monitoring::Services, true);
 
         tokio::spawn(async move {
             tokio::select! {
                 _ = receiver.recv() => {},
-                _ = monitor_services(interface, token_provider) => {}
+                _ = monitor_services(interface, token_provider, platform) => {}
             }
         });
     }
 
+    /// Replaces the running flow exporter, if any, with one built from `settings`.
+    pub(crate) async fn configure_flow_export(
+        &self,
+        settings: &FlowExportSettings,
+    ) -> Result<(), Error> {
+        self.terminate_flow_export();
+
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        if !self.has_packet_capture() {
+            log::warn!("Flow export configured while traffic monitoring is disabled");
+        }
+
+        let exporter = FlowExporter::start(settings).await?;
+        *self.flow_export.lock().unwrap() = Some(exporter);
+
+        Ok(())
+    }
+
+    pub(crate) fn terminate_flow_export(&self) {
+        if let Some(exporter) = self.flow_export.lock().unwrap().take() {
+            exporter.terminate();
+        }
+    }
+
+    /// Applies the process snapshot settings from the next telemetry sample on.
+    pub(crate) fn configure_process_snapshots(&self, settings: ProcessSnapshotSettings) {
+        if settings.enabled && !self.has_resource_monitoring() {
+            log::warn!("Process snapshots configured while telemetry monitoring is disabled");
+        }
+
+        if let Ok(mut current) = self.process_snapshots.lock() {
+            *current = settings;
+        }
+    }
+
     pub(crate) fn terminate_packet_capture(&mut self) {
         let Some(rx) = &self.packet_capture else {
             return;
@@ -182,6 +245,7 @@ impl TransmissionManager {
         log::info!("Terminating packet capture");
         rx.close();
         self.packet_capture = None;
+        METRICS.set_monitoring(Monitoring::Traffic, false);
     }
 
     pub(crate) fn terminate_resource_monitoring(&mut self) {
@@ -191,6 +255,7 @@ impl TransmissionManager {
         log::info!("Terminating resource monitoring");
         rx.close();
         self.resource_monitoring = None;
+        METRICS.set_monitoring(Monitoring::Telemetry, false);
     }
 
     pub(crate) fn terminate_sysconfig_monitoring(&mut self) {
@@ -201,7 +266,8 @@ impl TransmissionManager {
         log::info!("Terminating sysconf monitoring");
         let _ = terminate.send(());
 
-        self.sysconf_monitoring = None
+        self.sysconf_monitoring = None;
+        METRICS.set_monitoring(Monitoring::Configuration, false);
     }
 
     pub(crate) fn terminate_services_monitoring(&mut self) {
@@ -212,6 +278,7 @@ impl TransmissionManager {
         log::info!("Terminating sysconf monitoring");
         let _ = terminate.send(());
 
-        self.services_monitoring = None
+        self.services_monitoring = None;
+        METRICS.set_monitoring(Monitoring::Services, false);
     }
 }
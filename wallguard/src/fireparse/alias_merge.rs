@@ -0,0 +1,65 @@
+use wallguard_common::protobuf::wallguard_models::Alias;
+use xmltree::{Element, XMLNode};
+
+/// Adds `alias` to the aliases node of a pfSense or OPNsense config, merging its values
+/// into the alias of the same name if there is one. Returns whether the node changed.
+///
+/// The values of an alias are held by its `field` element, joined with `separator`.
+/// Values are only ever added, never removed.
+pub(super) fn upsert(
+    aliases_node: &mut Element,
+    alias: Alias,
+    field: &str,
+    separator: &str,
+    to_element: impl FnOnce(Alias) -> Element,
+) -> bool {
+    let existing = aliases_node
+        .children
+        .iter_mut()
+        .filter_map(|node| node.as_mut_element())
+        .filter(|e| e.name == "alias")
+        .find(|e| {
+            e.get_child("name")
+                .and_then(|e| e.get_text())
+                .is_some_and(|name| name == alias.name)
+        });
+
+    let Some(existing) = existing else {
+        aliases_node
+            .children
+            .push(XMLNode::Element(to_element(alias)));
+        return true;
+    };
+
+    let mut values: Vec<String> = existing
+        .get_child(field)
+        .and_then(|e| e.get_text())
+        .map(|text| text.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let mut changed = false;
+    for value in alias
+        .value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        if !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+            changed = true;
+        }
+    }
+
+    if changed {
+        let mut value_elem = Element::new(field);
+        value_elem
+            .children
+            .push(XMLNode::Text(values.join(separator)));
+        existing
+            .children
+            .retain(|node| node.as_element().is_none_or(|e| e.name != field));
+        existing.children.push(XMLNode::Element(value_elem));
+    }
+
+    changed
+}
@@ -7,6 +7,7 @@ use crate::fireparse::nft::NftablesParser;
 use crate::fireparse::opnsense::OpnSenseParser;
 use crate::{client_data::Platform, fireparse::pfsense::PfSenseParser};
 
+mod alias_merge;
 mod nft;
 mod opnsense;
 mod pfsense;
@@ -75,6 +76,10 @@ impl Fireparse {
         }
     }
 
+    /// Creates the alias, or adds its values to the existing alias of the same name.
+    ///
+    /// Like `nft add set`, values already in the alias are kept: an alias is never
+    /// replaced, and creating one twice doesn't duplicate it.
     pub async fn create_alias(alias: Alias, platform: Platform) -> Result<(), Error> {
         match platform {
             Platform::Generic => Err("Unsupported platform").handle_err(location!()),
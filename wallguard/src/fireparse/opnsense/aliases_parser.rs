@@ -1,3 +1,4 @@
+use crate::fireparse::alias_merge;
 use wallguard_common::protobuf::wallguard_models::Alias;
 use xmltree::{Element, XMLNode};
 
@@ -84,11 +85,18 @@ impl OpnSenseAliasesParser {
 
         alias_elem
     }
+
+    /// Adds `alias` to the aliases node, merging its values into the alias of the same
+    /// name if there is one. Returns whether the node changed.
+    pub fn upsert(aliases_node: &mut Element, alias: Alias) -> bool {
+        alias_merge::upsert(aliases_node, alias, "content", "\n", Self::to_element)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::OpnSenseAliasesParser;
+    use wallguard_common::protobuf::wallguard_models::Alias;
     use xmltree::Element;
 
     #[test]
@@ -133,6 +141,50 @@ mod tests {
         assert_eq!(aliases[0].description, "NoProxy group");
     }
 
+    #[test]
+    fn test_upsert_merges_into_existing_alias() {
+        let xml = r#"
+        <opnsense>
+            <OPNsense>
+                <Firewall>
+                    <Alias>
+                        <aliases>
+                            <alias>
+                                <name>Threats</name>
+                                <type>host</type>
+                                <content>1.1.1.1
+2.2.2.2</content>
+                                <description>Threats</description>
+                            </alias>
+                        </aliases>
+                    </Alias>
+                </Firewall>
+            </OPNsense>
+        </opnsense>
+        "#;
+
+        let mut doc = Element::parse(xml.as_bytes()).expect("Failed to parse XML");
+        let alias = Alias {
+            r#type: "host".to_string(),
+            name: "Threats".to_string(),
+            value: "2.2.2.2,3.3.3.3".to_string(),
+            ..Default::default()
+        };
+
+        let node = doc
+            .get_mut_child("OPNsense")
+            .and_then(|el| el.get_mut_child("Firewall"))
+            .and_then(|el| el.get_mut_child("Alias"))
+            .and_then(|el| el.get_mut_child("aliases"))
+            .unwrap();
+        assert!(OpnSenseAliasesParser::upsert(node, alias.clone()));
+        assert!(!OpnSenseAliasesParser::upsert(node, alias));
+
+        let aliases = OpnSenseAliasesParser::parse(&doc);
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].value, "1.1.1.1,2.2.2.2,3.3.3.3");
+    }
+
     #[test]
     fn test_parse_empty_aliases() {
         let xml = r#"
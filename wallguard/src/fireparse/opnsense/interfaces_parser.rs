@@ -163,6 +163,8 @@ mod tests {
             ip_addresses: vec!["8.8.8.8".parse().unwrap(), "8.8.4.4".parse().unwrap()],
             subnet_mask: None,
             gateway: None,
+            link_speed_mbps: None,
+            oper_state: None,
         };
 
         let interfaces = OpnSenseInterfacesParser::parse(&doc, vec![iface_data]);
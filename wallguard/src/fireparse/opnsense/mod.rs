@@ -94,9 +94,8 @@ impl OpnSenseParser {
         system::reload_configuraion().await
     }
 
+    /// Creates the alias, or adds the new values to the existing alias of the same name.
     pub async fn create_alias(alias: Alias) -> Result<(), Error> {
-        let element = OpnSenseAliasesParser::to_element(alias);
-
         let content = tokio::fs::read("/conf/config.xml")
             .await
             .handle_err(location!())?;
@@ -111,9 +110,10 @@ impl OpnSenseParser {
             .ok_or("Malformed config.xml")
             .handle_err(location!())?;
 
-        aliases_node
-            .children
-            .push(xmltree::XMLNode::Element(element));
+        // Skip the write and the reload if the alias already holds every value
+        if !OpnSenseAliasesParser::upsert(aliases_node, alias) {
+            return Ok(());
+        }
 
         let mut buffer = Vec::new();
         document.write(&mut buffer).handle_err(location!())?;
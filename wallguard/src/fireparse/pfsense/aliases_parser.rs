@@ -1,3 +1,4 @@
+use crate::fireparse::alias_merge;
 use wallguard_common::protobuf::wallguard_models::Alias;
 use xmltree::{Element, XMLNode};
 
@@ -69,12 +70,12 @@ impl PfSenseAliasesParser {
         type_elem.children.push(XMLNode::Text(alias.r#type));
         alias_elem.children.push(XMLNode::Element(type_elem));
 
-        let mut content_elem = Element::new("content");
+        let mut address_elem = Element::new("address");
         let value = alias.value.split(",").collect::<Vec<&str>>().join(" ");
-        content_elem.children.push(XMLNode::Text(value));
-        alias_elem.children.push(XMLNode::Element(content_elem));
+        address_elem.children.push(XMLNode::Text(value));
+        alias_elem.children.push(XMLNode::Element(address_elem));
 
-        let mut description_elem = Element::new("description");
+        let mut description_elem = Element::new("descr");
         description_elem
             .children
             .push(XMLNode::Text(alias.description));
@@ -82,11 +83,18 @@ impl PfSenseAliasesParser {
 
         alias_elem
     }
+
+    /// Adds `alias` to the aliases node, merging its values into the alias of the same
+    /// name if there is one. Returns whether the node changed.
+    pub fn upsert(aliases_node: &mut Element, alias: Alias) -> bool {
+        alias_merge::upsert(aliases_node, alias, "address", " ", Self::to_element)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::PfSenseAliasesParser;
+    use wallguard_common::protobuf::wallguard_models::Alias;
     use xmltree::Element;
 
     #[test]
@@ -236,6 +244,55 @@ mod tests {
         assert_eq!(aliases[0].description, "");
     }
 
+    #[test]
+    fn test_upsert_merges_into_existing_alias() {
+        let xml = r#"
+        <pfsense>
+            <aliases>
+                <alias>
+                    <name>Threats</name>
+                    <type>host</type>
+                    <address>1.1.1.1 2.2.2.2</address>
+                    <descr><![CDATA[Description]]></descr>
+                </alias>
+            </aliases>
+        </pfsense>
+        "#;
+
+        let mut doc = Element::parse(xml.as_bytes()).expect("Failed to parse XML");
+        let alias = |name: &str, value: &str| Alias {
+            r#type: "host".to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        };
+
+        let node = doc.get_mut_child("aliases").unwrap();
+        assert!(PfSenseAliasesParser::upsert(
+            node,
+            alias("Threats", "2.2.2.2,3.3.3.3")
+        ));
+        assert!(!PfSenseAliasesParser::upsert(
+            node,
+            alias("Threats", "1.1.1.1")
+        ));
+        assert!(PfSenseAliasesParser::upsert(
+            node,
+            alias("Other", "4.4.4.4")
+        ));
+        assert!(PfSenseAliasesParser::upsert(
+            node,
+            alias("Other", "5.5.5.5")
+        ));
+
+        let aliases = PfSenseAliasesParser::parse(&doc);
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases[0].value, "1.1.1.1,2.2.2.2,3.3.3.3");
+        assert_eq!(aliases[0].description, "Description");
+        assert_eq!(aliases[1].name, "Other");
+        assert_eq!(aliases[1].value, "4.4.4.4,5.5.5.5");
+    }
+
     #[test]
     fn test_parse_empty_aliases() {
         let xml = r#"
@@ -173,6 +173,8 @@ mod tests {
             ],
             subnet_mask: None,
             gateway: None,
+            link_speed_mbps: None,
+            oper_state: None,
         };
 
         let interfaces = PfSenseInterfacesParser::parse(&doc, vec![iface_data]);
@@ -5,6 +5,9 @@ use crate::server_data::ServerData;
 use crate::storage::Storage;
 
 use clap::Parser as _;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::LazyLock;
+use std::time::Instant;
 
 mod arguments;
 mod client_data;
@@ -14,6 +17,7 @@ mod control_channel;
 mod daemon;
 mod data_transmission;
 mod fireparse;
+mod metrics;
 mod netinfo;
 mod pty;
 mod reverse_tunnel;
@@ -28,6 +32,8 @@ mod remote_desktop;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
 fn init_logger() {
     #[cfg(unix)]
     let log_dir = std::path::PathBuf::from("/var/log");
@@ -100,6 +106,8 @@ fn check_privileges() {
 
 #[tokio::main]
 async fn main() {
+    LazyLock::force(&STARTED_AT);
+
     rustls::crypto::ring::default_provider()
         .install_default()
         .expect("Failed to install rustls crypto provider");
@@ -121,6 +129,17 @@ async fn main() {
 
     Storage::init().await.unwrap();
 
+    if let Some(port) = arguments.metrics_port {
+        let host = arguments
+            .metrics_host
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve_metrics(SocketAddr::new(host, port)).await {
+                log::error!("Failed to serve metrics: {}", err.to_str());
+            }
+        });
+    }
+
     let Ok(server_data) = ServerData::try_from(&arguments) else {
         log::error!("Failed to collect server information. Exiting ...");
         std::process::exit(-1);
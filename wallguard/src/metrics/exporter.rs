@@ -0,0 +1,67 @@
+use crate::metrics::METRICS;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+const MAX_REQUEST_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the agent metrics on `GET /metrics` at `addr`.
+pub(crate) async fn serve_metrics(addr: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).await.handle_err(location!())?;
+
+    log::info!("Serving OpenMetrics on http://{addr}/metrics");
+
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+
+        tokio::spawn(async move {
+            if tokio::time::timeout(REQUEST_TIMEOUT, handle_request(stream))
+                .await
+                .is_err()
+            {
+                log::debug!("Metrics request timed out");
+            }
+        });
+    }
+}
+
+async fn handle_request(mut stream: TcpStream) {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+
+        if request.len() > MAX_REQUEST_SIZE {
+            return;
+        }
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = METRICS.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        _ => {
+            String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        }
+    };
+
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
@@ -0,0 +1,298 @@
+mod exporter;
+mod openmetrics;
+
+pub(crate) use exporter::serve_metrics;
+
+use openmetrics::MetricsWriter;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use wallguard_common::protobuf::wallguard_service::SystemResource;
+
+/// Health of the agent and latest telemetry, exported by the optional local OpenMetrics endpoint.
+pub(crate) static METRICS: LazyLock<AgentMetrics> = LazyLock::new(AgentMetrics::default);
+
+/// The streams of items uploaded to the server.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Upload {
+    Connections,
+    Resources,
+}
+
+impl Upload {
+    fn as_str(self) -> &'static str {
+        match self {
+            Upload::Connections => "connections",
+            Upload::Resources => "resources",
+        }
+    }
+}
+
+/// The monitoring features run by the transmission manager.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Monitoring {
+    Traffic = 1,
+    Telemetry = 1 << 1,
+    Configuration = 1 << 2,
+    Services = 1 << 3,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AgentMetrics {
+    connections_queue: AtomicU64,
+    resources_queue: AtomicU64,
+    last_connections_upload: AtomicU64,
+    last_resources_upload: AtomicU64,
+    spool_bytes: AtomicU64,
+    spool_evicted_files: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    capture_dropped_packets: AtomicU64,
+    /// Bits of the `Monitoring` features that are running.
+    monitoring: AtomicU8,
+    telemetry: Mutex<Telemetry>,
+    /// Latest error and when it happened, reported with the heartbeats.
+    last_error: Mutex<Option<(i64, String)>>,
+}
+
+#[derive(Debug, Default)]
+struct Telemetry {
+    latest: Option<SystemResource>,
+    /// Interface counters accumulated from the per-sample deltas that are sent upstream.
+    interfaces: HashMap<String, [u64; 4]>,
+}
+
+impl AgentMetrics {
+    /// Number of items waiting in memory to be uploaded.
+    pub(crate) fn set_queue_len(&self, upload: Upload, len: usize) {
+        let gauge = match upload {
+            Upload::Connections => &self.connections_queue,
+            Upload::Resources => &self.resources_queue,
+        };
+        gauge.store(len as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_upload(&self, upload: Upload) {
+        let last = match upload {
+            Upload::Connections => &self.last_connections_upload,
+            Upload::Resources => &self.last_resources_upload,
+        };
+        last.store(chrono::Utc::now().timestamp() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_spool_size(&self, bytes: u64) {
+        self.spool_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_spool_evictions(&self, files: u64) {
+        self.spool_evicted_files.fetch_add(files, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_reconnect_attempt(&self) {
+        self.reconnect_attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_capture_drops(&self, packets: u64) {
+        self.capture_dropped_packets
+            .fetch_add(packets, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_monitoring(&self, monitoring: Monitoring, running: bool) {
+        if running {
+            self.monitoring
+                .fetch_or(monitoring as u8, Ordering::Relaxed);
+        } else {
+            self.monitoring
+                .fetch_and(!(monitoring as u8), Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn is_monitoring(&self, monitoring: Monitoring) -> bool {
+        self.monitoring.load(Ordering::Relaxed) & monitoring as u8 != 0
+    }
+
+    pub(crate) fn record_error(&self, error: impl Into<String>) {
+        if let Ok(mut last_error) = self.last_error.lock() {
+            *last_error = Some((chrono::Utc::now().timestamp(), error.into()));
+        }
+    }
+
+    pub(crate) fn last_error(&self) -> Option<(i64, String)> {
+        self.last_error.lock().ok()?.clone()
+    }
+
+    /// Items waiting in memory to be uploaded, across all streams.
+    pub(crate) fn queued_items(&self) -> u64 {
+        self.connections_queue.load(Ordering::Relaxed)
+            + self.resources_queue.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn spool_bytes(&self) -> u64 {
+        self.spool_bytes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_resource(&self, resource: &SystemResource) {
+        let Ok(mut telemetry) = self.telemetry.lock() else {
+            return;
+        };
+
+        for (name, stats) in &resource.interfaces {
+            let totals = telemetry.interfaces.entry(name.clone()).or_default();
+            totals[0] += stats.rx_bytes;
+            totals[1] += stats.tx_bytes;
+            totals[2] += stats.rx_packets;
+            totals[3] += stats.tx_packets;
+        }
+
+        telemetry.latest = Some(resource.clone());
+    }
+
+    /// Renders every metric in the OpenMetrics text format.
+    pub(crate) fn render(&self) -> String {
+        let mut writer = MetricsWriter::default();
+        let load = |value: &AtomicU64| value.load(Ordering::Relaxed) as f64;
+
+        writer.gauge(
+            "wallguard_agent_queue_items",
+            "Items waiting in memory to be uploaded",
+            &[
+                (
+                    vec![("stream", Upload::Connections.as_str())],
+                    load(&self.connections_queue),
+                ),
+                (
+                    vec![("stream", Upload::Resources.as_str())],
+                    load(&self.resources_queue),
+                ),
+            ],
+        );
+        writer.gauge(
+            "wallguard_agent_last_upload_timestamp_seconds",
+            "Time of the last successful upload, 0 if none",
+            &[
+                (
+                    vec![("stream", Upload::Connections.as_str())],
+                    load(&self.last_connections_upload),
+                ),
+                (
+                    vec![("stream", Upload::Resources.as_str())],
+                    load(&self.last_resources_upload),
+                ),
+            ],
+        );
+        writer.gauge(
+            "wallguard_agent_spool_bytes",
+            "Size of the on-disk spool of undelivered items",
+            &[(vec![], load(&self.spool_bytes))],
+        );
+        writer.counter(
+            "wallguard_agent_spool_evicted_files",
+            "Spool files dropped to stay within the size limit",
+            &[(vec![], load(&self.spool_evicted_files))],
+        );
+        writer.counter(
+            "wallguard_agent_reconnect_attempts",
+            "Attempts to reconnect to the server",
+            &[(vec![], load(&self.reconnect_attempts))],
+        );
+        writer.counter(
+            "wallguard_agent_capture_dropped_packets",
+            "Packets dropped by the kernel or the interface during capture sessions",
+            &[(vec![], load(&self.capture_dropped_packets))],
+        );
+
+        if let Ok(telemetry) = self.telemetry.lock() {
+            render_telemetry(&mut writer, &telemetry);
+        }
+
+        writer.finish()
+    }
+}
+
+fn render_telemetry(writer: &mut MetricsWriter, telemetry: &Telemetry) {
+    let Some(res) = &telemetry.latest else {
+        return;
+    };
+
+    let scalar = |value: f64| [(vec![], value)];
+
+    writer.gauge(
+        "wallguard_cpus",
+        "Number of CPUs",
+        &scalar(res.num_cpus as f64),
+    );
+    writer.gauge(
+        "wallguard_cpu_usage_percent",
+        "Global CPU usage",
+        &scalar(f64::from(res.global_cpu_usage)),
+    );
+    writer.gauge(
+        "wallguard_memory_total_bytes",
+        "Total memory",
+        &scalar(res.total_memory as f64),
+    );
+    writer.gauge(
+        "wallguard_memory_used_bytes",
+        "Used memory",
+        &scalar(res.used_memory as f64),
+    );
+    writer.gauge(
+        "wallguard_disk_total_bytes",
+        "Total disk space",
+        &scalar(res.total_disk_space as f64),
+    );
+    writer.gauge(
+        "wallguard_disk_available_bytes",
+        "Available disk space",
+        &scalar(res.available_disk_space as f64),
+    );
+    writer.gauge(
+        "wallguard_disk_read_bytes",
+        "Bytes read from disk during the last sample",
+        &scalar(res.read_bytes as f64),
+    );
+    writer.gauge(
+        "wallguard_disk_written_bytes",
+        "Bytes written to disk during the last sample",
+        &scalar(res.written_bytes as f64),
+    );
+
+    if let Some(health) = &res.firewall_health {
+        writer.gauge(
+            "wallguard_firewall_states",
+            "Entries of the state table",
+            &scalar(health.states as f64),
+        );
+        writer.gauge(
+            "wallguard_firewall_states_limit",
+            "Size of the state table",
+            &scalar(health.states_limit as f64),
+        );
+    }
+
+    let mut names: Vec<&String> = telemetry.interfaces.keys().collect();
+    names.sort();
+
+    for (index, (name, help)) in [
+        ("wallguard_interface_receive_bytes", "Bytes received"),
+        ("wallguard_interface_transmit_bytes", "Bytes transmitted"),
+        ("wallguard_interface_receive_packets", "Packets received"),
+        (
+            "wallguard_interface_transmit_packets",
+            "Packets transmitted",
+        ),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let samples: Vec<_> = names
+            .iter()
+            .map(|interface| {
+                (
+                    vec![("interface", interface.as_str())],
+                    telemetry.interfaces[*interface][index] as f64,
+                )
+            })
+            .collect();
+        writer.counter(name, help, &samples);
+    }
+}
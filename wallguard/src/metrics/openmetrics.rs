@@ -0,0 +1,73 @@
+use std::fmt::Write as _;
+
+pub(super) type Sample<'a> = (Vec<(&'a str, &'a str)>, f64);
+
+/// Builds a document in the OpenMetrics text exposition format.
+#[derive(Debug, Default)]
+pub(super) struct MetricsWriter {
+    output: String,
+}
+
+impl MetricsWriter {
+    pub(super) fn gauge(&mut self, name: &str, help: &str, samples: &[Sample]) {
+        self.family(name, "gauge", help, "", samples);
+    }
+
+    /// Counter samples carry the mandatory `_total` suffix.
+    pub(super) fn counter(&mut self, name: &str, help: &str, samples: &[Sample]) {
+        self.family(name, "counter", help, "_total", samples);
+    }
+
+    pub(super) fn finish(mut self) -> String {
+        self.output.push_str("# EOF\n");
+        self.output
+    }
+
+    fn family(&mut self, name: &str, kind: &str, help: &str, suffix: &str, samples: &[Sample]) {
+        let _ = writeln!(self.output, "# TYPE {name} {kind}");
+        let _ = writeln!(self.output, "# HELP {name} {help}");
+
+        for (labels, value) in samples {
+            self.output.push_str(name);
+            self.output.push_str(suffix);
+
+            if !labels.is_empty() {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(key, value)| format!("{key}=\"{}\"", escape(value)))
+                    .collect();
+                let _ = write!(self.output, "{{{}}}", labels.join(","));
+            }
+
+            let _ = writeln!(self.output, " {value}");
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openmetrics_format() {
+        let mut writer = MetricsWriter::default();
+        writer.gauge(
+            "queue_items",
+            "Queued items",
+            &[(vec![("stream", "conn\"s")], 3.0)],
+        );
+        writer.counter("reconnects", "Reconnects", &[(vec![], 2.0)]);
+
+        assert_eq!(
+            writer.finish(),
+            "# TYPE queue_items gauge\n# HELP queue_items Queued items\nqueue_items{stream=\"conn\\\"s\"} 3\n# TYPE reconnects counter\n# HELP reconnects Reconnects\nreconnects_total 2\n# EOF\n"
+        );
+    }
+}
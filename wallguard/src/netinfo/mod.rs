@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use nullnet_liberror::{ErrorHandler, Location, location};
 
-use crate::{token_provider::TokenProvider, wg_server::WGServer};
+use crate::{client_data::Platform, token_provider::TokenProvider, wg_server::WGServer};
 use wallguard_common::protobuf::wallguard_service::ServicesMessage;
 
 mod service;
@@ -14,12 +14,16 @@ const TIME_INTERVAL: Duration = Duration::from_secs(60);
 // time we retry) — instead we back off briefly and re-scan for a fresh one.
 const RETRY_INTERVAL: Duration = Duration::from_secs(5);
 
-pub async fn monitor_services(interface: WGServer, token_provider: TokenProvider) {
+pub async fn monitor_services(
+    interface: WGServer,
+    token_provider: TokenProvider,
+    platform: Platform,
+) {
     log::info!("Staring services monitoring ...");
 
     loop {
         let sockets = sock::get_sockets_info().await;
-        let services = service::gather_info(sockets).await;
+        let services = service::gather_info(sockets, platform).await;
 
         if !services.is_empty() {
             let token = match token_provider
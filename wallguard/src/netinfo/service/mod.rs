@@ -1,3 +1,4 @@
+use crate::client_data::Platform;
 use crate::netinfo::sock::SocketInfo;
 use std::net::SocketAddr;
 use wallguard_common::protobuf::wallguard_service::{
@@ -6,6 +7,7 @@ use wallguard_common::protobuf::wallguard_service::{
 
 mod http;
 mod pseudo;
+mod pseudo_capture;
 mod pseudo_rd;
 mod ssh;
 
@@ -16,6 +18,7 @@ pub enum Protocol {
     Ssh,
     Tty,
     RemoteDesktop,
+    Capture,
 }
 
 #[derive(Debug)]
@@ -34,6 +37,7 @@ impl From<ServiceInfo> for ServiceInfoGrpc {
                 Protocol::Ssh => ProtocolGrpc::Ssh.into(),
                 Protocol::Tty => ProtocolGrpc::Tty.into(),
                 Protocol::RemoteDesktop => ProtocolGrpc::Rd.into(),
+                Protocol::Capture => ProtocolGrpc::Capture.into(),
             },
             program: val.program,
             address: val.addr.ip().to_string(),
@@ -42,13 +46,18 @@ impl From<ServiceInfo> for ServiceInfoGrpc {
     }
 }
 
-pub async fn gather_info(mut sockets: Vec<SocketInfo>) -> Vec<ServiceInfo> {
+pub async fn gather_info(mut sockets: Vec<SocketInfo>, platform: Platform) -> Vec<ServiceInfo> {
     let mut retval = vec![];
 
     retval.extend(http::filter(&mut sockets).await);
     retval.extend(ssh::filter(&mut sockets).await);
     retval.extend(pseudo::filter(&mut sockets));
 
+    // The agent refuses capture sessions where it cannot monitor traffic
+    if platform.can_monitor_traffic() {
+        retval.extend(pseudo_capture::filter(&mut sockets));
+    }
+
     // pseudo_rd performs its own live check (tries Enigo::new) so it
     // naturally reports nothing when no user session is active.
     retval.extend(pseudo_rd::filter(&mut sockets));
@@ -0,0 +1,118 @@
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::collections::BTreeMap;
+use wallguard_common::protobuf::wallguard_rd::{ControlState, Role};
+
+/// Decides which session may send input: at most one, holding control.
+#[derive(Debug, Default)]
+pub struct ControlArbiter {
+    /// Role of every connected session, by ID
+    roles: BTreeMap<u32, Role>,
+    holder: Option<u32>,
+}
+
+impl ControlArbiter {
+    /// Registers a session; a controller takes control if nobody holds it.
+    pub fn join(&mut self, session: u32, role: Role) {
+        self.roles.insert(session, role);
+
+        if role == Role::Controller && self.holder.is_none() {
+            self.holder = Some(session);
+        }
+    }
+
+    /// Unregisters a session, releasing control if it held it.
+    pub fn leave(&mut self, session: u32) {
+        self.roles.remove(&session);
+        self.release(session);
+    }
+
+    /// Gives control to `session` right away if nobody holds it, otherwise
+    /// returns the session to ask for it.
+    pub fn request(&mut self, session: u32) -> Result<Option<u32>, Error> {
+        self.ensure_controller(session)?;
+
+        match self.holder {
+            Some(holder) if holder != session => Ok(Some(holder)),
+            _ => {
+                self.holder = Some(session);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Hands control from `from`, which must hold it, to `to`.
+    pub fn grant(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        if self.holder != Some(from) {
+            return Err(format!("Session {from} does not hold control")).handle_err(location!());
+        }
+
+        self.ensure_controller(to)?;
+        self.holder = Some(to);
+        Ok(())
+    }
+
+    /// Returns whether `session` held control.
+    pub fn release(&mut self, session: u32) -> bool {
+        if self.holder == Some(session) {
+            self.holder = None;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn has_control(&self, session: u32) -> bool {
+        self.holder == Some(session)
+    }
+
+    /// What each session is told about control.
+    pub fn states(&self) -> Vec<ControlState> {
+        self.roles
+            .iter()
+            .map(|(session, role)| ControlState {
+                session: *session,
+                role: (*role).into(),
+                holder: self.holder,
+            })
+            .collect()
+    }
+
+    fn ensure_controller(&self, session: u32) -> Result<(), Error> {
+        match self.roles.get(&session) {
+            Some(Role::Controller) => Ok(()),
+            Some(_) => Err(format!("Session {session} is view-only")).handle_err(location!()),
+            None => Err(format!("No session with ID {session}")).handle_err(location!()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_handoff() {
+        let mut arbiter = ControlArbiter::default();
+        arbiter.join(0, Role::ViewOnly);
+        arbiter.join(1, Role::Controller);
+        arbiter.join(2, Role::Controller);
+
+        // The first controller takes control, view-only sessions can never get it
+        assert!(arbiter.has_control(1));
+        assert!(arbiter.request(0).is_err());
+        assert!(arbiter.grant(1, 0).is_err());
+
+        // Requests go to the holder, which hands control over
+        assert_eq!(arbiter.request(2).unwrap(), Some(1));
+        assert!(arbiter.grant(2, 1).is_err());
+        arbiter.grant(1, 2).unwrap();
+        assert!(arbiter.has_control(2));
+        assert!(!arbiter.has_control(1));
+
+        // Once the holder leaves, control is free to take
+        arbiter.leave(2);
+        assert_eq!(arbiter.request(1).unwrap(), None);
+        assert!(arbiter.has_control(1));
+        assert_eq!(arbiter.states().len(), 2);
+    }
+}
@@ -1,23 +1,123 @@
+use super::SessionOptions;
+use super::rate_control::{Level, RateController};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
-use std::time::Duration;
+use std::time::Instant;
 use tokio::sync::mpsc;
-use wallguard_common::timestamped_packet::TimestampedPacket;
+use wallguard_common::protobuf::wallguard_rd::{AgentMessage, Quality, VideoFrame, agent_message};
+use wallguard_common::rd_protocol;
+
+/// A viewer waiting for a keyframe gets one once its channel is at most this full.
+const RESUME_OCCUPANCY: f32 = 0.5;
 
 #[derive(Debug)]
 pub struct Client {
     channel: mpsc::Sender<Vec<u8>>,
+    pub options: SessionOptions,
+    /// Encoding settings adapted to the link of this viewer
+    rate: RateController,
+    /// Set when the viewer joins, switches level or misses a message:
+    /// frames are skipped until the next keyframe
+    awaiting_keyframe: bool,
+    /// Downscale of the frames the viewer is looking at, to map its input back
+    downscale: usize,
 }
 
 impl Client {
-    pub fn new(channel: mpsc::Sender<Vec<u8>>) -> Self {
-        Self { channel }
+    pub fn new(channel: mpsc::Sender<Vec<u8>>, options: SessionOptions) -> Self {
+        let rate = RateController::new(Quality::Balanced);
+        let downscale = rate.level().downscale;
+
+        Self {
+            channel,
+            options,
+            rate,
+            awaiting_keyframe: true,
+            downscale,
+        }
+    }
+
+    /// Queues a message without waiting, so a slow viewer never holds up the others.
+    ///
+    /// A message that doesn't fit is dropped and the viewer is brought back
+    /// in sync with a keyframe once its channel drains.
+    pub fn send(&mut self, message: agent_message::Message) -> Result<(), Error> {
+        let message = AgentMessage {
+            message: Some(message),
+        };
+
+        let result = self
+            .channel
+            .try_send(rd_protocol::encode_framed(&message))
+            .handle_err(location!());
+
+        if result.is_err() {
+            self.awaiting_keyframe = true;
+        }
+
+        result
+    }
+
+    /// Queues a frame of the level the viewer is at, returning whether it was
+    /// the keyframe the viewer was waiting for.
+    pub fn send_frame(&mut self, frame: &VideoFrame, now: Instant) -> bool {
+        // Deltas are useless until the decoder has a keyframe to start from
+        if self.awaiting_keyframe && !frame.keyframe {
+            return false;
+        }
+
+        let level = self.rate.level();
+        let resumed = self.awaiting_keyframe;
+        let sent = self
+            .send(agent_message::Message::VideoFrame(frame.clone()))
+            .is_ok();
+
+        if sent {
+            self.downscale = level.downscale;
+            self.awaiting_keyframe = false;
+        }
+
+        let occupancy = if sent { self.occupancy() } else { 1.0 };
+        if self.rate.on_frame_sent(occupancy, now) {
+            log::debug!(
+                "Remote desktop: viewer moved to {} FPS, {} kbit/s, downscaled by {}",
+                self.rate.level().fps,
+                self.rate.level().bitrate / 1000,
+                self.rate.level().downscale
+            );
+
+            // The frames of the new level come from another encoder
+            self.awaiting_keyframe = true;
+            return false;
+        }
+
+        sent && resumed
+    }
+
+    pub fn level(&self) -> Level {
+        self.rate.level()
+    }
+
+    pub fn downscale(&self) -> usize {
+        self.downscale
+    }
+
+    /// Applies a preset requested by the viewer, switching to its best level.
+    pub fn set_quality(&mut self, quality: Quality, now: Instant) {
+        self.rate.set_quality(quality, now);
+        self.awaiting_keyframe = true;
+    }
+
+    pub fn request_keyframe(&mut self) {
+        self.awaiting_keyframe = true;
+    }
+
+    /// Whether the encoder of the viewer's level should send a keyframe for it.
+    pub fn wants_keyframe(&self) -> bool {
+        self.awaiting_keyframe && self.occupancy() <= RESUME_OCCUPANCY
     }
 
-    pub async fn send(&self, data: Vec<u8>, duration: Duration) -> Result<(), Error> {
-        let packet = TimestampedPacket::new(duration, data);
-        self.channel
-            .send(packet.to_bytes())
-            .await
-            .handle_err(location!())
+    /// How full the channel to the client is, between 0 and 1.
+    fn occupancy(&self) -> f32 {
+        1.0 - self.channel.capacity() as f32 / self.channel.max_capacity() as f32
     }
 }
@@ -0,0 +1,394 @@
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Clipboard text larger than this is not synced, in either direction.
+pub const MAX_CLIPBOARD_SIZE: usize = 256 * 1024;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The last text that went through the clipboard sync, in either direction.
+///
+/// Remembering it keeps a paste from the viewer from being echoed back to it
+/// once the watcher sees the text appear on the host.
+#[derive(Debug, Clone, Default)]
+pub struct LastSynced(Arc<Mutex<Option<String>>>);
+
+impl LastSynced {
+    /// Stores `text`, returning whether it differs from the previous one.
+    pub fn replace(&self, text: &str) -> bool {
+        let Ok(mut last) = self.0.lock() else {
+            return true;
+        };
+
+        if last.as_deref() == Some(text) {
+            return false;
+        }
+
+        *last = Some(text.to_string());
+        true
+    }
+}
+
+/// Watches the host clipboard on a dedicated thread, sending its text every
+/// time it changes. The thread stops once `sender` is closed.
+pub fn watch_host_clipboard(sender: mpsc::Sender<String>) {
+    std::thread::spawn(move || {
+        if let Err(err) = watch(sender) {
+            log::warn!("Host clipboard watcher stopped: {}", err.to_str());
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn watch(sender: mpsc::Sender<String>) -> Result<(), Error> {
+    use crate::client_data::platform::has_wayland_display;
+
+    // Like screen capture, prefer the native Wayland protocol when available:
+    // it works without XWayland and without the agent ever owning the focus.
+    if has_wayland_display() {
+        match wayland::DataControlWatcher::new() {
+            Ok(watcher) => {
+                log::info!("Clipboard: using Wayland (wlr-data-control) backend");
+                return watcher.run(sender);
+            }
+            Err(e) => log::debug!(
+                "Wayland clipboard unavailable ({}); falling back to X11",
+                e.to_str()
+            ),
+        }
+    }
+
+    poll_clipboard(sender)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch(sender: mpsc::Sender<String>) -> Result<(), Error> {
+    poll_clipboard(sender)
+}
+
+/// Polls the CLIPBOARD selection on X11 and the clipboard on Windows.
+fn poll_clipboard(sender: mpsc::Sender<String>) -> Result<(), Error> {
+    use copypasta::{ClipboardContext, ClipboardProvider};
+
+    let mut ctx = ClipboardContext::new().handle_err(location!())?;
+
+    // Whatever was copied before the session started is not sent
+    let mut last = ctx.get_contents().ok();
+
+    while !sender.is_closed() {
+        std::thread::sleep(POLL_INTERVAL);
+
+        // Fails when the clipboard is empty or does not hold text
+        let Ok(text) = ctx.get_contents() else {
+            continue;
+        };
+
+        if last.as_ref() != Some(&text) {
+            last = Some(text.clone());
+            let _ = sender.blocking_send(text);
+        }
+    }
+
+    Ok(())
+}
+
+// ── Wayland / wlr-data-control (Linux) ───────────────────────────────────────
+//
+// Implements `zwlr-data-control-unstable-v1` directly with `wayland-client` +
+// `wayland-protocols-wlr`, which lets a client that is not focused follow the
+// selection.  Supported by sway, hyprland, labwc, river, wayfire and KDE
+// Plasma 6; other sessions fall through to X11 via XWayland.
+
+#[cfg(target_os = "linux")]
+mod wayland {
+    use super::{MAX_CLIPBOARD_SIZE, POLL_INTERVAL};
+    use nix::errno::Errno;
+    use nix::fcntl::{FcntlArg, OFlag, fcntl};
+    use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+    use nullnet_liberror::{Error, ErrorHandler, Location, location};
+    use std::os::fd::AsFd;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc;
+    use wayland_client::{
+        Connection, Dispatch, EventQueue, Proxy, QueueHandle, event_created_child,
+        protocol::{wl_registry, wl_seat},
+    };
+    use wayland_protocols_wlr::data_control::v1::client::{
+        zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+        zwlr_data_control_manager_v1::{self, ZwlrDataControlManagerV1},
+        zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+    };
+
+    /// How long the source of a selection has to write its text.
+    const OFFER_READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Text formats, most preferred first.
+    const TEXT_MIME_TYPES: [&str; 4] = [
+        "text/plain;charset=utf-8",
+        "UTF8_STRING",
+        "text/plain",
+        "STRING",
+    ];
+
+    /// The MIME types advertised by an offer.
+    type MimeTypes = Mutex<Vec<String>>;
+
+    #[derive(Default)]
+    struct State {
+        seat: Option<wl_seat::WlSeat>,
+        manager: Option<ZwlrDataControlManagerV1>,
+
+        /// Offer of the latest selection, not read yet.
+        selection: Option<ZwlrDataControlOfferV1>,
+        /// Set when the compositor invalidates the data device.
+        finished: bool,
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, ()> for State {
+        fn event(
+            state: &mut Self,
+            registry: &wl_registry::WlRegistry,
+            event: wl_registry::Event,
+            _: &(),
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+        ) {
+            let wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } = event
+            else {
+                return;
+            };
+            match interface.as_str() {
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, version.min(1), qh, ()));
+                }
+                "zwlr_data_control_manager_v1" => {
+                    state.manager = Some(registry.bind(name, version.min(2), qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+        fn event(
+            state: &mut Self,
+            _: &ZwlrDataControlDeviceV1,
+            event: zwlr_data_control_device_v1::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            match event {
+                zwlr_data_control_device_v1::Event::Selection { id } => {
+                    if let Some(previous) = state.selection.take() {
+                        previous.destroy();
+                    }
+                    state.selection = id;
+                }
+                // Only the CLIPBOARD selection is synced
+                zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                    offer.destroy();
+                }
+                zwlr_data_control_device_v1::Event::Finished => state.finished = true,
+                _ => {}
+            }
+        }
+
+        event_created_child!(State, ZwlrDataControlDeviceV1, [
+            zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, MimeTypes::default()),
+        ]);
+    }
+
+    impl Dispatch<ZwlrDataControlOfferV1, MimeTypes> for State {
+        fn event(
+            _: &mut Self,
+            _: &ZwlrDataControlOfferV1,
+            event: zwlr_data_control_offer_v1::Event,
+            mime_types: &MimeTypes,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event
+                && let Ok(mut mime_types) = mime_types.lock()
+            {
+                mime_types.push(mime_type);
+            }
+        }
+    }
+
+    // No-op dispatches for objects whose events we don't need.
+    macro_rules! noop_dispatch {
+        ($iface:ty, $ev:ty) => {
+            impl Dispatch<$iface, ()> for State {
+                fn event(
+                    _: &mut Self,
+                    _: &$iface,
+                    _: $ev,
+                    _: &(),
+                    _: &Connection,
+                    _: &QueueHandle<Self>,
+                ) {
+                }
+            }
+        };
+    }
+    noop_dispatch!(wl_seat::WlSeat, wl_seat::Event);
+    noop_dispatch!(
+        ZwlrDataControlManagerV1,
+        zwlr_data_control_manager_v1::Event
+    );
+
+    // ── DataControlWatcher ────────────────────────────────────────────────────
+
+    pub struct DataControlWatcher {
+        conn: Connection,
+        event_queue: EventQueue<State>,
+        state: State,
+    }
+
+    impl DataControlWatcher {
+        pub fn new() -> Result<Self, Error> {
+            let conn = Connection::connect_to_env().handle_err(location!())?;
+            let mut event_queue = conn.new_event_queue::<State>();
+            let qh = event_queue.handle();
+
+            let mut state = State::default();
+            let _registry = conn.display().get_registry(&qh, ());
+
+            event_queue.roundtrip(&mut state).handle_err(location!())?;
+
+            let (Some(seat), Some(manager)) = (&state.seat, &state.manager) else {
+                return Err("compositor missing required globals \
+                     (wl_seat or zwlr_data_control_manager_v1) — \
+                     wlr-data-control may not be supported")
+                .handle_err(location!());
+            };
+
+            let _device = manager.get_data_device(seat, &qh, ());
+
+            // The compositor answers with the current selection right away:
+            // whatever was copied before the session started is not sent.
+            event_queue.roundtrip(&mut state).handle_err(location!())?;
+            if let Some(offer) = state.selection.take() {
+                offer.destroy();
+            }
+
+            Ok(Self {
+                conn,
+                event_queue,
+                state,
+            })
+        }
+
+        pub fn run(mut self, sender: mpsc::Sender<String>) -> Result<(), Error> {
+            while !sender.is_closed() {
+                if self.state.finished {
+                    return Err("data control device invalidated by the compositor")
+                        .handle_err(location!());
+                }
+
+                self.wait_for_events()?;
+
+                if let Some(offer) = self.state.selection.take() {
+                    let text = self.read_offer(&offer);
+                    offer.destroy();
+
+                    if let Some(text) = text {
+                        let _ = sender.blocking_send(text);
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Dispatches incoming events, waiting for them at most `POLL_INTERVAL`
+        /// so that the end of the session is noticed.
+        fn wait_for_events(&mut self) -> Result<(), Error> {
+            self.event_queue.flush().handle_err(location!())?;
+
+            if let Some(guard) = self.event_queue.prepare_read() {
+                let ready = {
+                    let mut fds = [PollFd::new(guard.connection_fd(), PollFlags::POLLIN)];
+                    let timeout = PollTimeout::try_from(POLL_INTERVAL).handle_err(location!())?;
+                    poll(&mut fds, timeout).handle_err(location!())?
+                };
+
+                // Dropping the guard without reading cancels the read
+                if ready > 0 {
+                    guard.read().handle_err(location!())?;
+                }
+            }
+
+            self.event_queue
+                .dispatch_pending(&mut self.state)
+                .handle_err(location!())?;
+
+            Ok(())
+        }
+
+        /// Reads the text of an offer, if it has any within the size limit.
+        ///
+        /// The source writes the text at its own pace: the pipe is read without blocking
+        /// and given up on after `OFFER_READ_TIMEOUT`, so that a stalled source cannot
+        /// hang the watcher.
+        fn read_offer(&self, offer: &ZwlrDataControlOfferV1) -> Option<String> {
+            let mime_types = offer.data::<MimeTypes>()?.lock().ok()?.clone();
+            let mime_type = TEXT_MIME_TYPES
+                .into_iter()
+                .find(|mime_type| mime_types.iter().any(|offered| offered == mime_type))?;
+
+            let (reader, writer) = nix::unistd::pipe().ok()?;
+            offer.receive(mime_type.to_string(), writer.as_fd());
+            self.conn.flush().ok()?;
+
+            // Close our end of the pipe, so that the read ends with the source's
+            drop(writer);
+
+            // Only our end: the write end is shared with the source
+            fcntl(&reader, FcntlArg::F_SETFL(OFlag::O_NONBLOCK)).ok()?;
+
+            let deadline = Instant::now() + OFFER_READ_TIMEOUT;
+            let mut text = Vec::new();
+            let mut buffer = [0u8; 8192];
+
+            loop {
+                let Some(remaining) = deadline
+                    .checked_duration_since(Instant::now())
+                    .filter(|remaining| !remaining.is_zero())
+                else {
+                    log::warn!("Timed out reading the host clipboard, not synced");
+                    return None;
+                };
+
+                let mut fds = [PollFd::new(reader.as_fd(), PollFlags::POLLIN)];
+                match poll(&mut fds, PollTimeout::try_from(remaining).ok()?) {
+                    Ok(0) | Err(Errno::EINTR) => continue,
+                    Ok(_) => {}
+                    Err(_) => return None,
+                }
+
+                match nix::unistd::read(&reader, &mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => text.extend_from_slice(&buffer[..read]),
+                    Err(Errno::EAGAIN | Errno::EINTR) => continue,
+                    Err(_) => return None,
+                }
+
+                if text.len() > MAX_CLIPBOARD_SIZE {
+                    log::warn!("Host clipboard exceeds {MAX_CLIPBOARD_SIZE} bytes, not synced");
+                    return None;
+                }
+            }
+
+            String::from_utf8(text).ok()
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::display::InputMapping;
+use wallguard_common::protobuf::wallguard_rd::{CursorShape, CursorUpdate};
+
+/// The pointer of the host, as read by a capture backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    /// Position of the hotspot, in desktop coordinates
+    pub x: i32,
+    pub y: i32,
+    pub visible: bool,
+    /// Set when the shape changed since the previous read
+    pub image: Option<CursorImage>,
+}
+
+/// A cursor shape, one pixel per desktop unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorImage {
+    pub width: u32,
+    pub height: u32,
+    pub hotspot_x: u32,
+    pub hotspot_y: u32,
+    /// Straight RGBA, row by row
+    pub pixels: Vec<u8>,
+}
+
+impl CursorImage {
+    /// The shape as shown in frames of `pixels_per_unit`, resized to the nearest pixel.
+    fn shape(&self, pixels_per_unit: f64) -> CursorShape {
+        if self.width == 0 || self.height == 0 {
+            return CursorShape::default();
+        }
+
+        let scale = |value: u32| (f64::from(value) * pixels_per_unit).round() as u32;
+        let (width, height) = (scale(self.width).max(1), scale(self.height).max(1));
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let src_y = (y * self.height / height).min(self.height - 1);
+            for x in 0..width {
+                let src_x = (x * self.width / width).min(self.width - 1);
+                let idx = ((src_y * self.width + src_x) * 4) as usize;
+                pixels.extend_from_slice(&self.pixels[idx..idx + 4]);
+            }
+        }
+
+        CursorShape {
+            width,
+            height,
+            hotspot_x: scale(self.hotspot_x).min(width - 1),
+            hotspot_y: scale(self.hotspot_y).min(height - 1),
+            pixels,
+        }
+    }
+}
+
+/// Turns the cursor reads into the updates the viewers need.
+#[derive(Debug, Default)]
+pub struct CursorTracker {
+    image: Option<CursorImage>,
+    /// Last update sent, without its shape
+    last: Option<CursorUpdate>,
+    pixels_per_unit: f64,
+}
+
+impl CursorTracker {
+    /// The update to send for `cursor`, if it moved or changed.
+    ///
+    /// The shape is included when it changed, when the frames are scaled
+    /// differently, and on `resend` for viewers that just joined.
+    pub fn update(
+        &mut self,
+        cursor: Cursor,
+        mapping: InputMapping,
+        resend: bool,
+    ) -> Option<CursorUpdate> {
+        let mut shape_changed = resend || mapping.pixels_per_unit != self.pixels_per_unit;
+        if let Some(image) = cursor.image {
+            self.image = Some(image);
+            shape_changed = true;
+        }
+        self.pixels_per_unit = mapping.pixels_per_unit;
+
+        let position = mapping.to_frame(cursor.x, cursor.y);
+        let (x, y) = position.unwrap_or_default();
+
+        let update = CursorUpdate {
+            x,
+            y,
+            // Hidden while over a monitor that is not shown
+            visible: cursor.visible && position.is_some(),
+            shape: None,
+        };
+
+        if !shape_changed && self.last.as_ref() == Some(&update) {
+            return None;
+        }
+        self.last = Some(update.clone());
+
+        let shape = self
+            .image
+            .as_ref()
+            .filter(|_| shape_changed)
+            .map(|image| image.shape(mapping.pixels_per_unit));
+
+        Some(CursorUpdate { shape, ..update })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_desktop::display::Rect;
+
+    #[test]
+    fn test_cursor_updates() {
+        let screen = Rect::new(0, 0, 1920, 1080);
+        let mapping = InputMapping {
+            viewport: screen,
+            pixels_per_unit: 0.5,
+            desktop: screen,
+        };
+
+        let image = CursorImage {
+            width: 2,
+            height: 2,
+            hotspot_x: 1,
+            hotspot_y: 1,
+            pixels: vec![255; 16],
+        };
+        let cursor = |x, image| Cursor {
+            x,
+            y: 100,
+            visible: true,
+            image,
+        };
+
+        let mut tracker = CursorTracker::default();
+        let update = tracker.update(cursor(200, Some(image)), mapping, false);
+        let update = update.unwrap();
+        assert_eq!((update.x, update.y, update.visible), (100, 50, true));
+        let shape = update.shape.unwrap();
+        assert_eq!((shape.width, shape.height, shape.pixels.len()), (1, 1, 4));
+        assert_eq!((shape.hotspot_x, shape.hotspot_y), (0, 0));
+
+        // Nothing changed
+        assert!(tracker.update(cursor(200, None), mapping, false).is_none());
+
+        // Moved: no shape
+        let update = tracker.update(cursor(400, None), mapping, false).unwrap();
+        assert_eq!(update.x, 200);
+        assert!(update.shape.is_none());
+
+        // A new viewer gets the shape again
+        let update = tracker.update(cursor(400, None), mapping, true).unwrap();
+        assert!(update.shape.is_some());
+
+        // Outside the viewport
+        let update = tracker.update(cursor(-10, None), mapping, false).unwrap();
+        assert!(!update.visible);
+    }
+}
@@ -0,0 +1,145 @@
+use wallguard_common::protobuf::wallguard_rd::{Monitor, ScreenLayout};
+
+/// An area of the desktop, in desktop coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The smallest area containing all of `rects`.
+    pub fn bounding(rects: impl IntoIterator<Item = Rect>) -> Option<Rect> {
+        rects.into_iter().reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            let right = (a.x + a.width as i32).max(b.x + b.width as i32);
+            let bottom = (a.y + a.height as i32).max(b.y + b.height as i32);
+            Rect::new(x, y, (right - x) as u32, (bottom - y) as u32)
+        })
+    }
+}
+
+/// A monitor of the host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Display {
+    pub id: u32,
+    pub name: String,
+    pub rect: Rect,
+    /// Pixels per desktop unit
+    pub scale: f64,
+    pub primary: bool,
+}
+
+impl Display {
+    pub fn layout(displays: &[Display], selected: Option<u32>) -> ScreenLayout {
+        let monitors = displays
+            .iter()
+            .map(|display| Monitor {
+                id: display.id,
+                name: display.name.clone(),
+                x: display.rect.x,
+                y: display.rect.y,
+                width: display.rect.width,
+                height: display.rect.height,
+                primary: display.primary,
+                scale: display.scale as f32,
+            })
+            .collect();
+
+        ScreenLayout { monitors, selected }
+    }
+}
+
+/// Maps the pixels of the frames sent to viewers back to desktop coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputMapping {
+    /// Area of the desktop shown in the frames
+    pub viewport: Rect,
+    /// Frame pixels per desktop unit
+    pub pixels_per_unit: f64,
+    /// Area covered by all the monitors
+    pub desktop: Rect,
+}
+
+impl InputMapping {
+    /// The mapping of the same frames shrunk by `factor`.
+    pub fn downscaled(self, factor: usize) -> Self {
+        Self {
+            pixels_per_unit: self.pixels_per_unit / factor.max(1) as f64,
+            ..self
+        }
+    }
+
+    pub fn to_desktop(self, x: i32, y: i32) -> (i32, i32) {
+        (
+            self.viewport.x + (f64::from(x) / self.pixels_per_unit).round() as i32,
+            self.viewport.y + (f64::from(y) / self.pixels_per_unit).round() as i32,
+        )
+    }
+
+    /// The frame pixel showing a point of the desktop, `None` if outside the viewport.
+    pub fn to_frame(self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (dx, dy) = (x - self.viewport.x, y - self.viewport.y);
+        if dx < 0 || dy < 0 || dx >= self.viewport.width as i32 || dy >= self.viewport.height as i32
+        {
+            return None;
+        }
+
+        Some((
+            (f64::from(dx) * self.pixels_per_unit).round() as i32,
+            (f64::from(dy) * self.pixels_per_unit).round() as i32,
+        ))
+    }
+}
+
+impl Default for InputMapping {
+    fn default() -> Self {
+        let screen = Rect::new(0, 0, 1920, 1080);
+        Self {
+            viewport: screen,
+            pixels_per_unit: 1.0,
+            desktop: screen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_mapping() {
+        let left = Rect::new(-1920, 0, 1920, 1080);
+        let right = Rect::new(0, 0, 2560, 1440);
+        let desktop = Rect::bounding([left, right]).unwrap();
+        assert_eq!(desktop, Rect::new(-1920, 0, 4480, 1440));
+
+        // Right monitor at HiDPI scale 2, frames downscaled by 2
+        let mapping = InputMapping {
+            viewport: right,
+            pixels_per_unit: 2.0 / 2.0,
+            desktop,
+        };
+        assert_eq!(mapping.to_desktop(100, 50), (100, 50));
+
+        // Left monitor, frames downscaled by 2
+        let mapping = InputMapping {
+            viewport: left,
+            pixels_per_unit: 0.5,
+            desktop,
+        };
+        assert_eq!(mapping.to_desktop(100, 50), (-1720, 100));
+    }
+}
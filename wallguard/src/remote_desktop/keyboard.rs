@@ -0,0 +1,181 @@
+/// A key identified by its position on the keyboard, whatever the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalKey {
+    /// Linux input event code, X11 keycodes being 8 more
+    pub evdev: u16,
+    /// PC set 1 scancode, `0xE0` prefixed for the extended keys
+    pub scancode: u16,
+}
+
+impl PhysicalKey {
+    /// The key with the given `KeyboardEvent.code`, `None` if unknown.
+    pub fn from_code(code: &str) -> Option<Self> {
+        PHYSICAL_KEYS
+            .iter()
+            .find(|(name, _, _)| *name == code)
+            .map(|&(_, evdev, scancode)| Self { evdev, scancode })
+    }
+
+    /// X11 keycode, as expected by XTest.
+    pub fn x11_keycode(self) -> u16 {
+        self.evdev + 8
+    }
+}
+
+/// Every `KeyboardEvent.code` that can be injected, with its evdev code and scancode.
+pub static PHYSICAL_KEYS: &[(&str, u16, u16)] = &[
+    ("Escape", 1, 0x01),
+    ("Digit1", 2, 0x02),
+    ("Digit2", 3, 0x03),
+    ("Digit3", 4, 0x04),
+    ("Digit4", 5, 0x05),
+    ("Digit5", 6, 0x06),
+    ("Digit6", 7, 0x07),
+    ("Digit7", 8, 0x08),
+    ("Digit8", 9, 0x09),
+    ("Digit9", 10, 0x0A),
+    ("Digit0", 11, 0x0B),
+    ("Minus", 12, 0x0C),
+    ("Equal", 13, 0x0D),
+    ("Backspace", 14, 0x0E),
+    ("Tab", 15, 0x0F),
+    ("KeyQ", 16, 0x10),
+    ("KeyW", 17, 0x11),
+    ("KeyE", 18, 0x12),
+    ("KeyR", 19, 0x13),
+    ("KeyT", 20, 0x14),
+    ("KeyY", 21, 0x15),
+    ("KeyU", 22, 0x16),
+    ("KeyI", 23, 0x17),
+    ("KeyO", 24, 0x18),
+    ("KeyP", 25, 0x19),
+    ("BracketLeft", 26, 0x1A),
+    ("BracketRight", 27, 0x1B),
+    ("Enter", 28, 0x1C),
+    ("ControlLeft", 29, 0x1D),
+    ("KeyA", 30, 0x1E),
+    ("KeyS", 31, 0x1F),
+    ("KeyD", 32, 0x20),
+    ("KeyF", 33, 0x21),
+    ("KeyG", 34, 0x22),
+    ("KeyH", 35, 0x23),
+    ("KeyJ", 36, 0x24),
+    ("KeyK", 37, 0x25),
+    ("KeyL", 38, 0x26),
+    ("Semicolon", 39, 0x27),
+    ("Quote", 40, 0x28),
+    ("Backquote", 41, 0x29),
+    ("ShiftLeft", 42, 0x2A),
+    ("Backslash", 43, 0x2B),
+    ("KeyZ", 44, 0x2C),
+    ("KeyX", 45, 0x2D),
+    ("KeyC", 46, 0x2E),
+    ("KeyV", 47, 0x2F),
+    ("KeyB", 48, 0x30),
+    ("KeyN", 49, 0x31),
+    ("KeyM", 50, 0x32),
+    ("Comma", 51, 0x33),
+    ("Period", 52, 0x34),
+    ("Slash", 53, 0x35),
+    ("ShiftRight", 54, 0x36),
+    ("NumpadMultiply", 55, 0x37),
+    ("AltLeft", 56, 0x38),
+    ("Space", 57, 0x39),
+    ("CapsLock", 58, 0x3A),
+    ("F1", 59, 0x3B),
+    ("F2", 60, 0x3C),
+    ("F3", 61, 0x3D),
+    ("F4", 62, 0x3E),
+    ("F5", 63, 0x3F),
+    ("F6", 64, 0x40),
+    ("F7", 65, 0x41),
+    ("F8", 66, 0x42),
+    ("F9", 67, 0x43),
+    ("F10", 68, 0x44),
+    ("NumLock", 69, 0x45),
+    ("ScrollLock", 70, 0x46),
+    ("Numpad7", 71, 0x47),
+    ("Numpad8", 72, 0x48),
+    ("Numpad9", 73, 0x49),
+    ("NumpadSubtract", 74, 0x4A),
+    ("Numpad4", 75, 0x4B),
+    ("Numpad5", 76, 0x4C),
+    ("Numpad6", 77, 0x4D),
+    ("NumpadAdd", 78, 0x4E),
+    ("Numpad1", 79, 0x4F),
+    ("Numpad2", 80, 0x50),
+    ("Numpad3", 81, 0x51),
+    ("Numpad0", 82, 0x52),
+    ("NumpadDecimal", 83, 0x53),
+    // The extra key of ISO keyboards, left of Z
+    ("IntlBackslash", 86, 0x56),
+    ("F11", 87, 0x57),
+    ("F12", 88, 0x58),
+    ("IntlRo", 89, 0x73),
+    ("Convert", 92, 0x79),
+    ("KanaMode", 93, 0x70),
+    ("NonConvert", 94, 0x7B),
+    ("NumpadEnter", 96, 0xE01C),
+    ("ControlRight", 97, 0xE01D),
+    ("NumpadDivide", 98, 0xE035),
+    ("PrintScreen", 99, 0xE037),
+    // AltGr on most layouts
+    ("AltRight", 100, 0xE038),
+    ("Home", 102, 0xE047),
+    ("ArrowUp", 103, 0xE048),
+    ("PageUp", 104, 0xE049),
+    ("ArrowLeft", 105, 0xE04B),
+    ("ArrowRight", 106, 0xE04D),
+    ("End", 107, 0xE04F),
+    ("ArrowDown", 108, 0xE050),
+    ("PageDown", 109, 0xE051),
+    ("Insert", 110, 0xE052),
+    ("Delete", 111, 0xE053),
+    ("AudioVolumeMute", 113, 0xE020),
+    ("AudioVolumeDown", 114, 0xE02E),
+    ("AudioVolumeUp", 115, 0xE030),
+    ("NumpadEqual", 117, 0x59),
+    ("NumpadComma", 121, 0x7E),
+    ("IntlYen", 124, 0x7D),
+    ("MetaLeft", 125, 0xE05B),
+    ("MetaRight", 126, 0xE05C),
+    ("ContextMenu", 127, 0xE05D),
+    ("F13", 183, 0x64),
+    ("F14", 184, 0x65),
+    ("F15", 185, 0x66),
+    ("F16", 186, 0x67),
+    ("F17", 187, 0x68),
+    ("F18", 188, 0x69),
+    ("F19", 189, 0x6A),
+    ("F20", 190, 0x6B),
+    ("F21", 191, 0x6C),
+    ("F22", 192, 0x6D),
+    ("F23", 193, 0x6E),
+    ("F24", 194, 0x76),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_keys() {
+        let key = PhysicalKey::from_code("KeyQ").unwrap();
+        assert_eq!((key.evdev, key.scancode, key.x11_keycode()), (16, 0x10, 24));
+
+        let altgr = PhysicalKey::from_code("AltRight").unwrap();
+        assert_eq!((altgr.evdev, altgr.scancode), (100, 0xE038));
+
+        assert!(PhysicalKey::from_code("").is_none());
+        assert!(PhysicalKey::from_code("q").is_none());
+
+        for (i, (code, evdev, scancode)) in PHYSICAL_KEYS.iter().enumerate() {
+            assert!(
+                PHYSICAL_KEYS[i + 1..]
+                    .iter()
+                    .all(|other| other.0 != *code && other.1 != *evdev && other.2 != *scancode),
+                "{code} is listed twice"
+            );
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use xkbcommon_dl::{
+    XKB_MOD_INVALID, XkbCommon, xkb_context_flags, xkb_keymap, xkb_keymap_compile_flags,
+    xkb_mod_mask_t, xkb_rule_names,
+};
+
+/// The key and modifiers typing a character on the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keystroke {
+    pub evdev: u16,
+    pub shift: bool,
+    /// AltGr, i.e. the third level
+    pub altgr: bool,
+}
+
+impl Keystroke {
+    pub fn plain(evdev: u16) -> Self {
+        Self {
+            evdev,
+            shift: false,
+            altgr: false,
+        }
+    }
+}
+
+/// Characters typed by the first layout of the host keymap.
+#[derive(Debug, Default)]
+pub struct HostKeymap {
+    keys: HashMap<char, Keystroke>,
+}
+
+/// XKB rule names, as configured by `localectl` or the distribution.
+#[derive(Debug, Default, PartialEq)]
+struct LayoutNames {
+    model: String,
+    layout: String,
+    variant: String,
+    options: Option<String>,
+}
+
+impl HostKeymap {
+    /// Compiles the keymap configured on the host, `None` if XKB cannot.
+    ///
+    /// The agent runs as a service, outside the user session: the layout comes
+    /// from `XKB_DEFAULT_*` if set, or from the system keyboard configuration.
+    /// libxkbcommon is loaded at runtime, hosts without it get `None`.
+    pub fn load() -> Option<Self> {
+        let Some(xkb) = xkbcommon_dl::xkbcommon_option() else {
+            log::debug!("libxkbcommon cannot be loaded");
+            return None;
+        };
+
+        let names = if std::env::var_os("XKB_DEFAULT_LAYOUT").is_some() {
+            LayoutNames::default()
+        } else {
+            system_layout_names()
+        };
+
+        let model = CString::new(names.model).ok()?;
+        let layout = CString::new(names.layout).ok()?;
+        let variant = CString::new(names.variant).ok()?;
+        let options = names.options.map(CString::new).transpose().ok()?;
+
+        // Empty names and null options fall back to the XKB defaults
+        let rule_names = xkb_rule_names {
+            rules: ptr::null(),
+            model: model.as_ptr(),
+            layout: layout.as_ptr(),
+            variant: variant.as_ptr(),
+            options: options
+                .as_ref()
+                .map_or(ptr::null(), |options| options.as_ptr()),
+        };
+
+        // SAFETY: the objects are only used while their owners are alive, and
+        // the names outlive the compilation of the keymap.
+        let keys = unsafe {
+            let context = Owned::new(
+                (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS),
+                *xkb.xkb_context_unref,
+            )?;
+            let keymap = Owned::new(
+                (xkb.xkb_keymap_new_from_names)(
+                    context.0,
+                    &rule_names,
+                    xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+                ),
+                *xkb.xkb_keymap_unref,
+            )?;
+            let state = Owned::new((xkb.xkb_state_new)(keymap.0), *xkb.xkb_state_unref)?;
+
+            let shift = mod_mask(xkb, keymap.0, &[c"Shift"]);
+            let altgr = mod_mask(xkb, keymap.0, &[c"Mod5", c"LevelThree"]);
+            let (min, max) = (
+                (xkb.xkb_keymap_min_keycode)(keymap.0),
+                (xkb.xkb_keymap_max_keycode)(keymap.0),
+            );
+
+            // From the fewest modifiers to the most: several keys may type the
+            // same character, the simplest one is kept. Levels needing anything
+            // but Shift and AltGr, e.g. Caps Lock, are never looked at.
+            let mut keys = HashMap::new();
+            for (with_shift, with_altgr) in
+                [(false, false), (true, false), (false, true), (true, true)]
+            {
+                if with_altgr && altgr == 0 {
+                    continue;
+                }
+
+                let mask = if with_shift { shift } else { 0 } | if with_altgr { altgr } else { 0 };
+                (xkb.xkb_state_update_mask)(state.0, mask, 0, 0, 0, 0, 0);
+
+                for keycode in min..=max {
+                    let Some(evdev) = keycode
+                        .checked_sub(8)
+                        .and_then(|evdev| u16::try_from(evdev).ok())
+                    else {
+                        continue;
+                    };
+                    let Some(c) = char::from_u32((xkb.xkb_state_key_get_utf32)(state.0, keycode))
+                    else {
+                        continue;
+                    };
+                    if c == '\0' || c.is_control() {
+                        continue;
+                    }
+
+                    keys.entry(c).or_insert(Keystroke {
+                        evdev,
+                        shift: with_shift,
+                        altgr: with_altgr,
+                    });
+                }
+            }
+
+            keys
+        };
+
+        log::info!(
+            "Host keymap '{}' types {} characters",
+            layout.to_string_lossy(),
+            keys.len()
+        );
+
+        Some(Self { keys })
+    }
+
+    pub fn keystroke(&self, c: char) -> Option<Keystroke> {
+        self.keys.get(&c).copied()
+    }
+}
+
+/// An XKB object, released when dropped.
+struct Owned<T>(*mut T, unsafe extern "C" fn(*mut T));
+
+impl<T> Owned<T> {
+    fn new(object: *mut T, unref: unsafe extern "C" fn(*mut T)) -> Option<Self> {
+        (!object.is_null()).then_some(Self(object, unref))
+    }
+}
+
+impl<T> Drop for Owned<T> {
+    fn drop(&mut self) {
+        // SAFETY: the object was created by XKB and is released only here
+        unsafe { (self.1)(self.0) }
+    }
+}
+
+/// The mask of the modifiers named `names`, leaving out those the keymap lacks.
+unsafe fn mod_mask(xkb: &XkbCommon, keymap: *mut xkb_keymap, names: &[&CStr]) -> xkb_mod_mask_t {
+    names
+        .iter()
+        .map(|name| unsafe { (xkb.xkb_keymap_mod_get_index)(keymap, name.as_ptr()) })
+        .filter(|index| *index != XKB_MOD_INVALID)
+        .fold(0, |mask, index| mask | (1 << index))
+}
+
+fn system_layout_names() -> LayoutNames {
+    // Debian and derivatives
+    if let Ok(config) = std::fs::read_to_string("/etc/default/keyboard") {
+        return parse_default_keyboard(&config);
+    }
+
+    // Written by localectl on the other distributions
+    std::fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf")
+        .map(|config| parse_xorg_keyboard(&config))
+        .unwrap_or_default()
+}
+
+/// Parses the `XKBLAYOUT="de"` lines of `/etc/default/keyboard`.
+fn parse_default_keyboard(config: &str) -> LayoutNames {
+    let mut names = LayoutNames::default();
+
+    for line in config.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+
+        match key.trim() {
+            "XKBMODEL" => names.model = value,
+            "XKBLAYOUT" => names.layout = value,
+            "XKBVARIANT" => names.variant = value,
+            "XKBOPTIONS" => names.options = Some(value),
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// Parses the `Option "XkbLayout" "de"` lines of an xorg.conf snippet.
+fn parse_xorg_keyboard(config: &str) -> LayoutNames {
+    let mut names = LayoutNames::default();
+
+    for line in config.lines() {
+        let mut words = line.split('"').skip(1).step_by(2);
+        if line.trim_start().starts_with("Option") {
+            let (Some(key), Some(value)) = (words.next(), words.next()) else {
+                continue;
+            };
+            let value = value.to_string();
+
+            match key {
+                "XkbModel" => names.model = value,
+                "XkbLayout" => names.layout = value,
+                "XkbVariant" => names.variant = value,
+                "XkbOptions" => names.options = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_names() {
+        let debian = "# KEYBOARD CONFIGURATION FILE\n\
+            XKBMODEL=\"pc105\"\n\
+            XKBLAYOUT=\"de\"\n\
+            XKBVARIANT=\"nodeadkeys\"\n\
+            XKBOPTIONS=\"\"\n\
+            BACKSPACE=\"guess\"\n";
+
+        let xorg = "Section \"InputClass\"\n\
+            \tIdentifier \"system-keyboard\"\n\
+            \tMatchIsKeyboard \"on\"\n\
+            \tOption \"XkbModel\" \"pc105\"\n\
+            \tOption \"XkbLayout\" \"de\"\n\
+            \tOption \"XkbVariant\" \"nodeadkeys\"\n\
+            \tOption \"XkbOptions\" \"\"\n\
+            EndSection\n";
+
+        let expected = LayoutNames {
+            model: String::from("pc105"),
+            layout: String::from("de"),
+            variant: String::from("nodeadkeys"),
+            options: Some(String::new()),
+        };
+
+        assert_eq!(parse_default_keyboard(debian), expected);
+        assert_eq!(parse_xorg_keyboard(xorg), expected);
+    }
+}
@@ -1,29 +1,14 @@
+use super::clipboard::{LastSynced, MAX_CLIPBOARD_SIZE};
+use super::display::{InputMapping, Rect};
+use super::keyboard::PhysicalKey;
 use copypasta::{ClipboardContext, ClipboardProvider};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
-use serde::{Deserialize, Serialize};
-use std::{fmt, sync::Arc};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
 use tokio::sync::Mutex;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MouseMessage {
-    message_type: String,
-    button: String,
-    x: i32,
-    y: i32,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct KeyboardMessage {
-    message_type: String,
-    key: String,
-    code: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ClipboardMessage {
-    message_type: String,
-    content: String,
-}
+use wallguard_common::protobuf::wallguard_rd::{Clipboard, InputEvent, MouseButton, input_event};
 
 // ── input backend ─────────────────────────────────────────────────────────────
 
@@ -65,7 +50,7 @@ impl InputBackend {
                         "Enigo unavailable ({enigo_err:?}); \
                          using uinput (Wayland / kernel) backend"
                     );
-                    let handler = super::uinput_handler::UinputHandler::new(None, None)?;
+                    let handler = super::uinput_handler::UinputHandler::new()?;
                     Ok(InputBackend::Uinput(handler))
                 }
                 #[cfg(not(target_os = "linux"))]
@@ -77,18 +62,20 @@ impl InputBackend {
         }
     }
 
-    fn move_mouse(&mut self, x: i32, y: i32) -> Result<(), Error> {
+    /// `desktop` is the area covered by all the monitors.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn move_mouse(&mut self, x: i32, y: i32, desktop: Rect) -> Result<(), Error> {
         match self {
             InputBackend::Enigo(e) => {
                 use enigo::{Coordinate, Mouse};
                 e.move_mouse(x, y, Coordinate::Abs).handle_err(location!())
             }
             #[cfg(target_os = "linux")]
-            InputBackend::Uinput(u) => u.move_abs(x, y),
+            InputBackend::Uinput(u) => u.move_abs(x, y, desktop),
         }
     }
 
-    fn button(&mut self, btn: &str, press: bool) -> Result<(), Error> {
+    fn button(&mut self, btn: MouseButton, press: bool) -> Result<(), Error> {
         match self {
             InputBackend::Enigo(e) => {
                 use enigo::{Direction, Mouse};
@@ -112,33 +99,45 @@ impl InputBackend {
         }
     }
 
-    fn key(&mut self, key: &str, direction: KeyDir) -> Result<(), Error> {
+    /// Injects the physical key at `code` when known, so that the host layout
+    /// applies, AltGr and dead keys included. `key` is the fallback.
+    fn key(&mut self, key: &str, code: &str, press: bool) -> Result<(), Error> {
         match self {
             InputBackend::Enigo(e) => {
                 use enigo::{Direction, Keyboard};
-                let k = parse_enigo_key(key);
-                let dir = match direction {
-                    KeyDir::Press => Direction::Press,
-                    KeyDir::Release => Direction::Release,
-                    KeyDir::Click => Direction::Click,
+                let dir = if press {
+                    Direction::Press
+                } else {
+                    Direction::Release
                 };
-                e.key(k, dir).handle_err(location!())
+
+                if let Some(physical) = PhysicalKey::from_code(code) {
+                    return e.raw(enigo_keycode(physical), dir).handle_err(location!());
+                }
+
+                match parse_enigo_key(key) {
+                    Some(k) => e.key(k, dir).handle_err(location!()),
+                    None => {
+                        log::debug!("Unknown key '{key}', ignored");
+                        Ok(())
+                    }
+                }
             }
             #[cfg(target_os = "linux")]
-            InputBackend::Uinput(u) => match direction {
-                KeyDir::Press => u.key_press(key),
-                KeyDir::Release => u.key_release(key),
-                KeyDir::Click => u.key_click(key),
-            },
+            InputBackend::Uinput(u) => u.key(key, code, press),
         }
     }
-}
 
-#[derive(Clone, Copy)]
-enum KeyDir {
-    Press,
-    Release,
-    Click,
+    fn text(&mut self, text: &str) -> Result<(), Error> {
+        match self {
+            InputBackend::Enigo(e) => {
+                use enigo::Keyboard;
+                e.text(text).handle_err(location!())
+            }
+            #[cfg(target_os = "linux")]
+            InputBackend::Uinput(u) => u.type_text(text),
+        }
+    }
 }
 
 // ── MessageHandler ────────────────────────────────────────────────────────────
@@ -147,6 +146,9 @@ enum KeyDir {
 pub struct MessageHandler {
     input: Arc<Mutex<InputBackend>>,
     clctx: Arc<Mutex<ClipboardContext>>,
+    last_clipboard: LastSynced,
+    /// Where the frames are on the desktop, before they are downscaled
+    mapping: Arc<RwLock<InputMapping>>,
 }
 
 impl fmt::Debug for MessageHandler {
@@ -158,98 +160,128 @@ impl fmt::Debug for MessageHandler {
 }
 
 impl MessageHandler {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(
+        last_clipboard: LastSynced,
+        mapping: Arc<RwLock<InputMapping>>,
+    ) -> Result<Self, Error> {
         let input = InputBackend::new()?;
         let clctx = ClipboardContext::new().handle_err(location!())?;
         Ok(Self {
             input: Arc::new(Mutex::new(input)),
             clctx: Arc::new(Mutex::new(clctx)),
+            last_clipboard,
+            mapping,
         })
     }
 
-    pub async fn on_message(&self, message: Vec<u8>) -> Result<(), Error> {
-        let json = serde_json::from_slice::<serde_json::Value>(&message).handle_err(location!())?;
-
-        let message_type = json
-            .get("message_type")
-            .ok_or("Message Type is missing")
-            .handle_err(location!())?
-            .as_str()
-            .ok_or("Wrong type")
+    /// Injects input from a viewer looking at frames downscaled by `downscale`.
+    pub async fn on_input(&self, input: InputEvent, downscale: usize) -> Result<(), Error> {
+        let event = input
+            .event
+            .ok_or("Input event is missing")
             .handle_err(location!())?;
 
-        match message_type.to_lowercase().as_str() {
-            "mousemove" | "mousedown" | "mouseup" => {
-                let msg = serde_json::from_value::<MouseMessage>(json).handle_err(location!())?;
-                self.on_mouse_message(msg).await?;
+        if let input_event::Event::Text(text) = &event
+            && text.text.len() > MAX_CLIPBOARD_SIZE
+        {
+            return Err(format!(
+                "Text of {} bytes exceeds the limit of {MAX_CLIPBOARD_SIZE}",
+                text.text.len()
+            ))
+            .handle_err(location!());
+        }
+
+        // The compositor sharing the screen through the portal takes input from it too
+        #[cfg(all(target_os = "linux", feature = "pipewire"))]
+        if let Some(portal) = super::portal::input()? {
+            return self.on_portal_input(&portal, event, downscale);
+        }
+
+        let mut backend = self.input.lock().await;
+        match event {
+            input_event::Event::MouseMove(mv) => {
+                // Coordinates are in pixels of the frames, which may show a single
+                // monitor and be downscaled
+                let mapping = self.mapping.read().unwrap().downscaled(downscale);
+                let (x, y) = mapping.to_desktop(mv.x, mv.y);
+                backend.move_mouse(x, y, mapping.desktop)
             }
-            "keyup" | "keydown" | "keypress" => {
-                let msg =
-                    serde_json::from_value::<KeyboardMessage>(json).handle_err(location!())?;
-                self.on_keyboard_message(msg).await?;
+            input_event::Event::MouseButton(btn) => {
+                let button = MouseButton::try_from(btn.button).handle_err(location!())?;
+                backend.button(button, btn.pressed)
             }
-            "clipboard" => {
-                let msg =
-                    serde_json::from_value::<ClipboardMessage>(json).handle_err(location!())?;
-                self.on_clipboard_message(msg).await?;
+            input_event::Event::Key(key) => backend.key(&key.key, &key.code, key.pressed),
+            input_event::Event::Text(text) => backend.text(&text.text),
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pipewire"))]
+    fn on_portal_input(
+        &self,
+        portal: &super::portal::PortalInput,
+        event: input_event::Event,
+        downscale: usize,
+    ) -> Result<(), Error> {
+        match event {
+            input_event::Event::MouseMove(mv) => {
+                let mapping = self.mapping.read().unwrap().downscaled(downscale);
+                let (x, y) = mapping.to_desktop(mv.x, mv.y);
+                portal.move_mouse(x, y)
             }
-            mt => {
-                return Err(format!("{mt} message type is not supported")).handle_err(location!());
+            input_event::Event::MouseButton(btn) => {
+                let button = MouseButton::try_from(btn.button).handle_err(location!())?;
+                portal.button(parse_uinput_button(button)?, btn.pressed)
             }
-        };
-
-        Ok(())
+            input_event::Event::Key(key) => portal.key(&key.key, &key.code, key.pressed),
+            input_event::Event::Text(text) => portal.text(&text.text),
+        }
     }
 
-    async fn on_mouse_message(&self, message: MouseMessage) -> Result<(), Error> {
-        let mut input = self.input.lock().await;
-        match message.message_type.to_lowercase().as_str() {
-            "mousemove" => input.move_mouse(message.x, message.y),
-            "mousedown" => input.button(&message.button, true),
-            "mouseup" => input.button(&message.button, false),
-            _ => Err(format!(
-                "Unsupported mouse message type {}",
-                message.message_type
+    pub async fn on_clipboard(&self, message: Clipboard) -> Result<(), Error> {
+        if message.text.len() > MAX_CLIPBOARD_SIZE {
+            return Err(format!(
+                "Clipboard of {} bytes exceeds the limit of {MAX_CLIPBOARD_SIZE}",
+                message.text.len()
             ))
-            .handle_err(location!()),
+            .handle_err(location!());
         }
-    }
 
-    async fn on_keyboard_message(&self, message: KeyboardMessage) -> Result<(), Error> {
-        let direction = match message.message_type.to_lowercase().as_str() {
-            "keydown" => KeyDir::Press,
-            "keyup" => KeyDir::Release,
-            _ => KeyDir::Click,
-        };
-        self.input.lock().await.key(&message.key, direction)
-    }
+        self.last_clipboard.replace(&message.text);
 
-    async fn on_clipboard_message(&self, message: ClipboardMessage) -> Result<(), Error> {
         self.clctx
             .lock()
             .await
-            .set_contents(message.content)
+            .set_contents(message.text)
             .handle_err(location!())
     }
 }
 
 // ── key / button parsers (Enigo) ──────────────────────────────────────────────
 
-fn parse_enigo_button(s: &str) -> Result<enigo::Button, Error> {
+fn parse_enigo_button(btn: MouseButton) -> Result<enigo::Button, Error> {
     use enigo::Button;
-    match s.to_lowercase().as_str() {
-        "left" => Ok(Button::Left),
-        "middle" => Ok(Button::Middle),
-        "right" | "rigth" => Ok(Button::Right),
-        "back" => Ok(Button::Back),
-        "forward" => Ok(Button::Forward),
-        _ => Err(format!("Unsupported mouse button {s}")).handle_err(location!()),
+    match btn {
+        MouseButton::Left => Ok(Button::Left),
+        MouseButton::Middle => Ok(Button::Middle),
+        MouseButton::Right => Ok(Button::Right),
+        MouseButton::Back => Ok(Button::Back),
+        MouseButton::Forward => Ok(Button::Forward),
+        MouseButton::Unspecified => Err("Unspecified mouse button").handle_err(location!()),
+    }
+}
+
+/// Keycode of `key` for `Keyboard::raw`: scancodes on Windows, X11 keycodes elsewhere.
+fn enigo_keycode(key: PhysicalKey) -> u16 {
+    if cfg!(target_os = "windows") {
+        key.scancode
+    } else {
+        key.x11_keycode()
     }
 }
 
-fn parse_enigo_key(key: &str) -> enigo::Key {
+fn parse_enigo_key(key: &str) -> Option<enigo::Key> {
     use enigo::Key;
-    match key.to_lowercase().as_str() {
+    let key = match key.to_lowercase().as_str() {
         "backspace" => Key::Backspace,
         "control" => Key::Control,
         "meta" => Key::Meta,
@@ -280,21 +312,31 @@ fn parse_enigo_key(key: &str) -> enigo::Key {
         "end" => Key::End,
         "pageup" => Key::PageUp,
         "pagedown" => Key::PageDown,
-        k => Key::Unicode(k.chars().next().unwrap_or('\0')),
-    }
+        "insert" => Key::Insert,
+        // Single characters are typed whatever the layout, anything else is unknown
+        _ => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Unicode(c),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(key)
 }
 
 // ── button parser (uinput) ────────────────────────────────────────────────────
 
 #[cfg(target_os = "linux")]
-fn parse_uinput_button(s: &str) -> Result<super::uinput_handler::MouseButton, Error> {
-    use super::uinput_handler::MouseButton;
-    match s.to_lowercase().as_str() {
-        "left" => Ok(MouseButton::Left),
-        "middle" => Ok(MouseButton::Middle),
-        "right" | "rigth" => Ok(MouseButton::Right),
-        "back" => Ok(MouseButton::Back),
-        "forward" => Ok(MouseButton::Forward),
-        _ => Err(format!("Unsupported mouse button {s}")).handle_err(location!()),
+fn parse_uinput_button(btn: MouseButton) -> Result<super::uinput_handler::MouseButton, Error> {
+    use super::uinput_handler::MouseButton as UinputButton;
+    match btn {
+        MouseButton::Left => Ok(UinputButton::Left),
+        MouseButton::Middle => Ok(UinputButton::Middle),
+        MouseButton::Right => Ok(UinputButton::Right),
+        MouseButton::Back => Ok(UinputButton::Back),
+        MouseButton::Forward => Ok(UinputButton::Forward),
+        MouseButton::Unspecified => Err("Unspecified mouse button").handle_err(location!()),
     }
 }
@@ -1,53 +1,108 @@
-use crate::remote_desktop::{messages::MessageHandler, screen_capturer::ScreenCapturer};
+use crate::remote_desktop::{
+    arbiter::ControlArbiter,
+    clipboard::{LastSynced, MAX_CLIPBOARD_SIZE},
+    cursor::{Cursor, CursorTracker},
+    display::InputMapping,
+    messages::MessageHandler,
+    rate_control::Level,
+    screen_capturer::ScreenCapturer,
+    screenshot::Screenshot,
+};
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use openh264::OpenH264API;
-use openh264::encoder::{Encoder, EncoderConfig};
-use openh264::formats::YUVBuffer;
+use openh264::encoder::{BitRate, Encoder, EncoderConfig, FrameRate, FrameType, RateControlMode};
+use openh264::formats::{RGBSource, YUVBuffer};
+use prost::Message;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, hash_map::Entry},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
     time::{Duration, Instant},
 };
-use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc, watch};
+use wallguard_common::protobuf::wallguard_rd::{
+    Clipboard, Codec, ControlRequest, CursorUpdate, Feature, Quality, Role, ScreenLayout,
+    VideoFrame, ViewerMessage, agent_message, control, viewer_message,
+};
+use wallguard_common::rd_protocol;
 
+mod arbiter;
 mod client;
+mod clipboard;
+mod cursor;
+mod display;
+mod keyboard;
+#[cfg(target_os = "linux")]
+mod keymap;
 mod messages;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod pipewire_stream;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod portal;
+mod rate_control;
 mod screen_capturer;
 mod screenshot;
 #[cfg(target_os = "linux")]
 mod uinput_handler;
 
-type ClientsInner = Arc<Mutex<HashMap<u128, client::Client>>>;
+type ClientsInner = Arc<Mutex<HashMap<u32, client::Client>>>;
+
+/// What a session, i.e. all the viewers of one RD tunnel, may do.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionOptions {
+    pub role: Role,
+    pub clipboard_to_host: bool,
+    pub clipboard_to_viewer: bool,
+}
 
 #[derive(Clone, Debug)]
 pub struct RemoteDesktopManager {
     clients: ClientsInner,
-    counter: Arc<RwLock<u128>>,
-    /// Set to true when a new viewer connects so the encode loop sends a keyframe
-    /// immediately, giving them a complete picture before the periodic interval.
-    force_keyframe: Arc<AtomicBool>,
+    counter: Arc<RwLock<u32>>,
+    /// Monitor last selected by a viewer, `None` for the whole desktop.
+    monitor: Arc<watch::Sender<Option<u32>>>,
+    /// Where the frames are on the desktop before downscaling, to map input coordinates back.
+    input_mapping: Arc<std::sync::RwLock<InputMapping>>,
     terminate: broadcast::Sender<()>,
     msg_handler: MessageHandler,
+    /// Which session may send input.
+    control: Arc<std::sync::Mutex<ControlArbiter>>,
+    /// Whether the host clipboard is being watched for viewers.
+    clipboard_watched: Arc<AtomicBool>,
+    last_clipboard: LastSynced,
 }
 
 impl RemoteDesktopManager {
     pub fn new() -> Result<Self, Error> {
         let (terminate, _) = broadcast::channel(1);
-        let msg_handler = MessageHandler::new()?;
+        let last_clipboard = LastSynced::default();
+        let input_mapping = Arc::new(std::sync::RwLock::new(InputMapping::default()));
+        let msg_handler = MessageHandler::new(last_clipboard.clone(), input_mapping.clone())?;
 
         Ok(Self {
             terminate,
             clients: Default::default(),
             counter: Default::default(),
-            force_keyframe: Arc::new(AtomicBool::new(false)),
+            monitor: Arc::new(watch::Sender::new(None)),
+            input_mapping,
             msg_handler,
+            control: Default::default(),
+            clipboard_watched: Arc::new(AtomicBool::new(false)),
+            last_clipboard,
         })
     }
 
-    pub async fn on_client_connected(&mut self, channel: mpsc::Sender<Vec<u8>>) -> u128 {
+    pub async fn has_clients(&self) -> bool {
+        !self.clients.lock().await.is_empty()
+    }
+
+    pub async fn on_client_connected(
+        &mut self,
+        channel: mpsc::Sender<Vec<u8>>,
+        options: SessionOptions,
+    ) -> u32 {
         let client_id = {
             let mut counter = self.counter.write().await;
             let id = *counter;
@@ -71,18 +126,30 @@ impl RemoteDesktopManager {
             });
         }
 
-        // New viewer: force an intra frame so they get a complete picture immediately
-        // rather than waiting up to KEYFRAME_INTERVAL frames for the next periodic one.
-        self.force_keyframe.store(true, Ordering::Relaxed);
+        if options.clipboard_to_viewer && !self.clipboard_watched.swap(true, Ordering::Relaxed) {
+            tokio::spawn(clipboard_loop(self.clone()));
+        }
+
+        // New viewers wait for a keyframe, which the encode loop sends right away
+        // rather than after up to KEYFRAME_INTERVAL frames.
+        let mut client = client::Client::new(channel, options);
+        let hello = rd_protocol::hello(&features(options));
+        let _ = client.send(agent_message::Message::Hello(hello));
+        lock.insert(client_id, client);
+        drop(lock);
 
-        lock.insert(client_id, client::Client::new(channel));
+        self.control.lock().unwrap().join(client_id, options.role);
+        self.broadcast_control_state().await;
 
-        log::debug!("Client with ID {client_id} has just connected");
+        log::debug!(
+            "Client with ID {client_id} has just connected as {}",
+            options.role.as_str_name()
+        );
 
         client_id
     }
 
-    pub async fn on_client_disconnected(&mut self, id: u128) -> Result<(), Error> {
+    pub async fn on_client_disconnected(&mut self, id: u32) -> Result<(), Error> {
         let mut lock = self.clients.lock().await;
 
         lock.remove(&id)
@@ -92,23 +159,154 @@ impl RemoteDesktopManager {
         if lock.is_empty() {
             let _ = self.terminate.send(());
         }
+        drop(lock);
+
+        self.control.lock().unwrap().leave(id);
+        self.broadcast_control_state().await;
 
         log::debug!("Client with ID {id} has just disconnected");
 
         Ok(())
     }
 
-    pub async fn on_client_message(&self, id: u128, message: Vec<u8>) -> Result<(), Error> {
-        if message.is_empty() {
-            return Ok(());
+    pub async fn on_client_message(&self, id: u32, message: Vec<u8>) -> Result<(), Error> {
+        let (options, downscale) = self
+            .clients
+            .lock()
+            .await
+            .get(&id)
+            .map(|client| (client.options, client.downscale()))
+            .ok_or(format!("No client with ID {id}"))
+            .handle_err(location!())?;
+
+        let message = ViewerMessage::decode(message.as_slice()).handle_err(location!())?;
+
+        match message.message {
+            Some(viewer_message::Message::Input(input)) => {
+                if !self.has_control(id) {
+                    log::trace!("Client {id} does not hold control, input dropped");
+                    return Ok(());
+                }
+
+                self.msg_handler.on_input(input, downscale).await
+            }
+            Some(viewer_message::Message::Clipboard(clipboard)) => {
+                if !options.clipboard_to_host || !self.has_control(id) {
+                    log::debug!("Client {id} may not paste into the host clipboard, ignoring");
+                    return Ok(());
+                }
+
+                self.msg_handler.on_clipboard(clipboard).await
+            }
+            Some(viewer_message::Message::Control(control)) => {
+                self.on_control(id, control.action).await
+            }
+            // Viewers negotiate with the server, which already holds our Hello
+            Some(viewer_message::Message::Hello(_)) | None => Ok(()),
+        }
+    }
+
+    async fn on_control(&self, id: u32, action: Option<control::Action>) -> Result<(), Error> {
+        match action {
+            // The keyframe comes with the layout and the control state, once the channel has room
+            Some(control::Action::RequestKeyframe(_)) => {
+                if let Some(client) = self.clients.lock().await.get_mut(&id) {
+                    client.request_keyframe();
+                }
+            }
+            // Each viewer adapts to its own link, starting from the preset it picked
+            Some(control::Action::SetQuality(quality)) => match Quality::try_from(quality) {
+                Ok(quality) => {
+                    log::debug!("Client {id} requested {} quality", quality.as_str_name());
+                    if let Some(client) = self.clients.lock().await.get_mut(&id) {
+                        client.set_quality(quality, Instant::now());
+                    }
+                }
+                Err(_) => log::warn!("Client {id} requested unknown quality {quality}"),
+            },
+            // The monitor applies to every viewer, so only the holder picks it
+            Some(control::Action::SelectMonitor(_)) if !self.has_control(id) => {
+                log::debug!("Client {id} does not hold control, monitor unchanged");
+            }
+            Some(control::Action::SelectMonitor(selection)) => {
+                self.monitor.send_replace(selection.id);
+            }
+            Some(control::Action::RequestControl(_)) => {
+                let holder = self.control.lock().unwrap().request(id)?;
+
+                match holder {
+                    // Let the holder decide whether to grant it
+                    Some(holder) => {
+                        if let Some(client) = self.clients.lock().await.get_mut(&holder) {
+                            let request = ControlRequest { session: id };
+                            client.send(agent_message::Message::ControlRequest(request))?;
+                        }
+                    }
+                    None => self.broadcast_control_state().await,
+                }
+            }
+            Some(control::Action::GrantControl(grant)) => {
+                self.control.lock().unwrap().grant(id, grant.session)?;
+                log::debug!("Client {id} handed control to client {}", grant.session);
+                self.broadcast_control_state().await;
+            }
+            Some(control::Action::ReleaseControl(_)) => {
+                let released = self.control.lock().unwrap().release(id);
+                if released {
+                    self.broadcast_control_state().await;
+                }
+            }
+            None => {}
         }
 
-        if !self.clients.lock().await.contains_key(&id) {
-            return Err(format!("No client with ID {id}")).handle_err(location!());
+        Ok(())
+    }
+
+    fn has_control(&self, id: u32) -> bool {
+        self.control.lock().unwrap().has_control(id)
+    }
+
+    /// Tells every session its role and who holds control.
+    async fn broadcast_control_state(&self) {
+        let mut clients = self.clients.lock().await;
+        self.send_control_state(&mut clients, |_| true);
+    }
+
+    /// Tells the sessions matching `filter` their role and who holds control.
+    fn send_control_state(
+        &self,
+        clients: &mut HashMap<u32, client::Client>,
+        filter: impl Fn(u32) -> bool,
+    ) {
+        let states = self.control.lock().unwrap().states();
+
+        for state in states {
+            if !filter(state.session) {
+                continue;
+            }
+
+            if let Some(client) = clients.get_mut(&state.session) {
+                let _ = client.send(agent_message::Message::ControlState(state));
+            }
         }
+    }
+}
 
-        self.msg_handler.on_message(message).await
+/// The protocol features offered to the viewers of a session.
+fn features(options: SessionOptions) -> Vec<Feature> {
+    let mut features = vec![
+        Feature::Quality,
+        Feature::ScreenLayout,
+        Feature::Control,
+        Feature::Cursor,
+        Feature::TextInput,
+    ];
+
+    if options.clipboard_to_host || options.clipboard_to_viewer {
+        features.push(Feature::Clipboard);
     }
+
+    features
 }
 
 async fn capture_loop(manager: RemoteDesktopManager) {
@@ -135,65 +333,337 @@ async fn capture_loop(manager: RemoteDesktopManager) {
     }
 }
 
-async fn capture_loop_impl(manager: RemoteDesktopManager) -> Result<(), Error> {
-    const TARGET_FPS: u64 = 24;
-    const KEYFRAME_INTERVAL: u64 = 60;
-
-    let target_frame_duration = Duration::from_millis(1000 / TARGET_FPS);
+/// Sends the text copied on the host to the viewers that want it, until the session ends.
+async fn clipboard_loop(manager: RemoteDesktopManager) {
+    let mut terminate_receiver = manager.terminate.subscribe();
 
-    let api = OpenH264API::from_source();
-    let config = EncoderConfig::new().skip_frames(false);
-    let mut encoder = Encoder::with_api_config(api, config).handle_err(location!())?;
-    let mut capturer = ScreenCapturer::new()?;
-    let mut frame_count: u64 = 0;
+    // The watcher thread stops once `receiver` is dropped
+    let (sender, mut receiver) = mpsc::channel(4);
+    clipboard::watch_host_clipboard(sender);
 
     loop {
-        let frame_start = Instant::now();
+        let text = tokio::select! {
+            _ = terminate_receiver.recv() => break,
+            text = receiver.recv() => match text {
+                Some(text) => text,
+                None => break,
+            },
+        };
 
-        // Skip capture entirely when nobody is watching.
-        if manager.clients.lock().await.is_empty() {
-            tokio::time::sleep(target_frame_duration).await;
+        if text.len() > MAX_CLIPBOARD_SIZE {
+            log::warn!(
+                "Host clipboard of {} bytes exceeds the limit of {MAX_CLIPBOARD_SIZE}, not synced",
+                text.len()
+            );
             continue;
         }
 
-        let screenshot = capturer.screenshot()?;
-        if screenshot.is_empty() {
-            tokio::time::sleep(target_frame_duration).await;
+        if !manager.last_clipboard.replace(&text) {
             continue;
         }
 
-        // Force an intra frame on the periodic interval or when a new viewer just
-        // joined (swap clears the flag atomically so we only do it once).
-        if frame_count.is_multiple_of(KEYFRAME_INTERVAL)
-            || manager.force_keyframe.swap(false, Ordering::Relaxed)
-        {
-            encoder.force_intra_frame();
+        let mut clients = manager.clients.lock().await;
+        for client in clients.values_mut() {
+            if !client.options.clipboard_to_viewer {
+                continue;
+            }
+
+            let message = agent_message::Message::Clipboard(Clipboard { text: text.clone() });
+            let _ = client.send(message);
         }
+    }
+
+    manager.clipboard_watched.store(false, Ordering::Relaxed);
+}
+
+/// Frames are encoded once per level the viewers are at.
+struct Stream {
+    encoder: Encoder,
+    frame_count: u64,
+    next_frame: Instant,
+    /// Screen contents last encoded, to skip identical frames
+    generation: u64,
+    /// Set when a viewer of the level needs a keyframe, or the layout changed
+    keyframe: bool,
+    cursor: CursorTracker,
+}
+
+/// What the viewers of one level get for a capture.
+struct StreamOutput {
+    level: Level,
+    /// Sent along with keyframes, for the viewers that start decoding there
+    layout: bool,
+    cursor: Option<CursorUpdate>,
+    frame: Option<VideoFrame>,
+}
+
+impl Stream {
+    const KEYFRAME_INTERVAL: u64 = 60;
+
+    fn new(level: Level, now: Instant) -> Result<Self, Error> {
+        log::debug!(
+            "Remote desktop: encoding at {} FPS, {} kbit/s, downscaled by {}",
+            level.fps,
+            level.bitrate / 1000,
+            level.downscale
+        );
 
-        // screenshot is moved here; no clone needed since we no longer cache it.
+        Ok(Self {
+            encoder: new_encoder(level)?,
+            frame_count: 0,
+            next_frame: now,
+            generation: 0,
+            keyframe: true,
+            cursor: CursorTracker::default(),
+        })
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.keyframe || self.next_frame <= now
+    }
+
+    fn encode(&mut self, level: Level, capture: &Capture, timestamp_ms: u64) -> StreamOutput {
+        let keyframe_requested = std::mem::take(&mut self.keyframe);
+
+        // The pointer moves on its own, even when the frame is skipped below
+        let cursor = capture.cursor.clone().and_then(|current| {
+            self.cursor.update(
+                current,
+                capture.mapping.downscaled(level.downscale),
+                keyframe_requested,
+            )
+        });
+
+        let mut output = StreamOutput {
+            level,
+            layout: keyframe_requested,
+            cursor,
+            frame: None,
+        };
+
+        // Nothing changed on screen: don't spend bandwidth on an identical frame,
+        // unless a viewer needs a keyframe to start decoding.
+        if !keyframe_requested && capture.generation == self.generation {
+            return output;
+        }
+        self.generation = capture.generation;
+
+        // Force an intra frame on the periodic interval or when a viewer asked for one
+        if self.frame_count.is_multiple_of(Self::KEYFRAME_INTERVAL) || keyframe_requested {
+            self.encoder.force_intra_frame();
+        }
+        self.frame_count += 1;
+
+        let screenshot = capture.screenshot.clone().downscale(level.downscale);
+        let (width, height) = screenshot.dimensions();
         let yuv_frame = YUVBuffer::from_rgb8_source(screenshot);
 
-        let encoded = match encoder.encode(&yuv_frame) {
-            Ok(bits) => bits.to_vec(),
+        let (encoded, keyframe) = match self.encoder.encode(&yuv_frame) {
+            Ok(bits) => (
+                bits.to_vec(),
+                matches!(bits.frame_type(), FrameType::IDR | FrameType::I),
+            ),
             Err(e) => {
                 log::warn!("Failed to encode frame: {:?}", e);
-                frame_count += 1;
-                continue;
+                return output;
             }
         };
 
         if !encoded.is_empty() {
-            let clients = manager.clients.lock().await;
-            for client in clients.values() {
-                let _ = client.send(encoded.clone(), target_frame_duration).await;
+            output.frame = Some(VideoFrame {
+                codec: Codec::H264.into(),
+                width: width as u32,
+                height: height as u32,
+                keyframe,
+                timestamp_ms,
+                data: encoded,
+            });
+        }
+
+        output
+    }
+}
+
+/// One read of the screen, shared by the streams.
+struct Capture {
+    screenshot: Screenshot,
+    /// Bumped whenever the screen contents change
+    generation: u64,
+    cursor: Option<Cursor>,
+    /// Mapping of the full-size frames
+    mapping: InputMapping,
+}
+
+async fn capture_loop_impl(manager: RemoteDesktopManager) -> Result<(), Error> {
+    // How often monitors are enumerated again, to notice hotplugs and mode changes
+    const DISPLAY_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+    // Wait between checks while nobody is watching or the screen can't be read
+    const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+
+    let mut monitor = manager.monitor.subscribe();
+    // Sends the initial layout along with the first frame
+    monitor.mark_changed();
+
+    // The portal backend waits on the user to share the screen, up to a minute
+    let mut capturer = tokio::task::spawn_blocking(ScreenCapturer::new)
+        .await
+        .handle_err(location!())??;
+    let mut streams: HashMap<Level, Stream> = HashMap::new();
+    let mut previous_frame = Vec::new();
+    let mut generation: u64 = 0;
+    let session_start = Instant::now();
+    let mut last_display_refresh = session_start;
+
+    loop {
+        let now = Instant::now();
+
+        // The levels the viewers are at, and whether one of them waits for a keyframe
+        let mut levels: HashMap<Level, bool> = HashMap::new();
+        for client in manager.clients.lock().await.values() {
+            *levels.entry(client.level()).or_default() |= client.wants_keyframe();
+        }
+
+        // Skip capture entirely when nobody is watching.
+        if levels.is_empty() {
+            streams.clear();
+            tokio::time::sleep(IDLE_INTERVAL).await;
+            continue;
+        }
+
+        streams.retain(|level, _| levels.contains_key(level));
+        for (level, wants_keyframe) in levels {
+            let stream = match streams.entry(level) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                // A new encoder starts with a keyframe
+                Entry::Vacant(entry) => entry.insert(Stream::new(level, now)?),
+            };
+            stream.keyframe |= wants_keyframe;
+        }
+
+        let mut layout_changed = false;
+
+        if monitor.has_changed().unwrap_or(false) {
+            let selected = *monitor.borrow_and_update();
+            match capturer.select(selected) {
+                Ok(()) => layout_changed = true,
+                Err(err) => log::warn!("Cannot select monitor: {}", err.to_str()),
             }
         }
 
-        frame_count += 1;
+        if now.duration_since(last_display_refresh) >= DISPLAY_REFRESH_INTERVAL {
+            last_display_refresh = now;
+            layout_changed |= capturer.refresh()?;
+        }
+
+        // A new layout usually changes the frame size, which needs a keyframe anyway
+        if layout_changed {
+            for stream in streams.values_mut() {
+                stream.keyframe = true;
+            }
+        }
+
+        let due: Vec<Level> = streams
+            .iter()
+            .filter(|(_, stream)| stream.is_due(now))
+            .map(|(level, _)| *level)
+            .collect();
+
+        if !due.is_empty() {
+            for level in &due {
+                if let Some(stream) = streams.get_mut(level) {
+                    stream.next_frame = now + level.frame_duration();
+                }
+            }
+
+            let screenshot = capturer.screenshot()?;
+            if screenshot.is_empty() {
+                tokio::time::sleep(IDLE_INTERVAL).await;
+                continue;
+            }
+
+            if *screenshot != *previous_frame {
+                generation += 1;
+                previous_frame.clear();
+                previous_frame.extend_from_slice(&screenshot);
+            }
+
+            let mapping = capturer.input_mapping(1);
+            *manager.input_mapping.write().unwrap() = mapping;
 
-        let elapsed = frame_start.elapsed();
-        if elapsed < target_frame_duration {
-            tokio::time::sleep(target_frame_duration - elapsed).await;
+            let cursor = capturer.cursor().unwrap_or_else(|err| {
+                log::debug!("Cannot read the cursor: {}", err.to_str());
+                None
+            });
+
+            let capture = Capture {
+                screenshot,
+                generation,
+                cursor,
+                mapping,
+            };
+            let timestamp_ms = session_start.elapsed().as_millis() as u64;
+
+            let outputs: Vec<StreamOutput> = due
+                .into_iter()
+                .filter_map(|level| {
+                    let stream = streams.get_mut(&level)?;
+                    Some(stream.encode(level, &capture, timestamp_ms))
+                })
+                .collect();
+
+            send_outputs(&manager, &outputs, capturer.layout()).await;
         }
+
+        let next_frame = streams
+            .values()
+            .map(|stream| stream.next_frame)
+            .min()
+            .unwrap_or(now + IDLE_INTERVAL);
+        tokio::time::sleep_until(next_frame.into()).await;
     }
 }
+
+/// Hands the output of each stream to the viewers of its level, without waiting
+/// on any of them.
+async fn send_outputs(
+    manager: &RemoteDesktopManager,
+    outputs: &[StreamOutput],
+    layout: ScreenLayout,
+) {
+    let now = Instant::now();
+    let mut resumed = Vec::new();
+
+    let mut clients = manager.clients.lock().await;
+    for (id, client) in clients.iter_mut() {
+        let Some(output) = outputs.iter().find(|output| output.level == client.level()) else {
+            continue;
+        };
+
+        if output.layout {
+            let _ = client.send(agent_message::Message::ScreenLayout(layout.clone()));
+        }
+
+        if let Some(update) = &output.cursor {
+            let _ = client.send(agent_message::Message::Cursor(update.clone()));
+        }
+
+        if let Some(frame) = &output.frame
+            && client.send_frame(frame, now)
+        {
+            resumed.push(*id);
+        }
+    }
+
+    // Viewers that joined or missed messages may have missed a control change too
+    manager.send_control_state(&mut clients, |id| resumed.contains(&id));
+}
+
+fn new_encoder(level: Level) -> Result<Encoder, Error> {
+    let config = EncoderConfig::new()
+        .skip_frames(false)
+        .rate_control_mode(RateControlMode::Bitrate)
+        .bitrate(BitRate::from_bps(level.bitrate))
+        .max_frame_rate(FrameRate::from_hz(level.fps as f32));
+
+    Encoder::with_api_config(OpenH264API::from_source(), config).handle_err(location!())
+}
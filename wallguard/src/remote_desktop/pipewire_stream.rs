@@ -0,0 +1,345 @@
+use super::screenshot::Screenshot;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use pipewire as pw;
+use pw::properties::properties;
+use pw::spa;
+use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+use pw::stream::{StreamFlags, StreamListener, StreamRc, StreamState};
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Frames shared by the PipeWire thread.
+#[derive(Default)]
+struct Shared {
+    /// Latest frame of each stream, in the order of the node IDs
+    frames: Mutex<Vec<Option<Screenshot>>>,
+    /// Set once a stream is disconnected, e.g. sharing was stopped at the host
+    closed: AtomicBool,
+}
+
+/// Video streams of a portal session, read on a PipeWire thread.
+pub struct PipeWireStreams {
+    shared: Arc<Shared>,
+    quit: pw::channel::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PipeWireStreams {
+    /// Connects to the nodes with the given IDs through the `remote` opened by the portal.
+    pub fn connect(remote: OwnedFd, node_ids: Vec<u32>) -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            frames: Mutex::new(vec![None; node_ids.len()]),
+            closed: AtomicBool::new(false),
+        });
+        let (quit, quit_receiver) = pw::channel::channel();
+        let (ready, connected) = std::sync::mpsc::channel();
+
+        let thread_shared = shared.clone();
+        let thread = std::thread::spawn(move || {
+            if let Err(err) = stream_loop(remote, &node_ids, thread_shared, quit_receiver, &ready) {
+                let _ = ready.send(Err(err.to_str().to_string()));
+            }
+        });
+
+        connected
+            .recv()
+            .map_err(|_| String::from("The PipeWire thread exited"))
+            .and_then(|result| result)
+            .handle_err(location!())?;
+
+        Ok(Self {
+            shared,
+            quit,
+            thread: Some(thread),
+        })
+    }
+
+    /// The latest frame of the stream at `index`, `None` until one arrives.
+    pub fn frame(&self, index: usize) -> Result<Option<Screenshot>, Error> {
+        if self.shared.closed.load(Ordering::Relaxed) {
+            return Err("Screen sharing was stopped at the host").handle_err(location!());
+        }
+
+        let frames = self
+            .shared
+            .frames
+            .lock()
+            .map_err(|_| "The PipeWire frames lock is poisoned")
+            .handle_err(location!())?;
+        Ok(frames.get(index).cloned().flatten())
+    }
+}
+
+impl Drop for PipeWireStreams {
+    fn drop(&mut self) {
+        let _ = self.quit.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct StreamData {
+    index: usize,
+    format: VideoInfoRaw,
+    shared: Arc<Shared>,
+}
+
+fn stream_loop(
+    remote: OwnedFd,
+    node_ids: &[u32],
+    shared: Arc<Shared>,
+    quit: pw::channel::Receiver<()>,
+    ready: &std::sync::mpsc::Sender<Result<(), String>>,
+) -> Result<(), Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoopRc::new(None).handle_err(location!())?;
+    let context = pw::context::ContextRc::new(&mainloop, None).handle_err(location!())?;
+    let core = context
+        .connect_fd_rc(remote, None)
+        .handle_err(location!())?;
+
+    let _quit = quit.attach(mainloop.loop_(), {
+        let mainloop = mainloop.clone();
+        move |()| mainloop.quit()
+    });
+
+    let format = format_param()?;
+    let mut streams: Vec<(StreamRc, StreamListener<StreamData>)> = Vec::new();
+
+    for (index, node_id) in node_ids.iter().enumerate() {
+        let stream = StreamRc::new(
+            core.clone(),
+            "wallguard-screen",
+            properties! {
+                *pw::keys::MEDIA_TYPE => "Video",
+                *pw::keys::MEDIA_CATEGORY => "Capture",
+                *pw::keys::MEDIA_ROLE => "Screen",
+            },
+        )
+        .handle_err(location!())?;
+
+        let data = StreamData {
+            index,
+            format: VideoInfoRaw::default(),
+            shared: shared.clone(),
+        };
+
+        let listener = stream
+            .add_local_listener_with_user_data(data)
+            .state_changed(|_, data, _, new| {
+                if let StreamState::Error(err) = &new {
+                    log::warn!("PipeWire stream failed: {err}");
+                }
+                if matches!(new, StreamState::Error(_) | StreamState::Unconnected) {
+                    data.shared.closed.store(true, Ordering::Relaxed);
+                }
+            })
+            .param_changed(|_, data, id, param| {
+                let Some(param) = param else {
+                    return;
+                };
+                if id != spa::param::ParamType::Format.as_raw() {
+                    return;
+                }
+
+                if let Err(err) = data.format.parse(param) {
+                    log::warn!("Cannot parse the PipeWire video format: {err}");
+                }
+            })
+            .process(|stream, data| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let Some(first) = buffer.datas_mut().first_mut() else {
+                    return;
+                };
+
+                let chunk = first.chunk();
+                let (offset, size, stride) = (
+                    chunk.offset() as usize,
+                    chunk.size() as usize,
+                    chunk.stride(),
+                );
+                // Frames with no size only move the pointer
+                if size == 0 {
+                    return;
+                }
+
+                // Only memory buffers are mapped, DMA-BUF is not negotiated
+                let Some(bytes) = first.data() else {
+                    return;
+                };
+                let Some(bytes) = bytes.get(offset..offset + size) else {
+                    return;
+                };
+
+                let (width, height) = (
+                    data.format.size().width as usize,
+                    data.format.size().height as usize,
+                );
+                let stride = usize::try_from(stride)
+                    .ok()
+                    .filter(|stride| *stride > 0)
+                    .unwrap_or(width * 4);
+
+                let frame = channel_order(data.format.format())
+                    .and_then(|order| packed_to_rgb(bytes, stride, width, height, order));
+                // A poisoned lock fails the capture when the frame is read
+                if let Some(rgb) = frame
+                    && let Ok(mut frames) = data.shared.frames.lock()
+                    && let Some(slot) = frames.get_mut(data.index)
+                {
+                    *slot = Some(Screenshot::new(rgb, width, height));
+                }
+            })
+            .register()
+            .handle_err(location!())?;
+
+        let pod = spa::pod::Pod::from_bytes(&format)
+            .ok_or("Invalid PipeWire format parameter")
+            .handle_err(location!())?;
+        stream
+            .connect(
+                spa::utils::Direction::Input,
+                Some(*node_id),
+                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+                &mut [pod],
+            )
+            .handle_err(location!())?;
+
+        streams.push((stream, listener));
+    }
+
+    let _ = ready.send(Ok(()));
+    mainloop.run();
+
+    Ok(())
+}
+
+/// The raw video formats accepted, 32 bits per pixel.
+fn format_param() -> Result<Vec<u8>, Error> {
+    let object = spa::pod::object!(
+        spa::utils::SpaTypes::ObjectParamFormat,
+        spa::param::ParamType::EnumFormat,
+        spa::pod::property!(
+            spa::param::format::FormatProperties::MediaType,
+            Id,
+            spa::param::format::MediaType::Video
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::MediaSubtype,
+            Id,
+            spa::param::format::MediaSubtype::Raw
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::BGRx,
+            VideoFormat::BGRx,
+            VideoFormat::BGRA,
+            VideoFormat::RGBx,
+            VideoFormat::RGBA,
+            VideoFormat::xRGB,
+            VideoFormat::ARGB,
+            VideoFormat::xBGR,
+            VideoFormat::ABGR,
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            spa::utils::Rectangle {
+                width: 1920,
+                height: 1080
+            },
+            spa::utils::Rectangle {
+                width: 1,
+                height: 1
+            },
+            spa::utils::Rectangle {
+                width: 8192,
+                height: 8192
+            }
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            spa::utils::Fraction { num: 30, denom: 1 },
+            spa::utils::Fraction { num: 0, denom: 1 },
+            spa::utils::Fraction { num: 144, denom: 1 }
+        ),
+    );
+
+    let (cursor, _) = spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(object),
+    )
+    .map_err(|err| format!("Cannot serialize the PipeWire format: {err:?}"))
+    .handle_err(location!())?;
+
+    Ok(cursor.into_inner())
+}
+
+/// Offsets of the red, green and blue bytes in a pixel of `format`.
+fn channel_order(format: VideoFormat) -> Option<[usize; 3]> {
+    match format {
+        VideoFormat::BGRx | VideoFormat::BGRA => Some([2, 1, 0]),
+        VideoFormat::RGBx | VideoFormat::RGBA => Some([0, 1, 2]),
+        VideoFormat::xRGB | VideoFormat::ARGB => Some([1, 2, 3]),
+        VideoFormat::xBGR | VideoFormat::ABGR => Some([3, 2, 1]),
+        _ => None,
+    }
+}
+
+/// Converts 32-bit pixels to packed RGB, stripping the row padding.
+fn packed_to_rgb(
+    data: &[u8],
+    stride: usize,
+    width: usize,
+    height: usize,
+    [r, g, b]: [usize; 3],
+) -> Option<Vec<u8>> {
+    if width == 0 || height == 0 || data.len() < stride * (height - 1) + width * 4 {
+        return None;
+    }
+
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for row in data.chunks(stride).take(height) {
+        for pixel in row[..width * 4].chunks_exact(4) {
+            rgb.extend_from_slice(&[pixel[r], pixel[g], pixel[b]]);
+        }
+    }
+
+    Some(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_to_rgb() {
+        // Two BGRx pixels per row, padded to 12 bytes
+        let data = [
+            3, 2, 1, 0, 6, 5, 4, 0, 9, 9, 9, 9, //
+            9, 8, 7, 0, 12, 11, 10, 0,
+        ];
+        let order = channel_order(VideoFormat::BGRx).unwrap();
+
+        assert_eq!(
+            packed_to_rgb(&data, 12, 2, 2, order).unwrap(),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+        );
+        assert!(packed_to_rgb(&data[..16], 12, 2, 2, order).is_none());
+        assert!(channel_order(VideoFormat::I420).is_none());
+    }
+}
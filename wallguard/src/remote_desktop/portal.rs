@@ -0,0 +1,446 @@
+use super::display::Rect;
+use super::keyboard::PhysicalKey;
+use super::uinput_handler::{MouseButton, named_key};
+use crate::storage::{Secret, Storage};
+use ashpd::desktop::remote_desktop::{DeviceType, KeyState, RemoteDesktop};
+use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType, Stream};
+use ashpd::desktop::{PersistMode, Session};
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use std::os::fd::OwnedFd;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long the user at the host has to answer the screen sharing dialog.
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Input of the session being shared, `None` without RemoteDesktop access.
+static INPUT: Mutex<Option<PortalInput>> = Mutex::new(None);
+
+/// A monitor shared through the portal.
+#[derive(Debug, Clone)]
+pub struct PortalStream {
+    pub node_id: u32,
+    /// Where the monitor is, in logical desktop coordinates
+    pub rect: Rect,
+}
+
+/// A ScreenCast session, along with RemoteDesktop when the portal offers it.
+///
+/// The session stays open on its own thread until this is dropped.
+pub struct PortalSession {
+    pub streams: Vec<PortalStream>,
+    _close: oneshot::Sender<()>,
+}
+
+impl PortalSession {
+    /// Opens a session and the PipeWire remote its streams are read from.
+    ///
+    /// The user at the host is asked once: the restore token of the previous
+    /// session lets the portal skip the dialog as long as it stays valid.
+    pub fn start() -> Result<(Self, OwnedFd), Error> {
+        let (ready, started) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .handle_err(location!());
+
+            match rt {
+                Ok(rt) => rt.block_on(run(ready)),
+                Err(err) => {
+                    let _ = ready.send(Err(err));
+                }
+            }
+        });
+
+        match started.recv_timeout(CONSENT_TIMEOUT) {
+            Ok(result) => result,
+            Err(_) => Err("The screen sharing dialog was not answered").handle_err(location!()),
+        }
+    }
+}
+
+impl Drop for PortalSession {
+    fn drop(&mut self) {
+        // A poisoned lock is logged by shared_input
+        if let Ok(mut input) = shared_input() {
+            *input = None;
+        }
+    }
+}
+
+/// The portal session currently sharing the screen, `None` without RemoteDesktop access.
+pub fn input() -> Result<Option<PortalInput>, Error> {
+    Ok(shared_input()?.clone())
+}
+
+fn shared_input() -> Result<MutexGuard<'static, Option<PortalInput>>, Error> {
+    INPUT
+        .lock()
+        .map_err(|_| "The portal input lock is poisoned")
+        .handle_err(location!())
+}
+
+enum Portal {
+    RemoteDesktop {
+        proxy: RemoteDesktop<'static>,
+        session: Session<'static, RemoteDesktop<'static>>,
+    },
+    Screencast {
+        session: Session<'static, Screencast<'static>>,
+    },
+}
+
+/// What a portal session grants, once started.
+struct Started {
+    portal: Portal,
+    streams: Vec<Stream>,
+    restore_token: Option<String>,
+    input: bool,
+}
+
+async fn run(ready: std::sync::mpsc::Sender<Result<(PortalSession, OwnedFd), Error>>) {
+    let (close, closed) = oneshot::channel();
+    let (commands, mut receiver) = mpsc::unbounded_channel();
+
+    let screencast = match Screencast::new().await.handle_err(location!()) {
+        Ok(screencast) => screencast,
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+
+    let started = match open(&screencast).await {
+        Ok(started) => started,
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+
+    if let Some(token) = &started.restore_token
+        && let Err(err) = Storage::set_value(Secret::PortalRestoreToken, token).await
+    {
+        log::warn!("Cannot save the portal restore token: {}", err.to_str());
+    }
+
+    let remote = match &started.portal {
+        Portal::RemoteDesktop { session, .. } => screencast.open_pipe_wire_remote(session).await,
+        Portal::Screencast { session } => screencast.open_pipe_wire_remote(session).await,
+    };
+
+    let result = remote.handle_err(location!()).and_then(|remote| {
+        let streams = portal_streams(&started.streams);
+
+        if started.input {
+            *shared_input()? = Some(PortalInput {
+                commands,
+                streams: streams.clone(),
+            });
+        }
+
+        let session = PortalSession {
+            streams,
+            _close: close,
+        };
+        Ok((session, remote))
+    });
+
+    // Nobody waits for the session anymore: dropping it closes it right away
+    let failed = result.is_err();
+    let _ = ready.send(result);
+
+    if !failed {
+        let mut closed = closed;
+        loop {
+            tokio::select! {
+                _ = &mut closed => break,
+                Some(command) = receiver.recv() => {
+                    if let Portal::RemoteDesktop { proxy, session } = &started.portal
+                        && let Err(err) = notify(proxy, session, command).await
+                    {
+                        log::debug!("Portal input failed: {err}");
+                    }
+                }
+            }
+        }
+    }
+
+    let closing = match &started.portal {
+        Portal::RemoteDesktop { session, .. } => session.close().await,
+        Portal::Screencast { session } => session.close().await,
+    };
+    if let Err(err) = closing {
+        log::debug!("Cannot close the portal session: {err}");
+    }
+}
+
+/// Starts a RemoteDesktop session sharing the monitors, or a ScreenCast one
+/// when the portal has no RemoteDesktop interface.
+async fn open(screencast: &Screencast<'static>) -> Result<Started, Error> {
+    let token = Storage::get_value(Secret::PortalRestoreToken).await;
+
+    match open_remote_desktop(screencast, token.as_deref()).await {
+        Ok(started) => return Ok(started),
+        Err(ashpd::Error::Response(err)) => {
+            return Err(format!("Screen sharing was refused: {err}")).handle_err(location!());
+        }
+        Err(err) => log::info!("RemoteDesktop portal unavailable ({err}), sharing the screen only"),
+    }
+
+    let session = screencast.create_session().await.handle_err(location!())?;
+    screencast
+        .select_sources(
+            &session,
+            CursorMode::Embedded,
+            SourceType::Monitor.into(),
+            true,
+            token.as_deref(),
+            PersistMode::ExplicitlyRevoked,
+        )
+        .await
+        .handle_err(location!())?;
+    let response = screencast
+        .start(&session, None)
+        .await
+        .and_then(|request| request.response())
+        .handle_err(location!())?;
+
+    Ok(Started {
+        streams: response.streams().to_vec(),
+        restore_token: response.restore_token().map(String::from),
+        portal: Portal::Screencast { session },
+        input: false,
+    })
+}
+
+async fn open_remote_desktop(
+    screencast: &Screencast<'static>,
+    token: Option<&str>,
+) -> Result<Started, ashpd::Error> {
+    let proxy = RemoteDesktop::new().await?;
+    let session = proxy.create_session().await?;
+
+    // RemoteDesktop sessions are persisted as a whole, screen included
+    proxy
+        .select_devices(
+            &session,
+            DeviceType::Keyboard | DeviceType::Pointer,
+            token,
+            PersistMode::ExplicitlyRevoked,
+        )
+        .await?;
+    screencast
+        .select_sources(
+            &session,
+            CursorMode::Embedded,
+            SourceType::Monitor.into(),
+            true,
+            None,
+            PersistMode::DoNot,
+        )
+        .await?;
+    let response = proxy.start(&session, None).await?.response()?;
+
+    Ok(Started {
+        streams: response.streams().unwrap_or_default().to_vec(),
+        restore_token: response.restore_token().map(String::from),
+        input: !response.devices().is_empty(),
+        portal: Portal::RemoteDesktop { proxy, session },
+    })
+}
+
+fn portal_streams(streams: &[Stream]) -> Vec<PortalStream> {
+    streams
+        .iter()
+        .filter_map(|stream| {
+            let Some((width, height)) = stream.size() else {
+                log::warn!(
+                    "Portal stream {} has no size, ignored",
+                    stream.pipe_wire_node_id()
+                );
+                return None;
+            };
+            let (x, y) = stream.position().unwrap_or_default();
+
+            Some(PortalStream {
+                node_id: stream.pipe_wire_node_id(),
+                rect: Rect::new(x, y, width as u32, height as u32),
+            })
+        })
+        .collect()
+}
+
+// ── input ─────────────────────────────────────────────────────────────────────
+
+enum Command {
+    PointerMotion { stream: u32, x: f64, y: f64 },
+    PointerButton { button: i32, pressed: bool },
+    Keycode { evdev: i32, pressed: bool },
+    Keysym { keysym: i32, pressed: bool },
+}
+
+async fn notify(
+    proxy: &RemoteDesktop<'static>,
+    session: &Session<'static, RemoteDesktop<'static>>,
+    command: Command,
+) -> Result<(), ashpd::Error> {
+    let state = |pressed| {
+        if pressed {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        }
+    };
+
+    match command {
+        Command::PointerMotion { stream, x, y } => {
+            proxy
+                .notify_pointer_motion_absolute(session, stream, x, y)
+                .await
+        }
+        Command::PointerButton { button, pressed } => {
+            proxy
+                .notify_pointer_button(session, button, state(pressed))
+                .await
+        }
+        Command::Keycode { evdev, pressed } => {
+            proxy
+                .notify_keyboard_keycode(session, evdev, state(pressed))
+                .await
+        }
+        Command::Keysym { keysym, pressed } => {
+            proxy
+                .notify_keyboard_keysym(session, keysym, state(pressed))
+                .await
+        }
+    }
+}
+
+/// Sends input to the compositor through the RemoteDesktop portal, which
+/// applies the host keymap itself.
+#[derive(Clone)]
+pub struct PortalInput {
+    commands: mpsc::UnboundedSender<Command>,
+    streams: Vec<PortalStream>,
+}
+
+impl PortalInput {
+    /// `x` and `y` are in logical desktop coordinates.
+    pub fn move_mouse(&self, x: i32, y: i32) -> Result<(), Error> {
+        // The portal only moves the pointer within a stream
+        let Some(stream) = self
+            .streams
+            .iter()
+            .find(|stream| contains(stream.rect, x, y))
+        else {
+            return Ok(());
+        };
+
+        self.send(Command::PointerMotion {
+            stream: stream.node_id,
+            x: f64::from(x - stream.rect.x),
+            y: f64::from(y - stream.rect.y),
+        })
+    }
+
+    pub fn button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        self.send(Command::PointerButton {
+            button: i32::from(button.evdev_code()),
+            pressed,
+        })
+    }
+
+    /// Presses or releases the key at `code`, or the key typing `key` without one.
+    pub fn key(&self, key: &str, code: &str, pressed: bool) -> Result<(), Error> {
+        if let Some(evdev) = PhysicalKey::from_code(code)
+            .map(|physical| physical.evdev)
+            .or_else(|| named_key(key))
+        {
+            return self.send(Command::Keycode {
+                evdev: i32::from(evdev),
+                pressed,
+            });
+        }
+
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.send(Command::Keysym {
+                keysym: keysym(c),
+                pressed,
+            }),
+            _ => {
+                log::debug!("Unknown key '{key}', ignored");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn text(&self, text: &str) -> Result<(), Error> {
+        for c in text.chars().filter(|c| *c != '\r') {
+            let keysym = keysym(c);
+            self.send(Command::Keysym {
+                keysym,
+                pressed: true,
+            })?;
+            self.send(Command::Keysym {
+                keysym,
+                pressed: false,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn send(&self, command: Command) -> Result<(), Error> {
+        self.commands
+            .send(command)
+            .map_err(|_| "The portal session is closed")
+            .handle_err(location!())
+    }
+}
+
+fn contains(rect: Rect, x: i32, y: i32) -> bool {
+    x >= rect.x
+        && y >= rect.y
+        && i64::from(x) < i64::from(rect.x) + i64::from(rect.width)
+        && i64::from(y) < i64::from(rect.y) + i64::from(rect.height)
+}
+
+/// The X keysym typing `c`, whatever the host layout.
+fn keysym(c: char) -> i32 {
+    match c {
+        '\n' => 0xff0d,
+        '\t' => 0xff09,
+        '\u{8}' => 0xff08,
+        // Latin-1 keysyms are the code points, the others are offset
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as i32,
+        c => 0x0100_0000 | c as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keysym() {
+        assert_eq!(keysym('a'), 0x61);
+        assert_eq!(keysym('~'), 0x7e);
+        assert_eq!(keysym('é'), 0xe9);
+        assert_eq!(keysym('€'), 0x0100_20ac);
+        assert_eq!(keysym('\n'), 0xff0d);
+    }
+
+    #[test]
+    fn test_contains() {
+        let rect = Rect::new(-1920, 0, 1920, 1080);
+        assert!(contains(rect, -1920, 0));
+        assert!(contains(rect, -1, 1079));
+        assert!(!contains(rect, 0, 0));
+        assert!(!contains(rect, -1920, 1080));
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+use wallguard_common::protobuf::wallguard_rd::Quality;
+
+/// Encoding settings of one step of the quality ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Level {
+    pub fps: u32,
+    /// Target bitrate of the encoder, in bits per second
+    pub bitrate: u32,
+    /// The screen is shrunk by this factor in both dimensions before encoding
+    pub downscale: usize,
+}
+
+impl Level {
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+}
+
+/// From the best to the worst quality.
+const LEVELS: [Level; 5] = [
+    Level {
+        fps: 30,
+        bitrate: 8_000_000,
+        downscale: 1,
+    },
+    Level {
+        fps: 24,
+        bitrate: 4_000_000,
+        downscale: 1,
+    },
+    Level {
+        fps: 15,
+        bitrate: 2_000_000,
+        downscale: 1,
+    },
+    Level {
+        fps: 10,
+        bitrate: 1_000_000,
+        downscale: 2,
+    },
+    Level {
+        fps: 5,
+        bitrate: 500_000,
+        downscale: 2,
+    },
+];
+
+/// Minimum time between two steps down, so that the previous one can take effect.
+const STEP_DOWN_DELAY: Duration = Duration::from_secs(1);
+
+/// Minimum time without congestion before stepping up again.
+const STEP_UP_DELAY: Duration = Duration::from_secs(5);
+
+/// Picks the encoding settings of a viewer from the quality it requested and
+/// from how full its channel is.
+///
+/// Frames waiting in the channel mean that the link can't keep up: the
+/// quality goes down one step at a time, and back up once the link has been
+/// idle for a while, without exceeding the preset.
+#[derive(Debug)]
+pub struct RateController {
+    /// Best level allowed by the requested quality
+    ceiling: usize,
+    level: usize,
+    last_change: Instant,
+    last_congestion: Instant,
+}
+
+impl RateController {
+    pub fn new(quality: Quality) -> Self {
+        let ceiling = ceiling(quality);
+        let now = Instant::now();
+
+        Self {
+            ceiling,
+            level: ceiling,
+            last_change: now,
+            last_congestion: now,
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        LEVELS[self.level]
+    }
+
+    /// Applies a preset requested by the viewer, starting over from its best level.
+    pub fn set_quality(&mut self, quality: Quality, now: Instant) {
+        self.ceiling = ceiling(quality);
+        self.level = self.ceiling;
+        self.last_change = now;
+        self.last_congestion = now;
+    }
+
+    /// Records how full the channel of the viewer is (between 0 and 1) once a
+    /// frame was queued, or 1 if it was dropped, returning whether the level changed.
+    pub fn on_frame_sent(&mut self, occupancy: f32, now: Instant) -> bool {
+        let congested = occupancy > 0.5;
+
+        if congested {
+            self.last_congestion = now;
+
+            if self.level + 1 < LEVELS.len()
+                && now.duration_since(self.last_change) >= STEP_DOWN_DELAY
+            {
+                self.level += 1;
+                self.last_change = now;
+                return true;
+            }
+        } else if self.level > self.ceiling
+            && now.duration_since(self.last_change) >= STEP_UP_DELAY
+            && now.duration_since(self.last_congestion) >= STEP_UP_DELAY
+        {
+            self.level -= 1;
+            self.last_change = now;
+            return true;
+        }
+
+        false
+    }
+}
+
+fn ceiling(quality: Quality) -> usize {
+    match quality {
+        Quality::High => 0,
+        Quality::Balanced | Quality::Unspecified => 1,
+        Quality::Low => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapts_to_congestion() {
+        let mut controller = RateController::new(Quality::Balanced);
+        let start = Instant::now();
+        assert_eq!(controller.level(), LEVELS[1]);
+
+        // Full channels: one step down per STEP_DOWN_DELAY at most
+        assert!(!controller.on_frame_sent(0.9, start));
+        assert!(controller.on_frame_sent(0.9, start + STEP_DOWN_DELAY));
+        assert_eq!(controller.level(), LEVELS[2]);
+
+        // Back up once the link has been idle long enough, not above the preset
+        let idle = start + STEP_DOWN_DELAY + STEP_UP_DELAY;
+        assert!(controller.on_frame_sent(0.0, idle));
+        assert_eq!(controller.level(), LEVELS[1]);
+        assert!(!controller.on_frame_sent(0.0, idle + STEP_UP_DELAY));
+
+        controller.set_quality(Quality::High, idle);
+        assert_eq!(controller.level(), LEVELS[0]);
+    }
+}
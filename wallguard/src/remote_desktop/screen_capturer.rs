@@ -1,24 +1,106 @@
+use super::cursor::Cursor;
+use super::display::{Display, InputMapping, Rect};
 use super::screenshot::Screenshot;
-use nullnet_liberror::Error;
+use nullnet_liberror::{Error, ErrorHandler, Location, location};
+use wallguard_common::protobuf::wallguard_rd::ScreenLayout;
 
 pub struct ScreenCapturer {
     inner: Box<dyn PlatformCapturer + Send>,
+    displays: Vec<Display>,
+    /// Monitor being captured, `None` for the whole desktop
+    selected: Option<u32>,
 }
 
 impl ScreenCapturer {
     pub fn new() -> Result<Self, Error> {
+        let mut inner = create_capturer()?;
+        let displays = inner.displays()?;
+
         Ok(Self {
-            inner: create_capturer()?,
+            inner,
+            displays,
+            selected: None,
         })
     }
 
+    /// Reads the monitors again, returning whether they changed.
+    pub fn refresh(&mut self) -> Result<bool, Error> {
+        let displays = self.inner.displays()?;
+        if displays == self.displays {
+            return Ok(false);
+        }
+
+        self.displays = displays;
+
+        // The selected monitor was unplugged: show the whole desktop instead
+        if self.selected_display().is_none() {
+            self.selected = None;
+        }
+
+        Ok(true)
+    }
+
+    /// Captures the monitor with the given ID from now on, or the whole desktop.
+    pub fn select(&mut self, id: Option<u32>) -> Result<(), Error> {
+        if let Some(id) = id
+            && !self.displays.iter().any(|display| display.id == id)
+        {
+            return Err(format!("No monitor with ID {id}")).handle_err(location!());
+        }
+
+        self.selected = id;
+        Ok(())
+    }
+
+    pub fn layout(&self) -> ScreenLayout {
+        Display::layout(&self.displays, self.selected)
+    }
+
+    /// How the pixels of the frames, once downscaled by `downscale`, map to the desktop.
+    pub fn input_mapping(&self, downscale: usize) -> InputMapping {
+        let desktop =
+            Rect::bounding(self.displays.iter().map(|display| display.rect)).unwrap_or_default();
+
+        // The whole desktop is captured at one pixel per unit
+        let (viewport, scale) = match self.selected_display() {
+            Some(display) => (display.rect, display.scale),
+            None => (desktop, 1.0),
+        };
+
+        InputMapping {
+            viewport,
+            pixels_per_unit: scale / downscale as f64,
+            desktop,
+        }
+    }
+
     pub fn screenshot(&mut self) -> Result<Screenshot, Error> {
-        self.inner.capture()
+        let display = self.selected_display().cloned();
+        self.inner.capture(display.as_ref())
+    }
+
+    /// The pointer, `None` if the backend draws it in the frames instead.
+    pub fn cursor(&mut self) -> Result<Option<Cursor>, Error> {
+        self.inner.cursor()
+    }
+
+    fn selected_display(&self) -> Option<&Display> {
+        let id = self.selected?;
+        self.displays.iter().find(|display| display.id == id)
     }
 }
 
 trait PlatformCapturer {
-    fn capture(&mut self) -> Result<Screenshot, Error>;
+    /// The monitors of the host.
+    fn displays(&mut self) -> Result<Vec<Display>, Error>;
+
+    /// Captures `display`, or the whole desktop when `None`.
+    fn capture(&mut self, display: Option<&Display>) -> Result<Screenshot, Error>;
+
+    /// The pointer, for backends that leave it out of the frames.
+    fn cursor(&mut self) -> Result<Option<Cursor>, Error> {
+        Ok(None)
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -33,10 +115,17 @@ fn create_capturer() -> Result<Box<dyn PlatformCapturer + Send>, Error> {
                 log::info!("Screen capture: using Wayland (wlr-screencopy) backend");
                 return Ok(Box::new(c));
             }
-            Err(e) => log::debug!(
-                "Wayland capturer unavailable ({}); falling back to X11",
-                e.to_str()
-            ),
+            Err(e) => log::debug!("Wayland capturer unavailable ({})", e.to_str()),
+        }
+
+        // GNOME and KDE only share the screen through the ScreenCast portal
+        #[cfg(feature = "pipewire")]
+        match portal_backend::PortalCapturer::new() {
+            Ok(c) => {
+                log::info!("Screen capture: using ScreenCast portal (PipeWire) backend");
+                return Ok(Box::new(c));
+            }
+            Err(e) => log::debug!("Portal capturer unavailable ({})", e.to_str()),
         }
     }
 
@@ -56,7 +145,6 @@ fn create_capturer() -> Result<Box<dyn PlatformCapturer + Send>, Error> {
 
 #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "windows")))]
 fn create_capturer() -> Result<Box<dyn PlatformCapturer + Send>, Error> {
-    use nullnet_liberror::{ErrorHandler, Location, location};
     Err("Screen capture is not supported on this platform").handle_err(location!())
 }
 
@@ -64,10 +152,13 @@ fn create_capturer() -> Result<Box<dyn PlatformCapturer + Send>, Error> {
 
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 mod x11 {
-    use super::{PlatformCapturer, Screenshot};
+    use super::{Cursor, Display, PlatformCapturer, Rect, Screenshot};
+    use crate::remote_desktop::cursor::CursorImage;
     use nullnet_liberror::{Error, ErrorHandler, Location, location};
     use x11rb::{
         connection::Connection,
+        protocol::randr::ConnectionExt as _,
+        protocol::xfixes::ConnectionExt as _,
         protocol::xproto::{ConnectionExt, ImageFormat},
         rust_connection::RustConnection,
     };
@@ -75,9 +166,10 @@ mod x11 {
     pub struct X11Capturer {
         conn: RustConnection,
         root: u32,
-        width: u16,
-        height: u16,
         bits_per_pixel: u8,
+        /// Whether XFixes can report the cursor, which GetImage leaves out
+        xfixes: bool,
+        cursor_serial: Option<u32>,
     }
 
     impl X11Capturer {
@@ -85,7 +177,7 @@ mod x11 {
             let (conn, screen_num) = RustConnection::connect(None).handle_err(location!())?;
 
             // Borrow conn inside a block so the borrow ends before conn is moved into Self.
-            let (root, width, height, bits_per_pixel) = {
+            let (root, bits_per_pixel) = {
                 let setup = conn.setup();
                 let screen = &setup.roots[screen_num];
                 let bpp = setup
@@ -94,35 +186,118 @@ mod x11 {
                     .find(|f| f.depth == screen.root_depth)
                     .map(|f| f.bits_per_pixel)
                     .unwrap_or(32);
-                (
-                    screen.root,
-                    screen.width_in_pixels,
-                    screen.height_in_pixels,
-                    bpp,
-                )
+                (screen.root, bpp)
             };
 
+            // XFixes must be told the version we speak before any other request
+            let xfixes = conn
+                .xfixes_query_version(4, 0)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .is_some();
+            if !xfixes {
+                log::info!("XFixes unavailable, the cursor is not streamed");
+            }
+
             Ok(Self {
                 root,
-                width,
-                height,
                 bits_per_pixel,
                 conn,
+                xfixes,
+                cursor_serial: None,
             })
         }
+
+        /// The root window spans the whole desktop; its size follows RandR changes.
+        fn root_rect(&self) -> Result<Rect, Error> {
+            let geometry = self
+                .conn
+                .get_geometry(self.root)
+                .handle_err(location!())?
+                .reply()
+                .handle_err(location!())?;
+
+            Ok(Rect::new(
+                0,
+                0,
+                u32::from(geometry.width),
+                u32::from(geometry.height),
+            ))
+        }
+
+        /// Monitors as reported by RandR, identified by their name atom.
+        fn randr_monitors(&self) -> Result<Vec<Display>, Error> {
+            let reply = self
+                .conn
+                .randr_get_monitors(self.root, true)
+                .handle_err(location!())?
+                .reply()
+                .handle_err(location!())?;
+
+            let mut displays = Vec::with_capacity(reply.monitors.len());
+            for monitor in reply.monitors {
+                let name = self
+                    .conn
+                    .get_atom_name(monitor.name)
+                    .handle_err(location!())?
+                    .reply()
+                    .handle_err(location!())?
+                    .name;
+
+                displays.push(Display {
+                    id: monitor.name,
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    rect: Rect::new(
+                        i32::from(monitor.x),
+                        i32::from(monitor.y),
+                        u32::from(monitor.width),
+                        u32::from(monitor.height),
+                    ),
+                    scale: 1.0,
+                    primary: monitor.primary,
+                });
+            }
+
+            Ok(displays)
+        }
     }
 
     impl PlatformCapturer for X11Capturer {
-        fn capture(&mut self) -> Result<Screenshot, Error> {
+        fn displays(&mut self) -> Result<Vec<Display>, Error> {
+            match self.randr_monitors() {
+                Ok(displays) if !displays.is_empty() => Ok(displays),
+                result => {
+                    if let Err(err) = result {
+                        log::debug!("RandR monitors unavailable: {}", err.to_str());
+                    }
+
+                    // Without RandR the whole screen is a single monitor
+                    Ok(vec![Display {
+                        id: 0,
+                        name: String::from("screen"),
+                        rect: self.root_rect()?,
+                        scale: 1.0,
+                        primary: true,
+                    }])
+                }
+            }
+        }
+
+        fn capture(&mut self, display: Option<&Display>) -> Result<Screenshot, Error> {
+            let rect = match display {
+                Some(display) => display.rect,
+                None => self.root_rect()?,
+            };
+
             let reply = self
                 .conn
                 .get_image(
                     ImageFormat::Z_PIXMAP,
                     self.root,
-                    0,
-                    0,
-                    self.width,
-                    self.height,
+                    rect.x as i16,
+                    rect.y as i16,
+                    rect.width as u16,
+                    rect.height as u16,
                     !0u32,
                 )
                 .handle_err(location!())?
@@ -133,10 +308,61 @@ mod x11 {
 
             Ok(Screenshot::new(
                 rgb,
-                self.width as usize,
-                self.height as usize,
+                rect.width as usize,
+                rect.height as usize,
             ))
         }
+
+        fn cursor(&mut self) -> Result<Option<Cursor>, Error> {
+            if !self.xfixes {
+                return Ok(None);
+            }
+
+            let reply = self
+                .conn
+                .xfixes_get_cursor_image()
+                .handle_err(location!())?
+                .reply()
+                .handle_err(location!())?;
+
+            // The serial changes with the shape, which is only converted then
+            let mut image = None;
+            if self.cursor_serial != Some(reply.cursor_serial) {
+                self.cursor_serial = Some(reply.cursor_serial);
+                image = Some(CursorImage {
+                    width: u32::from(reply.width),
+                    height: u32::from(reply.height),
+                    hotspot_x: u32::from(reply.xhot),
+                    hotspot_y: u32::from(reply.yhot),
+                    pixels: argb_to_rgba(&reply.cursor_image),
+                });
+            }
+
+            Ok(Some(Cursor {
+                x: i32::from(reply.x),
+                y: i32::from(reply.y),
+                // XFixes has no notion of a hidden cursor, only of an empty one
+                visible: true,
+                image,
+            }))
+        }
+    }
+
+    /// Converts the premultiplied ARGB pixels of XFixes to straight RGBA.
+    fn argb_to_rgba(pixels: &[u32]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(pixels.len() * 4);
+        for pixel in pixels {
+            let [a, r, g, b] = pixel.to_be_bytes();
+            let unpremultiply = |c: u8| {
+                if a == 0 {
+                    0
+                } else {
+                    (u32::from(c) * 255 / u32::from(a)).min(255) as u8
+                }
+            };
+            rgba.extend([unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+        }
+        rgba
     }
 
     fn raw_to_rgb(data: &[u8], bits_per_pixel: u8) -> Vec<u8> {
@@ -183,17 +409,18 @@ mod x11 {
 // memory file that we mmap and read.
 //
 // Supported compositors: sway, hyprland, labwc, river, wayfire, KDE Plasma 6.
-// GNOME/mutter does not implement wlr-screencopy; those sessions fall through
-// to X11 via XWayland automatically (see create_capturer).
+// GNOME/mutter does not implement wlr-screencopy; those sessions go through
+// the ScreenCast portal when built with the `pipewire` feature, or fall
+// through to X11 via XWayland (see create_capturer).
 
 #[cfg(target_os = "linux")]
 mod wayland {
-    use super::{PlatformCapturer, Screenshot};
+    use super::{Display, PlatformCapturer, Rect, Screenshot};
     use nullnet_liberror::{Error, ErrorHandler, Location, location};
     use std::num::NonZeroUsize;
     use std::os::fd::AsFd;
     use wayland_client::{
-        Connection, Dispatch, EventQueue, QueueHandle,
+        Connection, Dispatch, EventQueue, QueueHandle, WEnum,
         protocol::{wl_buffer, wl_output, wl_registry, wl_shm, wl_shm_pool},
     };
     use wayland_protocols_wlr::screencopy::v1::client::{
@@ -203,11 +430,42 @@ mod wayland {
 
     // ── Shared state threaded through the event dispatch ──────────────────────
 
+    struct Output {
+        /// Registry name of the global, stable while the output is plugged
+        global: u32,
+        proxy: wl_output::WlOutput,
+        name: String,
+        x: i32,
+        y: i32,
+        // Current mode, in pixels
+        width: i32,
+        height: i32,
+        scale: i32,
+    }
+
+    impl Output {
+        fn display(&self) -> Display {
+            let scale = self.scale.max(1);
+            Display {
+                id: self.global,
+                name: self.name.clone(),
+                rect: Rect::new(
+                    self.x,
+                    self.y,
+                    (self.width / scale) as u32,
+                    (self.height / scale) as u32,
+                ),
+                scale: f64::from(scale),
+                primary: false,
+            }
+        }
+    }
+
     #[derive(Default)]
     struct Session {
         // Wayland globals — populated during initialisation roundtrips.
         shm: Option<wl_shm::WlShm>,
-        output: Option<wl_output::WlOutput>,
+        outputs: Vec<Output>,
         manager: Option<ZwlrScreencopyManagerV1>,
 
         // Per-frame values from zwlr_screencopy_frame_v1::Buffer event.
@@ -232,24 +490,69 @@ mod wayland {
             _: &Connection,
             qh: &QueueHandle<Self>,
         ) {
-            let wl_registry::Event::Global {
-                name,
-                interface,
-                version,
-            } = event
-            else {
+            match event {
+                wl_registry::Event::Global {
+                    name,
+                    interface,
+                    version,
+                } => match interface.as_str() {
+                    "wl_shm" => {
+                        state.shm = Some(registry.bind(name, 1, qh, ()));
+                    }
+                    "wl_output" => {
+                        state.outputs.push(Output {
+                            global: name,
+                            proxy: registry.bind(name, version.min(4), qh, name),
+                            name: format!("output-{name}"),
+                            x: 0,
+                            y: 0,
+                            width: 0,
+                            height: 0,
+                            scale: 1,
+                        });
+                    }
+                    "zwlr_screencopy_manager_v1" => {
+                        state.manager = Some(registry.bind(name, version.min(3), qh, ()));
+                    }
+                    _ => {}
+                },
+                wl_registry::Event::GlobalRemove { name } => {
+                    state.outputs.retain(|output| output.global != name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl Dispatch<wl_output::WlOutput, u32> for Session {
+        fn event(
+            state: &mut Self,
+            _: &wl_output::WlOutput,
+            event: wl_output::Event,
+            global: &u32,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            let Some(output) = state.outputs.iter_mut().find(|o| o.global == *global) else {
                 return;
             };
-            match interface.as_str() {
-                "wl_shm" => {
-                    state.shm = Some(registry.bind(name, 1, qh, ()));
-                }
-                "wl_output" if state.output.is_none() => {
-                    state.output = Some(registry.bind(name, version.min(4), qh, ()));
+
+            match event {
+                wl_output::Event::Geometry { x, y, .. } => {
+                    output.x = x;
+                    output.y = y;
                 }
-                "zwlr_screencopy_manager_v1" => {
-                    state.manager = Some(registry.bind(name, version.min(3), qh, ()));
+                wl_output::Event::Mode {
+                    flags: WEnum::Value(flags),
+                    width,
+                    height,
+                    ..
+                } if flags.contains(wl_output::Mode::Current) => {
+                    output.width = width;
+                    output.height = height;
                 }
+                wl_output::Event::Scale { factor } => output.scale = factor,
+                wl_output::Event::Name { name } => output.name = name,
                 _ => {}
             }
         }
@@ -272,8 +575,8 @@ mod wayland {
                     stride,
                 } => {
                     state.format = match format {
-                        wayland_client::WEnum::Value(f) => f as u32,
-                        wayland_client::WEnum::Unknown(n) => n,
+                        WEnum::Value(f) => f as u32,
+                        WEnum::Unknown(n) => n,
                     };
                     state.width = width;
                     state.height = height;
@@ -305,7 +608,6 @@ mod wayland {
     noop_dispatch!(wl_shm::WlShm, wl_shm::Event);
     noop_dispatch!(wl_shm_pool::WlShmPool, wl_shm_pool::Event);
     noop_dispatch!(wl_buffer::WlBuffer, wl_buffer::Event);
-    noop_dispatch!(wl_output::WlOutput, wl_output::Event);
     noop_dispatch!(ZwlrScreencopyManagerV1, zwlr_screencopy_manager_v1::Event);
 
     // ── WaylandCapturer ───────────────────────────────────────────────────────
@@ -335,7 +637,7 @@ mod wayland {
                 .roundtrip(&mut session)
                 .handle_err(location!())?;
 
-            if session.shm.is_none() || session.output.is_none() || session.manager.is_none() {
+            if session.shm.is_none() || session.outputs.is_empty() || session.manager.is_none() {
                 return Err("compositor missing required globals \
                      (wl_shm, wl_output, or zwlr_screencopy_manager_v1) — \
                      wlr-screencopy may not be supported")
@@ -351,10 +653,62 @@ mod wayland {
     }
 
     impl PlatformCapturer for WaylandCapturer {
-        fn capture(&mut self) -> Result<Screenshot, Error> {
+        fn displays(&mut self) -> Result<Vec<Display>, Error> {
+            // Picks up outputs plugged, unplugged or reconfigured since the last call
+            self.event_queue
+                .roundtrip(&mut self.session)
+                .handle_err(location!())?;
+
+            Ok(self.session.outputs.iter().map(Output::display).collect())
+        }
+
+        fn capture(&mut self, display: Option<&Display>) -> Result<Screenshot, Error> {
+            if let Some(display) = display {
+                let output = self
+                    .session
+                    .outputs
+                    .iter()
+                    .find(|output| output.global == display.id)
+                    .map(|output| output.proxy.clone())
+                    .ok_or("Wayland output unplugged")
+                    .handle_err(location!())?;
+
+                return self.capture_output(&output);
+            }
+
+            // Whole desktop: compose the outputs at their logical positions
+            let layout: Vec<_> = self
+                .session
+                .outputs
+                .iter()
+                .map(|output| (output.proxy.clone(), output.display()))
+                .collect();
+            let desktop = Rect::bounding(layout.iter().map(|(_, display)| display.rect))
+                .ok_or("No Wayland output")
+                .handle_err(location!())?;
+
+            let (width, height) = (desktop.width as usize, desktop.height as usize);
+            let mut canvas = Screenshot::new(vec![0; width * height * 3], width, height);
+
+            for (output, display) in layout {
+                let shot = self
+                    .capture_output(&output)?
+                    .resize(display.rect.width as usize, display.rect.height as usize);
+                canvas.paste(
+                    &shot,
+                    (display.rect.x - desktop.x) as usize,
+                    (display.rect.y - desktop.y) as usize,
+                );
+            }
+
+            Ok(canvas)
+        }
+    }
+
+    impl WaylandCapturer {
+        fn capture_output(&mut self, output: &wl_output::WlOutput) -> Result<Screenshot, Error> {
             // Clone Wayland proxies upfront so we don't hold borrows into
             // `self.session` while also passing `&mut self.session` to roundtrip.
-            let output = self.session.output.as_ref().unwrap().clone();
             let manager = self.session.manager.as_ref().unwrap().clone();
             let shm = self.session.shm.as_ref().unwrap().clone();
 
@@ -365,8 +719,9 @@ mod wayland {
             self.session.ready = false;
             self.session.failed = false;
 
-            // Ask the compositor for a screencopy frame (cursor not included).
-            let frame = manager.capture_output(0, &output, &self.qh, ());
+            // wlr-screencopy cannot report the pointer on its own: have the
+            // compositor draw it in the frame instead.
+            let frame = manager.capture_output(1, output, &self.qh, ());
 
             // Roundtrip to receive the Buffer event (gives us dimensions + format).
             self.event_queue
@@ -486,40 +841,386 @@ mod wayland {
     }
 }
 
+// ── ScreenCast portal + PipeWire (GNOME, KDE) ────────────────────────────────
+
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod portal_backend {
+    use super::super::pipewire_stream::PipeWireStreams;
+    use super::super::portal::PortalSession;
+    use super::{Display, PlatformCapturer, Rect, Screenshot};
+    use nullnet_liberror::{Error, ErrorHandler, Location, location};
+    use openh264::formats::RGBSource;
+
+    pub struct PortalCapturer {
+        // Declared first so the streams disconnect before the session closes
+        streams: PipeWireStreams,
+        session: PortalSession,
+    }
+
+    impl PortalCapturer {
+        pub fn new() -> Result<Self, Error> {
+            let (session, remote) = PortalSession::start()?;
+            if session.streams.is_empty() {
+                return Err("No monitor was shared").handle_err(location!());
+            }
+
+            let node_ids = session.streams.iter().map(|s| s.node_id).collect();
+            let streams = PipeWireStreams::connect(remote, node_ids)?;
+
+            Ok(Self { streams, session })
+        }
+    }
+
+    impl PlatformCapturer for PortalCapturer {
+        fn displays(&mut self) -> Result<Vec<Display>, Error> {
+            self.session
+                .streams
+                .iter()
+                .enumerate()
+                .map(|(index, stream)| {
+                    // Streams are sized in logical units, frames in pixels
+                    let scale = match self.streams.frame(index)? {
+                        Some(frame) if stream.rect.width > 0 => {
+                            frame.dimensions().0 as f64 / f64::from(stream.rect.width)
+                        }
+                        _ => 1.0,
+                    };
+
+                    Ok(Display {
+                        id: stream.node_id,
+                        name: format!("Monitor {}", index + 1),
+                        rect: stream.rect,
+                        scale,
+                        primary: index == 0,
+                    })
+                })
+                .collect()
+        }
+
+        fn capture(&mut self, display: Option<&Display>) -> Result<Screenshot, Error> {
+            if let Some(display) = display {
+                let index = self
+                    .session
+                    .streams
+                    .iter()
+                    .position(|stream| stream.node_id == display.id)
+                    .ok_or("Portal stream closed")
+                    .handle_err(location!())?;
+
+                // No frame yet: the capture loop tries again later
+                return Ok(self.streams.frame(index)?.unwrap_or_default());
+            }
+
+            // Whole desktop: compose the monitors at their logical positions
+            let desktop = Rect::bounding(self.session.streams.iter().map(|stream| stream.rect))
+                .ok_or("No portal stream")
+                .handle_err(location!())?;
+
+            let (width, height) = (desktop.width as usize, desktop.height as usize);
+            let mut canvas = Screenshot::new(vec![0; width * height * 3], width, height);
+            let mut captured = false;
+
+            for (index, stream) in self.session.streams.iter().enumerate() {
+                let Some(frame) = self.streams.frame(index)? else {
+                    continue;
+                };
+                // Fractional scaling leaves frames a non-integer multiple of the logical size
+                let frame = frame.resize(stream.rect.width as usize, stream.rect.height as usize);
+
+                canvas.paste(
+                    &frame,
+                    (stream.rect.x - desktop.x) as usize,
+                    (stream.rect.y - desktop.y) as usize,
+                );
+                captured = true;
+            }
+
+            Ok(if captured {
+                canvas
+            } else {
+                Screenshot::default()
+            })
+        }
+    }
+}
+
 // ── Windows / GDI ────────────────────────────────────────────────────────────
 
 #[cfg(target_os = "windows")]
 mod windows_backend {
-    use super::{PlatformCapturer, Screenshot};
+    use super::{Cursor, Display, PlatformCapturer, Rect, Screenshot};
+    use crate::remote_desktop::cursor::CursorImage;
     use nullnet_liberror::{Error, ErrorHandler, Location, location};
     use std::mem;
-    use winapi::shared::windef::{HBITMAP, HDC};
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::{HBITMAP, HCURSOR, HDC, HMONITOR, LPRECT};
     use winapi::um::wingdi::{
-        BI_RGB, BITMAPINFO, BITMAPINFOHEADER, BitBlt, CreateCompatibleBitmap, CreateCompatibleDC,
-        DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDIBits, SRCCOPY, SelectObject,
+        BI_RGB, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BitBlt, CreateCompatibleBitmap,
+        CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDIBits, GetObjectW, SRCCOPY,
+        SelectObject,
+    };
+    use winapi::um::winuser::{
+        CURSOR_SHOWING, CURSORINFO, EnumDisplayMonitors, GetCursorInfo, GetDC, GetIconInfo,
+        GetMonitorInfoW, GetSystemMetrics, ICONINFO, MONITORINFO, MONITORINFOEXW,
+        MONITORINFOF_PRIMARY, ReleaseDC, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+        SM_YVIRTUALSCREEN,
     };
-    use winapi::um::winuser::{GetDC, GetSystemMetrics, ReleaseDC, SM_CXSCREEN, SM_CYSCREEN};
 
-    pub struct GdiCapturer;
+    pub struct GdiCapturer {
+        /// Handle of the last cursor read, kept as an integer to stay `Send`
+        cursor: usize,
+    }
 
     impl GdiCapturer {
         pub fn new() -> Result<Self, Error> {
-            Ok(Self)
+            Ok(Self { cursor: 0 })
         }
     }
 
     impl PlatformCapturer for GdiCapturer {
-        fn capture(&mut self) -> Result<Screenshot, Error> {
-            capture_screen()
+        fn displays(&mut self) -> Result<Vec<Display>, Error> {
+            let mut displays: Vec<Display> = Vec::new();
+
+            // SAFETY: the callback only runs during the call, while `displays` is alive.
+            let ok = unsafe {
+                EnumDisplayMonitors(
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    Some(monitor_callback),
+                    &mut displays as *mut Vec<Display> as LPARAM,
+                )
+            };
+
+            if ok == 0 {
+                return Err("EnumDisplayMonitors failed").handle_err(location!());
+            }
+
+            Ok(displays)
+        }
+
+        fn capture(&mut self, display: Option<&Display>) -> Result<Screenshot, Error> {
+            let rect = match display {
+                Some(display) => display.rect,
+                // SAFETY: GetSystemMetrics has no preconditions.
+                None => unsafe {
+                    Rect::new(
+                        GetSystemMetrics(SM_XVIRTUALSCREEN),
+                        GetSystemMetrics(SM_YVIRTUALSCREEN),
+                        GetSystemMetrics(SM_CXVIRTUALSCREEN) as u32,
+                        GetSystemMetrics(SM_CYVIRTUALSCREEN) as u32,
+                    )
+                },
+            };
+
+            capture_screen(rect)
+        }
+
+        fn cursor(&mut self) -> Result<Option<Cursor>, Error> {
+            let mut info: CURSORINFO = unsafe { mem::zeroed() };
+            info.cbSize = mem::size_of::<CURSORINFO>() as u32;
+
+            // SAFETY: `info` is a properly sized CURSORINFO.
+            if unsafe { GetCursorInfo(&mut info) } == 0 {
+                return Err("GetCursorInfo failed").handle_err(location!());
+            }
+
+            let visible = info.flags & CURSOR_SHOWING != 0 && !info.hCursor.is_null();
+
+            let mut image = None;
+            if visible && info.hCursor as usize != self.cursor {
+                image = Some(cursor_image(info.hCursor)?);
+                self.cursor = info.hCursor as usize;
+            }
+
+            Ok(Some(Cursor {
+                x: info.ptScreenPos.x,
+                y: info.ptScreenPos.y,
+                visible,
+                image,
+            }))
+        }
+    }
+
+    /// Reads the shape of `cursor` as straight RGBA.
+    fn cursor_image(cursor: HCURSOR) -> Result<CursorImage, Error> {
+        let mut info: ICONINFO = unsafe { mem::zeroed() };
+
+        // SAFETY: `cursor` comes from GetCursorInfo; the bitmaps it hands
+        // out are ours and deleted below.
+        if unsafe { GetIconInfo(cursor, &mut info) } == 0 {
+            return Err("GetIconInfo failed").handle_err(location!());
         }
+
+        let mask = bitmap_bgra(info.hbmMask);
+        let color = if info.hbmColor.is_null() {
+            None
+        } else {
+            bitmap_bgra(info.hbmColor)
+        };
+
+        unsafe {
+            if !info.hbmColor.is_null() {
+                DeleteObject(info.hbmColor as _);
+            }
+            DeleteObject(info.hbmMask as _);
+        }
+
+        let (mask_width, mask_height, mask) = mask
+            .ok_or("Failed to read the cursor mask")
+            .handle_err(location!())?;
+
+        let (width, height, pixels) = match color {
+            Some((width, height, color)) => {
+                let has_alpha = color.chunks_exact(4).any(|bgra| bgra[3] != 0);
+                let pixels = color
+                    .chunks_exact(4)
+                    .zip(mask.chunks_exact(4))
+                    .flat_map(|(bgra, and)| {
+                        // Without alpha channel, the AND mask tells the transparent pixels
+                        let alpha = if has_alpha {
+                            bgra[3]
+                        } else if and[0] != 0 {
+                            0
+                        } else {
+                            255
+                        };
+                        [bgra[2], bgra[1], bgra[0], alpha]
+                    })
+                    .collect();
+                (width, height, pixels)
+            }
+            // Monochrome: the AND mask on top of the XOR mask
+            None => {
+                let height = mask_height / 2;
+                let half = (mask_width * height * 4) as usize;
+                let (and, xor) = mask.split_at(half);
+                let pixels = and
+                    .chunks_exact(4)
+                    .zip(xor.chunks_exact(4))
+                    .flat_map(|(and, xor)| match (and[0] != 0, xor[0] != 0) {
+                        (false, false) => [0, 0, 0, 255],
+                        (false, true) => [255, 255, 255, 255],
+                        (true, false) => [0, 0, 0, 0],
+                        // Inverts the screen, which a viewer cannot do: show it black
+                        (true, true) => [0, 0, 0, 255],
+                    })
+                    .collect();
+                (mask_width, height, pixels)
+            }
+        };
+
+        Ok(CursorImage {
+            width,
+            height,
+            hotspot_x: info.xHotspot,
+            hotspot_y: info.yHotspot,
+            pixels,
+        })
+    }
+
+    /// Reads `bitmap` as top-down 32bpp BGRA, with its size.
+    fn bitmap_bgra(bitmap: HBITMAP) -> Option<(u32, u32, Vec<u8>)> {
+        // SAFETY: `bitmap` is valid for the call, `header` and `raw` are
+        // sized as GetObjectW and GetDIBits expect.
+        unsafe {
+            let mut header: BITMAP = mem::zeroed();
+            if GetObjectW(
+                bitmap as _,
+                mem::size_of::<BITMAP>() as i32,
+                &mut header as *mut BITMAP as _,
+            ) == 0
+            {
+                return None;
+            }
+
+            let (width, height) = (header.bmWidth, header.bmHeight);
+            if width <= 0 || height <= 0 {
+                return None;
+            }
+
+            let mut bmi: BITMAPINFO = mem::zeroed();
+            bmi.bmiHeader = BITMAPINFOHEADER {
+                biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB,
+                biSizeImage: 0,
+                biXPelsPerMeter: 0,
+                biYPelsPerMeter: 0,
+                biClrUsed: 0,
+                biClrImportant: 0,
+            };
+
+            let mut raw = vec![0u8; width as usize * height as usize * 4];
+
+            let screen_dc: HDC = GetDC(std::ptr::null_mut());
+            if screen_dc.is_null() {
+                return None;
+            }
+
+            let scan_lines = GetDIBits(
+                screen_dc,
+                bitmap,
+                0,
+                height as u32,
+                raw.as_mut_ptr() as _,
+                &mut bmi,
+                DIB_RGB_COLORS,
+            );
+            ReleaseDC(std::ptr::null_mut(), screen_dc);
+
+            (scan_lines != 0).then_some((width as u32, height as u32, raw))
+        }
+    }
+
+    unsafe extern "system" fn monitor_callback(
+        monitor: HMONITOR,
+        _: HDC,
+        _: LPRECT,
+        data: LPARAM,
+    ) -> BOOL {
+        // SAFETY: `data` is the vector passed to EnumDisplayMonitors.
+        let displays = unsafe { &mut *(data as *mut Vec<Display>) };
+
+        let mut info: MONITORINFOEXW = unsafe { mem::zeroed() };
+        info.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+
+        if unsafe {
+            GetMonitorInfoW(
+                monitor,
+                &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+            )
+        } != 0
+        {
+            let area = info.rcMonitor;
+            let device = &info.szDevice;
+            let len = device.iter().position(|&c| c == 0).unwrap_or(device.len());
+
+            displays.push(Display {
+                id: displays.len() as u32,
+                name: String::from_utf16_lossy(&device[..len]),
+                rect: Rect::new(
+                    area.left,
+                    area.top,
+                    (area.right - area.left) as u32,
+                    (area.bottom - area.top) as u32,
+                ),
+                scale: 1.0,
+                primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+            });
+        }
+
+        TRUE
     }
 
-    fn capture_screen() -> Result<Screenshot, Error> {
+    fn capture_screen(rect: Rect) -> Result<Screenshot, Error> {
         // SAFETY: all GDI/User32 calls are valid given the handle lifetimes
         // managed within this function; no pointers escape.
         unsafe {
-            let width = GetSystemMetrics(SM_CXSCREEN);
-            let height = GetSystemMetrics(SM_CYSCREEN);
+            let width = rect.width as i32;
+            let height = rect.height as i32;
 
             let screen_dc: HDC = GetDC(std::ptr::null_mut());
             if screen_dc.is_null() {
@@ -541,7 +1242,10 @@ mod windows_backend {
 
             let old_obj = SelectObject(compat_dc, bitmap as _);
 
-            if BitBlt(compat_dc, 0, 0, width, height, screen_dc, 0, 0, SRCCOPY) == 0 {
+            if BitBlt(
+                compat_dc, 0, 0, width, height, screen_dc, rect.x, rect.y, SRCCOPY,
+            ) == 0
+            {
                 SelectObject(compat_dc, old_obj);
                 DeleteObject(bitmap as _);
                 DeleteDC(compat_dc);
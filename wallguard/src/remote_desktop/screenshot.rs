@@ -20,6 +20,89 @@ impl Screenshot {
     pub fn is_empty(&self) -> bool {
         self.buffer.is_empty()
     }
+
+    /// Shrinks the image by `factor` in both dimensions, averaging each block of pixels.
+    pub fn downscale(self, factor: usize) -> Self {
+        if factor <= 1 {
+            return self;
+        }
+
+        let width = self.width / factor;
+        let height = self.height / factor;
+        let block = (factor * factor) as u32;
+        let mut buffer = Vec::with_capacity(width * height * 3);
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 3];
+                for dy in 0..factor {
+                    let row = (y * factor + dy) * self.width;
+                    for dx in 0..factor {
+                        let idx = (row + x * factor + dx) * 3;
+                        for (channel, value) in sum.iter_mut().zip(&self.buffer[idx..idx + 3]) {
+                            *channel += u32::from(*value);
+                        }
+                    }
+                }
+                buffer.extend(sum.map(|channel| (channel / block) as u8));
+            }
+        }
+
+        Self::new(buffer, width, height)
+    }
+
+    /// Resamples the image to `width` x `height`, averaging the pixels each output pixel covers.
+    pub fn resize(self, width: usize, height: usize) -> Self {
+        if (width, height) == (self.width, self.height) || self.is_empty() {
+            return self;
+        }
+
+        let mut buffer = Vec::with_capacity(width * height * 3);
+
+        for y in 0..height {
+            let (top, bottom) = Self::span(y, height, self.height);
+            for x in 0..width {
+                let (left, right) = Self::span(x, width, self.width);
+                let mut sum = [0u32; 3];
+                for row in top..bottom {
+                    for column in left..right {
+                        let idx = (row * self.width + column) * 3;
+                        for (channel, value) in sum.iter_mut().zip(&self.buffer[idx..idx + 3]) {
+                            *channel += u32::from(*value);
+                        }
+                    }
+                }
+                let count = ((bottom - top) * (right - left)) as u32;
+                buffer.extend(sum.map(|channel| (channel / count) as u8));
+            }
+        }
+
+        Self::new(buffer, width, height)
+    }
+
+    /// The source pixels covered by output pixel `index`, at least one.
+    fn span(index: usize, output: usize, source: usize) -> (usize, usize) {
+        let start = (index * source / output).min(source - 1);
+        let end = ((index + 1) * source / output).clamp(start + 1, source);
+        (start, end)
+    }
+
+    /// Copies `other` into this image with its top-left corner at (`x`, `y`), clipping what falls outside.
+    pub fn paste(&mut self, other: &Screenshot, x: usize, y: usize) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let columns = other.width.min(self.width - x);
+        let rows = other.height.min(self.height - y);
+
+        for row in 0..rows {
+            let src = row * other.width * 3;
+            let dst = ((y + row) * self.width + x) * 3;
+            self.buffer[dst..dst + columns * 3]
+                .copy_from_slice(&other.buffer[src..src + columns * 3]);
+        }
+    }
 }
 
 impl Deref for Screenshot {
@@ -53,3 +136,34 @@ impl RGB8Source for Screenshot {
         &self.buffer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Screenshot {
+        let buffer = (0..width * height)
+            .flat_map(|i| [(i % width) as u8, (i / width) as u8, 0])
+            .collect();
+        Screenshot::new(buffer, width, height)
+    }
+
+    #[test]
+    fn resize_to_fractional_scale() {
+        // A 1.25x monitor: 5 pixels for every 4 logical units
+        let resized = gradient(10, 5).resize(8, 4);
+
+        assert_eq!(resized.dimensions(), (8, 4));
+        assert_eq!(resized.len(), 8 * 4 * 3);
+        assert_eq!(&resized[..3], &[0, 0, 0]);
+        assert_eq!(&resized[resized.len() - 3..], &[8, 3, 0]);
+    }
+
+    #[test]
+    fn resize_to_same_size_is_noop() {
+        let image = gradient(4, 3);
+        let resized = image.clone().resize(4, 3);
+
+        assert_eq!(&*resized, &*image);
+    }
+}
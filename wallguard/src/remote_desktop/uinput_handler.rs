@@ -9,50 +9,55 @@
 /// runs as root so `/dev/uinput` access is always available.
 ///
 /// # Coordinate space
-/// The pointer uses `ABS_X / ABS_Y` with the range `[0, screen_w)` × `[0, screen_h)`.
-/// Screen dimensions are read from the first Wayland output at construction
-/// time (or fall back to 1920×1080 if they cannot be determined).
+/// The pointer uses `ABS_X / ABS_Y` with the range `[0, ABS_RANGE]`, which the
+/// compositor stretches over the area covered by all the monitors.  Desktop
+/// coordinates are normalized against that area, so multi-monitor layouts with
+/// negative origins work without recreating the device.
+use super::display::Rect;
+use super::keyboard::{PHYSICAL_KEYS, PhysicalKey};
+use super::keymap::{HostKeymap, Keystroke};
 use evdev::{
     AbsInfo, AbsoluteAxisType, AttributeSet, EventType, InputEvent, Key, UinputAbsSetup,
     uinput::VirtualDeviceBuilder,
 };
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 
+/// Upper bound of the pointer axes.
+const ABS_RANGE: i32 = 65535;
+
 // Composite handle over the two virtual devices.
 pub struct UinputHandler {
     keyboard: evdev::uinput::VirtualDevice,
     pointer: evdev::uinput::VirtualDevice,
-    /// Width of the virtual screen (ABS_X upper bound).
-    screen_w: i32,
-    /// Height of the virtual screen (ABS_Y upper bound).
-    screen_h: i32,
+    /// Layout of the host, to find the keys typing a character
+    keymap: Option<HostKeymap>,
 }
 
 impl UinputHandler {
     /// Create the virtual keyboard and pointer devices.
-    ///
-    /// `screen_w` / `screen_h` set the coordinate range for the pointer.
-    /// Pass `None` to use the default 1920×1080 range.
-    pub fn new(screen_w: Option<i32>, screen_h: Option<i32>) -> Result<Self, Error> {
-        let screen_w = screen_w.unwrap_or(1920);
-        let screen_h = screen_h.unwrap_or(1080);
-
+    pub fn new() -> Result<Self, Error> {
         let keyboard = build_keyboard()?;
-        let pointer = build_pointer(screen_w, screen_h)?;
+        let pointer = build_pointer()?;
+
+        let keymap = HostKeymap::load();
+        if keymap.is_none() {
+            log::warn!("Cannot compile the host keymap, characters are typed as on a US layout");
+        }
 
         Ok(Self {
             keyboard,
             pointer,
-            screen_w,
-            screen_h,
+            keymap,
         })
     }
 
     // ── mouse ────────────────────────────────────────────────────────────────
 
-    pub fn move_abs(&mut self, x: i32, y: i32) -> Result<(), Error> {
-        let x = x.clamp(0, self.screen_w - 1);
-        let y = y.clamp(0, self.screen_h - 1);
+    /// Moves the pointer to (`x`, `y`) in desktop coordinates, `desktop` being
+    /// the area covered by all the monitors.
+    pub fn move_abs(&mut self, x: i32, y: i32, desktop: Rect) -> Result<(), Error> {
+        let x = normalize(x - desktop.x, desktop.width);
+        let y = normalize(y - desktop.y, desktop.height);
         self.pointer
             .emit(&[
                 InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, x),
@@ -82,36 +87,106 @@ impl UinputHandler {
 
     // ── keyboard ─────────────────────────────────────────────────────────────
 
-    pub fn key_press(&mut self, key: &str) -> Result<(), Error> {
-        self.send_key(key, 1)
+    /// Presses or releases the key at `code`, for the host layout to translate.
+    ///
+    /// Viewers sending no physical code get `key` typed as is instead.
+    pub fn key(&mut self, key: &str, code: &str, pressed: bool) -> Result<(), Error> {
+        let value = i32::from(pressed);
+
+        if let Some(physical) = PhysicalKey::from_code(code) {
+            return self.emit_keys(&[(physical.evdev, value)]);
+        }
+
+        if let Some(named) = named_key(key) {
+            return self.emit_keys(&[(named, value)]);
+        }
+
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => match self.keystroke(c) {
+                Some(keystroke) => self.emit_keystroke(keystroke, value),
+                None => {
+                    log::debug!("No key types '{c}' on the host, ignored");
+                    Ok(())
+                }
+            },
+            // Dead keys and the like, which only make sense with a physical code
+            _ => {
+                log::debug!("Unknown key '{key}', ignored");
+                Ok(())
+            }
+        }
     }
 
-    pub fn key_release(&mut self, key: &str) -> Result<(), Error> {
-        self.send_key(key, 0)
+    /// Types `text` with the keys of the host layout.
+    pub fn type_text(&mut self, text: &str) -> Result<(), Error> {
+        let mut skipped = 0;
+
+        for c in text.chars() {
+            let keystroke = match c {
+                // Windows line endings: Enter once
+                '\r' => continue,
+                '\n' => Some(Keystroke::plain(Key::KEY_ENTER.code())),
+                '\t' => Some(Keystroke::plain(Key::KEY_TAB.code())),
+                c => self.keystroke(c),
+            };
+
+            match keystroke {
+                Some(keystroke) => {
+                    self.emit_keystroke(keystroke, 1)?;
+                    self.emit_keystroke(keystroke, 0)?;
+                }
+                None => skipped += 1,
+            }
+        }
+
+        if skipped > 0 {
+            log::warn!("{skipped} characters have no key on the host layout, not typed");
+        }
+
+        Ok(())
     }
 
-    pub fn key_click(&mut self, key: &str) -> Result<(), Error> {
-        self.send_key(key, 1)?;
-        self.send_key(key, 0)
+    fn keystroke(&self, c: char) -> Option<Keystroke> {
+        match &self.keymap {
+            Some(keymap) => keymap
+                .keystroke(c)
+                .filter(|keystroke| is_advertised(keystroke.evdev)),
+            None => char_to_key(c).map(|(shift, evdev)| Keystroke {
+                evdev,
+                shift,
+                altgr: false,
+            }),
+        }
     }
 
-    fn send_key(&mut self, key: &str, value: i32) -> Result<(), Error> {
-        let (needs_shift, code) = map_key(key);
-        if needs_shift {
-            self.keyboard
-                .emit(&[InputEvent::new(
-                    EventType::KEY,
-                    Key::KEY_LEFTSHIFT.code(),
-                    value,
-                )])
-                .handle_err(location!())?;
+    /// Presses the modifiers before the key, and releases them after it.
+    fn emit_keystroke(&mut self, keystroke: Keystroke, value: i32) -> Result<(), Error> {
+        let mut keys = Vec::with_capacity(3);
+        if keystroke.shift {
+            keys.push((Key::KEY_LEFTSHIFT.code(), value));
         }
-        self.keyboard
-            .emit(&[
-                InputEvent::new(EventType::KEY, code, value),
-                InputEvent::new(EventType::SYNCHRONIZATION, 0, 0),
-            ])
-            .handle_err(location!())
+        if keystroke.altgr {
+            keys.push((Key::KEY_RIGHTALT.code(), value));
+        }
+
+        if value == 0 {
+            keys.insert(0, (keystroke.evdev, value));
+        } else {
+            keys.push((keystroke.evdev, value));
+        }
+
+        self.emit_keys(&keys)
+    }
+
+    fn emit_keys(&mut self, keys: &[(u16, i32)]) -> Result<(), Error> {
+        let mut events: Vec<_> = keys
+            .iter()
+            .map(|&(code, value)| InputEvent::new(EventType::KEY, code, value))
+            .collect();
+        events.push(InputEvent::new(EventType::SYNCHRONIZATION, 0, 0));
+
+        self.keyboard.emit(&events).handle_err(location!())
     }
 }
 
@@ -127,7 +202,7 @@ pub enum MouseButton {
 }
 
 impl MouseButton {
-    fn evdev_code(self) -> u16 {
+    pub fn evdev_code(self) -> u16 {
         match self {
             MouseButton::Left => Key::BTN_LEFT.code(),
             MouseButton::Right => Key::BTN_RIGHT.code(),
@@ -145,6 +220,9 @@ fn build_keyboard() -> Result<evdev::uinput::VirtualDevice, Error> {
     for k in ALL_KEYBOARD_KEYS {
         keys.insert(*k);
     }
+    for (_, evdev, _) in PHYSICAL_KEYS {
+        keys.insert(Key::new(*evdev));
+    }
     VirtualDeviceBuilder::new()
         .handle_err(location!())?
         .name("WallGuard Virtual Keyboard")
@@ -154,7 +232,13 @@ fn build_keyboard() -> Result<evdev::uinput::VirtualDevice, Error> {
         .handle_err(location!())
 }
 
-fn build_pointer(w: i32, h: i32) -> Result<evdev::uinput::VirtualDevice, Error> {
+fn normalize(offset: i32, length: u32) -> i32 {
+    let last = i64::from(length.max(2) - 1);
+    let offset = i64::from(offset).clamp(0, last);
+    (offset * i64::from(ABS_RANGE) / last) as i32
+}
+
+fn build_pointer() -> Result<evdev::uinput::VirtualDevice, Error> {
     let mut buttons = AttributeSet::<Key>::new();
     for b in [
         Key::BTN_LEFT,
@@ -167,8 +251,14 @@ fn build_pointer(w: i32, h: i32) -> Result<evdev::uinput::VirtualDevice, Error>
     }
 
     // AbsInfo::new(value, minimum, maximum, fuzz, flat, resolution)
-    let abs_x = UinputAbsSetup::new(AbsoluteAxisType::ABS_X, AbsInfo::new(0, 0, w, 0, 0, 1));
-    let abs_y = UinputAbsSetup::new(AbsoluteAxisType::ABS_Y, AbsInfo::new(0, 0, h, 0, 0, 1));
+    let abs_x = UinputAbsSetup::new(
+        AbsoluteAxisType::ABS_X,
+        AbsInfo::new(0, 0, ABS_RANGE, 0, 0, 1),
+    );
+    let abs_y = UinputAbsSetup::new(
+        AbsoluteAxisType::ABS_Y,
+        AbsInfo::new(0, 0, ABS_RANGE, 0, 0, 1),
+    );
 
     VirtualDeviceBuilder::new()
         .handle_err(location!())?
@@ -185,56 +275,57 @@ fn build_pointer(w: i32, h: i32) -> Result<evdev::uinput::VirtualDevice, Error>
 
 // ── key mapping ───────────────────────────────────────────────────────────────
 
-/// Map a key name (as sent by the browser client) to `(needs_shift, evdev_code)`.
-///
-/// `needs_shift` is true for uppercase letters and shift-modified symbols so
-/// that the virtual keyboard sends a syntactically correct key sequence.
-fn map_key(name: &str) -> (bool, u16) {
-    match name.to_lowercase().as_str() {
-        // Named keys
-        "backspace" => (false, Key::KEY_BACKSPACE.code()),
-        "control" => (false, Key::KEY_LEFTCTRL.code()),
-        "meta" => (false, Key::KEY_LEFTMETA.code()),
-        "alt" => (false, Key::KEY_LEFTALT.code()),
-        "tab" => (false, Key::KEY_TAB.code()),
-        "capslock" => (false, Key::KEY_CAPSLOCK.code()),
-        "shift" => (false, Key::KEY_LEFTSHIFT.code()),
-        "escape" => (false, Key::KEY_ESC.code()),
-        "delete" => (false, Key::KEY_DELETE.code()),
-        "enter" => (false, Key::KEY_ENTER.code()),
-        "arrowup" => (false, Key::KEY_UP.code()),
-        "arrowdown" => (false, Key::KEY_DOWN.code()),
-        "arrowleft" => (false, Key::KEY_LEFT.code()),
-        "arrowright" => (false, Key::KEY_RIGHT.code()),
-        "home" => (false, Key::KEY_HOME.code()),
-        "end" => (false, Key::KEY_END.code()),
-        "pageup" => (false, Key::KEY_PAGEUP.code()),
-        "pagedown" => (false, Key::KEY_PAGEDOWN.code()),
-        "f1" => (false, Key::KEY_F1.code()),
-        "f2" => (false, Key::KEY_F2.code()),
-        "f3" => (false, Key::KEY_F3.code()),
-        "f4" => (false, Key::KEY_F4.code()),
-        "f5" => (false, Key::KEY_F5.code()),
-        "f6" => (false, Key::KEY_F6.code()),
-        "f7" => (false, Key::KEY_F7.code()),
-        "f8" => (false, Key::KEY_F8.code()),
-        "f9" => (false, Key::KEY_F9.code()),
-        "f10" => (false, Key::KEY_F10.code()),
-        "f11" => (false, Key::KEY_F11.code()),
-        "f12" => (false, Key::KEY_F12.code()),
-
-        // Single character: use first char of original (pre-lowercase) name
-        // to detect uppercase / shifted symbols.
-        _ => {
-            let c = name.chars().next().unwrap_or('\0');
-            char_to_key(c)
-        }
-    }
+/// Map a named key (`KeyboardEvent.key` as sent by the browser client) to its evdev code.
+pub fn named_key(name: &str) -> Option<u16> {
+    let key = match name.to_lowercase().as_str() {
+        "backspace" => Key::KEY_BACKSPACE,
+        "control" => Key::KEY_LEFTCTRL,
+        "meta" => Key::KEY_LEFTMETA,
+        "alt" => Key::KEY_LEFTALT,
+        "altgraph" => Key::KEY_RIGHTALT,
+        "tab" => Key::KEY_TAB,
+        "capslock" => Key::KEY_CAPSLOCK,
+        "shift" => Key::KEY_LEFTSHIFT,
+        "escape" => Key::KEY_ESC,
+        "insert" => Key::KEY_INSERT,
+        "delete" => Key::KEY_DELETE,
+        "enter" => Key::KEY_ENTER,
+        "arrowup" => Key::KEY_UP,
+        "arrowdown" => Key::KEY_DOWN,
+        "arrowleft" => Key::KEY_LEFT,
+        "arrowright" => Key::KEY_RIGHT,
+        "home" => Key::KEY_HOME,
+        "end" => Key::KEY_END,
+        "pageup" => Key::KEY_PAGEUP,
+        "pagedown" => Key::KEY_PAGEDOWN,
+        "f1" => Key::KEY_F1,
+        "f2" => Key::KEY_F2,
+        "f3" => Key::KEY_F3,
+        "f4" => Key::KEY_F4,
+        "f5" => Key::KEY_F5,
+        "f6" => Key::KEY_F6,
+        "f7" => Key::KEY_F7,
+        "f8" => Key::KEY_F8,
+        "f9" => Key::KEY_F9,
+        "f10" => Key::KEY_F10,
+        "f11" => Key::KEY_F11,
+        "f12" => Key::KEY_F12,
+        _ => return None,
+    };
+
+    Some(key.code())
+}
+
+fn is_advertised(evdev: u16) -> bool {
+    PHYSICAL_KEYS.iter().any(|(_, code, _)| *code == evdev)
+        || ALL_KEYBOARD_KEYS.iter().any(|key| key.code() == evdev)
 }
 
 /// Map a Unicode character to `(needs_shift, evdev_code)` using a US QWERTY layout.
-fn char_to_key(c: char) -> (bool, u16) {
-    match c {
+///
+/// Only used when the host keymap cannot be compiled.
+fn char_to_key(c: char) -> Option<(bool, u16)> {
+    let key = match c {
         'a' => (false, Key::KEY_A.code()),
         'b' => (false, Key::KEY_B.code()),
         'c' => (false, Key::KEY_C.code()),
@@ -330,17 +421,19 @@ fn char_to_key(c: char) -> (bool, u16) {
         '?' => (true, Key::KEY_SLASH.code()),
         '`' => (false, Key::KEY_GRAVE.code()),
         '~' => (true, Key::KEY_GRAVE.code()),
-        // Fall back to space for unmapped characters.
-        _ => (false, Key::KEY_SPACE.code()),
-    }
+        _ => return None,
+    };
+
+    Some(key)
 }
 
 // ── keyboard key set ──────────────────────────────────────────────────────────
 
 /// Every key the virtual keyboard device advertises to the kernel.
 ///
-/// Only keys actually used by `map_key()` / `char_to_key()` are listed — the
-/// kernel rejects device creation if you claim a key code that does not exist.
+/// Only keys actually used by `named_key()` / `char_to_key()` are listed, on
+/// top of `PHYSICAL_KEYS` — the kernel rejects device creation if you claim a
+/// key code that does not exist.
 static ALL_KEYBOARD_KEYS: &[Key] = &[
     Key::KEY_A,
     Key::KEY_B,
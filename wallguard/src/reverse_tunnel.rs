@@ -60,6 +60,10 @@ impl ReverseTunnel {
         Self { addr }
     }
 
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
     pub async fn request_channel(&self, token: &str) -> Result<TunnelInstance, Error> {
         let digest = sha256_digest_bytes(token);
 
@@ -37,6 +37,11 @@ pub enum Secret {
     InstallationCode,
     AppId,
     AppSecret,
+    FlowExportSettings,
+    ProcessSnapshotSettings,
+    /// Lets the screen sharing portal skip its dialog
+    #[cfg(all(target_os = "linux", feature = "pipewire"))]
+    PortalRestoreToken,
 }
 
 impl Secret {
@@ -45,6 +50,10 @@ impl Secret {
             Secret::InstallationCode => "InstallationCode",
             Secret::AppId => "AppId",
             Secret::AppSecret => "AppSecret",
+            Secret::FlowExportSettings => "FlowExportSettings",
+            Secret::ProcessSnapshotSettings => "ProcessSnapshotSettings",
+            #[cfg(all(target_os = "linux", feature = "pipewire"))]
+            Secret::PortalRestoreToken => "PortalRestoreToken",
         }
     }
 }
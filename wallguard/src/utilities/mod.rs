@@ -1,3 +1,4 @@
 pub mod hash;
+pub mod pcapng;
 pub mod ssh;
 pub mod system;
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// Builds the Section Header Block that opens every pcapng stream.
+pub fn section_header_block() -> Vec<u8> {
+    let mut body = Vec::with_capacity(16);
+    body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    // Section length is unknown while streaming
+    body.extend_from_slice(&(-1i64).to_le_bytes());
+
+    block(SECTION_HEADER_BLOCK, &body)
+}
+
+/// Builds an Interface Description Block.
+///
+/// Timestamps are written with the default microsecond resolution.
+pub fn interface_description_block(link_type: u16, snaplen: u32) -> Vec<u8> {
+    let mut body = Vec::with_capacity(8);
+    body.extend_from_slice(&link_type.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&snaplen.to_le_bytes());
+
+    block(INTERFACE_DESCRIPTION_BLOCK, &body)
+}
+
+/// Builds an Enhanced Packet Block for a packet captured on `interface_id`.
+pub fn enhanced_packet_block(
+    interface_id: u32,
+    timestamp: Duration,
+    data: &[u8],
+    original_len: u32,
+) -> Vec<u8> {
+    let micros = timestamp.as_micros() as u64;
+
+    let mut body = Vec::with_capacity(20 + padded_len(data.len()));
+    body.extend_from_slice(&interface_id.to_le_bytes());
+    body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(micros as u32).to_le_bytes());
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&original_len.to_le_bytes());
+    body.extend_from_slice(data);
+    body.resize(20 + padded_len(data.len()), 0);
+
+    block(ENHANCED_PACKET_BLOCK, &body)
+}
+
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let total_len = (12 + body.len()) as u32;
+
+    let mut retval = Vec::with_capacity(total_len as usize);
+    retval.extend_from_slice(&block_type.to_le_bytes());
+    retval.extend_from_slice(&total_len.to_le_bytes());
+    retval.extend_from_slice(body);
+    retval.extend_from_slice(&total_len.to_le_bytes());
+    retval
+}
+
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_section_header_block() {
+        let shb = section_header_block();
+        assert_eq!(shb.len(), 28);
+        assert_eq!(read_u32(&shb, 0), SECTION_HEADER_BLOCK);
+        assert_eq!(read_u32(&shb, 4), 28);
+        assert_eq!(read_u32(&shb, 8), BYTE_ORDER_MAGIC);
+        assert_eq!(read_u32(&shb, 24), 28);
+    }
+
+    #[test]
+    fn test_enhanced_packet_block_is_padded() {
+        let epb = enhanced_packet_block(0, Duration::from_micros(0x1_0000_0002), &[1, 2, 3], 60);
+        assert_eq!(epb.len(), 36);
+        assert_eq!(epb.len() % 4, 0);
+        assert_eq!(read_u32(&epb, 4), 36);
+        assert_eq!(read_u32(&epb, 12), 1);
+        assert_eq!(read_u32(&epb, 16), 2);
+        assert_eq!(read_u32(&epb, 20), 3);
+        assert_eq!(read_u32(&epb, 24), 60);
+        assert_eq!(&epb[28..32], &[1, 2, 3, 0]);
+        assert_eq!(read_u32(&epb, 32), 36);
+    }
+}
@@ -18,3 +18,19 @@ pub async fn reload_configuraion() -> Result<(), Error> {
         Ok(())
     }
 }
+
+/// Runs a command, returning its standard output if it succeeded.
+pub async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().await.ok()?;
+
+    if !output.status.success() {
+        log::debug!(
+            "'{program} {}' exited with {}",
+            args.join(" "),
+            output.status
+        );
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
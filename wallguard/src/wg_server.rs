@@ -1,11 +1,12 @@
+use crate::constants::INGEST_BATCH_SIZE;
 use nullnet_liberror::{Error, ErrorHandler, Location, location};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::sync::{Mutex, mpsc};
 use tonic::Streaming;
 use wallguard_common::protobuf::wallguard_commands::{ClientMessage, ServerMessage};
 use wallguard_common::protobuf::wallguard_service::{
-    ConfigSnapshot, ConnectionsData, DeviceSettingsRequest, DeviceSettingsResponse,
-    ServicesMessage, SystemResourcesData,
+    ConfigSnapshot, Connection, ConnectionsBatch, DeviceSettingsRequest, DeviceSettingsResponse,
+    ServicesMessage, SystemResource, SystemResourcesBatch,
 };
 use wallguard_common::wallguard_interface::WallGuardGrpcInterface;
 
@@ -87,21 +88,58 @@ impl WGServer {
             .await
     }
 
-    pub async fn handle_connections_data(&self, data: ConnectionsData) -> Result<(), Error> {
-        self.get_interface()
+    /// Sends the connections over the ingestion stream, split in sequenced batches.
+    ///
+    /// Returns how many connections, counting from the first one, the server
+    /// acknowledged as stored: only those may be dropped by the caller.
+    pub async fn stream_connections(
+        &self,
+        token: &str,
+        connections: &[Connection],
+    ) -> Result<usize, Error> {
+        let batches = connections
+            .chunks(INGEST_BATCH_SIZE)
+            .zip(1..)
+            .map(|(chunk, sequence)| ConnectionsBatch {
+                sequence,
+                connections: chunk.to_vec(),
+            })
+            .collect();
+
+        let acked = self
+            .get_interface()
             .await?
-            .handle_connections_data(data)
-            .await
+            .stream_connections_data(token, batches)
+            .await?;
+
+        Ok(acked_records(acked, connections.len()))
     }
 
-    pub async fn handle_system_resources_data(
+    /// Sends the system resources over the ingestion stream, split in sequenced batches.
+    ///
+    /// Returns how many resources, counting from the first one, the server
+    /// acknowledged as stored: only those may be dropped by the caller.
+    pub async fn stream_system_resources(
         &self,
-        data: SystemResourcesData,
-    ) -> Result<(), Error> {
-        self.get_interface()
+        token: &str,
+        resources: &[SystemResource],
+    ) -> Result<usize, Error> {
+        let batches = resources
+            .chunks(INGEST_BATCH_SIZE)
+            .zip(1..)
+            .map(|(chunk, sequence)| SystemResourcesBatch {
+                sequence,
+                resources: chunk.to_vec(),
+            })
+            .collect();
+
+        let acked = self
+            .get_interface()
             .await?
-            .handle_system_resources_data(data)
-            .await
+            .stream_system_resources_data(token, batches)
+            .await?;
+
+        Ok(acked_records(acked, resources.len()))
     }
 
     pub async fn get_device_settings(
@@ -122,3 +160,10 @@ impl WGServer {
         self.get_interface().await?.report_services(data).await
     }
 }
+
+fn acked_records(acked_sequence: u64, total: usize) -> usize {
+    usize::try_from(acked_sequence)
+        .unwrap_or(usize::MAX)
+        .saturating_mul(INGEST_BATCH_SIZE)
+        .min(total)
+}